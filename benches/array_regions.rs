@@ -0,0 +1,66 @@
+//!
+//! Benchmarks comparing reads of many small array regions into fresh vectors against reads into a
+//! reused buffer.
+//!
+
+#![feature(test)]
+
+extern crate test;
+
+mod common;
+
+use rust_jni::{JNIEnv, JNativeArray, JNativeType, JNativeVec};
+use common::with_env;
+use test::Bencher;
+
+const ARRAY_LEN: usize = 1024;
+const REGION_LEN: usize = 16;
+const READS: usize = 10_000;
+
+/// Create an int array holding its own indices
+fn index_array<'a>(env: &JNIEnv<'a>) -> JNativeArray<'a> {
+    let arr = env.new_native_array(ARRAY_LEN, JNativeType::Int).expect("Couldn't create array");
+    let vals = (0..ARRAY_LEN as i32).collect();
+    env.set_native_array_region(&arr, 0, ARRAY_LEN, &JNativeVec::Int(vals)).expect("Couldn't fill array");
+    arr
+}
+
+#[bench]
+fn bench_region_allocating(b: &mut Bencher) {
+    with_env(|env| {
+        let arr = index_array(env);
+
+        b.iter(|| {
+            let mut sum = 0i64;
+            for read in 0..READS {
+                let start = (read * REGION_LEN) % ARRAY_LEN;
+                if let JNativeVec::Int(vals) = env.get_native_array_region(&arr, start, REGION_LEN).unwrap() {
+                    sum += vals[0] as i64;
+                }
+            }
+            sum
+        });
+    })
+}
+
+#[bench]
+fn bench_region_into(b: &mut Bencher) {
+    with_env(|env| {
+        let arr = index_array(env);
+        let arr = match &arr {
+            JNativeArray::Int(arr) => arr,
+            _ => unreachable!()
+        };
+        let mut buf = [0; REGION_LEN];
+
+        b.iter(|| {
+            let mut sum = 0i64;
+            for read in 0..READS {
+                let start = (read * REGION_LEN) % ARRAY_LEN;
+                env.read_int_region_into(arr, start, &mut buf).unwrap();
+                sum += buf[0] as i64;
+            }
+            sum
+        });
+    })
+}
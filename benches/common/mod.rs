@@ -0,0 +1,38 @@
+//!
+//! Helpers shared by the benchmarks. Each benchmark compiles its own copy, and may not use every
+//! helper.
+//!
+
+#![allow(dead_code)]
+
+use rust_jni::{JavaVM, JNIEnv, JNIVersion};
+
+/// Gets the benchmark JVM, creating it on first use, and passes it to the provided closure
+pub fn with_vm<F>(f: F)
+    where
+        F: FnOnce(&JavaVM)
+{
+    let mut existing = JavaVM::get_existing(JNIVersion::Ver18).expect("Failed to get existing VMs");
+
+    let jvm = if !existing.is_empty() {
+        &mut existing[0]
+    } else {
+        let options = JavaVM::default_init_args(JNIVersion::Ver18).expect("Couldn't get default JVM args");
+        let (jvm, _) = JavaVM::create_with_options(options).expect("Couldn't create JVM");
+        Box::leak(Box::new(jvm))
+    };
+
+    f(jvm)
+}
+
+/// Gets the benchmark JVM, creating it on first use, and passes an attached environment to the
+/// provided closure
+pub fn with_env<F>(f: F)
+    where
+        F: FnOnce(&JNIEnv)
+{
+    with_vm(|jvm| {
+        let env = jvm.attach_current_thread().expect("Couldn't attach bench thread");
+        f(&env)
+    })
+}
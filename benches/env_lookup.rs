@@ -0,0 +1,42 @@
+//!
+//! Benchmarks comparing getting the current thread's environment from the JVM each time against
+//! the per-thread cache of [JavaVM::current_env].
+//!
+
+#![feature(test)]
+
+extern crate test;
+
+mod common;
+
+use rust_jni::JavaVM;
+use common::with_vm;
+use test::Bencher;
+
+const LOOKUPS: usize = 10_000;
+
+#[bench]
+fn bench_get_local_env(b: &mut Bencher) {
+    with_vm(|vm: &JavaVM| {
+        let _env = vm.attach_current_thread().expect("Couldn't attach bench thread");
+
+        b.iter(|| {
+            for _ in 0..LOOKUPS {
+                test::black_box(vm.get_local_env().unwrap());
+            }
+        });
+    })
+}
+
+#[bench]
+fn bench_current_env(b: &mut Bencher) {
+    with_vm(|vm: &JavaVM| {
+        let _env = vm.attach_current_thread().expect("Couldn't attach bench thread");
+
+        b.iter(|| {
+            for _ in 0..LOOKUPS {
+                test::black_box(vm.current_env().unwrap());
+            }
+        });
+    })
+}
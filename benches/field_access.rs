@@ -0,0 +1,60 @@
+//!
+//! Benchmarks comparing reads of an int field through JValue against the typed accessor.
+//!
+
+#![feature(test)]
+
+extern crate test;
+
+mod common;
+
+use rust_jni::{JNIEnv, JObject, JFieldID, JValue};
+use common::with_env;
+use test::Bencher;
+
+const READS: usize = 1_000_000;
+
+/// Create an Integer, and get the ID of the int field holding its value
+fn boxed_int<'a>(env: &JNIEnv<'a>) -> (JObject<'a>, JFieldID) {
+    let cls = env.find_class("java.lang.Integer").expect("Couldn't find Integer");
+    let value_of = env.get_static_method_id(&cls, "valueOf", "(int) -> java.lang.Integer")
+        .expect("Couldn't get valueOf");
+    let obj = env.call_static_method(&cls, &value_of, &vec![JValue::Int(1234)])
+        .expect("Couldn't box int")
+        .expect("Unexpected void result")
+        .into_obj()
+        .expect("Expected an object")
+        .expect("Unexpected null Integer");
+    let id = env.get_field_id(&cls, "value", "int").expect("Couldn't get value field");
+    (obj, id)
+}
+
+#[bench]
+fn bench_field_jvalue(b: &mut Bencher) {
+    with_env(|env| {
+        let (obj, id) = boxed_int(env);
+
+        b.iter(|| {
+            let mut sum = 0i64;
+            for _ in 0..READS {
+                sum += env.get_field(&obj, &id).unwrap().into_int().unwrap() as i64;
+            }
+            sum
+        });
+    })
+}
+
+#[bench]
+fn bench_field_typed(b: &mut Bencher) {
+    with_env(|env| {
+        let (obj, id) = boxed_int(env);
+
+        b.iter(|| {
+            let mut sum = 0i64;
+            for _ in 0..READS {
+                sum += env.get_field_int(&obj, &id).unwrap() as i64;
+            }
+            sum
+        });
+    })
+}
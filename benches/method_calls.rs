@@ -0,0 +1,48 @@
+//!
+//! Benchmarks comparing checked method calls against calls with prevalidated FFI arguments.
+//!
+
+#![feature(test)]
+
+extern crate test;
+
+mod common;
+
+use rust_jni::JValue;
+use common::with_env;
+use test::Bencher;
+
+const CALLS: usize = 10_000;
+
+#[bench]
+fn bench_call_checked(b: &mut Bencher) {
+    with_env(|env| {
+        let str = env.new_string_utf("benchmark string").expect("Couldn't create string");
+        let cls = env.get_object_class(str.as_object()).expect("Couldn't get string class");
+        let char_at = env.get_method_id(&cls, "charAt", "(int) -> char").expect("Couldn't get charAt");
+        let args = vec![JValue::Int(4)];
+
+        b.iter(|| {
+            for _ in 0..CALLS {
+                test::black_box(env.call_method(str.as_object(), &char_at, &args).unwrap());
+            }
+        });
+    })
+}
+
+#[bench]
+fn bench_call_prevalidated(b: &mut Bencher) {
+    with_env(|env| {
+        let str = env.new_string_utf("benchmark string").expect("Couldn't create string");
+        let cls = env.get_object_class(str.as_object()).expect("Couldn't get string class");
+        let char_at = env.get_method_id(&cls, "charAt", "(int) -> char").expect("Couldn't get charAt");
+        let args = JValue::make_ffi_vec(&[JValue::Int(4)]);
+
+        b.iter(|| {
+            for _ in 0..CALLS {
+                // SAFETY: charAt takes a single int
+                test::black_box(unsafe { env.call_method_prevalidated(str.as_object(), &char_at, &args) }.unwrap());
+            }
+        });
+    })
+}
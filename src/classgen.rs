@@ -0,0 +1,454 @@
+//!
+//! Module containing a minimal class file writer, for generating small throwaway classes to pass to
+//! [define_class][crate::env::JNIEnv::define_class] in tests. It only emits classes with fields,
+//! native methods and an optional trivial constructor, not arbitrary method bodies. The crate also
+//! uses it internally, for small helper classes backed by native methods.
+//!
+
+use std::collections::HashMap;
+
+use crate::mutf8::modified_utf8;
+
+/// Magic number starting every class file
+const MAGIC: u32 = 0xCAFE_BABE;
+/// Class file major version emitted, Java 8. This is the newest version that doesn't need stack
+/// map frames, and is accepted by every JVM this crate supports
+const MAJOR_VERSION: u16 = 52;
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_STATIC: u16 = 0x0008;
+const ACC_FINAL: u16 = 0x0010;
+const ACC_SUPER: u16 = 0x0020;
+const ACC_NATIVE: u16 = 0x0100;
+
+const OP_ALOAD_0: u8 = 0x2a;
+const OP_INVOKESPECIAL: u8 = 0xb7;
+const OP_RETURN: u8 = 0xb1;
+
+///
+/// A compile-time constant value for a static final field, stored with a `ConstantValue` attribute
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClassConstant {
+    /// A boolean constant
+    Boolean(bool),
+    /// A byte constant
+    Byte(i8),
+    /// A char constant, as a UTF-16 code unit
+    Char(u16),
+    /// A short constant
+    Short(i16),
+    /// An int constant
+    Int(i32),
+    /// A long constant
+    Long(i64),
+    /// A float constant
+    Float(f32),
+    /// A double constant
+    Double(f64),
+    /// A `java.lang.String` constant
+    String(String)
+}
+
+impl ClassConstant {
+
+    /// Get the field descriptor of a field holding this constant
+    pub fn descriptor(&self) -> &'static str {
+        match self {
+            ClassConstant::Boolean(_) => "Z",
+            ClassConstant::Byte(_) => "B",
+            ClassConstant::Char(_) => "C",
+            ClassConstant::Short(_) => "S",
+            ClassConstant::Int(_) => "I",
+            ClassConstant::Long(_) => "J",
+            ClassConstant::Float(_) => "F",
+            ClassConstant::Double(_) => "D",
+            ClassConstant::String(_) => "Ljava/lang/String;"
+        }
+    }
+}
+
+/// A single constant pool entry
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum PoolEntry {
+    Utf8(Vec<u8>),
+    Integer(i32),
+    Float(u32),
+    Long(i64),
+    Double(u64),
+    Class(u16),
+    String(u16),
+    MethodRef(u16, u16),
+    NameAndType(u16, u16)
+}
+
+/// Constant pool under construction, sharing equal entries
+#[derive(Default)]
+struct ConstantPool {
+    entries: Vec<PoolEntry>,
+    indices: HashMap<PoolEntry, u16>,
+    /// Next free index. Pool indices start at 1, and longs and doubles take two slots
+    next: u16
+}
+
+impl ConstantPool {
+
+    fn new() -> ConstantPool {
+        ConstantPool {
+            next: 1,
+            ..ConstantPool::default()
+        }
+    }
+
+    fn add(&mut self, entry: PoolEntry) -> u16 {
+        if let Some(&idx) = self.indices.get(&entry) {
+            return idx
+        }
+
+        let idx = self.next;
+        self.next += match entry {
+            PoolEntry::Long(_) | PoolEntry::Double(_) => 2,
+            _ => 1
+        };
+        self.indices.insert(entry.clone(), idx);
+        self.entries.push(entry);
+        idx
+    }
+
+    fn utf8(&mut self, str: &str) -> u16 {
+        self.add(PoolEntry::Utf8(modified_utf8(str)))
+    }
+
+    fn class(&mut self, name: &str) -> u16 {
+        let name = self.utf8(&internal_name(name));
+        self.add(PoolEntry::Class(name))
+    }
+
+    fn method_ref(&mut self, class: &str, name: &str, desc: &str) -> u16 {
+        let class = self.class(class);
+        let name = self.utf8(name);
+        let desc = self.utf8(desc);
+        let nat = self.add(PoolEntry::NameAndType(name, desc));
+        self.add(PoolEntry::MethodRef(class, nat))
+    }
+
+    fn constant(&mut self, val: &ClassConstant) -> u16 {
+        let entry = match val {
+            ClassConstant::Boolean(val) => PoolEntry::Integer(*val as i32),
+            ClassConstant::Byte(val) => PoolEntry::Integer(*val as i32),
+            ClassConstant::Char(val) => PoolEntry::Integer(*val as i32),
+            ClassConstant::Short(val) => PoolEntry::Integer(*val as i32),
+            ClassConstant::Int(val) => PoolEntry::Integer(*val),
+            ClassConstant::Long(val) => PoolEntry::Long(*val),
+            ClassConstant::Float(val) => PoolEntry::Float(val.to_bits()),
+            ClassConstant::Double(val) => PoolEntry::Double(val.to_bits()),
+            ClassConstant::String(val) => {
+                let str = self.utf8(val);
+                PoolEntry::String(str)
+            }
+        };
+        self.add(entry)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        write_u16(out, self.next);
+        for entry in &self.entries {
+            match entry {
+                PoolEntry::Utf8(bytes) => {
+                    out.push(1);
+                    write_u16(out, bytes.len() as u16);
+                    out.extend_from_slice(bytes);
+                }
+                PoolEntry::Integer(val) => {
+                    out.push(3);
+                    out.extend_from_slice(&val.to_be_bytes());
+                }
+                PoolEntry::Float(bits) => {
+                    out.push(4);
+                    out.extend_from_slice(&bits.to_be_bytes());
+                }
+                PoolEntry::Long(val) => {
+                    out.push(5);
+                    out.extend_from_slice(&val.to_be_bytes());
+                }
+                PoolEntry::Double(bits) => {
+                    out.push(6);
+                    out.extend_from_slice(&bits.to_be_bytes());
+                }
+                PoolEntry::Class(name) => {
+                    out.push(7);
+                    write_u16(out, *name);
+                }
+                PoolEntry::String(str) => {
+                    out.push(8);
+                    write_u16(out, *str);
+                }
+                PoolEntry::MethodRef(class, nat) => {
+                    out.push(10);
+                    write_u16(out, *class);
+                    write_u16(out, *nat);
+                }
+                PoolEntry::NameAndType(name, desc) => {
+                    out.push(12);
+                    write_u16(out, *name);
+                    write_u16(out, *desc);
+                }
+            }
+        }
+    }
+}
+
+/// A native method declared by a generated class
+struct NativeDecl {
+    name: String,
+    descriptor: String,
+    is_static: bool
+}
+
+/// A field declared by a generated class
+struct FieldDecl {
+    name: String,
+    descriptor: String,
+    is_static: bool,
+    constant: Option<ClassConstant>
+}
+
+///
+/// A builder for a minimal class file. Classes are public, extend `java.lang.Object` unless told
+/// otherwise, and have a public no-argument constructor that only calls the superclass's. Fields
+/// are public, and types are given as JVM field descriptors, such as `I` or `Ljava/lang/String;`.
+///
+/// ```
+/// # #[cfg(feature = "classgen")] {
+/// use rust_jni::classgen::{ClassFileBuilder, ClassConstant};
+///
+/// let bytes = ClassFileBuilder::new("Example")
+///     .field("x", "I")
+///     .constant("LIMIT", ClassConstant::Long(10))
+///     .build();
+/// assert_eq!(&bytes[..4], b"\xca\xfe\xba\xbe");
+/// # }
+/// ```
+///
+pub struct ClassFileBuilder {
+    name: String,
+    superclass: String,
+    constructor: bool,
+    interfaces: Vec<String>,
+    fields: Vec<FieldDecl>,
+    natives: Vec<NativeDecl>
+}
+
+impl ClassFileBuilder {
+
+    /// Start a new class with the given name, in either `a.b.C` or `a/b/C` form
+    pub fn new(name: &str) -> ClassFileBuilder {
+        ClassFileBuilder {
+            name: String::from(name),
+            superclass: String::from("java.lang.Object"),
+            constructor: true,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            natives: Vec::new()
+        }
+    }
+
+    /// Set the superclass of this class. It must have an accessible no-argument constructor,
+    /// unless the constructor is disabled
+    pub fn superclass(mut self, name: &str) -> Self {
+        self.superclass = String::from(name);
+        self
+    }
+
+    /// Add an interface this class implements. Its methods must be provided, such as by native
+    /// methods
+    pub fn interface(mut self, name: &str) -> Self {
+        self.interfaces.push(String::from(name));
+        self
+    }
+
+    /// Set whether to generate the no-argument constructor. Without it, instances can only be
+    /// made with `alloc_object`
+    pub fn constructor(mut self, constructor: bool) -> Self {
+        self.constructor = constructor;
+        self
+    }
+
+    /// Add a public instance field
+    pub fn field(mut self, name: &str, descriptor: &str) -> Self {
+        self.fields.push(FieldDecl {
+            name: String::from(name),
+            descriptor: String::from(descriptor),
+            is_static: false,
+            constant: None
+        });
+        self
+    }
+
+    /// Add a public static field, initially zero or null
+    pub fn static_field(mut self, name: &str, descriptor: &str) -> Self {
+        self.fields.push(FieldDecl {
+            name: String::from(name),
+            descriptor: String::from(descriptor),
+            is_static: true,
+            constant: None
+        });
+        self
+    }
+
+    /// Add a public static final field, initialized to a constant value when the class is
+    /// prepared
+    pub fn constant(mut self, name: &str, val: ClassConstant) -> Self {
+        self.fields.push(FieldDecl {
+            name: String::from(name),
+            descriptor: String::from(val.descriptor()),
+            is_static: true,
+            constant: Some(val)
+        });
+        self
+    }
+
+    /// Add a public native method, with a JVM method descriptor such as `(I)V`. Its
+    /// implementation must be registered before it's called
+    pub fn native_method(mut self, name: &str, descriptor: &str, is_static: bool) -> Self {
+        self.natives.push(NativeDecl {
+            name: String::from(name),
+            descriptor: String::from(descriptor),
+            is_static
+        });
+        self
+    }
+
+    /// Write out the class file
+    pub fn build(&self) -> Vec<u8> {
+        let mut pool = ConstantPool::new();
+
+        let this_class = pool.class(&self.name);
+        let super_class = pool.class(&self.superclass);
+        let interfaces: Vec<u16> = self.interfaces.iter()
+            .map(|name| pool.class(name))
+            .collect();
+
+        let mut fields = Vec::new();
+        write_u16(&mut fields, self.fields.len() as u16);
+        for field in &self.fields {
+            let mut flags = ACC_PUBLIC;
+            if field.is_static {
+                flags |= ACC_STATIC;
+            }
+            if field.constant.is_some() {
+                flags |= ACC_FINAL;
+            }
+
+            write_u16(&mut fields, flags);
+            write_u16(&mut fields, pool.utf8(&field.name));
+            write_u16(&mut fields, pool.utf8(&field.descriptor));
+
+            match &field.constant {
+                Some(val) => {
+                    let attr_name = pool.utf8("ConstantValue");
+                    let val = pool.constant(val);
+                    write_u16(&mut fields, 1);
+                    write_u16(&mut fields, attr_name);
+                    fields.extend_from_slice(&2u32.to_be_bytes());
+                    write_u16(&mut fields, val);
+                }
+                None => write_u16(&mut fields, 0)
+            }
+        }
+
+        let mut methods = Vec::new();
+        write_u16(&mut methods, self.natives.len() as u16 + self.constructor as u16);
+        for native in &self.natives {
+            let mut flags = ACC_PUBLIC | ACC_NATIVE;
+            if native.is_static {
+                flags |= ACC_STATIC;
+            }
+
+            write_u16(&mut methods, flags);
+            write_u16(&mut methods, pool.utf8(&native.name));
+            write_u16(&mut methods, pool.utf8(&native.descriptor));
+            // Native methods have no code
+            write_u16(&mut methods, 0);
+        }
+
+        if self.constructor {
+            let name = pool.utf8("<init>");
+            let desc = pool.utf8("()V");
+            let code_name = pool.utf8("Code");
+            let super_init = pool.method_ref(&self.superclass, "<init>", "()V");
+
+            let mut code = vec![OP_ALOAD_0, OP_INVOKESPECIAL];
+            write_u16(&mut code, super_init);
+            code.push(OP_RETURN);
+
+            write_u16(&mut methods, ACC_PUBLIC);
+            write_u16(&mut methods, name);
+            write_u16(&mut methods, desc);
+            write_u16(&mut methods, 1);
+            write_u16(&mut methods, code_name);
+            // max_stack, max_locals, code_length, code, no exception table or attributes
+            let attr_len = 2 + 2 + 4 + code.len() + 2 + 2;
+            methods.extend_from_slice(&(attr_len as u32).to_be_bytes());
+            write_u16(&mut methods, 1);
+            write_u16(&mut methods, 1);
+            methods.extend_from_slice(&(code.len() as u32).to_be_bytes());
+            methods.extend_from_slice(&code);
+            write_u16(&mut methods, 0);
+            write_u16(&mut methods, 0);
+        }
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&MAGIC.to_be_bytes());
+        write_u16(&mut out, 0);
+        write_u16(&mut out, MAJOR_VERSION);
+        pool.write(&mut out);
+        write_u16(&mut out, ACC_PUBLIC | ACC_SUPER);
+        write_u16(&mut out, this_class);
+        write_u16(&mut out, super_class);
+        write_u16(&mut out, interfaces.len() as u16);
+        for interface in interfaces {
+            write_u16(&mut out, interface);
+        }
+        out.extend_from_slice(&fields);
+        out.extend_from_slice(&methods);
+        // No class attributes
+        write_u16(&mut out, 0);
+        out
+    }
+}
+
+fn write_u16(out: &mut Vec<u8>, val: u16) {
+    out.extend_from_slice(&val.to_be_bytes());
+}
+
+/// Convert a class name to the internal form, with `/` separating packages
+fn internal_name(name: &str) -> String {
+    name.replace('.', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_class() {
+        let bytes = ClassFileBuilder::new("a.Empty").constructor(false).build();
+        let expected: &[u8] = b"\xca\xfe\xba\xbe\x00\x00\x00\x34\x00\x05\
+\x01\x00\x07a/Empty\x07\x00\x01\x01\x00\x10java/lang/Object\x07\x00\x03\
+\x00\x21\x00\x02\x00\x04\x00\x00\x00\x00\x00\x00\x00\x00";
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_constant_pool_sharing() {
+        let mut pool = ConstantPool::new();
+        let first = pool.utf8("I");
+        let long = pool.constant(&ClassConstant::Long(1));
+        let after = pool.utf8("J");
+        assert_eq!(pool.utf8("I"), first);
+        assert_eq!(long, 2);
+        assert_eq!(after, 4);
+        assert_eq!(pool.constant(&ClassConstant::Int(1)), pool.constant(&ClassConstant::Boolean(true)));
+    }
+}
@@ -4,18 +4,71 @@
 //! many of the return-type specific functions into single functions using enums
 //!
 
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::convert::{Infallible, TryFrom};
 use std::ffi::CString;
+use std::marker::PhantomData;
+use std::rc::Rc;
 use std::slice;
+use std::sync::Mutex;
 
 use crate::{ffi, JNativeType, JNativeArray, JNativeSlice, ReleaseMode, JNativeVec};
-use crate::ffi::constants::JNI_ERR;
-use crate::types::{JNIVersion, JType, JValue, JObject, JClass, JMethodID, JFieldID, JThrowable, JString, JArray, JObjectArray, JavaDownCast, JNonVoidType, JNINativeMethod, JavaUpCast};
+use crate::ffi::constants::{JNI_ERR, JNI_FALSE};
+use crate::types::{JNIVersion, JType, JValue, NullPolicy, JObject, JClass, JMethodID, JFieldID, JGlobalRef, JGlobal, JThrowable, JString, JArray, JObjectArray, JBooleanArray, JByteArray, JCharArray, JShortArray, JIntArray, JLongArray, JFloatArray, JDoubleArray, JavaDownCast, JavaClassType, JavaPrimitive, JNonVoidType, JNINativeMethod, JavaUpCast};
 use crate::error::{Error, Result};
 use crate::mangling::{mangle_class, TypeSignature};
-use crate::vm::JavaVM;
+use crate::mutf8::{mutf8_from_str, units_from_mutf8};
+use crate::vm::{JavaVM, vm_destroyed};
 use crate::types::jtype::JRefType;
 use crate::types::object::JWeak;
-
+use crate::{get_cls, get_method_id, get_static_method_id};
+
+pub use stats::LocalRefStats;
+pub use call::{MethodCall, IntoJava, FromJava};
+pub use batch::FieldBatch;
+pub use cached::CachedCall;
+pub use monitor::MonitorGuard;
+pub use frame::LocalFrame;
+pub use auto_local::AutoLocal;
+#[cfg(feature = "arrays-ext")]
+pub use arrays::ArrayElements;
+
+mod stats;
+mod call;
+mod batch;
+mod cached;
+mod monitor;
+mod frame;
+mod auto_local;
+mod threads;
+mod functional;
+mod fields;
+mod unload;
+
+// Convenience layers, which the core API doesn't depend on
+
+#[cfg(feature = "arrays-ext")]
+mod arrays;
+#[cfg(feature = "strings-ext")]
+mod strings;
+#[cfg(feature = "collections")]
+mod collections;
+#[cfg(feature = "reflect")]
+mod reflect;
+#[cfg(feature = "buffers")]
+mod buffers;
+
+/// `java.lang.reflect.Modifier` flag for public members and classes
+const MODIFIER_PUBLIC: i32 = 0x0001;
+/// `java.lang.reflect.Modifier` flag for static members
+const MODIFIER_STATIC: i32 = 0x0008;
+/// `java.lang.reflect.Modifier` flag for final members and classes
+const MODIFIER_FINAL: i32 = 0x0010;
+/// `java.lang.reflect.Modifier` flag for interfaces
+const MODIFIER_INTERFACE: i32 = 0x0200;
+/// `java.lang.reflect.Modifier` flag for abstract classes and methods
+const MODIFIER_ABSTRACT: i32 = 0x0400;
 
 /// Handy utility for converting a `&str` into a `CString`, returning a rust_jni error on failure
 fn cstr_from_str(str: &str) -> Result<CString> {
@@ -25,36 +78,224 @@ fn cstr_from_str(str: &str) -> Result<CString> {
         })
 }
 
+/// Decode UTF-16 code units from Java into chars, combining surrogate pairs. Returns Err naming
+/// the index of the first unpaired surrogate, as Java strings can legally contain them
+fn decode_utf16(units: &[u16]) -> Result<Vec<char>> {
+    let mut idx = 0;
+    let mut out = Vec::with_capacity(units.len());
+    for c in std::char::decode_utf16(units.iter().copied()) {
+        match c {
+            Ok(c) => {
+                idx += c.len_utf16();
+                out.push(c);
+            }
+            Err(err) => return Err(Error::new(
+                &format!("Unpaired surrogate 0x{:04X} at index {}", err.unpaired_surrogate(), idx),
+                JNI_ERR
+            ))
+        }
+    }
+    Ok(out)
+}
+
+/// Decode a single Java char, which is Err if it's a lone surrogate
+fn decode_char(unit: u16) -> Result<char> {
+    std::char::from_u32(unit as u32)
+        .ok_or_else(|| Error::new(&format!("Java char 0x{:04X} is an unpaired surrogate", unit), JNI_ERR))
+}
+
+
+thread_local! {
+    /// Counter bumped every time the current thread detaches from the JVM. Environments record the
+    /// value they were created under, so use after a detach can be detected
+    static ATTACH_GENERATION: Cell<u64> = Cell::new(0);
+    /// Number of calls into Java made through the wrapper currently running on this thread
+    static CALL_DEPTH: Cell<usize> = Cell::new(0);
+    /// Most calls into Java allowed to be running on this thread at once, if limited
+    static CALL_DEPTH_LIMIT: Cell<Option<usize>> = Cell::new(None);
+    /// Signatures parsed by environments on this thread, keyed by the form they were passed in
+    static SIGNATURE_CACHE: RefCell<HashMap<String, Rc<ParsedSignature>>> = RefCell::new(HashMap::new());
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Number of signatures parsed on this thread, rather than found in the signature cache
+    static SIGNATURE_PARSES: Cell<usize> = Cell::new(0);
+}
+
+/// Generate a method reading a region of one primitive array type into an existing buffer
+macro_rules! region_readers {
+    ($name:ident, $arr_ty:ident, $elem_ty:ty, $ffi_get:ident, $desc:literal $(, note $note:literal)?) => {
+        #[doc = concat!("Read a region of ", $desc, " into an existing buffer, filling it from `start`. Avoids ")]
+        #[doc = "allocating when reading many regions, see"]
+        #[doc = concat!("[get_native_array_region][Self::get_native_array_region] for the allocating form", $(". ", $note)?)]
+        pub fn $name(&self, arr: &$arr_ty, start: usize, dst: &mut [$elem_ty]) -> Result<()> {
+            self.check_array_region(arr.downcast(), start, dst.len())?;
+            let env = self.internal_env();
+
+            // SAFETY: Internal pointer use, the buffer holds exactly the region's length
+            unsafe {
+                env.$ffi_get(arr.borrow_ptr(), start as i32, dst.len() as i32, dst.as_mut_ptr());
+            }
+
+            if self.exception_check() {
+                Err(Error::new("Couldn't get array region", JNI_ERR))
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The newest class file version the JVM supports and its Java version, once read by
+/// [JNIEnv::define_class]. These can't change while the JVM runs
+static CLASS_VERSION_SUPPORT: Mutex<Option<(Option<u16>, String)>> = Mutex::new(None);
+
+/// Most signatures cached per thread. Once full the cache is cleared, so one-off lookups of many
+/// different signatures can't grow it without bound
+const SIGNATURE_CACHE_SIZE: usize = 256;
+
+/// A signature parsed from the syntax defined in the root documentation, along with its mangled
+/// form
+struct ParsedSignature {
+    sig: TypeSignature,
+    mangled: String
+}
+
+/// Get the attach generation of the current thread
+pub(crate) fn attach_generation() -> u64 {
+    ATTACH_GENERATION.with(|gen| gen.get())
+}
+
+/// Mark the current thread as detached, invalidating any environments created before now
+pub(crate) fn bump_attach_generation() {
+    ATTACH_GENERATION.with(|gen| gen.set(gen.get().wrapping_add(1)))
+}
+
+
+/// A call into Java counted towards the thread's call depth, until dropped
+struct CallDepthGuard;
+
+impl Drop for CallDepthGuard {
+    fn drop(&mut self) {
+        CALL_DEPTH.with(|depth| depth.set(depth.get() - 1))
+    }
+}
+
 
-/// Higher-level construct representing a JNIEnv
-pub struct JNIEnv {
+/// Higher-level construct representing a JNIEnv. The `'env` lifetime is the scope the environment's
+/// local references are valid for, such as the native call it was passed to, and every object
+/// created through it is bounded by that scope rather than by a borrow of the environment.
+///
+/// Objects can't be given a longer lifetime than the environment's scope:
+///
+/// ```compile_fail
+/// use rust_jni::{JNIEnv, JClass};
+///
+/// fn escape<'a>(env: &JNIEnv<'a>) -> JClass<'static> {
+///     env.find_class("java.lang.Object").unwrap()
+/// }
+/// ```
+///
+/// Nor can a native function return them through the lifetime of an unrelated parameter:
+///
+/// ```compile_fail
+/// use rust_jni::{JNIEnv, JObject, JavaDownCast};
+///
+/// fn native<'a, 'b>(env: &JNIEnv<'a>, _this: JObject<'b>) -> JObject<'b> {
+///     env.new_string_utf("escaped").unwrap().downcast()
+/// }
+/// ```
+///
+/// And objects from an attached thread can't outlive the VM they were attached through:
+///
+/// ```compile_fail
+/// use rust_jni::{JavaVM, JNIVersion};
+///
+/// let cls = {
+///     let vm = JavaVM::get_existing(JNIVersion::Ver18).unwrap().remove(0);
+///     let env = vm.attach_current_thread().unwrap();
+///     env.find_class("java.lang.Object").unwrap()
+/// };
+/// ```
+///
+/// Objects may outlive the borrow of the environment that made them though, as long as they stay
+/// within its scope:
+///
+/// ```no_run
+/// use rust_jni::{JNIEnv, JClass, JObject};
+///
+/// fn native<'a>(env: &JNIEnv<'a>, _this: JObject<'a>) -> JClass<'a> {
+///     let cls = {
+///         let borrowed = env;
+///         borrowed.find_class("java.lang.Object").unwrap()
+///     };
+///     cls
+/// }
+/// ```
+pub struct JNIEnv<'env> {
     version: JNIVersion,
-    backing_ptr: *mut ffi::JNIEnv
+    backing_ptr: *mut ffi::JNIEnv,
+    #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+    generation: u64,
+    scope: PhantomData<&'env ()>
 }
 
-impl JNIEnv {
+/// Cloning an environment gives another handle to the same thread's environment, for storing
+/// alongside other state. Like the original, a clone can't leave the thread it was made on, and
+/// shares all of the original's state, such as local reference accounting and cached IDs.
+impl Clone for JNIEnv<'_> {
+    fn clone(&self) -> Self {
+        JNIEnv {
+            version: self.version,
+            backing_ptr: self.backing_ptr,
+            #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+            generation: self.generation,
+            scope: PhantomData
+        }
+    }
+}
+
+impl<'env> JNIEnv<'env> {
 
     /// Create a new JNIEnv from a pointer to an [ffi::JNIEnv]. This environment will
     /// live as long as the current thread, generally. Thus this type is not marked Send or Sync.
-    pub fn new(env: *mut ffi::JNIEnv) -> Result<JNIEnv> {
+    pub fn new(env: *mut ffi::JNIEnv) -> Result<JNIEnv<'env>> {
         if env.is_null() {
             Err(Error::new_null("JNIEnv Constructor"))
         } else {
             // SAFETY: Pointer is definitely not null here
-            let version;
+            let raw_version;
             unsafe {
-                version = <*mut ffi::JNIEnv>::as_ref(env)
+                raw_version = <*mut ffi::JNIEnv>::as_ref(env)
                     .expect("Couldn't get ref to checked pionter")
-                    .get_version()
-                    .into();
+                    .get_version();
             }
+            // Newer JVMs than this crate knows still gate features by the newest version it knows
+            let version = JNIVersion::at_most(raw_version)
+                .ok_or_else(|| Error::new_unsupported(&format!("Unknown JNI version 0x{:08X}", raw_version)))?;
             Ok(JNIEnv {
                 version,
-                backing_ptr: env
+                backing_ptr: env,
+                #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+                generation: attach_generation(),
+                scope: PhantomData
             })
         }
     }
 
+    /// Build an environment from a pointer and version already known to be valid for the current
+    /// thread, skipping the version lookup
+    pub(crate) fn from_cached(env: *mut ffi::JNIEnv, version: JNIVersion) -> JNIEnv<'env> {
+        JNIEnv {
+            version,
+            backing_ptr: env,
+            #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+            generation: attach_generation(),
+            scope: PhantomData
+        }
+    }
+
     /// Get the backing environment pointer
     pub unsafe fn borrow_ptr(&self) -> *mut ffi::JNIEnv {
         self.backing_ptr
@@ -63,6 +304,14 @@ impl JNIEnv {
     /// Non public way to get a reference to the internal environment. Not unsafe only because
     /// it's not public.
     fn internal_env(&self) -> &ffi::JNIEnv {
+        #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+        {
+            let current = attach_generation();
+            if current != self.generation {
+                panic!("JNIEnv used after its thread was detached (created gen {}, now {})", self.generation, current)
+            }
+        }
+
         // SAFETY: The real_env pointer is private, and only set to non-null values in checked locations
         unsafe {
             if let Some(env) = self.backing_ptr.as_ref() {
@@ -73,36 +322,154 @@ impl JNIEnv {
         }
     }
 
-    /// Get the version of the associated JVM
-    pub fn get_version(&self) -> JNIVersion {
+    /// Funnel for every local reference the wrapper receives from the JVM, so it can be counted
+    /// for leak checking
+    fn track_local<T>(&self, ptr: *mut T) -> *mut T {
+        if !ptr.is_null() {
+            stats::record_created();
+        }
+        ptr
+    }
+
+    /// Get statistics on the local references created and deleted through the wrapper on this
+    /// thread. Only counted with the `leak-check` or `runtime-checks` features
+    pub fn local_ref_stats(&self) -> LocalRefStats {
+        stats::snapshot()
+    }
+
+    /// Reset the local reference statistics for this thread back to zero
+    pub fn reset_stats(&self) {
+        stats::reset()
+    }
+
+    /// Get the version of the associated JVM. Returns Err if the JVM reports a version
+    /// [JNIVersion] doesn't know
+    pub fn get_version(&self) -> Result<JNIVersion> {
         let env = self.internal_env();
-        JNIVersion::from(env.get_version())
+        JNIVersion::try_from(env.get_version())
     }
 
     /// Define a new JVM class. The class will have the given name and be owned by the given loader,
-    /// created from the passed byte buffer.
-    pub fn define_class(&self, name: &str, loader: &JObject, buffer: &[u8]) -> Result<JClass> {
+    /// created from the passed byte buffer. If the JVM rejects the class, such as with a
+    /// `ClassFormatError` or `VerifyError`, the error is cleared and returned as an
+    /// [Error::Exception]. Class files newer than the JVM supports are an Err before reaching the
+    /// JVM, naming both versions
+    pub fn define_class(&self, name: &str, loader: &JObject, buffer: &[u8]) -> Result<JClass<'env>> {
+        self.check_class_version(name, buffer)?;
+
         let env = self.internal_env();
-        let name = cstr_from_str(name)?;
+        let c_name = cstr_from_str(name)?;
 
         // SAFETY: Internal pointer use
         let new_cls = unsafe {
-            env.define_class(name.as_ptr(), loader.borrow_ptr(), buffer.as_ptr() as _, buffer.len() as i32)
+            self.track_local(env.define_class(c_name.as_ptr(), loader.borrow_ptr(), buffer.as_ptr() as _, buffer.len() as i32))
         };
 
         if new_cls.is_null() {
-            Err(Error::new("Could not define new Java Class", JNI_ERR))
+            Err(self.call_exception_error())
         } else {
             Ok(JClass::new(new_cls)?)
         }
     }
 
+    /// Get the system class loader, as `ClassLoader.getSystemClassLoader` would. This is the loader
+    /// classes on the application class path are defined by
+    pub fn system_class_loader(&self) -> Result<JObject<'env>> {
+        let ldr_cls = get_cls!(self, "java.lang.ClassLoader");
+        let get_ldr = get_static_method_id!(self, ldr_cls, "getSystemClassLoader", "() -> java.lang.ClassLoader");
+
+        self.call_static_method(ldr_cls, get_ldr, &vec![])?
+            .expect("Unexpected void result")
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("System class loader"))
+    }
+
+    /// Define a new JVM class owned by the [system class loader][Self::system_class_loader], as
+    /// [define_class][Self::define_class] does
+    pub fn define_class_in_system_loader(&self, name: &str, buffer: &[u8]) -> Result<JClass<'env>> {
+        let loader = self.system_class_loader()?;
+        let cls = self.define_class(name, &loader, buffer);
+        self.delete_local_ref(loader);
+        cls
+    }
+
+    /// Check the class file version of a class is one the running JVM supports. Buffers that
+    /// aren't class files are left for the JVM to reject
+    fn check_class_version(&self, name: &str, buffer: &[u8]) -> Result<()> {
+        if buffer.len() < 8 || buffer[..4] != [0xCA, 0xFE, 0xBA, 0xBE] {
+            return Ok(())
+        }
+        let major = u16::from_be_bytes([buffer[6], buffer[7]]);
+
+        let (supported, java) = self.class_version_support()?;
+        match supported {
+            Some(supported) if major > supported => Err(Error::new(
+                &format!(
+                    "Class {} has class file version {}, but the JVM (Java {}) only supports up to version {}",
+                    name, major, java, supported
+                ),
+                JNI_ERR
+            )),
+            _ => Ok(())
+        }
+    }
+
+    /// Get the newest class file major version the JVM supports, if it reports one, along with its
+    /// Java version. Both are read from system properties on first use, then reused
+    fn class_version_support(&self) -> Result<(Option<u16>, String)> {
+        let mut support = CLASS_VERSION_SUPPORT.lock().expect("Class version lock poisoned");
+        if let Some(support) = &*support {
+            return Ok(support.clone())
+        }
+
+        // java.class.version is the newest class file version supported, such as "61.0"
+        let supported = self.system_property("java.class.version")?
+            .and_then(|ver| ver.split('.').next()?.parse::<u16>().ok());
+        let java = self.system_property("java.version")?.unwrap_or_else(|| String::from("unknown"));
+        *support = Some((supported, java.clone()));
+        Ok((supported, java))
+    }
+
+    /// Read a Java system property, as `System.getProperty` would
+    fn system_property(&self, name: &str) -> Result<Option<String>> {
+        let sys_cls = get_cls!(self, "java.lang.System");
+        let get_prop = get_static_method_id!(self, sys_cls, "getProperty", "(java.lang.String) -> java.lang.String");
+
+        let key = self.new_string_utf(name)?;
+        let val = self.call_static_method(sys_cls, get_prop, &vec![(&key).downcast().into()])?
+            .expect("Unexpected void result")
+            .into_obj()?;
+        self.delete_local_ref(key.downcast());
+
+        match val {
+            Some(val) => {
+                // SAFETY: Guaranteed safe upcast, getProperty returns a String
+                let val = unsafe { val.upcast_raw() };
+                let out = self.get_string_chars(&val)?.into_iter().collect();
+                self.delete_local_ref(val.downcast());
+                Ok(Some(out))
+            }
+            None => Ok(None)
+        }
+    }
+
+    /// Define a new JVM class as [define_class][Self::define_class] does, but first pass the bytes
+    /// to a verifier, such as a format check by a bytecode generator. If the verifier returns Err
+    /// the class isn't defined, and the verifier's error is returned
+    pub fn define_class_with<F>(&self, name: &str, loader: &JObject, buffer: &[u8], verify: F) -> Result<JClass<'env>>
+        where
+            F: FnOnce(&[u8]) -> Result<()>
+    {
+        verify(buffer)?;
+        self.define_class(name, loader, buffer)
+    }
+
     /// Find an existing class by name. The passed name should consist only of ASCII characters
-    pub fn find_class(&self, name: &str) -> Result<JClass> {
+    pub fn find_class(&self, name: &str) -> Result<JClass<'env>> {
         let env = self.internal_env();
-        let c_name = cstr_from_str(&mangle_class(name).mangled())?;
+        let c_name = cstr_from_str(&self.parse_signature(name).mangled)?;
 
-        let new_cls = env.find_class(c_name.as_ptr());
+        let new_cls = self.track_local(env.find_class(c_name.as_ptr()));
         if new_cls.is_null() {
             Err(Error::new(&format!("Could not find Java Class {}", name), JNI_ERR))
         } else {
@@ -122,16 +489,13 @@ impl JNIEnv {
         // SAFETY: Internal pointer use
         let id = unsafe { env.from_reflected_method(method.borrow_ptr()) };
 
-        let ret_cls = self.call_method(method, &get_ret, &vec![])?
-            .expect("Unexpected void result")
+        let ret_cls = self.call_value_method(method, &get_ret, &vec![])?
             .into_obj()?
             .expect("Unexpected null result");
-        let ret_name = self.call_method(&ret_cls, &get_name, &vec![])?
-            .expect("Unexpected void result")
+        let ret_name = self.call_value_method(&ret_cls, &get_name, &vec![])?
             .into_obj()?
             .expect("Unexpected null result");
-        let num_args = self.call_method(method, &get_num_args, &vec![])?
-            .expect("Unexpected void result")
+        let num_args = self.call_value_method(method, &get_num_args, &vec![])?
             .into_int()? as usize;
 
         // SAFETY: Guaranteed safe upcast, we know the type
@@ -157,12 +521,10 @@ impl JNIEnv {
         // SAFETY: Internal pointer use
         let id = unsafe { env.from_reflected_field(field.borrow_ptr()) };
 
-        let ty_cls = self.call_method(field, &get_ty, &vec![])?
-            .expect("Unexpected void result")
+        let ty_cls = self.call_value_method(field, &get_ty, &vec![])?
             .into_obj()?
             .expect("Unexpected null result");
-        let ty_name = self.call_method(&ty_cls, &get_name, &vec![])?
-            .expect("Unexpected void result")
+        let ty_name = self.call_value_method(&ty_cls, &get_name, &vec![])?
             .into_obj()?
             .expect("Unexpected null result");
 
@@ -181,12 +543,12 @@ impl JNIEnv {
     /// Build a reflected Method object from a class, method ID, and static-ness
     ///
     /// TODO: Maybe make is_static part of IDs?
-    pub fn to_reflected_method(&self, cls: &JClass, id: &JMethodID, is_static: bool) -> Result<JObject> {
+    pub fn to_reflected_method(&self, cls: &JClass, id: &JMethodID, is_static: bool) -> Result<JObject<'env>> {
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
         let obj = unsafe {
-            env.to_reflected_method(cls.borrow_ptr(), id.borrow_ptr(), is_static.into())
+            self.track_local(env.to_reflected_method(cls.borrow_ptr(), id.borrow_ptr(), is_static.into()))
         };
 
         if obj.is_null() {
@@ -199,12 +561,12 @@ impl JNIEnv {
     /// Build a reflected Field object from a class, field ID, and static-ness
     ///
     /// TODO: Maybe make is_static part of IDs?
-    pub fn to_reflected_field(&self, cls: &JClass, id: &JFieldID, is_static: bool) -> Result<JObject> {
+    pub fn to_reflected_field(&self, cls: &JClass, id: &JFieldID, is_static: bool) -> Result<JObject<'env>> {
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
         let obj = unsafe {
-            env.to_reflected_field(cls.borrow_ptr(), id.borrow_ptr(), is_static.into())
+            self.track_local(env.to_reflected_field(cls.borrow_ptr(), id.borrow_ptr(), is_static.into()))
         };
 
         if obj.is_null() {
@@ -216,11 +578,11 @@ impl JNIEnv {
 
     /// Get the superclass of a given class. Will return an error if the class is Object or other
     /// class with no superclass.
-    pub fn get_superclass(&self, cls: &JClass) -> Result<JClass> {
+    pub fn get_superclass(&self, cls: &JClass) -> Result<JClass<'env>> {
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
-        let obj = unsafe { env.get_superclass(cls.borrow_ptr()) };
+        let obj = unsafe { self.track_local(env.get_superclass(cls.borrow_ptr())) };
         if obj.is_null() {
             Err(Error::new("Could not get object superclass", JNI_ERR))
         } else {
@@ -235,10 +597,19 @@ impl JNIEnv {
 
         // SAFETY: Internal pointer use
         unsafe {
-            env.is_assignable_from(from.borrow_ptr(), to.borrow_ptr())
+            env.is_assignable_from(from.borrow_ptr(), to.borrow_ptr()) != JNI_FALSE
         }
     }
 
+    /// Check whether a class represents an interface, as opposed to a concrete or abstract class
+    pub fn class_is_interface(&self, cls: &JClass) -> Result<bool> {
+        let cls_cls = self.find_class("java.lang.Class")?;
+        let is_iface = self.get_method_id(&cls_cls, "isInterface", "() -> boolean")?;
+
+        self.call_value_method(cls.downcast(), &is_iface, &vec![])?
+            .into_bool()
+    }
+
     /// Start throwing an exception on the JVM. Result is Ok if exception *is* thrown, Err if no
     /// exception is thrown.
     pub fn throw(&self, exception: &JThrowable) -> Result<()> {
@@ -247,38 +618,60 @@ impl JNIEnv {
         // SAFETY: Internal pointer use
         let result = unsafe { env.throw(exception.borrow_ptr()) };
         if result != 0 {
-            Err(Error::new("Could not throw exception", JNI_ERR))
+            Err(Error::new("Could not throw exception", result))
         } else {
             Ok(())
         }
     }
 
     /// Start throwing a new instance of an exception on the JVM. Result is Ok if exception *is*
-    /// thrown, Err if no exception is thrown.
+    /// thrown, Err if no exception is thrown. The message may contain any characters, including
+    /// NUL and those outside the BMP
     pub fn throw_new(&self, cls: &JClass, msg: &str) -> Result<()> {
         let env = self.internal_env();
-        let c_msg = cstr_from_str(msg)?;
+        let c_msg = mutf8_from_str(msg);
 
         // SAFETY: Internal pointer use
         let result = unsafe { env.throw_new(cls.borrow_ptr(), c_msg.as_ptr()) };
         if result != 0 {
-            Err(Error::new("Could not throw exception", JNI_ERR))
+            Err(Error::new("Could not throw exception", result))
         } else {
             Ok(())
         }
     }
 
+    /// Start throwing the Java exception best matching an error, such as `InterruptedException`
+    /// for [Error::Interrupted]. An [Error::Exception] is thrown again as its original class and
+    /// message. Errors with no closer match are thrown as a `RuntimeException`
+    pub fn throw_error(&self, err: &Error) -> Result<()> {
+        if let Error::Exception(name, msg) = err {
+            let cls = self.find_class(name)?;
+            return self.throw_new(&cls, msg.as_deref().unwrap_or(""))
+        }
+
+        let name = match err {
+            Error::Interrupted => "java.lang.InterruptedException",
+            Error::NullPointer(_) => "java.lang.NullPointerException",
+            Error::Unsupported(_) => "java.lang.UnsupportedOperationException",
+            Error::ResourceExhausted(name) => name,
+            _ => "java.lang.RuntimeException"
+        };
+
+        let cls = self.find_class(name)?;
+        self.throw_new(&cls, &err.to_string())
+    }
+
     /// Check whether an exception is currently occuring on the JVM
     pub fn exception_check(&self) -> bool {
         let env = self.internal_env();
-        env.exception_check()
+        env.exception_check() != JNI_FALSE
     }
 
     /// Get the current exception being thrown, or Err
-    pub fn exception_occurred(&self) -> Result<JThrowable> {
+    pub fn exception_occurred(&self) -> Result<JThrowable<'env>> {
         let env = self.internal_env();
 
-        let exc = env.exception_occurred();
+        let exc = self.track_local(env.exception_occurred());
         if exc.is_null() {
             Err(Error::new("No active exception to retrieve", JNI_ERR))
         } else {
@@ -310,8 +703,47 @@ impl JNIEnv {
         }
     }
 
-    /// Raise a fatal error, and don't expect the JVM to continue.
-    pub fn fatal_error(&self, msg: &str) -> Result<!> {
+    /// If an exception is being thrown, clear it and return it as an [Error::Exception], so native
+    /// code can stop with `env.propagate_if_pending()?`. Otherwise Ok
+    pub fn propagate_if_pending(&self) -> Result<()> {
+        if !self.exception_check() {
+            return Ok(())
+        }
+
+        let exc = self.exception_occurred()?;
+        self.exception_clear()?;
+
+        let (name, msg) = self.describe_exception(&exc)?;
+        Err(Error::Exception(name, msg))
+    }
+
+    /// Get the class name and message of an exception. Must be called with no exception pending
+    fn describe_exception(&self, exc: &JThrowable) -> Result<(String, Option<String>)> {
+        let cls_cls = get_cls!(self, "java.lang.Class");
+        let get_name = get_method_id!(self, cls_cls, "getName", "() -> java.lang.String");
+        let throwable_cls = get_cls!(self, "java.lang.Throwable");
+        let get_message = get_method_id!(self, throwable_cls, "getMessage", "() -> java.lang.String");
+
+        let exc_cls = self.get_object_class(exc.downcast())?;
+        let name = self.call_value_method(&exc_cls.downcast(), get_name, &vec![])?
+            .into_obj()?
+            .expect("Unexpected null result");
+        let msg = self.call_value_method(exc.downcast(), get_message, &vec![])?
+            .into_obj()?;
+
+        // SAFETY: Guaranteed safe upcasts, we know the types
+        let name: String = unsafe { self.get_string_chars(&name.upcast_raw())? }.into_iter().collect();
+        let msg: Option<String> = match msg {
+            Some(msg) => Some(unsafe { self.get_string_chars(&msg.upcast_raw())? }.into_iter().collect()),
+            None => None
+        };
+
+        Ok((name, msg))
+    }
+
+    /// Raise a fatal error, and don't expect the JVM to continue. Only returns if the message
+    /// couldn't be converted for the JVM
+    pub fn fatal_error(&self, msg: &str) -> Result<Infallible> {
         let env = self.internal_env();
         let c_msg = cstr_from_str(msg)?;
 
@@ -332,9 +764,8 @@ impl JNIEnv {
     }
 
     /// Push a frame onto the JVM. All references created within this frame will be freed once it
-    /// is closed.
-    ///
-    /// TODO: Maybe make this return a new 'environment' so all refs don't outlive it?
+    /// is closed. See [with_local_frame][Self::with_local_frame] for a frame references can't
+    /// outlive
     pub fn push_local_frame(&self, capacity: i32) -> Result<()> {
         let env = self.internal_env();
 
@@ -342,6 +773,7 @@ impl JNIEnv {
         if result != 0 {
             Err(Error::new(&format!("Couldn't push local from with capacity {}", capacity), result))
         } else {
+            stats::record_push_frame();
             Ok(())
         }
     }
@@ -361,6 +793,15 @@ impl JNIEnv {
         };
 
         let out = env.pop_local_frame(ptr);
+        if let Some(frame) = stats::record_pop_frame() {
+            if cfg!(feature = "leak-check") {
+                eprintln!(
+                    "rust_jni: local frame popped, {} created, {} deleted, {} freed by pop",
+                    frame.created, frame.deleted, frame.outstanding
+                );
+            }
+        }
+        let out = self.track_local(out);
 
         if out.is_null() {
             None
@@ -370,21 +811,35 @@ impl JNIEnv {
         }
     }
 
-    /// Create a new global reference, from an existing reference to an object
-    pub fn new_global_ref(&self, obj: &JObject) -> Result<JObject<'static>> {
+    /// Create a new global reference, from an existing reference to an object. The reference is
+    /// deleted when the returned [JGlobalRef] is dropped, unless it's [leaked][JGlobalRef::leak]
+    pub fn new_global_ref(&self, obj: &JObject) -> Result<JGlobalRef> {
         let env = self.internal_env();
+        let vm = self.get_jvm()?;
 
         // SAFETY: Internal pointer use
         let obj = unsafe { env.new_global_ref(obj.borrow_ptr()) };
         if obj.is_null() {
             Err(Error::new("Couldn't create new globabl reference", JNI_ERR))
         } else {
-            Ok(JObject::new(obj)?)
+            // SAFETY: The reference was just created, so nothing else owns it
+            Ok(unsafe { JGlobalRef::new(JObject::new(obj)?, vm) })
         }
     }
 
-    /// Delete an existing global reference.
+    /// Create a new global reference which can be sent to and dropped on other threads, from an
+    /// existing reference to an object. This is the preferred way to keep an object past the
+    /// current native call, the reference being deleted when the [JGlobal] is dropped
+    pub fn new_global(&self, obj: &JObject) -> Result<JGlobal> {
+        self.new_global_ref(obj)
+    }
+
+    /// Delete an existing global reference, such as one from [JGlobalRef::leak]. Does nothing once
+    /// the JVM has been destroyed, as the reference died with it
     pub fn delete_global_ref(&self, obj: JObject<'static>) {
+        if vm_destroyed() {
+            return
+        }
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
@@ -395,11 +850,11 @@ impl JNIEnv {
 
     /// Create a new local reference to an object. This can be used to increment refcount and
     /// prevent garbage collection on a delete_local_ref call.
-    pub fn new_local_ref(&self, obj: &JObject) -> Result<JObject> {
+    pub fn new_local_ref(&self, obj: &JObject) -> Result<JObject<'env>> {
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
-        let obj = unsafe { env.new_local_ref(obj.borrow_ptr()) };
+        let obj = unsafe { self.track_local(env.new_local_ref(obj.borrow_ptr())) };
         if obj.is_null() {
             Err(Error::new("Couldn't create new local reference", JNI_ERR))
         } else {
@@ -417,6 +872,7 @@ impl JNIEnv {
         unsafe {
             env.delete_local_ref(obj.borrow_ptr());
         }
+        stats::record_deleted();
     }
 
     /// Check whether two references refer to the same object
@@ -425,46 +881,79 @@ impl JNIEnv {
 
         // SAFETY: Internal pointer use
         unsafe {
-            env.is_same_object(obj1.borrow_ptr(), obj2.borrow_ptr())
+            env.is_same_object(obj1.borrow_ptr(), obj2.borrow_ptr()) != JNI_FALSE
         }
     }
 
     /// Allocate an object with enough space to hold an instance of the passed class, but do not
-    /// call any constructor or do any initialization
-    pub fn alloc_object(&self, cls: &JClass) -> Result<JObject> {
+    /// call any constructor or do any initialization. Returns Err without calling into the JVM if
+    /// the class is an interface or abstract, as those can't be instantiated
+    pub fn alloc_object(&self, cls: &JClass) -> Result<JObject<'env>> {
+        let cls_cls = get_cls!(self, "java.lang.Class");
+        let get_modifiers = get_method_id!(self, cls_cls, "getModifiers", "() -> int");
+
+        let modifiers = self.call_value_method(cls.downcast(), get_modifiers, &vec![])?
+            .into_int()?;
+        let kind = if modifiers & MODIFIER_INTERFACE != 0 {
+            Some("an interface")
+        } else if modifiers & MODIFIER_ABSTRACT != 0 {
+            Some("an abstract class")
+        } else {
+            None
+        };
+
+        if let Some(kind) = kind {
+            let name = self.class_name(cls)?;
+            return Err(Error::new(&format!("Can't allocate an instance of {}, it's {}", name, kind), JNI_ERR))
+        }
+
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
-        let obj = unsafe { env.alloc_object(cls.borrow_ptr()) };
-        if obj.is_null() {
-            Err(Error::new("Couldn't allocate object", JNI_ERR))
+        let obj = unsafe { self.track_local(env.alloc_object(cls.borrow_ptr())) };
+        self.created_object(obj)
+    }
+
+    /// Check the result of creating an object. A pending exception is cleared and returned as for
+    /// method calls, even if an object was returned. A null result without an exception means the
+    /// JVM couldn't allocate the object, so is reported as running out of memory
+    fn created_object(&self, obj: *mut ffi::JObject) -> Result<JObject<'env>> {
+        if self.exception_check() {
+            Err(self.call_exception_error())
+        } else if obj.is_null() {
+            Err(Error::ResourceExhausted(String::from("java.lang.OutOfMemoryError")))
         } else {
             Ok(JObject::new(obj)?)
         }
     }
 
     /// Create a new object, calling a constructor with the passed args. Constructors are methods
-    /// with the name `<init>`
-    pub fn new_object(&self, cls: &JClass, id: &JMethodID, args: &[JValue]) -> Result<JObject> {
+    /// with the name `<init>`.
+    ///
+    /// If the constructor throws, the exception is cleared and returned as an [Error::Exception], as
+    /// for method calls. If the JVM gives no object without throwing,
+    /// it couldn't allocate one, which is an [Error::ResourceExhausted] for `OutOfMemoryError`
+    pub fn new_object(&self, cls: &JClass, id: &JMethodID, args: &[JValue]) -> Result<JObject<'env>> {
+        self.check_arg_count(id, args, "new_object")?;
+        self.check_threads(None, args, "new_object")?;
+        let _depth = self.enter_call("new_object")?;
+
         let env = self.internal_env();
 
         let c_args = JValue::make_ffi_vec(args);
 
         // SAFETY: Internal pointer use
-        let obj = unsafe { env.new_object(cls.borrow_ptr(), id.borrow_ptr(), c_args.as_ptr()) };
-        if obj.is_null() {
-            Err(Error::new("Couldn't create new object", JNI_ERR))
-        } else {
-            Ok(JObject::new(obj)?)
-        }
+        let obj = unsafe { self.track_local(env.new_object(cls.borrow_ptr(), id.borrow_ptr(), c_args.as_ptr())) };
+        // A throwing constructor also gives null, so the exception is checked for first
+        self.created_object(obj)
     }
 
     /// Get the class of an object
-    pub fn get_object_class(&self, obj: &JObject) -> Result<JClass> {
+    pub fn get_object_class(&self, obj: &JObject) -> Result<JClass<'env>> {
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
-        let cls = unsafe { env.get_object_class(obj.borrow_ptr()) };
+        let cls = unsafe { self.track_local(env.get_object_class(obj.borrow_ptr())) };
         if cls.is_null() {
             Err(Error::new("Couldn't get object class", JNI_ERR))
         } else {
@@ -472,14 +961,176 @@ impl JNIEnv {
         }
     }
 
+    /// Get the class of an object along with its binary name, as given by `Class.getName`, such
+    /// as `java.lang.String` or `[I`
+    pub fn object_class_and_name(&self, obj: &JObject) -> Result<(JClass<'env>, String)> {
+        let cls = self.get_object_class(obj)?;
+        let name = self.class_name(&cls)?;
+        Ok((cls, name))
+    }
+
+    /// Get the name of a class, as given by `Class.getName`
+    fn class_name(&self, cls: &JClass) -> Result<String> {
+        let cls_cls = get_cls!(self, "java.lang.Class");
+        let get_name = get_method_id!(self, cls_cls, "getName", "() -> java.lang.String");
+
+        let name = self.call_value_method(cls.downcast(), get_name, &vec![])?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Class.getName"))?;
+        // SAFETY: Guaranteed safe upcast, getName returns a String
+        let name = unsafe { name.upcast_raw() };
+        let out = self.get_string_chars(&name)?.into_iter().collect();
+        self.delete_local_ref(name.downcast());
+        Ok(out)
+    }
+
     /// Check whether an object is an instance of a given class
     pub fn is_instance_of(&self, obj: &JObject, cls: &JClass) -> bool {
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
         unsafe {
-            env.is_instance_of(obj.borrow_ptr(), cls.borrow_ptr())
+            env.is_instance_of(obj.borrow_ptr(), cls.borrow_ptr()) != JNI_FALSE
+        }
+    }
+
+    /// Parse a signature in the syntax defined in the root documentation, reusing the result if an
+    /// environment on this thread parsed the same signature before
+    fn parse_signature(&self, sig: &str) -> Rc<ParsedSignature> {
+        if let Some(parsed) = SIGNATURE_CACHE.with(|cache| cache.borrow().get(sig).cloned()) {
+            return parsed
+        }
+
+        #[cfg(test)]
+        SIGNATURE_PARSES.with(|parses| parses.set(parses.get() + 1));
+
+        let parsed = mangle_class(sig);
+        let parsed = Rc::new(ParsedSignature {
+            mangled: parsed.mangled(),
+            sig: parsed
+        });
+
+        SIGNATURE_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            if cache.len() >= SIGNATURE_CACHE_SIZE {
+                cache.clear();
+            }
+            cache.insert(String::from(sig), parsed.clone());
+        });
+        parsed
+    }
+
+    /// Check that an object passed as a class really is a `java.lang.Class`, to catch instances
+    /// wrongly cast to [JClass]. Only checked with debug assertions or the `runtime-checks` feature
+    fn check_is_class(&self, cls: &JClass, ctx: &str) -> Result<()> {
+        #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+        {
+            let cls_cls = get_cls!(self, "java.lang.Class");
+            let obj: &JObject = cls.downcast();
+            if !self.is_instance_of(obj, cls_cls) {
+                let get_name = get_method_id!(self, cls_cls, "getName", "() -> java.lang.String");
+                let obj_cls = self.get_object_class(obj)?;
+                let name = self.call_value_method(&obj_cls.downcast(), get_name, &vec![])?
+                    .into_obj()?
+                    .expect("Unexpected null result");
+
+                // SAFETY: Guaranteed safe upcast, we know the type
+                let name: String = unsafe { self.get_string_chars(&name.upcast_raw())? }.into_iter().collect();
+                return Err(Error::new(
+                    &format!("{} expected a java.lang.Class, but was passed an instance of {}", ctx, name),
+                    JNI_ERR
+                ))
+            }
         }
+
+        #[cfg(not(any(debug_assertions, feature = "runtime-checks")))]
+        let _ = (cls, ctx);
+
+        Ok(())
+    }
+
+    /// Check the number of arguments passed to a method matches the number it takes
+    fn check_arg_count(&self, id: &JMethodID, args: &[JValue], ctx: &str) -> Result<()> {
+        if args.len() == id.num_args() {
+            return Ok(())
+        }
+
+        Err(Error::new(
+            &format!(
+                "{} expected {} arguments for a method returning {}, but was passed {}",
+                ctx, id.num_args(), format!("{:?}", id.ret_ty()).to_lowercase(), args.len()
+            ),
+            JNI_ERR
+        ))
+    }
+
+    /// Check that a receiver and arguments aren't local references created on another thread, as
+    /// local references are only valid on the thread that made them. Objects from another thread
+    /// are only allowed if they're global or weak global references. Only checked with debug
+    /// assertions or the `runtime-checks` feature
+    fn check_threads(&self, obj: Option<&JObject>, args: &[JValue], ctx: &str) -> Result<()> {
+        #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+        {
+            let current = std::thread::current();
+            let arg_objs = args.iter().filter_map(|arg| match arg {
+                JValue::Object(Some(obj)) => Some(obj),
+                _ => None
+            });
+
+            for obj in obj.into_iter().chain(arg_objs) {
+                let owner = obj.creating_thread();
+                if owner == current.id() {
+                    continue
+                }
+
+                match self.get_object_ref_type(obj) {
+                    JRefType::Global | JRefType::WeakGlobal => (),
+                    _ => return Err(Error::new(
+                        &format!(
+                            "{} was passed a local reference created on thread {:?}, but called on thread {:?} ({})",
+                            ctx, owner, current.id(), current.name().unwrap_or("unnamed")
+                        ),
+                        JNI_ERR
+                    ))
+                }
+            }
+        }
+
+        #[cfg(not(any(debug_assertions, feature = "runtime-checks")))]
+        let _ = (obj, args, ctx);
+
+        Ok(())
+    }
+
+    /// Count a call into Java towards the current thread's call depth, failing if that would pass
+    /// the limit set by [set_call_depth_limit][Self::set_call_depth_limit]
+    fn enter_call(&self, ctx: &str) -> Result<CallDepthGuard> {
+        let depth = CALL_DEPTH.with(|depth| depth.get());
+        if let Some(limit) = CALL_DEPTH_LIMIT.with(|limit| limit.get()) {
+            if depth >= limit {
+                return Err(Error::new(
+                    &format!("{} would pass the call depth limit of {}", ctx, limit),
+                    JNI_ERR
+                ))
+            }
+        }
+
+        CALL_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        Ok(CallDepthGuard)
+    }
+
+    /// Limit how many calls into Java made through this wrapper may be running on the current
+    /// thread at once, or remove the limit with None. Calls nest when Java calls back into native
+    /// code which calls Java again, and unbounded recursion that way overflows the native stack
+    /// with no diagnostic. Past the limit, method calls and object creation are an Err instead.
+    /// There's no limit by default
+    pub fn set_call_depth_limit(&self, limit: Option<usize>) {
+        CALL_DEPTH_LIMIT.with(|cell| cell.set(limit))
+    }
+
+    /// Get how many calls into Java made through this wrapper are running on the current thread
+    pub fn call_depth(&self) -> usize {
+        CALL_DEPTH.with(|depth| depth.get())
     }
 
     /// Get a method ID from a class, name, and signature. The signature uses the syntax defined
@@ -487,19 +1138,20 @@ impl JNIEnv {
     pub fn get_method_id(&self, cls: &JClass, name: &str, sig: &str) -> Result<JMethodID> {
         let env = self.internal_env();
         let c_name = cstr_from_str(name)?;
+        self.check_is_class(cls, "get_method_id")?;
 
-        let sig = mangle_class(sig);
+        let sig = self.parse_signature(sig);
         let num_args;
         let ret_ty;
 
-        if let TypeSignature::Method(args, ret) = &sig {
+        if let TypeSignature::Method(args, ret) = &sig.sig {
             num_args = args.len();
             ret_ty = ret.java_type();
         } else {
             return Err(Error::new("Expected method signature", JNI_ERR));
         }
 
-        let c_sig = cstr_from_str(&sig.mangled())?;
+        let c_sig = cstr_from_str(&sig.mangled)?;
 
         // SAFETY: Internal pointer use
         let id = unsafe { env.get_method_id(cls.borrow_ptr(), c_name.as_ptr(), c_sig.as_ptr()) };
@@ -510,16 +1162,88 @@ impl JNIEnv {
         }
     }
 
+    /// Take the exception left pending by a method call, clearing it so the JNI can be used again.
+    /// Stack overflows and out of memory errors get their own error, so bridges can tell resource
+    /// exhaustion apart from exceptions thrown by the called code, anything else is an
+    /// [Error::Exception] naming its class and message
+    fn call_exception_error(&self) -> Error {
+        let env = self.internal_env();
+
+        // Most JNI functions can't be called with a pending exception, so take it aside while
+        // inspecting it
+        let exc = env.exception_occurred();
+        env.exception_clear();
+
+        let mut exhausted = None;
+        for name in &["java.lang.StackOverflowError", "java.lang.OutOfMemoryError"] {
+            match self.find_class(name) {
+                Ok(cls) => {
+                    // SAFETY: Internal pointer use
+                    let is_instance = unsafe { env.is_instance_of(exc as _, cls.borrow_ptr()) } != JNI_FALSE;
+                    self.delete_local_ref(cls.downcast());
+                    if is_instance {
+                        exhausted = Some(*name);
+                        break
+                    }
+                }
+                Err(_) => {
+                    env.exception_clear();
+                }
+            }
+        }
+
+        let err = match exhausted {
+            Some(name) => Error::ResourceExhausted(String::from(name)),
+            None => match JThrowable::new(exc).and_then(|exc| self.describe_exception(&exc)) {
+                Ok((name, msg)) => Error::Exception(name, msg),
+                Err(_) => {
+                    // Describing may itself throw, the original exception takes priority
+                    env.exception_clear();
+                    Error::new("Error occured during method call", JNI_ERR)
+                }
+            }
+        };
+        env.delete_local_ref(exc as _);
+        err
+    }
+
     /// Call a method on an object. Takes the object to bind to `this`, the ID of the method, and
     /// the arguments to pass. Return Err if the method errors, otherwise Ok. Option is None if the
-    /// method is void typed, otherwise a JValue containing the return.
-    pub fn call_method(&self, obj: &JObject, id: &JMethodID, args: &[JValue]) -> Result<Option<JValue>> {
-        if args.len() != id.num_args() {
-            return Err(Error::new("Invalid number of arguement for method", JNI_ERR))
+    /// method is void typed, otherwise a JValue containing the return. If the method throws, the
+    /// exception is cleared and returned as an [Error::Exception].
+    pub fn call_method(&self, obj: &JObject, id: &JMethodID, args: &[JValue]) -> Result<Option<JValue<'env>>> {
+        self.check_arg_count(id, args, "call_method")?;
+        self.check_threads(Some(obj), args, "call_method")?;
+
+        let args = JValue::make_ffi_vec(args);
+        // SAFETY: The argument count was checked above, and the arguments converted from JValues
+        unsafe { self.call_method_prevalidated(obj, id, &args) }
+    }
+
+    /// Call a non-void method on an object, returning its result directly. Returns Err without
+    /// calling the method if the method ID is void typed, otherwise the same as
+    /// [call_method][Self::call_method]
+    pub fn call_value_method(&self, obj: &JObject, id: &JMethodID, args: &[JValue]) -> Result<JValue<'env>> {
+        if id.ret_ty() == JType::Void {
+            return Err(Error::new("call_value_method can't call a void method", JNI_ERR))
         }
 
+        self.call_method(obj, id, args)?
+            .ok_or_else(|| Error::new("Non-void method gave no result", JNI_ERR))
+    }
+
+    /// Call a method on an object with arguments already converted to FFI values, as made by
+    /// [JValue::make_ffi_vec]. Returns the same as [call_method][Self::call_method], but skips
+    /// converting and checking the arguments, for hot loops making the same call many times.
+    ///
+    /// # Safety
+    ///
+    /// The arguments must match the method signature in count and type, and any object arguments
+    /// must still be live references. Nothing checks this, and a mismatch is undefined behavior
+    pub unsafe fn call_method_prevalidated(&self, obj: &JObject, id: &JMethodID, args: &[ffi::JValue]) -> Result<Option<JValue<'env>>> {
+        let _depth = self.enter_call("call_method")?;
+
         let env = self.internal_env();
-        let args = JValue::make_ffi_vec(args);
 
         // SAFETY: Internal pointer use
         let (raw_obj, raw_id) = unsafe { (
@@ -528,7 +1252,11 @@ impl JNIEnv {
 
         let result = match id.ret_ty() {
             JType::Object => {
-                let result = env.call_object_method(raw_obj, raw_id, args.as_ptr());
+                let result = self.track_local(env.call_object_method(raw_obj, raw_id, args.as_ptr()));
+                // A thrown exception also gives null, so check for one before trusting the result
+                if self.exception_check() {
+                    return Err(self.call_exception_error())
+                }
                 if result.is_null() {
                     Some(JValue::Object(None))
                 } else {
@@ -537,7 +1265,7 @@ impl JNIEnv {
             }
             JType::Boolean => {
                 let result = env.call_boolean_method(raw_obj, raw_id, args.as_ptr());
-                Some(JValue::Bool(result))
+                Some(JValue::Bool(result != JNI_FALSE))
             }
             JType::Byte => {
                 let result = env.call_byte_method(raw_obj, raw_id, args.as_ptr());
@@ -546,7 +1274,7 @@ impl JNIEnv {
             JType::Char => {
                 let result = env.call_char_method(raw_obj, raw_id, args.as_ptr());
                 Some(JValue::Char(
-                    std::char::from_u32(result as u32).expect("Java returned bad char")
+                    decode_char(result)?
                 ))
             }
             JType::Short => {
@@ -576,7 +1304,7 @@ impl JNIEnv {
         };
 
         if self.exception_check() {
-            Err(Error::new("Error occured during method call", JNI_ERR))
+            Err(self.call_exception_error())
         } else {
             Ok(result)
         }
@@ -586,10 +1314,10 @@ impl JNIEnv {
     /// Takes the object to bind to `this`, the class to use, the ID of the method, and the
     /// arguments to pass. Return Err if the method errors, otherwise Ok. Option is None if the
     /// method is void typed, otherwise a JValue containing the return.
-    pub fn call_nonvirtual_method(&self, obj: &JObject, cls: &JClass, id: &JMethodID, args: &[JValue]) -> Result<Option<JValue>> {
-        if args.len() != id.num_args() {
-            return Err(Error::new("Invalid number of arguments for method", JNI_ERR))
-        }
+    pub fn call_nonvirtual_method(&self, obj: &JObject, cls: &JClass, id: &JMethodID, args: &[JValue]) -> Result<Option<JValue<'env>>> {
+        self.check_arg_count(id, args, "call_nonvirtual_method")?;
+        self.check_threads(Some(obj), args, "call_nonvirtual_method")?;
+        let _depth = self.enter_call("call_nonvirtual_method")?;
 
         let env = self.internal_env();
         let args = JValue::make_ffi_vec(args);
@@ -601,7 +1329,11 @@ impl JNIEnv {
 
         let result = match id.ret_ty() {
             JType::Object => {
-                let result = env.call_nonvirtual_object_method(raw_obj, raw_cls, raw_id, args.as_ptr());
+                let result = self.track_local(env.call_nonvirtual_object_method(raw_obj, raw_cls, raw_id, args.as_ptr()));
+                // A thrown exception also gives null, so check for one before trusting the result
+                if self.exception_check() {
+                    return Err(self.call_exception_error())
+                }
                 if result.is_null() {
                     Some(JValue::Object(None))
                 } else {
@@ -610,7 +1342,7 @@ impl JNIEnv {
             }
             JType::Boolean => {
                 let result = env.call_nonvirtual_boolean_method(raw_obj, raw_cls, raw_id, args.as_ptr());
-                Some(JValue::Bool(result))
+                Some(JValue::Bool(result != JNI_FALSE))
             }
             JType::Byte => {
                 let result = env.call_nonvirtual_byte_method(raw_obj, raw_cls, raw_id, args.as_ptr());
@@ -619,7 +1351,7 @@ impl JNIEnv {
             JType::Char => {
                 let result = env.call_nonvirtual_char_method(raw_obj, raw_cls, raw_id, args.as_ptr());
                 Some(JValue::Char(
-                    std::char::from_u32(result as u32).expect("Java returned bad char")
+                    decode_char(result)?
                 ))
             }
             JType::Short => {
@@ -649,22 +1381,57 @@ impl JNIEnv {
         };
 
         if self.exception_check() {
-            Err(Error::new("Error occured during method call", JNI_ERR))
+            Err(self.call_exception_error())
         } else {
             Ok(result)
         }
     }
 
+    /// Call an interface default method on an object without doing virtual lookup, so the
+    /// interface's implementation runs even if the object's class overrides it. Takes the object
+    /// to bind to `this`, the interface declaring the method, the name and signature of the
+    /// method, and the arguments to pass. Return Err if the class isn't an interface, the object
+    /// doesn't implement it, or the method errors. Option is None if the method is void typed,
+    /// otherwise a JValue containing the return.
+    pub fn call_default_method(&self, obj: &JObject, interface: &JClass, name: &str, sig: &str, args: &[JValue]) -> Result<Option<JValue<'env>>> {
+        if !self.class_is_interface(interface)? {
+            return Err(Error::new("Class passed as interface isn't an interface", JNI_ERR))
+        }
+
+        if !self.is_instance_of(obj, interface) {
+            return Err(Error::new("Object doesn't implement the passed interface", JNI_ERR))
+        }
+
+        let id = self.get_method_id(interface, name, sig)?;
+        self.call_nonvirtual_method(obj, interface, &id, args)
+    }
+
+    /// Call the superclass's version of a method on an object, as `super.name(...)` would from the
+    /// object's class. The method is resolved from the superclass of the object's class, and
+    /// called without virtual lookup, so overrides in the object's class are skipped. Return Err
+    /// if the class has no superclass, the method can't be found, or the method errors. Option is
+    /// None if the method is void typed, otherwise a JValue containing the return.
+    pub fn call_super_method(&self, obj: &JObject, name: &str, sig: &str, args: &[JValue]) -> Result<Option<JValue<'env>>> {
+        let cls = self.get_object_class(obj)?;
+        let super_cls = self.get_superclass(&cls)?;
+        self.delete_local_ref(cls.downcast());
+
+        let id = self.get_method_id(&super_cls, name, sig)?;
+        let result = self.call_nonvirtual_method(obj, &super_cls, &id, args);
+        self.delete_local_ref(super_cls.downcast());
+        result
+    }
+
     /// Get a field ID from a class, name, and type. The type uses the syntax defined in the root
     /// documentation
     pub fn get_field_id(&self, cls: &JClass, name: &str, sig: &str) -> Result<JFieldID> {
         let env = self.internal_env();
         let c_name = cstr_from_str(name)?;
 
-        let sig = mangle_class(sig);
-        let ty= sig.java_type().as_nonvoid().expect("Expected field type to be non-void");
+        let sig = self.parse_signature(sig);
+        let ty= sig.sig.java_type().as_nonvoid().expect("Expected field type to be non-void");
 
-        let c_sig = cstr_from_str(&sig.mangled())?;
+        let c_sig = cstr_from_str(&sig.mangled)?;
 
         // SAFETY: Internal pointer use
         let id = unsafe { env.get_field_id(cls.borrow_ptr(), c_name.as_ptr(), c_sig.as_ptr()) };
@@ -678,7 +1445,7 @@ impl JNIEnv {
     /// Get the value of a field on an object. Takes the object to retrieve from and the ID of the
     /// field. Returns Err if the field can't be retrieved, otherwise Ok with a JValue containing
     /// the current value
-    pub fn get_field(&self, obj: &JObject, id: &JFieldID) -> Result<JValue> {
+    pub fn get_field(&self, obj: &JObject, id: &JFieldID) -> Result<JValue<'env>> {
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
@@ -688,45 +1455,21 @@ impl JNIEnv {
 
         Ok(match id.ty() {
             JNonVoidType::Object => {
-                let result = env.get_object_field(raw_obj, raw_id);
+                let result = self.track_local(env.get_object_field(raw_obj, raw_id));
                 if result.is_null() {
                     JValue::Object(None)
                 } else {
                     JValue::Object(Some(JObject::new(result)?))
                 }
             }
-            JNonVoidType::Boolean => {
-                let result = env.get_boolean_field(raw_obj, raw_id);
-                JValue::Bool(result)
-            }
-            JNonVoidType::Byte => {
-                let result = env.get_byte_field(raw_obj, raw_id);
-                JValue::Byte(result)
-            }
-            JNonVoidType::Char => {
-                let result = env.get_char_field(raw_obj, raw_id);
-                JValue::Char(std::char::from_u32(result as u32).expect("Java returned bad char"))
-            }
-            JNonVoidType::Short => {
-                let result = env.get_short_field(raw_obj, raw_id);
-                JValue::Short(result)
-            }
-            JNonVoidType::Int => {
-                let result = env.get_int_field(raw_obj, raw_id);
-                JValue::Int(result)
-            }
-            JNonVoidType::Long => {
-                let result = env.get_long_field(raw_obj, raw_id);
-                JValue::Long(result)
-            }
-            JNonVoidType::Float => {
-                let result = env.get_float_field(raw_obj, raw_id);
-                JValue::Float(result)
-            }
-            JNonVoidType::Double => {
-                let result = env.get_double_field(raw_obj, raw_id);
-                JValue::Double(result)
-            }
+            JNonVoidType::Boolean => JValue::Bool(self.get_field_bool(obj, id)?),
+            JNonVoidType::Byte => JValue::Byte(self.get_field_byte(obj, id)?),
+            JNonVoidType::Char => JValue::Char(self.get_field_char(obj, id)?),
+            JNonVoidType::Short => JValue::Short(self.get_field_short(obj, id)?),
+            JNonVoidType::Int => JValue::Int(self.get_field_int(obj, id)?),
+            JNonVoidType::Long => JValue::Long(self.get_field_long(obj, id)?),
+            JNonVoidType::Float => JValue::Float(self.get_field_float(obj, id)?),
+            JNonVoidType::Double => JValue::Double(self.get_field_double(obj, id)?),
         })
     }
 
@@ -752,30 +1495,14 @@ impl JNIEnv {
                     env.set_object_field(raw_obj, raw_id, obj);
                 }
             }
-            JNonVoidType::Boolean => {
-                env.set_boolean_field(raw_obj, raw_id, val.into_bool()? as ffi::JBoolean);
-            }
-            JNonVoidType::Byte => {
-                env.set_byte_field(raw_obj, raw_id, val.into_byte()? as ffi::JByte);
-            }
-            JNonVoidType::Char => {
-                env.set_char_field(raw_obj, raw_id, val.into_char()? as ffi::JChar);
-            }
-            JNonVoidType::Short => {
-                env.set_short_field(raw_obj, raw_id, val.into_short()? as ffi::JShort);
-            }
-            JNonVoidType::Int => {
-                env.set_int_field(raw_obj, raw_id, val.into_int()? as ffi::JInt);
-            }
-            JNonVoidType::Long => {
-                env.set_long_field(raw_obj, raw_id, val.into_long()? as ffi::JLong);
-            }
-            JNonVoidType::Float => {
-                env.set_float_field(raw_obj, raw_id, val.into_float()? as ffi::JFloat);
-            }
-            JNonVoidType::Double => {
-                env.set_double_field(raw_obj, raw_id, val.into_double()? as ffi::JDouble);
-            }
+            JNonVoidType::Boolean => self.set_field_bool(obj, id, val.into_bool()?)?,
+            JNonVoidType::Byte => self.set_field_byte(obj, id, val.into_byte()?)?,
+            JNonVoidType::Char => self.set_field_char(obj, id, val.into_char()?)?,
+            JNonVoidType::Short => self.set_field_short(obj, id, val.into_short()?)?,
+            JNonVoidType::Int => self.set_field_int(obj, id, val.into_int()?)?,
+            JNonVoidType::Long => self.set_field_long(obj, id, val.into_long()?)?,
+            JNonVoidType::Float => self.set_field_float(obj, id, val.into_float()?)?,
+            JNonVoidType::Double => self.set_field_double(obj, id, val.into_double()?)?,
         }
 
         Ok(())
@@ -786,19 +1513,20 @@ impl JNIEnv {
     pub fn get_static_method_id(&self, cls: &JClass, name: &str, sig: &str) -> Result<JMethodID> {
         let env = self.internal_env();
         let c_name = cstr_from_str(name)?;
+        self.check_is_class(cls, "get_static_method_id")?;
 
-        let sig = mangle_class(sig);
+        let sig = self.parse_signature(sig);
         let num_args;
         let ret_ty;
 
-        if let TypeSignature::Method(args, ret) = &sig {
+        if let TypeSignature::Method(args, ret) = &sig.sig {
             num_args = args.len();
             ret_ty = ret.java_type();
         } else {
             return Err(Error::new("Expected method signature", JNI_ERR));
         }
 
-        let c_sig = cstr_from_str(&sig.mangled())?;
+        let c_sig = cstr_from_str(&sig.mangled)?;
 
         // SAFETY: Internal pointer use
         let id = unsafe { env.get_static_method_id(cls.borrow_ptr(), c_name.as_ptr(), c_sig.as_ptr()) };
@@ -812,10 +1540,10 @@ impl JNIEnv {
     /// Call a static method on an class. Takes the class to use, the ID of the method, and the
     /// arguments to pass. Return Err if the method errors, otherwise Ok. Option is None if the
     /// method is void typed, otherwise a JValue containing the return.
-    pub fn call_static_method(&self, cls: &JClass, id: &JMethodID, args: &[JValue]) -> Result<Option<JValue>> {
-        if args.len() != id.num_args() {
-            return Err(Error::new("Invalid number of arguments for method", JNI_ERR))
-        }
+    pub fn call_static_method(&self, cls: &JClass, id: &JMethodID, args: &[JValue]) -> Result<Option<JValue<'env>>> {
+        self.check_arg_count(id, args, "call_static_method")?;
+        self.check_threads(None, args, "call_static_method")?;
+        let _depth = self.enter_call("call_static_method")?;
 
         let env = self.internal_env();
         let args = JValue::make_ffi_vec(args);
@@ -827,7 +1555,11 @@ impl JNIEnv {
 
         let result = match id.ret_ty() {
             JType::Object => {
-                let result = env.call_static_object_method(raw_cls, raw_id, args.as_ptr());
+                let result = self.track_local(env.call_static_object_method(raw_cls, raw_id, args.as_ptr()));
+                // A thrown exception also gives null, so check for one before trusting the result
+                if self.exception_check() {
+                    return Err(self.call_exception_error())
+                }
                 if result.is_null() {
                     Some(JValue::Object(None))
                 } else {
@@ -836,7 +1568,7 @@ impl JNIEnv {
             }
             JType::Boolean => {
                 let result = env.call_static_boolean_method(raw_cls, raw_id, args.as_ptr());
-                Some(JValue::Bool(result))
+                Some(JValue::Bool(result != JNI_FALSE))
             }
             JType::Byte => {
                 let result = env.call_static_byte_method(raw_cls, raw_id, args.as_ptr());
@@ -845,7 +1577,7 @@ impl JNIEnv {
             JType::Char => {
                 let result = env.call_static_char_method(raw_cls, raw_id, args.as_ptr());
                 Some(JValue::Char(
-                    std::char::from_u32(result as u32).expect("Java returned bad char")
+                    decode_char(result)?
                 ))
             }
             JType::Short => {
@@ -875,7 +1607,7 @@ impl JNIEnv {
         };
 
         if self.exception_check() {
-            Err(Error::new("Error occured during method call", JNI_ERR))
+            Err(self.call_exception_error())
         } else {
             Ok(result)
         }
@@ -887,10 +1619,10 @@ impl JNIEnv {
         let env = self.internal_env();
         let c_name = cstr_from_str(name)?;
 
-        let sig = mangle_class(sig);
-        let ty= sig.java_type().as_nonvoid().expect("Expected field type to be non-void");
+        let sig = self.parse_signature(sig);
+        let ty= sig.sig.java_type().as_nonvoid().expect("Expected field type to be non-void");
 
-        let c_sig = cstr_from_str(&sig.mangled())?;
+        let c_sig = cstr_from_str(&sig.mangled)?;
 
         // SAFETY: Internal pointer use
         let id = unsafe {
@@ -907,7 +1639,7 @@ impl JNIEnv {
     /// Get the value of a static field on a class. Takes the class to retrieve from and the ID of
     /// the field. Returns Err if the field can't be retrieved, otherwise Ok with a JValue
     /// containing the current value
-    pub fn get_static_field(&self, cls: &JClass, id: &JFieldID) -> Result<JValue> {
+    pub fn get_static_field(&self, cls: &JClass, id: &JFieldID) -> Result<JValue<'env>> {
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
@@ -917,45 +1649,21 @@ impl JNIEnv {
 
         Ok(match id.ty() {
             JNonVoidType::Object => {
-                let result = env.get_static_object_field(raw_cls, raw_id);
+                let result = self.track_local(env.get_static_object_field(raw_cls, raw_id));
                 if result.is_null() {
                     JValue::Object(None)
                 } else {
                     JValue::Object(Some(JObject::new(result)?))
                 }
             }
-            JNonVoidType::Boolean => {
-                let result = env.get_static_boolean_field(raw_cls, raw_id);
-                JValue::Bool(result)
-            }
-            JNonVoidType::Byte => {
-                let result = env.get_static_byte_field(raw_cls, raw_id);
-                JValue::Byte(result)
-            }
-            JNonVoidType::Char => {
-                let result = env.get_static_char_field(raw_cls, raw_id);
-                JValue::Char(std::char::from_u32(result as u32).expect("Java returned bad char"))
-            }
-            JNonVoidType::Short => {
-                let result = env.get_static_short_field(raw_cls, raw_id);
-                JValue::Short(result)
-            }
-            JNonVoidType::Int => {
-                let result = env.get_static_int_field(raw_cls, raw_id);
-                JValue::Int(result)
-            }
-            JNonVoidType::Long => {
-                let result = env.get_static_long_field(raw_cls, raw_id);
-                JValue::Long(result)
-            }
-            JNonVoidType::Float => {
-                let result = env.get_static_float_field(raw_cls, raw_id);
-                JValue::Float(result)
-            }
-            JNonVoidType::Double => {
-                let result = env.get_static_double_field(raw_cls, raw_id);
-                JValue::Double(result)
-            }
+            JNonVoidType::Boolean => JValue::Bool(self.get_static_field_bool(cls, id)?),
+            JNonVoidType::Byte => JValue::Byte(self.get_static_field_byte(cls, id)?),
+            JNonVoidType::Char => JValue::Char(self.get_static_field_char(cls, id)?),
+            JNonVoidType::Short => JValue::Short(self.get_static_field_short(cls, id)?),
+            JNonVoidType::Int => JValue::Int(self.get_static_field_int(cls, id)?),
+            JNonVoidType::Long => JValue::Long(self.get_static_field_long(cls, id)?),
+            JNonVoidType::Float => JValue::Float(self.get_static_field_float(cls, id)?),
+            JNonVoidType::Double => JValue::Double(self.get_static_field_double(cls, id)?),
         })
     }
 
@@ -981,42 +1689,117 @@ impl JNIEnv {
                     env.set_static_object_field(raw_cls, raw_id, obj);
                 }
             }
-            JNonVoidType::Boolean => {
-                env.set_static_boolean_field(raw_cls, raw_id, val.into_bool()? as ffi::JBoolean);
-            }
-            JNonVoidType::Byte => {
-                env.set_static_byte_field(raw_cls, raw_id, val.into_byte()? as ffi::JByte);
-            }
-            JNonVoidType::Char => {
-                env.set_static_char_field(raw_cls, raw_id, val.into_char()? as ffi::JChar);
-            }
-            JNonVoidType::Short => {
-                env.set_static_short_field(raw_cls, raw_id, val.into_short()? as ffi::JShort);
-            }
-            JNonVoidType::Int => {
-                env.set_static_int_field(raw_cls, raw_id, val.into_int()? as ffi::JInt);
-            }
-            JNonVoidType::Long => {
-                env.set_static_long_field(raw_cls, raw_id, val.into_long()? as ffi::JLong);
-            }
-            JNonVoidType::Float => {
-                env.set_static_float_field(raw_cls, raw_id, val.into_float()? as ffi::JFloat);
+            JNonVoidType::Boolean => self.set_static_field_bool(cls, id, val.into_bool()?)?,
+            JNonVoidType::Byte => self.set_static_field_byte(cls, id, val.into_byte()?)?,
+            JNonVoidType::Char => self.set_static_field_char(cls, id, val.into_char()?)?,
+            JNonVoidType::Short => self.set_static_field_short(cls, id, val.into_short()?)?,
+            JNonVoidType::Int => self.set_static_field_int(cls, id, val.into_int()?)?,
+            JNonVoidType::Long => self.set_static_field_long(cls, id, val.into_long()?)?,
+            JNonVoidType::Float => self.set_static_field_float(cls, id, val.into_float()?)?,
+            JNonVoidType::Double => self.set_static_field_double(cls, id, val.into_double()?)?,
+        }
+
+        Ok(())
+    }
+
+    /// Read a static object field as a typed reference, checked to be an instance of the type's
+    /// class. The field is looked up with the type's class as its signature, then by name alone if
+    /// it's declared as another type. This lets fields of types without a wrapper, such as
+    /// `Comparator`, be read as a plain [JObject]. Returns None if the field is null
+    pub fn get_static_field_as<T: JavaClassType<'env>>(&self, cls: &JClass, name: &str) -> Result<Option<T>> {
+        let id = match self.get_static_field_id(cls, name, T::class_name()) {
+            Ok(id) => id,
+            Err(err) => {
+                if !self.exception_check() {
+                    return Err(err)
+                }
+
+                // Only a NoSuchFieldError is cleared, the field may be declared as another type
+                let exc = self.exception_occurred()?;
+                self.exception_clear()?;
+                let no_such_field = get_cls!(self, "java.lang.NoSuchFieldError");
+                let is_no_such_field = self.is_instance_of((&exc).downcast(), no_such_field);
+                if !is_no_such_field {
+                    self.throw(&exc)?;
+                }
+                self.delete_local_ref(exc.downcast());
+                if !is_no_such_field {
+                    return Err(err)
+                }
+
+                self.static_field_by_name(cls, name)?
             }
-            JNonVoidType::Double => {
-                env.set_static_double_field(raw_cls, raw_id, val.into_double()? as ffi::JDouble);
+        };
+
+        if id.ty() != JNonVoidType::Object {
+            return Err(Error::new(&format!("Static field {} isn't an object", name), JNI_ERR))
+        }
+
+        let obj = match self.get_static_field(cls, &id)?.into_obj()? {
+            Some(obj) => obj,
+            None => return Ok(None)
+        };
+        let target = self.find_class(T::class_name())?;
+        let is_instance = self.is_instance_of(&obj, &target);
+        self.delete_local_ref(target.downcast());
+        if !is_instance {
+            self.delete_local_ref(obj);
+            return Err(Error::new(&format!("Static field {} isn't a {}", name, T::class_name()), JNI_ERR))
+        }
+
+        // SAFETY: Checked the object is an instance of the class above
+        Ok(Some(unsafe { T::from_object_unchecked(obj) }))
+    }
+
+    /// Read a static primitive field as its rust type, such as an `int` field as [i32]
+    pub fn get_static_field_primitive<T: JavaPrimitive<'env>>(&self, cls: &JClass, name: &str) -> Result<T> {
+        let id = self.get_static_field_id(cls, name, T::java_name())?;
+        T::from_jvalue(self.get_static_field(cls, &id)?)
+    }
+
+    /// Get the ID of a public static field by name alone, whatever its declared type
+    fn static_field_by_name(&self, cls: &JClass, name: &str) -> Result<JFieldID> {
+        let cls_cls = get_cls!(self, "java.lang.Class");
+        let get_field = get_method_id!(self, cls_cls, "getField", "(java.lang.String) -> java.lang.reflect.Field");
+        let field_cls = get_cls!(self, "java.lang.reflect.Field");
+        let get_modifiers = get_method_id!(self, field_cls, "getModifiers", "() -> int");
+
+        let name_str = self.new_string_utf(name)?;
+        let field = self.call_value_method(cls.downcast(), get_field, &vec![name_str.as_object().into()]);
+        self.delete_local_ref(name_str.downcast());
+        let field = field?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Class.getField"))?;
+        let modifiers = self.call_value_method(&field, get_modifiers, &vec![]).and_then(|val| val.into_int());
+        let modifiers = match modifiers {
+            Ok(modifiers) => modifiers,
+            Err(err) => {
+                self.delete_local_ref(field);
+                return Err(err)
             }
+        };
+        if modifiers & MODIFIER_STATIC == 0 {
+            self.delete_local_ref(field);
+            return Err(Error::new(&format!("Field {} isn't static", name), JNI_ERR))
         }
 
-        Ok(())
+        let id = self.from_reflected_field(&field);
+        self.delete_local_ref(field);
+        id
     }
 
-    /// Create a new [String][JString] object from a slice of characters
-    pub fn new_string(&self, chars: &[char]) -> Result<JString> {
+    /// Create a new [String][JString] object from a slice of characters. Characters outside the
+    /// basic multilingual plane are stored as surrogate pairs
+    pub fn new_string(&self, chars: &[char]) -> Result<JString<'env>> {
         let env = self.internal_env();
 
-        let chars: Vec<u16> = chars.iter().map(|c| {*c as u16}).collect();
+        let mut units = Vec::with_capacity(chars.len());
+        for c in chars {
+            let mut buf = [0; 2];
+            units.extend_from_slice(c.encode_utf16(&mut buf));
+        }
 
-        let result = env.new_string(chars.as_ptr(), chars.len() as i32);
+        let result = self.track_local(env.new_string(units.as_ptr(), units.len() as i32));
         if result.is_null() {
             Err(Error::new("Couldn't create new string", JNI_ERR))
         } else {
@@ -1034,10 +1817,11 @@ impl JNIEnv {
         }
     }
 
-    /// Get a vector of the [char]s in a [String][JString]
+    /// Get a vector of the [char]s in a [String][JString]. Surrogate pairs are combined, and an
+    /// unpaired surrogate is an Err naming its index
     pub fn get_string_chars(&self, str: &JString) -> Result<Vec<char>> {
         let env = self.internal_env();
-        let mut is_copy = false;
+        let mut is_copy = JNI_FALSE;
 
         // SAFETY: Internal pointer use
         let chars = unsafe { env.get_string_chars(str.borrow_ptr(), &mut is_copy) };
@@ -1049,27 +1833,23 @@ impl JNIEnv {
         // SAFETY: Java verifies returned pointer will be valid until release_string_chars is called
         let raw_slice = unsafe { slice::from_raw_parts(chars, self.get_string_length(str)) };
 
-        let out = raw_slice
-            .into_iter()
-            .map(|c| {
-                std::char::from_u32(*c as u32).expect("Java returned bad char")
-            })
-            .collect();
+        let out = decode_utf16(raw_slice);
 
         // SAFETY: Internal pointer use
         unsafe {
             env.release_string_chars(str.borrow_ptr(), chars)
         }
 
-        Ok(out)
+        out
     }
 
-    /// Create a new [String][JString] object from a UTF string
-    pub fn new_string_utf(&self, str: &str) -> Result<JString> {
+    /// Create a new [String][JString] object from a UTF string. The string is passed to the JVM as
+    /// modified UTF-8, so it may contain any chars, including NUL
+    pub fn new_string_utf(&self, str: &str) -> Result<JString<'env>> {
         let env = self.internal_env();
-        let c_str = cstr_from_str(str)?;
+        let c_str = mutf8_from_str(str);
 
-        let new_str = env.new_string_utf(c_str.as_ptr());
+        let new_str = self.track_local(env.new_string_utf(c_str.as_ptr()));
         if new_str.is_null() {
             Err(Error::new("Couldn't create string from UTF", JNI_ERR))
         } else {
@@ -1087,10 +1867,12 @@ impl JNIEnv {
         }
     }
 
-    /// Get the characters of a [String][JString] as a slice of modified UTF bytes
+    /// Get the characters of a [String][JString] as the raw modified UTF-8 bytes the JVM gives.
+    /// These are not standard UTF-8, as NUL and characters outside the BMP are encoded differently,
+    /// see [get_string][Self::get_string] to read them into a rust string
     pub fn get_string_utf_chars(&self, str: &JString) -> Result<Vec<u8>> {
         let env = self.internal_env();
-        let mut is_copy = false;
+        let mut is_copy = JNI_FALSE;
 
         // SAFETY: Internal pointer use
         let chars = unsafe { env.get_string_utf_chars(str.borrow_ptr(), &mut is_copy) as *const u8 };
@@ -1112,6 +1894,13 @@ impl JNIEnv {
         Ok(vec)
     }
 
+    /// Read a [String][JString] into a rust string, decoding the modified UTF-8 the JVM gives. An
+    /// unpaired surrogate in the string is an Err naming its index, as rust strings can't hold one
+    pub fn get_string(&self, str: &JString) -> Result<String> {
+        let units = units_from_mutf8(&self.get_string_utf_chars(str)?)?;
+        Ok(decode_utf16(&units)?.into_iter().collect())
+    }
+
     /// Get the length of an array
     pub fn get_array_length(&self, array: &JArray) -> usize {
         let env = self.internal_env();
@@ -1123,8 +1912,8 @@ impl JNIEnv {
     }
 
     /// Create a new array of objects, with a type of the given class and initialized to the given
-    /// object value.
-    pub fn new_object_array(&self, len: usize, cls: &JClass, init: Option<&JObject>) -> Result<JObjectArray> {
+    /// object value. With no initial value, every element starts as null.
+    pub fn new_object_array(&self, len: usize, cls: &JClass, init: Option<&JObject>) -> Result<JObjectArray<'env>> {
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
@@ -1137,7 +1926,7 @@ impl JNIEnv {
         };
 
         // SAFETY: Internal pointer use
-        let result = unsafe { env.new_object_array(len as i32, cls.borrow_ptr(), raw_init) };
+        let result = unsafe { self.track_local(env.new_object_array(len as i32, cls.borrow_ptr(), raw_init)) };
 
         if result.is_null() {
             Err(Error::new("Couldn't create new object array", JNI_ERR))
@@ -1147,7 +1936,7 @@ impl JNIEnv {
     }
 
     /// Get the element of an object array at a given index
-    pub fn get_object_array_element(&self, array: &JObjectArray, idx: usize) -> Result<JObject> {
+    pub fn get_object_array_element(&self, array: &JObjectArray, idx: usize) -> Result<JObject<'env>> {
         let env = self.internal_env();
 
         if idx >= self.get_array_length(array.downcast()) {
@@ -1155,7 +1944,7 @@ impl JNIEnv {
         }
 
         // SAFETY: Internal pointer use
-        let result = unsafe { env.get_object_array_element(array.borrow_ptr(), idx as i32) };
+        let result = unsafe { self.track_local(env.get_object_array_element(array.borrow_ptr(), idx as i32)) };
         if result.is_null() {
             Err(Error::new("Failed to get array element", JNI_ERR))
         } else {
@@ -1163,6 +1952,28 @@ impl JNIEnv {
         }
     }
 
+    /// Collect the elements of an object array into a [Vec], treating null elements as the policy
+    /// says. Objects have no default besides null, so [NullPolicy::DefaultOf] is the same as
+    /// [NullPolicy::AsNone]
+    pub fn object_array_to_vec(&self, array: &JObjectArray, nulls: NullPolicy) -> Result<Vec<Option<JObject<'env>>>> {
+        let env = self.internal_env();
+        let len = self.get_array_length(array.downcast());
+
+        let mut out = Vec::with_capacity(len);
+        for idx in 0..len {
+            // SAFETY: Internal pointer use
+            let elem = unsafe { self.track_local(env.get_object_array_element(array.borrow_ptr(), idx as i32)) };
+            if self.exception_check() {
+                return Err(Error::new("Failed to get array element", JNI_ERR))
+            }
+
+            let elem = if elem.is_null() { None } else { Some(JObject::new(elem)?) };
+            out.push(nulls.apply(elem, "object_array_to_vec", idx, || None)?);
+        }
+
+        Ok(out)
+    }
+
     /// Set the element of an object array at a given index
     pub fn set_object_array_element(&self, array: &JObjectArray, idx: usize, val: &JObject) -> Result<()> {
         let env = self.internal_env();
@@ -1179,12 +1990,29 @@ impl JNIEnv {
         Ok(())
     }
 
-    /// Create a new java array of a primitive type
-    pub fn new_native_array(&self, len: usize, ty: JNativeType) -> Result<JNativeArray> {
+    /// Set the element of an object array at a given index to null
+    pub fn set_object_array_null(&self, array: &JObjectArray, idx: usize) -> Result<()> {
+        let env = self.internal_env();
+
+        if idx >= self.get_array_length(array.downcast()) {
+            return Err(Error::new("Index outside array bounds", JNI_ERR))
+        }
+
+        // SAFETY: Internal pointer use
+        unsafe {
+            env.set_object_array_element(array.borrow_ptr(), idx as i32, std::ptr::null_mut());
+        }
+
+        Ok(())
+    }
+
+    /// Create a new java array of a primitive type. The JNI guarantees every element starts as
+    /// zero, or false for booleans
+    pub fn new_native_array(&self, len: usize, ty: JNativeType) -> Result<JNativeArray<'env>> {
         let len = len as i32;
         let env = self.internal_env();
 
-        let result: *mut ffi::JArray = match ty {
+        let result: *mut ffi::JArray = self.track_local(match ty {
             JNativeType::Boolean =>
                 env.new_boolean_array(len) as _,
             JNativeType::Byte =>
@@ -1201,15 +2029,20 @@ impl JNIEnv {
                 env.new_float_array(len) as _,
             JNativeType::Double =>
                 env.new_double_array(len) as _
-        };
+        });
 
         if result.is_null() {
-            Err(Error::new("Couldn't create new native array", JNI_ERR))
-        } else {
-            // SAFETY: Types must match do to above match statement
-            unsafe {
-                Ok(JNativeArray::new_raw(result, ty)?)
-            }
+            return Err(Error::new("Couldn't create new native array", JNI_ERR))
+        }
+
+        // SAFETY: Types must match do to above match statement, which debug builds double check
+        #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+        unsafe {
+            JNativeArray::new_checked(self, result, ty)
+        }
+        #[cfg(not(any(debug_assertions, feature = "runtime-checks")))]
+        unsafe {
+            JNativeArray::new_raw(result, ty)
         }
     }
 
@@ -1220,7 +2053,7 @@ impl JNIEnv {
 
         // SAFETY: Internal pointer use
         unsafe {
-            let mut is_copy = false;
+            let mut is_copy = JNI_FALSE;
             let len = self.get_array_length(jarr);
 
             let ptr: *mut std::ffi::c_void = match arr {
@@ -1273,90 +2106,114 @@ impl JNIEnv {
             return Err(Error::new("Invalid array/slice combo", JNI_ERR))
         }
 
+        // SAFETY: Internal pointer use, the slice was checked to match the array
+        unsafe { self.release_native_array_ptr(arr, slice.borrow_ptr(), mode.into()) };
+        Ok(())
+    }
+
+    /// Release the elements of a primitive java array from a raw pointer, with a raw release mode.
+    /// Unsafe, the pointer must come from getting the elements of that same array
+    unsafe fn release_native_array_ptr(&self, arr: &JNativeArray, ptr: *mut std::ffi::c_void, mode: i32) {
         let env = self.internal_env();
-        let mode = mode.into();
 
-        // SAFETY: Internal pointer use
-        unsafe {
-            match arr {
-                JNativeArray::Boolean(arr) => {
-                    env.release_boolean_array_elements(arr.borrow_ptr(), slice.borrow_ptr() as _, mode)
-                }
-                JNativeArray::Byte(arr) => {
-                    env.release_byte_array_elements(arr.borrow_ptr(), slice.borrow_ptr() as _, mode)
-                }
-                JNativeArray::Char(arr) => {
-                    env.release_char_array_elements(arr.borrow_ptr(), slice.borrow_ptr() as _, mode)
-                }
-                JNativeArray::Short(arr) => {
-                    env.release_short_array_elements(arr.borrow_ptr(), slice.borrow_ptr() as _, mode)
-                }
-                JNativeArray::Int(arr) => {
-                    env.release_int_array_elements(arr.borrow_ptr(), slice.borrow_ptr() as _, mode)
-                }
-                JNativeArray::Long(arr) => {
-                    env.release_long_array_elements(arr.borrow_ptr(), slice.borrow_ptr() as _, mode)
-                }
-                JNativeArray::Float(arr) => {
-                    env.release_float_array_elements(arr.borrow_ptr(), slice.borrow_ptr() as _, mode)
-                }
-                JNativeArray::Double(arr) => {
-                    env.release_double_array_elements(arr.borrow_ptr(), slice.borrow_ptr() as _, mode)
-                }
+        match arr {
+            JNativeArray::Boolean(arr) => {
+                env.release_boolean_array_elements(arr.borrow_ptr(), ptr as _, mode)
+            }
+            JNativeArray::Byte(arr) => {
+                env.release_byte_array_elements(arr.borrow_ptr(), ptr as _, mode)
+            }
+            JNativeArray::Char(arr) => {
+                env.release_char_array_elements(arr.borrow_ptr(), ptr as _, mode)
+            }
+            JNativeArray::Short(arr) => {
+                env.release_short_array_elements(arr.borrow_ptr(), ptr as _, mode)
+            }
+            JNativeArray::Int(arr) => {
+                env.release_int_array_elements(arr.borrow_ptr(), ptr as _, mode)
+            }
+            JNativeArray::Long(arr) => {
+                env.release_long_array_elements(arr.borrow_ptr(), ptr as _, mode)
+            }
+            JNativeArray::Float(arr) => {
+                env.release_float_array_elements(arr.borrow_ptr(), ptr as _, mode)
+            }
+            JNativeArray::Double(arr) => {
+                env.release_double_array_elements(arr.borrow_ptr(), ptr as _, mode)
             }
         }
+    }
 
-        Ok(())
+    /// Check a region lies within an array, before reading or writing it
+    fn check_array_region(&self, arr: &JArray, start: usize, len: usize) -> Result<()> {
+        let arr_len = self.get_array_length(arr);
+        match start.checked_add(len) {
+            Some(end) if end <= arr_len => Ok(()),
+            _ => Err(Error::new(
+                &format!("Array region {}..{} out of bounds for length {}", start, start.saturating_add(len), arr_len),
+                JNI_ERR
+            ))
+        }
     }
 
+    region_readers!(
+        read_boolean_region_into, JBooleanArray, ffi::JBoolean, get_boolean_array_region, "a boolean array",
+        note "Values are left as [JNI_TRUE][ffi::constants::JNI_TRUE] and [JNI_FALSE], as the JVM doesn't promise every element is one of the two"
+    );
+    region_readers!(read_byte_region_into, JByteArray, i8, get_byte_array_region, "a byte array");
+    region_readers!(read_char_region_into, JCharArray, u16, get_char_array_region, "a char array");
+    region_readers!(read_short_region_into, JShortArray, i16, get_short_array_region, "a short array");
+    region_readers!(read_int_region_into, JIntArray, i32, get_int_array_region, "an int array");
+    region_readers!(read_long_region_into, JLongArray, i64, get_long_array_region, "a long array");
+    region_readers!(read_float_region_into, JFloatArray, f32, get_float_array_region, "a float array");
+    region_readers!(read_double_region_into, JDoubleArray, f64, get_double_array_region, "a double array");
+
     /// Get a partial slice of a primitive java array
     pub fn get_native_array_region(&self, arr: &JNativeArray, start: usize, len: usize) -> Result<JNativeVec> {
-        let env = self.internal_env();
+        let out = match arr {
+            JNativeArray::Boolean(arr) => {
+                let mut out = vec![JNI_FALSE; len];
+                self.read_boolean_region_into(arr, start, &mut out)?;
+                JNativeVec::Boolean(out.into_iter().map(|val| val != JNI_FALSE).collect())
+            }
+            JNativeArray::Byte(arr) => {
+                let mut out = vec![Default::default(); len];
+                self.read_byte_region_into(arr, start, &mut out)?;
+                JNativeVec::Byte(out)
+            }
+            JNativeArray::Char(arr) => {
+                let mut out = vec![Default::default(); len];
+                self.read_char_region_into(arr, start, &mut out)?;
+                JNativeVec::Char(out)
+            }
+            JNativeArray::Short(arr) => {
+                let mut out = vec![Default::default(); len];
+                self.read_short_region_into(arr, start, &mut out)?;
+                JNativeVec::Short(out)
+            }
+            JNativeArray::Int(arr) => {
+                let mut out = vec![Default::default(); len];
+                self.read_int_region_into(arr, start, &mut out)?;
+                JNativeVec::Int(out)
+            }
+            JNativeArray::Long(arr) => {
+                let mut out = vec![Default::default(); len];
+                self.read_long_region_into(arr, start, &mut out)?;
+                JNativeVec::Long(out)
+            }
+            JNativeArray::Float(arr) => {
+                let mut out = vec![Default::default(); len];
+                self.read_float_region_into(arr, start, &mut out)?;
+                JNativeVec::Float(out)
+            }
+            JNativeArray::Double(arr) => {
+                let mut out = vec![Default::default(); len];
+                self.read_double_region_into(arr, start, &mut out)?;
+                JNativeVec::Double(out)
+            }
+        };
 
-        unsafe {
-            Ok(match arr {
-                JNativeArray::Boolean(arr) => {
-                    let mut out = Vec::with_capacity(len);
-                    env.get_boolean_array_region(arr.borrow_ptr(), start as i32, len as i32, out.as_mut_ptr());
-                    JNativeVec::Boolean(out)
-                }
-                JNativeArray::Byte(arr) => {
-                    let mut out = Vec::with_capacity(len);
-                    env.get_byte_array_region(arr.borrow_ptr(), start as i32, len as i32, out.as_mut_ptr());
-                    JNativeVec::Byte(out)
-                }
-                JNativeArray::Char(arr) => {
-                    let mut out = Vec::with_capacity(len);
-                    env.get_char_array_region(arr.borrow_ptr(), start as i32, len as i32, out.as_mut_ptr());
-                    JNativeVec::Char(out.into_iter().map(|c| {std::char::from_u32(c as u32).expect("Java returned bad char")}).collect())
-                }
-                JNativeArray::Short(arr) => {
-                    let mut out = Vec::with_capacity(len);
-                    env.get_short_array_region(arr.borrow_ptr(), start as i32, len as i32, out.as_mut_ptr());
-                    JNativeVec::Short(out)
-                }
-                JNativeArray::Int(arr) => {
-                    let mut out = Vec::with_capacity(len);
-                    env.get_int_array_region(arr.borrow_ptr(), start as i32, len as i32, out.as_mut_ptr());
-                    JNativeVec::Int(out)
-                }
-                JNativeArray::Long(arr) => {
-                    let mut out = Vec::with_capacity(len);
-                    env.get_long_array_region(arr.borrow_ptr(), start as i32, len as i32, out.as_mut_ptr());
-                    JNativeVec::Long(out)
-                }
-                JNativeArray::Float(arr) => {
-                    let mut out = Vec::with_capacity(len);
-                    env.get_float_array_region(arr.borrow_ptr(), start as i32, len as i32, out.as_mut_ptr());
-                    JNativeVec::Float(out)
-                }
-                JNativeArray::Double(arr) => {
-                    let mut out = Vec::with_capacity(len);
-                    env.get_double_array_region(arr.borrow_ptr(), start as i32, len as i32, out.as_mut_ptr());
-                    JNativeVec::Double(out)
-                }
-            })
-        }
+        Ok(out)
     }
 
     /// Release a partial slice of a primitive java array
@@ -1373,9 +2230,9 @@ impl JNIEnv {
         unsafe {
             match arr {
                 JNativeArray::Boolean(arr) => {
-                    let temp: &Vec<_>;
+                    let temp: Vec<_>;
                     if let JNativeVec::Boolean(vec) = slice {
-                        temp = vec;
+                        temp = vec.iter().map(|&val| ffi::JBoolean::from(val)).collect();
                     } else {
                         unreachable!()
                     }
@@ -1393,9 +2250,9 @@ impl JNIEnv {
                     env.set_byte_array_region(arr.borrow_ptr(), start, len, temp.as_ptr());
                 }
                 JNativeArray::Char(arr) => {
-                    let temp: Vec<_>;
+                    let temp: &Vec<_>;
                     if let JNativeVec::Char(vec) = slice {
-                        temp = vec.iter().map(|c| {*c as u16}).collect()
+                        temp = vec;
                     } else {
                         unreachable!()
                     }
@@ -1458,8 +2315,11 @@ impl JNIEnv {
         }
     }
 
-    /// Register a set of native methods to a Java class
+    /// Register a set of native methods to a Java class. The class is remembered first, so the
+    /// natives are unregistered by [jni_on_unload][crate::jni_on_unload] when the library is
+    /// unloaded, and nothing is registered if it can't be remembered
     pub fn register_natives(&self, cls: &JClass, methods: &[JNINativeMethod]) -> Result<()> {
+        self.record_natives(cls)?;
         let env = self.internal_env();
 
         let methods = JNINativeMethod::make_ffi_vec(methods);
@@ -1473,6 +2333,32 @@ impl JNIEnv {
         }
     }
 
+    /// Register a set of native methods to a Java class, first checking no method appears twice.
+    /// The JVM lets the last of two registrations with the same name and signature win, which
+    /// hides copy-paste mistakes, so duplicates are an Err listing each collision
+    pub fn register_natives_checked(&self, cls: &JClass, methods: &[JNINativeMethod]) -> Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        let mut dupes = Vec::new();
+        for method in methods {
+            let key = (method.name(), method.signature());
+            if !seen.insert(key) && !dupes.contains(&key) {
+                dupes.push(key);
+            }
+        }
+
+        if !dupes.is_empty() {
+            let dupes: Vec<_> = dupes.iter()
+                .map(|(name, sig)| format!("{}{}", name, sig))
+                .collect();
+            return Err(Error::new(
+                &format!("Native methods registered more than once: {}", dupes.join(", ")),
+                JNI_ERR
+            ))
+        }
+
+        self.register_natives(cls, methods)
+    }
+
     /// Unregister native methods from a java class
     pub fn unregister_natives(&self, cls: &JClass) -> Result<()> {
         let env = self.internal_env();
@@ -1512,26 +2398,85 @@ impl JNIEnv {
         }
     }
 
+    /// Get the `java.lang.Thread` object of the current thread
+    fn current_thread(&self) -> Result<JObject<'env>> {
+        let cls = get_cls!(self, "java.lang.Thread");
+        let id = get_static_method_id!(self, cls, "currentThread", "() -> java.lang.Thread");
+
+        self.call_static_method(cls, id, &vec![])?
+            .expect("Unexpected void result")
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Thread.currentThread"))
+    }
+
+    /// Check whether the current Java thread has been interrupted, without clearing the flag
+    pub fn thread_interrupted(&self) -> Result<bool> {
+        let cls = get_cls!(self, "java.lang.Thread");
+        let id = get_method_id!(self, cls, "isInterrupted", "() -> boolean");
+
+        self.call_value_method(&self.current_thread()?, id, &vec![])?
+            .into_bool()
+    }
+
+    /// Clear the current Java thread's interrupt flag, returning whether it was set
+    pub fn clear_interrupt(&self) -> Result<bool> {
+        let cls = get_cls!(self, "java.lang.Thread");
+        let id = get_static_method_id!(self, cls, "interrupted", "() -> boolean");
+
+        self.call_static_method(cls, id, &vec![])?
+            .expect("Unexpected void result")
+            .into_bool()
+    }
+
+    /// Set the current Java thread's interrupt flag
+    pub fn interrupt_current(&self) -> Result<()> {
+        let cls = get_cls!(self, "java.lang.Thread");
+        let id = get_method_id!(self, cls, "interrupt", "() -> void");
+
+        self.call_method(&self.current_thread()?, id, &vec![])?;
+        Ok(())
+    }
+
+    /// Return [Error::Interrupted] if the current Java thread has been interrupted, so long-running
+    /// native code can bail out with `?`. Like `Thread.interrupted`, this clears the flag, as the
+    /// error takes its place
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.clear_interrupt()? {
+            Err(Error::Interrupted)
+        } else {
+            Ok(())
+        }
+    }
+
     /// Get the JVM instance associated with this environment
     pub fn get_jvm(&self) -> Result<JavaVM> {
         let env = self.internal_env();
         let mut vm = std::ptr::null_mut();
-        env.get_java_vm(&mut vm);
+        let result = env.get_java_vm(&mut vm);
+        if result != 0 {
+            return Err(Error::new("Couldn't get JVM from environment", result))
+        }
 
         JavaVM::new(self.version, vm, false)
     }
 
-    /// Get a region of a string as a vector of chars
+    /// Get a region of a string as a vector of chars. An unpaired surrogate in the region, including
+    /// half of a pair split by its bounds, is an Err
     pub fn get_string_region(&self, str: JString, start: usize, len: usize) -> Result<Vec<char>> {
         let env = self.internal_env();
-        let mut buffer = Vec::with_capacity(len);
+        let mut buffer = vec![0; len];
 
         // SAFETY: Internal pointer use
         unsafe {
             env.get_string_region(str.borrow_ptr(), start as i32, len as i32, buffer.as_mut_ptr());
         }
 
-        Ok(buffer.into_iter().map(|c| {std::char::from_u32(c as u32).expect("Java returned bad char")}).collect())
+        // An out of range region throws StringIndexOutOfBoundsException
+        if self.exception_check() {
+            return Err(self.call_exception_error())
+        }
+
+        decode_utf16(&buffer)
     }
 
     /// Get a region of a string as a vector of bytes
@@ -1558,7 +2503,7 @@ impl JNIEnv {
 
         // SAFETY: Internal pointer use
         unsafe {
-            let mut is_copy = false;
+            let mut is_copy = JNI_FALSE;
             let len = self.get_array_length(jarr);
             let ptr = env.get_primitive_array_critical(jarr.borrow_ptr() as _, &mut is_copy);
 
@@ -1619,8 +2564,12 @@ impl JNIEnv {
         }
     }
 
-    /// Delete an existing weak global reference to an object
+    /// Delete an existing weak global reference to an object. Does nothing once the JVM has been
+    /// destroyed, as the reference died with it
     pub fn delete_weak_global_ref(&self, weak: JWeak<'static>) {
+        if vm_destroyed() {
+            return
+        }
         let env = self.internal_env();
 
         unsafe {
@@ -1628,14 +2577,74 @@ impl JNIEnv {
         }
     }
 
-    /// Create a new direct byte buffer from a slice of bytes
-    pub fn new_direct_byte_buffer<'a>(&self, buff: &'a mut [u8]) -> Result<JObject<'a>> {
+    /// Get a new local reference to the referent of a weak global reference, or None if it's been
+    /// collected. The local reference keeps the referent alive for as long as it's in use
+    pub fn upgrade_weak(&self, weak: &JWeak) -> Result<Option<JObject<'env>>> {
         let env = self.internal_env();
 
-        let obj = env.new_direct_byte_buffer(
+        // SAFETY: Internal pointer use, weak references are valid anywhere a reference is
+        let obj = unsafe { self.track_local(env.new_local_ref(weak.borrow_ptr() as _)) };
+        if !obj.is_null() {
+            Ok(Some(JObject::new(obj)?))
+        } else if !self.exception_check() && self.is_weak_collected(weak) {
+            Ok(None)
+        } else {
+            Err(Error::new("Couldn't upgrade weak global reference", JNI_ERR))
+        }
+    }
+
+    /// Check whether the referent of a weak global reference has been collected, which leaves the
+    /// reference the same as null
+    pub(crate) fn is_weak_collected(&self, weak: &JWeak) -> bool {
+        let env = self.internal_env();
+
+        // SAFETY: Internal pointer use
+        unsafe {
+            env.is_same_object(weak.borrow_ptr() as _, std::ptr::null_mut()) != JNI_FALSE
+        }
+    }
+
+    /// Call a method with a weak global reference as the receiver. The reference is upgraded for
+    /// the length of the call, so the referent can't be collected partway through. The outer
+    /// Option is None if the referent was already collected, otherwise the inner Option is the
+    /// result as for [call_method][Self::call_method]
+    pub fn call_method_weak(&self, weak: &JWeak, id: &JMethodID, args: &[JValue]) -> Result<Option<Option<JValue<'env>>>> {
+        let obj = match self.upgrade_weak(weak)? {
+            Some(obj) => obj,
+            None => return Ok(None)
+        };
+
+        let result = self.call_method(&obj, id, args);
+        self.delete_local_ref(obj);
+        result.map(Some)
+    }
+
+    /// Get the value of a field of an object held by a weak global reference. Returns None if the
+    /// referent was already collected
+    pub fn get_field_weak(&self, weak: &JWeak, id: &JFieldID) -> Result<Option<JValue<'env>>> {
+        let obj = match self.upgrade_weak(weak)? {
+            Some(obj) => obj,
+            None => return Ok(None)
+        };
+
+        let result = self.get_field(&obj, id);
+        self.delete_local_ref(obj);
+        result.map(Some)
+    }
+
+    /// Create a new direct byte buffer from a slice of bytes. Requires JNI 1.4, older environments
+    /// get [Error::Unsupported]
+    pub fn new_direct_byte_buffer<'a>(&self, buff: &'a mut [u8]) -> Result<JObject<'a>>
+        where
+            'env: 'a
+    {
+        self.check_nio_support()?;
+        let env = self.internal_env();
+
+        let obj = self.track_local(env.new_direct_byte_buffer(
             buff.as_mut_ptr() as *mut std::ffi::c_void,
             buff.len() as i64
-        );
+        ));
 
         if obj.is_null() {
             Err(Error::new("Couldn't create direct byte buffer", JNI_ERR))
@@ -1644,8 +2653,10 @@ impl JNIEnv {
         }
     }
 
-    /// Get a slice from a direct byte buffer object
+    /// Get a slice from a direct byte buffer object. Requires JNI 1.4, older environments get
+    /// [Error::Unsupported]
     pub fn get_direct_buffer_slice<'a>(&self, buff: &JObject<'a>) -> Result<&'a mut [u8]> {
+        self.check_nio_support()?;
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use, returned pointer is guaranteed valid as long as buffer is valid
@@ -1661,6 +2672,18 @@ impl JNIEnv {
         }
     }
 
+    /// Check the environment provides the NIO functions, which were added in JNI 1.4. Older
+    /// function tables may not have the slots at all
+    fn check_nio_support(&self) -> Result<()> {
+        if self.version < JNIVersion::Ver14 {
+            Err(Error::new_unsupported(&format!(
+                "Direct buffers require JNI 1.4 or newer, but the environment is {:?}", self.version
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
     /// Get the type of a reference, this function can be used to determine if a reference has been
     /// GCed and is thus no longer safe to use
     pub fn get_object_ref_type(&self, obj: &JObject) -> JRefType {
@@ -1672,17 +2695,48 @@ impl JNIEnv {
     }
 
     /// Get the module a class is defined in
-    pub fn get_module(&self, cls: &JClass) -> Result<JObject> {
+    pub fn get_module(&self, cls: &JClass) -> Result<JObject<'env>> {
         let env = self.internal_env();
 
         // SAFETY: Internal pointer use
-        let result = unsafe { env.get_module(cls.borrow_ptr()) };
+        let result = unsafe { self.track_local(env.get_module(cls.borrow_ptr())) };
         if result.is_null() {
             Err(Error::new("Couldn't get module for class", JNI_ERR))
         } else {
             Ok(JObject::new(result)?)
         }
     }
+
+    /// Make one module read another, allowing `from` to access the types `to` exports to it.
+    /// The JVM only lets a named module change its own reads, so with no Java caller on the stack
+    /// this is only effective when `from` is an unnamed module.
+    pub fn add_module_reads(&self, from: &JObject, to: &JObject) -> Result<()> {
+        let mod_cls = self.find_class("java.lang.Module")?;
+        let add_reads = self.get_method_id(&mod_cls, "addReads", "(java.lang.Module) -> java.lang.Module")?;
+
+        self.call_method(from, &add_reads, &vec![to.into()])?;
+        Ok(())
+    }
+
+    /// Check whether code in module `from` can access the public types of package `pkg` in
+    /// module `to`. This requires both that `from` reads `to`, and that `to` exports the package
+    /// to `from`.
+    pub fn module_can_access(&self, from: &JObject, pkg: &str, to: &JObject) -> Result<bool> {
+        let mod_cls = self.find_class("java.lang.Module")?;
+        let can_read = self.get_method_id(&mod_cls, "canRead", "(java.lang.Module) -> boolean")?;
+        let is_exported = self.get_method_id(&mod_cls, "isExported", "(java.lang.String, java.lang.Module) -> boolean")?;
+
+        let reads = self.call_value_method(from, &can_read, &vec![to.into()])?
+            .into_bool()?;
+        if !reads {
+            return Ok(false)
+        }
+
+        let pkg = self.new_string_utf(pkg)?;
+        self.call_value_method(to, &is_exported, &vec![pkg.downcast().into(), from.into()])?
+            .into_bool()
+    }
+
 }
 
 #[cfg(test)]
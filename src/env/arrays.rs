@@ -0,0 +1,161 @@
+//!
+//! Module containing array helpers beyond the core JNI array functions.
+//!
+
+use std::ops::{Deref, DerefMut};
+use std::ffi::c_void;
+use std::slice;
+
+use crate::{ffi, JNativeArray, JNativeSlice, JNativeType, JNativeVec, ReleaseMode};
+use crate::ffi::constants::{JNI_ERR, JNI_COMMIT, JNI_ABORT, JNI_FALSE};
+use crate::types::{JValue, JObject, JByteArray, JMethodID, JavaDownCast};
+use crate::error::{Error, Result};
+use super::JNIEnv;
+
+///
+/// The elements of a primitive java array, borrowed by
+/// [with_native_array_elements][JNIEnv::with_native_array_elements]. Dereferences to the
+/// [JNativeSlice] of the elements. Changes reach the array according to the release mode passed
+/// when borrowing, or earlier through [commit][Self::commit].
+///
+pub struct ArrayElements<'a, 'env> {
+    env: &'a JNIEnv<'env>,
+    arr: &'a JNativeArray<'a>,
+    slice: JNativeSlice<'a>,
+    mode: i32
+}
+
+impl ArrayElements<'_, '_> {
+
+    /// Write the current elements back to the array, without giving up the borrow. Later changes
+    /// are still handled by the release mode
+    pub fn commit(&self) -> Result<()> {
+        // SAFETY: Internal pointer use, the slice came from this array
+        unsafe { self.env.release_native_array_ptr(self.arr, self.slice.borrow_ptr(), JNI_COMMIT) };
+        Ok(())
+    }
+}
+
+impl<'a> Deref for ArrayElements<'a, '_> {
+    type Target = JNativeSlice<'a>;
+
+    fn deref(&self) -> &JNativeSlice<'a> {
+        &self.slice
+    }
+}
+
+impl<'a> DerefMut for ArrayElements<'a, '_> {
+    fn deref_mut(&mut self) -> &mut JNativeSlice<'a> {
+        &mut self.slice
+    }
+}
+
+impl Drop for ArrayElements<'_, '_> {
+    fn drop(&mut self) {
+        // SAFETY: Internal pointer use, the slice came from this array and isn't used again
+        unsafe { self.env.release_native_array_ptr(self.arr, self.slice.borrow_ptr(), self.mode) };
+    }
+}
+
+/// Releases the critically borrowed elements of an array when dropped, discarding any changes
+struct CriticalRelease<'a, 'env> {
+    env: &'a JNIEnv<'env>,
+    arr: *mut ffi::JArray,
+    ptr: *mut c_void
+}
+
+impl Drop for CriticalRelease<'_, '_> {
+    fn drop(&mut self) {
+        let env = self.env.internal_env();
+        env.release_primitive_array_critical(self.arr, self.ptr, JNI_ABORT);
+    }
+}
+
+impl<'env> JNIEnv<'env> {
+
+    /// Borrow the elements of a primitive java array for the length of a closure, releasing them
+    /// with the given mode once it returns or panics. [ReleaseMode::CopyFree] writes changes back,
+    /// while [ReleaseMode::Abort] discards any not already written with
+    /// [commit][ArrayElements::commit]. The elements are always freed at the end, so
+    /// [ReleaseMode::Commit] acts as `CopyFree`
+    pub fn with_native_array_elements<'a, R, F>(&'a self, arr: &'a JNativeArray<'a>, mode: ReleaseMode, f: F) -> Result<R>
+        where
+            F: FnOnce(&mut ArrayElements<'a, 'env>) -> R
+    {
+        let mode = match mode {
+            ReleaseMode::Commit => ReleaseMode::CopyFree,
+            mode => mode
+        };
+
+        let mut elements = ArrayElements {
+            env: self,
+            arr,
+            slice: self.get_native_array_elements(arr)?,
+            mode: mode.into()
+        };
+        Ok(f(&mut elements))
+    }
+
+    /// Borrow the bytes of a java byte array for the length of a closure, through
+    /// `GetPrimitiveArrayCritical` so the JVM may hand them over without a copy. The bytes are
+    /// released once the closure returns or panics. While they're borrowed the JVM may hold off
+    /// garbage collection, so the closure must not call into the JNI, or block on threads that do
+    pub fn with_byte_array_critical<T, F>(&self, arr: &JByteArray, f: F) -> Result<T>
+        where
+            F: FnOnce(&[u8]) -> T
+    {
+        let env = self.internal_env();
+        let len = self.get_array_length(arr.downcast());
+
+        let mut is_copy = JNI_FALSE;
+        // SAFETY: Internal pointer use
+        let ptr = unsafe { env.get_primitive_array_critical(arr.borrow_ptr() as _, &mut is_copy) };
+        if ptr.is_null() {
+            return Err(Error::new("Couldn't get critical array elements", JNI_ERR))
+        }
+
+        // SAFETY: Internal pointer use
+        let _release = CriticalRelease { env: self, arr: unsafe { arr.borrow_ptr() } as _, ptr };
+        // SAFETY: The JVM gave a pointer to the array's bytes, valid until released after the closure
+        let bytes = unsafe { slice::from_raw_parts(ptr as *const u8, len) };
+        Ok(f(bytes))
+    }
+
+    /// Narrow an object to a primitive java array, inspecting the name of its class to pick the
+    /// right [JNativeArray] variant. Returns None if the object isn't a primitive array
+    pub fn as_native_array<'a>(&self, obj: JObject<'a>) -> Result<Option<JNativeArray<'a>>> {
+        let (cls, name) = self.object_class_and_name(&obj)?;
+        self.delete_local_ref(cls.downcast());
+
+        match JNativeType::from_array_descriptor(&name) {
+            // SAFETY: Internal pointer use, the array type was checked against the class above
+            Some(ty) => unsafe { Ok(Some(JNativeArray::new_raw(obj.borrow_ptr() as _, ty)?)) },
+            None => Ok(None)
+        }
+    }
+
+    /// Call a method returning a primitive array, and read the whole returned array into a
+    /// [JNativeVec]. Returns None if the method returned null, and Err if it returned something
+    /// other than an array of the expected type
+    pub fn call_native_array_method(&self, obj: &JObject, id: &JMethodID, args: &[JValue], ty: JNativeType) -> Result<Option<JNativeVec>> {
+        let result = self.call_method(obj, id, args)?
+            .ok_or_else(|| Error::new("call_native_array_method expected a method returning an array, not void", JNI_ERR))?
+            .into_obj()?;
+
+        let result = match result {
+            Some(result) => result,
+            None => return Ok(None)
+        };
+
+        let arr = match self.as_native_array(result)? {
+            Some(arr) if arr.jtype() == ty => arr,
+            _ => return Err(Error::new(
+                &format!("call_native_array_method expected the method to return a {:?} array", ty),
+                JNI_ERR
+            ))
+        };
+
+        let len = self.get_array_length(arr.as_jarray());
+        self.get_native_array_region(&arr, 0, len).map(Some)
+    }
+}
@@ -0,0 +1,55 @@
+//!
+//! Module containing a local reference deleted when dropped, for native code creating many
+//! references before returning to Java.
+//!
+
+use std::ops::Deref;
+
+use crate::types::JObject;
+use super::JNIEnv;
+
+///
+/// A local reference deleted when dropped, created by [JNIEnv::auto_local]. Local references
+/// otherwise live until the native method returns, so loops creating one per iteration can run out
+/// of room in the local reference table. Borrows the environment, so it can't outlive it.
+///
+pub struct AutoLocal<'a, 'env> {
+    env: &'a JNIEnv<'env>,
+    obj: Option<JObject<'env>>
+}
+
+impl<'a, 'env> AutoLocal<'a, 'env> {
+
+    /// Give up ownership of the reference without deleting it, leaving it to live until the native
+    /// method returns, or to be deleted by hand
+    pub fn forget(mut self) -> JObject<'env> {
+        self.obj.take().expect("AutoLocal used after being forgotten")
+    }
+}
+
+impl<'env> Deref for AutoLocal<'_, 'env> {
+    type Target = JObject<'env>;
+
+    fn deref(&self) -> &JObject<'env> {
+        self.obj.as_ref().expect("AutoLocal used after being forgotten")
+    }
+}
+
+impl Drop for AutoLocal<'_, '_> {
+    fn drop(&mut self) {
+        if let Some(obj) = self.obj.take() {
+            self.env.delete_local_ref(obj);
+        }
+    }
+}
+
+impl<'env> JNIEnv<'env> {
+
+    /// Take ownership of a local reference, deleting it when the returned [AutoLocal] is dropped
+    pub fn auto_local<'a>(&'a self, obj: JObject<'env>) -> AutoLocal<'a, 'env> {
+        AutoLocal {
+            env: self,
+            obj: Some(obj)
+        }
+    }
+}
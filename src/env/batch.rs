@@ -0,0 +1,85 @@
+//!
+//! Module containing a helper for setting many fields of an object at once, checking every value
+//! before any of them are written.
+//!
+
+use crate::ffi::constants::JNI_ERR;
+use crate::types::{JNonVoidType, JValue, JObject, JFieldID};
+use crate::error::{Error, Result};
+use super::JNIEnv;
+
+///
+/// A batch of field writes to apply to one object. The JNI has no bulk field set, so applying the
+/// batch still sets each field separately, but every value is checked against its field's type
+/// first, so a mismatch leaves the object untouched. Object values aren't checked against the
+/// field's declared class, and the fields aren't checked to belong to the object's class.
+///
+#[derive(Default)]
+pub struct FieldBatch<'a, 'env> {
+    writes: Vec<(&'a JFieldID, JValue<'env>)>
+}
+
+impl<'a, 'env> FieldBatch<'a, 'env> {
+
+    /// Create a new, empty batch
+    pub fn new() -> FieldBatch<'a, 'env> {
+        FieldBatch {
+            writes: Vec::new()
+        }
+    }
+
+    /// Add a write of a value to a field. Later writes to the same field win
+    pub fn set(mut self, id: &'a JFieldID, val: impl Into<JValue<'env>>) -> Self {
+        self.writes.push((id, val.into()));
+        self
+    }
+
+    /// Get the number of writes in this batch
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    /// Check whether this batch has no writes
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// Check every value matches the type of the field it's written to
+    fn validate(&self) -> Result<()> {
+        for (idx, (id, val)) in self.writes.iter().enumerate() {
+            let matches = match (val, id.ty()) {
+                (JValue::Bool(_), JNonVoidType::Boolean) |
+                (JValue::Byte(_), JNonVoidType::Byte) |
+                (JValue::Char(_), JNonVoidType::Char) |
+                (JValue::Short(_), JNonVoidType::Short) |
+                (JValue::Int(_), JNonVoidType::Int) |
+                (JValue::Long(_), JNonVoidType::Long) |
+                (JValue::Float(_), JNonVoidType::Float) |
+                (JValue::Double(_), JNonVoidType::Double) |
+                (JValue::Object(_), JNonVoidType::Object) => true,
+                _ => false
+            };
+
+            if !matches {
+                return Err(Error::new(
+                    &format!("Write {} of field batch should be {:?}, got {:?}", idx, id.ty(), val),
+                    JNI_ERR
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Apply every write in this batch to an object, in the order they were added. Returns Err
+    /// without writing anything if any value doesn't match its field's type
+    pub fn apply(self, env: &JNIEnv<'env>, obj: &JObject) -> Result<()> {
+        self.validate()?;
+
+        for (id, val) in self.writes {
+            env.set_field(obj, id, val)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+//!
+//! Module containing I/O interop helpers, such as converting between Java file descriptors and
+//! raw OS handles.
+//!
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+#[cfg(windows)]
+use std::os::windows::io::RawHandle;
+
+use crate::ffi::constants::JNI_ERR;
+use crate::types::{JValue, JObject, JClass, JFieldID};
+use crate::error::{Error, Result};
+use super::JNIEnv;
+
+impl<'env> JNIEnv<'env> {
+
+    /// Look up the private field backing a `java.io.FileDescriptor`. Its layout isn't part of the
+    /// Java API, so a missing field is reported as unsupported
+    fn file_descriptor_field(&self, name: &str, ty: &str) -> Result<(JClass<'env>, JFieldID)> {
+        let fd_cls = self.find_class("java.io.FileDescriptor")?;
+
+        match self.get_field_id(&fd_cls, name, ty) {
+            Ok(id) => Ok((fd_cls, id)),
+            Err(_) => {
+                if self.exception_check() {
+                    self.exception_clear()?;
+                }
+                Err(Error::new_unsupported(&format!("java.io.FileDescriptor has no {} field `{}`", ty, name)))
+            }
+        }
+    }
+
+    /// Get the raw file descriptor backing a `java.io.FileDescriptor`. The descriptor is still
+    /// owned by the Java object, closing it from rust while Java can still use or close it will
+    /// cause a double close
+    #[cfg(unix)]
+    pub fn file_descriptor_to_raw(&self, fd_obj: &JObject) -> Result<RawFd> {
+        let (_, fd_id) = self.file_descriptor_field("fd", "int")?;
+
+        let fd = self.get_field(fd_obj, &fd_id)?.into_int()?;
+        if fd < 0 {
+            Err(Error::new("FileDescriptor isn't open", JNI_ERR))
+        } else {
+            Ok(fd)
+        }
+    }
+
+    /// Wrap a raw file descriptor in a new `java.io.FileDescriptor`. Ownership passes to Java, so
+    /// the descriptor shouldn't be closed from rust once a Java stream may close it
+    #[cfg(unix)]
+    pub fn raw_to_file_descriptor(&self, fd: RawFd) -> Result<JObject<'env>> {
+        let (fd_cls, fd_id) = self.file_descriptor_field("fd", "int")?;
+        let con_id = self.get_method_id(&fd_cls, "<init>", "() -> void")?;
+
+        let fd_obj = self.new_object(&fd_cls, &con_id, &vec![])?;
+        self.set_field(&fd_obj, &fd_id, JValue::Int(fd))?;
+        Ok(fd_obj)
+    }
+
+    /// Get the raw handle backing a `java.io.FileDescriptor`. The handle is still owned by the
+    /// Java object, closing it from rust while Java can still use or close it will cause a double
+    /// close
+    #[cfg(windows)]
+    pub fn file_descriptor_to_raw(&self, fd_obj: &JObject) -> Result<RawHandle> {
+        let (_, handle_id) = self.file_descriptor_field("handle", "long")?;
+
+        let handle = self.get_field(fd_obj, &handle_id)?.into_long()?;
+        if handle == -1 {
+            Err(Error::new("FileDescriptor isn't open", JNI_ERR))
+        } else {
+            Ok(handle as RawHandle)
+        }
+    }
+
+    /// Wrap a raw handle in a new `java.io.FileDescriptor`. Ownership passes to Java, so the
+    /// handle shouldn't be closed from rust once a Java stream may close it
+    #[cfg(windows)]
+    pub fn raw_to_file_descriptor(&self, handle: RawHandle) -> Result<JObject<'env>> {
+        let (fd_cls, handle_id) = self.file_descriptor_field("handle", "long")?;
+        let con_id = self.get_method_id(&fd_cls, "<init>", "() -> void")?;
+
+        let fd_obj = self.new_object(&fd_cls, &con_id, &vec![])?;
+        self.set_field(&fd_obj, &handle_id, JValue::Long(handle as i64))?;
+        Ok(fd_obj)
+    }
+}
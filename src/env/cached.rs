@@ -0,0 +1,128 @@
+//!
+//! Module containing an opt-in memoizing wrapper over method calls, for methods the caller knows
+//! always give the same result for the same arguments.
+//!
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::types::{JValue, JObject, JClass, JMethodID};
+use crate::error::Result;
+use super::JNIEnv;
+
+///
+/// A memoized method call. Results are stored under a key computed from the call's arguments, and
+/// later calls with the same key return the stored result without calling into Java. Object
+/// results are kept as global references owned by the cache, which are deleted by
+/// [clear][Self::clear]. Dropping a cache without clearing it leaks those references.
+///
+/// Only the arguments make up the key, so instance calls through one cache should all use the same
+/// receiver.
+///
+pub struct CachedCall<K, F> {
+    id: JMethodID,
+    key: F,
+    cache: RefCell<HashMap<K, JValue<'static>>>
+}
+
+impl<K, F> CachedCall<K, F>
+    where
+        K: Eq + Hash,
+        F: Fn(&[JValue]) -> K
+{
+
+    /// Create a new, empty cache for a method, keying calls with the given function
+    pub fn new(id: JMethodID, key: F) -> CachedCall<K, F> {
+        CachedCall {
+            id,
+            key,
+            cache: RefCell::new(HashMap::new())
+        }
+    }
+
+    /// Get the number of results currently cached
+    pub fn len(&self) -> usize {
+        self.cache.borrow().len()
+    }
+
+    /// Check whether no results are cached
+    pub fn is_empty(&self) -> bool {
+        self.cache.borrow().is_empty()
+    }
+
+    /// Get a cached result, or call the method and store its result. Void methods are never cached
+    fn get_or_call<'a, 'e>(
+        &'a self,
+        env: &JNIEnv,
+        args: &[JValue],
+        call: impl FnOnce() -> Result<Option<JValue<'e>>>
+    ) -> Result<Option<JValue<'a>>> {
+        let key = (self.key)(args);
+
+        if let Some(val) = self.cache.borrow().get(&key) {
+            return Ok(Some(alias(val)))
+        }
+
+        let val = match call()? {
+            Some(JValue::Object(Some(obj))) => {
+                let global = env.new_global_ref(&obj)?.leak();
+                env.delete_local_ref(obj);
+                JValue::Object(Some(global))
+            }
+            Some(val) => copy_primitive(&val),
+            None => return Ok(None)
+        };
+
+        let out = alias(&val);
+        self.cache.borrow_mut().insert(key, val);
+        Ok(Some(out))
+    }
+
+    /// Call the method on an object, or return the cached result for these arguments
+    pub fn call<'a>(&'a self, env: &JNIEnv, obj: &JObject, args: &[JValue]) -> Result<Option<JValue<'a>>> {
+        self.get_or_call(env, args, || env.call_method(obj, &self.id, args))
+    }
+
+    /// Call the method statically on a class, or return the cached result for these arguments
+    pub fn call_static<'a>(&'a self, env: &JNIEnv, cls: &JClass, args: &[JValue]) -> Result<Option<JValue<'a>>> {
+        self.get_or_call(env, args, || env.call_static_method(cls, &self.id, args))
+    }
+
+    /// Remove every cached result, deleting the global references held for objects
+    pub fn clear(&mut self, env: &JNIEnv) {
+        for (_, val) in self.cache.get_mut().drain() {
+            if let JValue::Object(Some(obj)) = val {
+                env.delete_global_ref(obj);
+            }
+        }
+    }
+}
+
+/// Borrow a cached value, without giving up the cache's ownership of object references. The
+/// caller ties the result's lifetime to the cache
+fn alias<'a>(val: &JValue) -> JValue<'a> {
+    match val {
+        JValue::Object(Some(obj)) => {
+            // SAFETY: Internal pointer use, the global reference lives until the cache is cleared
+            let ptr = unsafe { obj.borrow_ptr() };
+            JValue::Object(Some(JObject::new(ptr).expect("Null pointer in cached JObject")))
+        }
+        val => copy_primitive(val)
+    }
+}
+
+/// Copy a value which holds no object reference. Non-null objects are copied as null
+fn copy_primitive<'b>(val: &JValue) -> JValue<'b> {
+    match val {
+        JValue::Bool(val) => JValue::Bool(*val),
+        JValue::Byte(val) => JValue::Byte(*val),
+        JValue::Char(val) => JValue::Char(*val),
+        JValue::Short(val) => JValue::Short(*val),
+        JValue::Int(val) => JValue::Int(*val),
+        JValue::Long(val) => JValue::Long(*val),
+        JValue::Float(val) => JValue::Float(*val),
+        JValue::Double(val) => JValue::Double(*val),
+        JValue::Object(_) => JValue::Object(None)
+    }
+}
@@ -0,0 +1,285 @@
+//!
+//! Module containing a builder-style API for method calls, as an alternative to passing positional
+//! [JValue] slices when a call has many arguments.
+//!
+
+use crate::ffi::constants::JNI_ERR;
+use crate::types::{JType, JValue, JObject, JClass, JMethodID, JavaDownCast, JavaUpCast, FromJValue};
+use crate::error::{Error, Result};
+use crate::mangling::TypeSignature;
+use crate::get_cls;
+use super::JNIEnv;
+
+///
+/// A trait for Rust values which can be converted into a Java value, possibly by creating a new
+/// Java object. Anything convertible into a [JValue] can be, as well as `&str` and `String`, which
+/// become new Java strings.
+///
+pub trait IntoJava<'env> {
+    /// Whether the conversion creates a new local reference, which the receiver should free once
+    /// it's done with the value
+    const NEW_LOCAL: bool = false;
+
+    /// Convert this value into a JValue
+    fn into_java(self, env: &JNIEnv<'env>) -> Result<JValue<'env>>;
+}
+
+impl<'env, T: Into<JValue<'env>>> IntoJava<'env> for T {
+    fn into_java(self, _env: &JNIEnv<'env>) -> Result<JValue<'env>> {
+        Ok(self.into())
+    }
+}
+
+impl<'env> IntoJava<'env> for &str {
+    const NEW_LOCAL: bool = true;
+
+    fn into_java(self, env: &JNIEnv<'env>) -> Result<JValue<'env>> {
+        Ok(JValue::Object(Some(env.new_string_utf(self)?.downcast())))
+    }
+}
+
+impl<'env> IntoJava<'env> for String {
+    const NEW_LOCAL: bool = true;
+
+    fn into_java(self, env: &JNIEnv<'env>) -> Result<JValue<'env>> {
+        self.as_str().into_java(env)
+    }
+}
+
+///
+/// A trait for Rust values which can be converted from a Java value, possibly by reading a Java
+/// object. The opposite of [IntoJava]. Anything with [FromJValue] can be, as well as `String`,
+/// which is read from a Java string. Primitives can also use `TryFrom<JValue>` directly, without
+/// an environment.
+///
+pub trait FromJava<'env>: Sized {
+    /// Convert a JValue into this type, or Err if it holds a different type
+    fn from_java(val: JValue<'env>, env: &JNIEnv<'env>) -> Result<Self>;
+}
+
+impl<'env, T: FromJValue<'env>> FromJava<'env> for T {
+    fn from_java(val: JValue<'env>, _env: &JNIEnv<'env>) -> Result<Self> {
+        T::from_jvalue(val)
+    }
+}
+
+impl<'env> FromJava<'env> for String {
+    fn from_java(val: JValue<'env>, env: &JNIEnv<'env>) -> Result<Self> {
+        let obj = JObject::from_jvalue(val)?;
+        let str_cls = get_cls!(env, "java.lang.String");
+        if !env.is_instance_of(&obj, str_cls) {
+            return Err(Error::new("JValue isn't a string", JNI_ERR))
+        }
+
+        // SAFETY: Checked to be a String above
+        let str = unsafe { obj.upcast_raw() };
+        Ok(env.get_string_chars(&str)?.into_iter().collect())
+    }
+}
+
+/// Copy a value, aliasing the reference of an object value rather than creating a new one
+fn copy_value<'env>(val: &JValue<'env>) -> JValue<'env> {
+    match val {
+        JValue::Bool(val) => JValue::Bool(*val),
+        JValue::Byte(val) => JValue::Byte(*val),
+        JValue::Char(val) => JValue::Char(*val),
+        JValue::Short(val) => JValue::Short(*val),
+        JValue::Int(val) => JValue::Int(*val),
+        JValue::Long(val) => JValue::Long(*val),
+        JValue::Float(val) => JValue::Float(*val),
+        JValue::Double(val) => JValue::Double(*val),
+        JValue::Object(Some(obj)) => JValue::from(obj),
+        JValue::Object(None) => JValue::Object(None)
+    }
+}
+
+/// What a [MethodCall] will invoke
+enum CallTarget<'a, 'env> {
+    Method(&'a JObject<'env>),
+    Static(&'a JClass<'env>),
+    Constructor(&'a JClass<'env>)
+}
+
+///
+/// A builder for a single method call. The method ID is resolved once when the builder is created,
+/// arguments are accumulated one at a time, and their count and types are checked against the
+/// signature when the call is invoked. Strings passed as arguments are allocated by the builder,
+/// and freed once it's done.
+///
+pub struct MethodCall<'a, 'env> {
+    env: &'a JNIEnv<'env>,
+    target: CallTarget<'a, 'env>,
+    name: String,
+    id: JMethodID,
+    arg_types: Vec<JType>,
+    args: Vec<JValue<'env>>,
+    arena: Vec<JObject<'env>>,
+    error: Option<Error>
+}
+
+impl<'a, 'env> MethodCall<'a, 'env> {
+
+    fn new(env: &'a JNIEnv<'env>, target: CallTarget<'a, 'env>, name: &str, sig: &str) -> Result<MethodCall<'a, 'env>> {
+        let arg_types = match &env.parse_signature(sig).sig {
+            TypeSignature::Method(args, _) => args.iter().map(|arg| arg.java_type()).collect(),
+            _ => return Err(Error::new("Expected method signature", JNI_ERR))
+        };
+
+        let id = match &target {
+            CallTarget::Method(obj) => {
+                let cls = env.get_object_class(obj)?;
+                env.get_method_id(&cls, name, sig)?
+            }
+            CallTarget::Static(cls) => env.get_static_method_id(cls, name, sig)?,
+            CallTarget::Constructor(cls) => env.get_method_id(cls, name, sig)?
+        };
+
+        Ok(MethodCall {
+            env,
+            target,
+            name: String::from(name),
+            id,
+            arg_types,
+            args: Vec::new(),
+            arena: Vec::new(),
+            error: None
+        })
+    }
+
+    /// Add the next argument to the call. If converting the argument fails, the error is
+    /// returned when the call is invoked
+    pub fn arg<T: IntoJava<'env>>(mut self, val: T) -> Self {
+        match val.into_java(self.env) {
+            Ok(val) => {
+                if let (true, JValue::Object(Some(obj))) = (T::NEW_LOCAL, &val) {
+                    // The object is kept alive in the arena until the call ends
+                    self.arena.push(obj.share());
+                }
+                self.args.push(val);
+            }
+            Err(err) => {
+                self.error.get_or_insert(err);
+            }
+        }
+        self
+    }
+
+    /// Add the next argument to the call as a new Java string
+    pub fn arg_str(mut self, str: &str) -> Result<Self> {
+        self = self.arg(str);
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => Ok(self)
+        }
+    }
+
+    /// Check the accumulated arguments against the method signature
+    fn validate(&mut self) -> Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err)
+        }
+
+        if self.args.len() != self.arg_types.len() {
+            return Err(Error::new(
+                &format!("Method {} takes {} arguments, but {} were given", self.name, self.arg_types.len(), self.args.len()),
+                JNI_ERR
+            ))
+        }
+
+        for (idx, (arg, ty)) in self.args.iter().zip(self.arg_types.iter()).enumerate() {
+            let matches = match (arg, ty) {
+                (JValue::Bool(_), JType::Boolean) |
+                (JValue::Byte(_), JType::Byte) |
+                (JValue::Char(_), JType::Char) |
+                (JValue::Short(_), JType::Short) |
+                (JValue::Int(_), JType::Int) |
+                (JValue::Long(_), JType::Long) |
+                (JValue::Float(_), JType::Float) |
+                (JValue::Double(_), JType::Double) |
+                (JValue::Object(_), JType::Object) => true,
+                _ => false
+            };
+
+            if !matches {
+                return Err(Error::new(
+                    &format!("Argument {} of method {} should be {:?}, got {:?}", idx, self.name, ty, arg),
+                    JNI_ERR
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Invoke the call. Option is None if the method is void typed, otherwise a JValue containing
+    /// the return. Constructors return the new object.
+    pub fn invoke(mut self) -> Result<Option<JValue<'env>>> {
+        self.validate()?;
+
+        match &self.target {
+            CallTarget::Method(obj) => self.env.call_method(obj, &self.id, &self.args),
+            CallTarget::Static(cls) => self.env.call_static_method(cls, &self.id, &self.args),
+            CallTarget::Constructor(cls) => {
+                let obj = self.env.new_object(cls, &self.id, &self.args)?;
+                Ok(Some(JValue::Object(Some(obj))))
+            }
+        }
+    }
+
+    /// Invoke the call, converting the return into a specific type. Errors if the method is void
+    /// typed or returns a different type
+    pub fn invoke_returning<T: FromJava<'env>>(self) -> Result<T> {
+        let env = self.env;
+        let name = self.name.clone();
+        let val = self.invoke()?
+            .ok_or_else(|| Error::new(&format!("Method {} has no return value", name), JNI_ERR))?;
+
+        T::from_java(val, env)
+    }
+}
+
+impl Drop for MethodCall<'_, '_> {
+    fn drop(&mut self) {
+        for obj in self.arena.drain(..) {
+            self.env.delete_local_ref(obj);
+        }
+    }
+}
+
+impl<'env> JNIEnv<'env> {
+
+    /// Start building a call of a method on an object. The method is looked up on the object's
+    /// class, using the syntax defined in the root documentation for the signature
+    pub fn method<'a>(&'a self, obj: &'a JObject<'env>, name: &str, sig: &str) -> Result<MethodCall<'a, 'env>> {
+        MethodCall::new(self, CallTarget::Method(obj), name, sig)
+    }
+
+    /// Start building a call of a static method on a class
+    pub fn static_method<'a>(&'a self, cls: &'a JClass<'env>, name: &str, sig: &str) -> Result<MethodCall<'a, 'env>> {
+        MethodCall::new(self, CallTarget::Static(cls), name, sig)
+    }
+
+    /// Start building a call of a constructor of a class. Invoking the call creates the new object
+    pub fn constructor<'a>(&'a self, cls: &'a JClass<'env>, sig: &str) -> Result<MethodCall<'a, 'env>> {
+        MethodCall::new(self, CallTarget::Constructor(cls), "<init>", sig)
+    }
+
+    /// Construct an object with the constructor taking the given components in order, such as the
+    /// canonical constructor of a record. Each component is its type name, in the same syntax as
+    /// signatures, along with its value, so the constructor signature doesn't need writing out.
+    /// Returns Err if a value doesn't match its type, or the class has no such constructor
+    pub fn new_record(&self, class_name: &str, components: &[(&str, JValue<'env>)]) -> Result<JObject<'env>> {
+        let cls = self.find_class(class_name)?;
+        let types: Vec<&str> = components.iter().map(|(ty, _)| ty.trim()).collect();
+        let sig = format!("({}) -> void", types.join(", "));
+
+        let result = self.constructor(&cls, &sig).and_then(|mut call| {
+            for (_, val) in components {
+                call = call.arg(copy_value(val));
+            }
+            call.invoke_returning()
+        });
+        self.delete_local_ref(cls.downcast());
+        result
+    }
+}
@@ -0,0 +1,270 @@
+//!
+//! Module containing helpers for Java collection and container types.
+//!
+
+use crate::ffi::constants::JNI_ERR;
+use crate::types::{JValue, JObject, JClass, NullPolicy};
+use crate::error::{Error, Result};
+use crate::{get_cls, get_method_id, get_static_method_id};
+use super::JNIEnv;
+
+impl<'env> JNIEnv<'env> {
+
+    /// Unwrap a `java.util.Optional`, returning None if it's empty and the contained value
+    /// otherwise
+    pub fn unwrap_optional(&self, opt: &JObject) -> Result<Option<JObject<'env>>> {
+        let opt_cls = self.find_class("java.util.Optional")?;
+        let is_present = self.get_method_id(&opt_cls, "isPresent", "() -> boolean")?;
+        let get = self.get_method_id(&opt_cls, "get", "() -> java.lang.Object")?;
+
+        let present = self.call_value_method(opt, &is_present, &vec![])?
+            .into_bool()?;
+        if !present {
+            return Ok(None)
+        }
+
+        self.call_value_method(opt, &get, &vec![])?
+            .into_obj()
+    }
+
+    /// Check an object is an instance of a collection or container class, before calling its methods
+    fn check_instance(&self, opt: &JObject, cls: &JClass, name: &str) -> Result<()> {
+        if self.is_instance_of(opt, cls) {
+            Ok(())
+        } else {
+            Err(Error::new(&format!("Expected an instance of {}", name), JNI_ERR))
+        }
+    }
+
+    /// Convert a `java.util.Optional` into an Option, returning None if it's empty and the
+    /// contained value otherwise. Returns Err if the object isn't an Optional
+    pub fn optional_to_rust(&self, opt: &JObject) -> Result<Option<JObject<'env>>> {
+        let opt_cls = get_cls!(self, "java.util.Optional");
+        let or_else = get_method_id!(self, opt_cls, "orElse", "(java.lang.Object) -> java.lang.Object");
+        self.check_instance(opt, opt_cls, "java.util.Optional")?;
+
+        self.call_value_method(opt, or_else, &vec![JValue::Object(None)])?
+            .into_obj()
+    }
+
+    /// Convert an Option into a `java.util.Optional`, which is empty if the Option is None
+    pub fn rust_to_optional(&self, val: Option<&JObject>) -> Result<JObject<'env>> {
+        let opt_cls = get_cls!(self, "java.util.Optional");
+        let of_nullable = get_static_method_id!(self, opt_cls, "ofNullable", "(java.lang.Object) -> java.util.Optional");
+
+        let arg = val.map(JValue::from).unwrap_or(JValue::Object(None));
+        self.call_static_method(opt_cls, of_nullable, &vec![arg])?
+            .expect("Unexpected void result")
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Optional.ofNullable"))
+    }
+
+    /// Convert a `java.util.OptionalInt` into an Option. Returns Err if the object isn't an
+    /// OptionalInt
+    pub fn optional_int_to_rust(&self, opt: &JObject) -> Result<Option<i32>> {
+        let opt_cls = get_cls!(self, "java.util.OptionalInt");
+        let is_present = get_method_id!(self, opt_cls, "isPresent", "() -> boolean");
+        let get = get_method_id!(self, opt_cls, "getAsInt", "() -> int");
+        self.check_instance(opt, opt_cls, "java.util.OptionalInt")?;
+
+        let present = self.call_value_method(opt, is_present, &vec![])?
+            .into_bool()?;
+        if !present {
+            return Ok(None)
+        }
+
+        self.call_value_method(opt, get, &vec![])?
+            .into_int()
+            .map(Some)
+    }
+
+    /// Convert an Option into a `java.util.OptionalInt`, which is empty if the Option is None
+    pub fn rust_to_optional_int(&self, val: Option<i32>) -> Result<JObject<'env>> {
+        let opt_cls = get_cls!(self, "java.util.OptionalInt");
+        let result = match val {
+            Some(val) => {
+                let of = get_static_method_id!(self, opt_cls, "of", "(int) -> java.util.OptionalInt");
+                self.call_static_method(opt_cls, of, &vec![JValue::Int(val)])?
+            }
+            None => {
+                let empty = get_static_method_id!(self, opt_cls, "empty", "() -> java.util.OptionalInt");
+                self.call_static_method(opt_cls, empty, &vec![])?
+            }
+        };
+
+        result.expect("Unexpected void result")
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("OptionalInt construction"))
+    }
+
+    /// Convert a `java.util.OptionalLong` into an Option. Returns Err if the object isn't an
+    /// OptionalLong
+    pub fn optional_long_to_rust(&self, opt: &JObject) -> Result<Option<i64>> {
+        let opt_cls = get_cls!(self, "java.util.OptionalLong");
+        let is_present = get_method_id!(self, opt_cls, "isPresent", "() -> boolean");
+        let get = get_method_id!(self, opt_cls, "getAsLong", "() -> long");
+        self.check_instance(opt, opt_cls, "java.util.OptionalLong")?;
+
+        let present = self.call_value_method(opt, is_present, &vec![])?
+            .into_bool()?;
+        if !present {
+            return Ok(None)
+        }
+
+        self.call_value_method(opt, get, &vec![])?
+            .into_long()
+            .map(Some)
+    }
+
+    /// Convert an Option into a `java.util.OptionalLong`, which is empty if the Option is None
+    pub fn rust_to_optional_long(&self, val: Option<i64>) -> Result<JObject<'env>> {
+        let opt_cls = get_cls!(self, "java.util.OptionalLong");
+        let result = match val {
+            Some(val) => {
+                let of = get_static_method_id!(self, opt_cls, "of", "(long) -> java.util.OptionalLong");
+                self.call_static_method(opt_cls, of, &vec![JValue::Long(val)])?
+            }
+            None => {
+                let empty = get_static_method_id!(self, opt_cls, "empty", "() -> java.util.OptionalLong");
+                self.call_static_method(opt_cls, empty, &vec![])?
+            }
+        };
+
+        result.expect("Unexpected void result")
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("OptionalLong construction"))
+    }
+
+    /// Convert a `java.util.OptionalDouble` into an Option. Returns Err if the object isn't an
+    /// OptionalDouble
+    pub fn optional_double_to_rust(&self, opt: &JObject) -> Result<Option<f64>> {
+        let opt_cls = get_cls!(self, "java.util.OptionalDouble");
+        let is_present = get_method_id!(self, opt_cls, "isPresent", "() -> boolean");
+        let get = get_method_id!(self, opt_cls, "getAsDouble", "() -> double");
+        self.check_instance(opt, opt_cls, "java.util.OptionalDouble")?;
+
+        let present = self.call_value_method(opt, is_present, &vec![])?
+            .into_bool()?;
+        if !present {
+            return Ok(None)
+        }
+
+        self.call_value_method(opt, get, &vec![])?
+            .into_double()
+            .map(Some)
+    }
+
+    /// Convert an Option into a `java.util.OptionalDouble`, which is empty if the Option is None
+    pub fn rust_to_optional_double(&self, val: Option<f64>) -> Result<JObject<'env>> {
+        let opt_cls = get_cls!(self, "java.util.OptionalDouble");
+        let result = match val {
+            Some(val) => {
+                let of = get_static_method_id!(self, opt_cls, "of", "(double) -> java.util.OptionalDouble");
+                self.call_static_method(opt_cls, of, &vec![JValue::Double(val)])?
+            }
+            None => {
+                let empty = get_static_method_id!(self, opt_cls, "empty", "() -> java.util.OptionalDouble");
+                self.call_static_method(opt_cls, empty, &vec![])?
+            }
+        };
+
+        result.expect("Unexpected void result")
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("OptionalDouble construction"))
+    }
+
+    /// Get the number of elements in a `java.util.List`. Returns Err if the object isn't a List
+    pub fn list_len(&self, list: &JObject) -> Result<usize> {
+        let list_cls = get_cls!(self, "java.util.List");
+        let size = get_method_id!(self, list_cls, "size", "() -> int");
+        self.check_instance(list, list_cls, "java.util.List")?;
+
+        let len = self.call_value_method(list, size, &vec![])?
+            .into_int()?;
+        Ok(len as usize)
+    }
+
+    /// Collect the elements of a `java.util.List` into a [Vec], in order. The list is walked with
+    /// its iterator, so this is linear for any kind of list. Null elements are None. Returns Err
+    /// if the object isn't a List
+    pub fn list_to_vec(&self, list: &JObject) -> Result<Vec<Option<JObject<'env>>>> {
+        self.list_to_vec_with(list, NullPolicy::AsNone)
+    }
+
+    /// Collect the elements of a `java.util.List` as [list_to_vec][Self::list_to_vec] does,
+    /// treating null elements as the policy says
+    pub fn list_to_vec_with(&self, list: &JObject, nulls: NullPolicy) -> Result<Vec<Option<JObject<'env>>>> {
+        let list_cls = get_cls!(self, "java.util.List");
+        let iterator = get_method_id!(self, list_cls, "iterator", "() -> java.util.Iterator");
+        let iter_cls = get_cls!(self, "java.util.Iterator");
+        let has_next = get_method_id!(self, iter_cls, "hasNext", "() -> boolean");
+        let next = get_method_id!(self, iter_cls, "next", "() -> java.lang.Object");
+
+        let len = self.list_len(list)?;
+        let iter = self.call_value_method(list, iterator, &vec![])?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("List.iterator"))?;
+
+        let mut out = Vec::with_capacity(len);
+        while self.call_value_method(&iter, has_next, &vec![])?.into_bool()? {
+            let elem = self.call_value_method(&iter, next, &vec![])?
+                .into_obj()?;
+            out.push(nulls.apply(elem, "list_to_vec", out.len(), || None)?);
+        }
+
+        self.delete_local_ref(iter);
+        Ok(out)
+    }
+
+    /// Collect the entries of a `java.util.Map` into a [Vec] of key-value pairs, in the map's
+    /// iteration order. Null keys and values are None, as maps such as `HashMap` allow them.
+    /// Returns Err if the object isn't a Map
+    pub fn map_entries(&self, map: &JObject) -> Result<Vec<(Option<JObject<'env>>, Option<JObject<'env>>)>> {
+        self.map_entries_with(map, NullPolicy::AsNone)
+    }
+
+    /// Collect the entries of a `java.util.Map` as [map_entries][Self::map_entries] does, treating
+    /// null keys and values as the policy says
+    pub fn map_entries_with(&self, map: &JObject, nulls: NullPolicy) -> Result<Vec<(Option<JObject<'env>>, Option<JObject<'env>>)>> {
+        let map_cls = get_cls!(self, "java.util.Map");
+        let size = get_method_id!(self, map_cls, "size", "() -> int");
+        let entry_set = get_method_id!(self, map_cls, "entrySet", "() -> java.util.Set");
+        let set_cls = get_cls!(self, "java.util.Set");
+        let iterator = get_method_id!(self, set_cls, "iterator", "() -> java.util.Iterator");
+        let iter_cls = get_cls!(self, "java.util.Iterator");
+        let has_next = get_method_id!(self, iter_cls, "hasNext", "() -> boolean");
+        let next = get_method_id!(self, iter_cls, "next", "() -> java.lang.Object");
+        let entry_cls = get_cls!(self, "java.util.Map$Entry");
+        let get_key = get_method_id!(self, entry_cls, "getKey", "() -> java.lang.Object");
+        let get_value = get_method_id!(self, entry_cls, "getValue", "() -> java.lang.Object");
+        self.check_instance(map, map_cls, "java.util.Map")?;
+
+        let len = self.call_value_method(map, size, &vec![])?
+            .into_int()?;
+        let entries = self.call_value_method(map, entry_set, &vec![])?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Map.entrySet"))?;
+        let iter = self.call_value_method(&entries, iterator, &vec![])?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Set.iterator"))?;
+
+        let mut out = Vec::with_capacity(len as usize);
+        while self.call_value_method(&iter, has_next, &vec![])?.into_bool()? {
+            let entry = self.call_value_method(&iter, next, &vec![])?
+                .into_obj()?
+                .ok_or_else(|| Error::new_null("Map entry"))?;
+            let key = self.call_value_method(&entry, get_key, &vec![])?
+                .into_obj()?;
+            let value = self.call_value_method(&entry, get_value, &vec![])?
+                .into_obj()?;
+            self.delete_local_ref(entry);
+            let key = nulls.apply(key, "map_entries key", out.len(), || None)?;
+            let value = nulls.apply(value, "map_entries value", out.len(), || None)?;
+            out.push((key, value));
+        }
+
+        self.delete_local_ref(iter);
+        self.delete_local_ref(entries);
+        Ok(out)
+    }
+}
@@ -0,0 +1,208 @@
+//!
+//! Module containing typed accessors for primitive fields, which call the matching JNI function
+//! directly instead of going through a [JValue][crate::types::JValue]. The generic field accessors
+//! are built on these.
+//!
+
+use crate::ffi;
+use crate::ffi::constants::JNI_FALSE;
+use crate::types::{JNIVersion, JValue, JObject, JClass, JFieldID, JNonVoidType, JavaDownCast};
+use crate::error::{Error, Result};
+use crate::{get_cls, get_static_method_id};
+use super::{JNIEnv, decode_char};
+
+#[cfg(any(debug_assertions, feature = "runtime-checks"))]
+use crate::ffi::constants::JNI_ERR;
+
+/// Generate the instance and static getters and setters for one primitive field type. Takes the
+/// rust type, the field type, the names of the generated methods and the FFI functions they call,
+/// and how to convert the raw value from and into JNI
+macro_rules! field_accessors {
+    (
+        $rust_ty:ty, $ty:ident, $name:literal,
+        $get:ident => $ffi_get:ident, $set:ident => $ffi_set:ident,
+        $get_static:ident => $ffi_get_static:ident, $set_static:ident => $ffi_set_static:ident,
+        from |$raw:ident| $from_raw:expr,
+        into |$val:ident| $into_raw:expr
+    ) => {
+        #[doc = concat!("Get the value of a ", $name, " field on an object, without wrapping it in a JValue. ")]
+        #[doc = "Returns Err if the ID is for a field of another type, or the reference is a local from "]
+        #[doc = "another thread, when checks are enabled"]
+        pub fn $get(&self, obj: &JObject, id: &JFieldID) -> Result<$rust_ty> {
+            self.check_field_type(id, JNonVoidType::$ty, stringify!($get))?;
+            self.check_threads(Some(obj), &[], stringify!($get))?;
+            let env = self.internal_env();
+
+            // SAFETY: Internal pointer use
+            let $raw = unsafe { env.$ffi_get(obj.borrow_ptr(), id.borrow_ptr()) };
+            Ok($from_raw)
+        }
+
+        #[doc = concat!("Set the value of a ", $name, " field on an object, without wrapping it in a JValue. ")]
+        #[doc = "Returns Err if the ID is for a field of another type, or the reference is a local from "]
+        #[doc = "another thread, when checks are enabled"]
+        pub fn $set(&self, obj: &JObject, id: &JFieldID, $val: $rust_ty) -> Result<()> {
+            self.check_field_type(id, JNonVoidType::$ty, stringify!($set))?;
+            self.check_threads(Some(obj), &[], stringify!($set))?;
+            let env = self.internal_env();
+
+            // SAFETY: Internal pointer use
+            unsafe { env.$ffi_set(obj.borrow_ptr(), id.borrow_ptr(), $into_raw) };
+            Ok(())
+        }
+
+        #[doc = concat!("Get the value of a static ", $name, " field on a class, without wrapping it in a JValue. ")]
+        #[doc = "Returns Err if the ID is for a field of another type, or the reference is a local from "]
+        #[doc = "another thread, when checks are enabled"]
+        pub fn $get_static(&self, cls: &JClass, id: &JFieldID) -> Result<$rust_ty> {
+            self.check_field_type(id, JNonVoidType::$ty, stringify!($get_static))?;
+            self.check_threads(Some(cls.downcast()), &[], stringify!($get_static))?;
+            let env = self.internal_env();
+
+            // SAFETY: Internal pointer use
+            let $raw = unsafe { env.$ffi_get_static(cls.borrow_ptr(), id.borrow_ptr()) };
+            Ok($from_raw)
+        }
+
+        #[doc = concat!("Set the value of a static ", $name, " field on a class, without wrapping it in a JValue. ")]
+        #[doc = "Returns Err if the ID is for a field of another type, or the reference is a local from "]
+        #[doc = "another thread, when checks are enabled"]
+        pub fn $set_static(&self, cls: &JClass, id: &JFieldID, $val: $rust_ty) -> Result<()> {
+            self.check_field_type(id, JNonVoidType::$ty, stringify!($set_static))?;
+            self.check_threads(Some(cls.downcast()), &[], stringify!($set_static))?;
+            let env = self.internal_env();
+
+            // SAFETY: Internal pointer use
+            unsafe { env.$ffi_set_static(cls.borrow_ptr(), id.borrow_ptr(), $into_raw) };
+            Ok(())
+        }
+    }
+}
+
+impl<'env> JNIEnv<'env> {
+
+    /// Check that a field ID is for a field of the expected type. The check is skipped in release
+    /// builds without runtime checks, where a mismatched ID reads or writes the wrong slot
+    fn check_field_type(&self, id: &JFieldID, expected: JNonVoidType, ctx: &str) -> Result<()> {
+        #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+        {
+            if id.ty() != expected {
+                return Err(Error::new(
+                    &format!("{} expected a field of type {:?}, but the ID is for a {:?} field", ctx, expected, id.ty()),
+                    JNI_ERR
+                ))
+            }
+        }
+
+        #[cfg(not(any(debug_assertions, feature = "runtime-checks")))]
+        let _ = (id, expected, ctx);
+
+        Ok(())
+    }
+
+    field_accessors!(
+        bool, Boolean, "boolean",
+        get_field_bool => get_boolean_field, set_field_bool => set_boolean_field,
+        get_static_field_bool => get_static_boolean_field, set_static_field_bool => set_static_boolean_field,
+        from |raw| raw != JNI_FALSE,
+        into |val| ffi::JBoolean::from(val)
+    );
+
+    field_accessors!(
+        i8, Byte, "byte",
+        get_field_byte => get_byte_field, set_field_byte => set_byte_field,
+        get_static_field_byte => get_static_byte_field, set_static_field_byte => set_static_byte_field,
+        from |raw| raw,
+        into |val| val
+    );
+
+    field_accessors!(
+        char, Char, "char",
+        get_field_char => get_char_field, set_field_char => set_char_field,
+        get_static_field_char => get_static_char_field, set_static_field_char => set_static_char_field,
+        from |raw| decode_char(raw)?,
+        into |val| val as ffi::JChar
+    );
+
+    field_accessors!(
+        i16, Short, "short",
+        get_field_short => get_short_field, set_field_short => set_short_field,
+        get_static_field_short => get_static_short_field, set_static_field_short => set_static_short_field,
+        from |raw| raw,
+        into |val| val
+    );
+
+    field_accessors!(
+        i32, Int, "int",
+        get_field_int => get_int_field, set_field_int => set_int_field,
+        get_static_field_int => get_static_int_field, set_static_field_int => set_static_int_field,
+        from |raw| raw,
+        into |val| val
+    );
+
+    field_accessors!(
+        i64, Long, "long",
+        get_field_long => get_long_field, set_field_long => set_long_field,
+        get_static_field_long => get_static_long_field, set_static_field_long => set_static_long_field,
+        from |raw| raw,
+        into |val| val
+    );
+
+    field_accessors!(
+        f32, Float, "float",
+        get_field_float => get_float_field, set_field_float => set_float_field,
+        get_static_field_float => get_static_float_field, set_static_field_float => set_static_float_field,
+        from |raw| raw,
+        into |val| val
+    );
+
+    field_accessors!(
+        f64, Double, "double",
+        get_field_double => get_double_field, set_field_double => set_double_field,
+        get_static_field_double => get_static_double_field, set_static_field_double => set_static_double_field,
+        from |raw| raw,
+        into |val| val
+    );
+
+    /// Get the value of a field on an object with acquire ordering, as `VarHandle.getAcquire`
+    /// would. Reads and writes after it can't be moved before it, so it sees everything written
+    /// before the value was stored by [set_field_release][Self::set_field_release] or a Java
+    /// `volatile` write. This is weaker than a `volatile` read, as an earlier release store to
+    /// another field may still be moved after it. Follows the read with `VarHandle.acquireFence`,
+    /// so requires Java 9
+    pub fn get_field_acquire(&self, obj: &JObject, id: &JFieldID) -> Result<JValue<'env>> {
+        self.check_var_handle_support()?;
+        let val = self.get_field(obj, id)?;
+
+        let cls = get_cls!(self, "java.lang.invoke.VarHandle");
+        let fence = get_static_method_id!(self, cls, "acquireFence", "() -> void");
+        self.call_static_method(cls, fence, &vec![])?;
+        Ok(val)
+    }
+
+    /// Set the value of a field on an object with release ordering, as `VarHandle.setRelease`
+    /// would. Reads and writes before it can't be moved after it, so a thread seeing the value
+    /// through [get_field_acquire][Self::get_field_acquire] or a Java `volatile` read also sees
+    /// them. This is weaker than a `volatile` write, as a later acquire load of another field may
+    /// still be moved before it. Precedes the write with `VarHandle.releaseFence`, so requires
+    /// Java 9
+    pub fn set_field_release(&self, obj: &JObject, id: &JFieldID, val: JValue) -> Result<()> {
+        self.check_var_handle_support()?;
+
+        let cls = get_cls!(self, "java.lang.invoke.VarHandle");
+        let fence = get_static_method_id!(self, cls, "releaseFence", "() -> void");
+        self.call_static_method(cls, fence, &vec![])?;
+        self.set_field(obj, id, val)
+    }
+
+    /// Check the JVM has `VarHandle`, which was added in Java 9
+    fn check_var_handle_support(&self) -> Result<()> {
+        if self.version < JNIVersion::Ver9 {
+            Err(Error::new_unsupported(&format!(
+                "Acquire and release field access requires Java 9 or newer, but the environment is {:?}", self.version
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
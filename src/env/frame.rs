@@ -0,0 +1,65 @@
+//!
+//! Module containing a scoped local frame, whose references can't outlive it.
+//!
+
+use std::ops::Deref;
+
+use crate::types::JObject;
+use crate::error::Result;
+use super::JNIEnv;
+
+///
+/// An environment scoped to a local frame, created by [JNIEnv::with_local_frame]. It derefs to a
+/// [JNIEnv] with the lifetime of the frame, so every reference created through it borrows from the
+/// frame, and the compiler rejects any that would escape it.
+///
+pub struct LocalFrame<'frame> {
+    env: JNIEnv<'frame>
+}
+
+impl<'frame> Deref for LocalFrame<'frame> {
+    type Target = JNIEnv<'frame>;
+
+    fn deref(&self) -> &JNIEnv<'frame> {
+        &self.env
+    }
+}
+
+impl<'env> JNIEnv<'env> {
+
+    /// Run a closure within a new local frame with room for at least `capacity` references,
+    /// popping the frame once it returns. References created through the [LocalFrame] can't
+    /// outlive it, except the one object the closure returns, which is promoted to the outer scope:
+    ///
+    /// ```compile_fail
+    /// use rust_jni::{JNIEnv, JClass};
+    ///
+    /// fn escape<'a>(env: &JNIEnv<'a>) -> Option<JClass<'a>> {
+    ///     let mut escaped = None;
+    ///     env.with_local_frame(1, |frame| {
+    ///         escaped = Some(frame.find_class("java.lang.Object")?);
+    ///         Ok(None)
+    ///     }).unwrap();
+    ///     escaped
+    /// }
+    /// ```
+    ///
+    /// The frame is popped whether or not the closure succeeds, and its error is returned as-is.
+    /// References created through the outer environment inside the closure are still freed with
+    /// the frame, but aren't caught by the compiler
+    pub fn with_local_frame<F>(&self, capacity: i32, f: F) -> Result<Option<JObject<'env>>>
+    where
+        F: for<'frame> FnOnce(&LocalFrame<'frame>) -> Result<Option<JObject<'frame>>>
+    {
+        self.push_local_frame(capacity)?;
+
+        let frame = LocalFrame { env: self.clone() };
+        match f(&frame) {
+            Ok(obj) => Ok(self.pop_local_frame(obj).map(|obj| obj.share())),
+            Err(err) => {
+                self.pop_local_frame(None);
+                Err(err)
+            }
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//!
+//! Module containing support for implementing Java functional interfaces with rust closures. The
+//! returned object is a `java.lang.reflect.Proxy`, whose generated invocation handler calls the
+//! closure through a native `invoke`.
+//!
+
+use std::sync::Mutex;
+
+use crate::ffi;
+use crate::ffi::constants::JNI_ERR;
+use crate::types::{JValue, JObject, JClass, JObjectArray, JFieldID, JNINativeMethod, NullPolicy, JavaDownCast, JavaUpCast};
+use crate::error::{Error, Result};
+use crate::classgen::ClassFileBuilder;
+use crate::panic_bridge::catch_panic;
+use crate::{get_cls, get_method_id, get_static_method_id, get_field_id};
+use super::{JNIEnv, MODIFIER_ABSTRACT};
+
+/// Name of the generated InvocationHandler class whose `invoke` calls a rust closure
+const HANDLER_CLASS: &str = "rustjni/RustInvocationHandler";
+
+/// A closure implementing the abstract method of a functional interface
+type FunctionalBody = Box<dyn for<'a> Fn(&JNIEnv<'a>, &[Option<JObject<'a>>]) -> Result<Option<JObject<'a>>> + Send + Sync>;
+
+/// Global reference to the generated InvocationHandler class, once defined
+static HANDLER: Mutex<usize> = Mutex::new(0);
+
+/// Global reference to the `Cleaner` freeing closures of collected handlers, once created. Stays
+/// zero on JVMs before 9, which have no `Cleaner`
+static CLEANER: Mutex<usize> = Mutex::new(0);
+
+/// Native `invoke` of the generated InvocationHandler class. Calls the closure in the handler's
+/// handle, throwing an Err it returns into Java unless it already left an exception pending
+extern "system" fn invoke_closure(
+    env: *mut ffi::JNIEnv,
+    this: *mut ffi::JObject,
+    proxy: *mut ffi::JObject,
+    method: *mut ffi::JObject,
+    args: *mut ffi::JObjectArray
+) -> *mut ffi::JObject {
+    let env = match JNIEnv::new(env) {
+        Ok(env) => env,
+        Err(_) => return std::ptr::null_mut()
+    };
+
+    catch_panic(&env, std::ptr::null_mut(), || {
+        let this = JObject::new(this).expect("Null InvocationHandler in native invoke");
+        let proxy = JObject::new(proxy).expect("Null proxy in native invoke");
+        let method = JObject::new(method).expect("Null method in native invoke");
+        let args = JObjectArray::new(args).ok();
+
+        match env.invoke_functional(&this, &proxy, &method, args.as_ref()) {
+            Ok(Some(obj)) => obj.into_raw() as *mut ffi::JObject,
+            Ok(None) => std::ptr::null_mut(),
+            Err(err) => {
+                if !env.exception_check() {
+                    env.throw_error(&err).expect("Couldn't throw closure error");
+                }
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+impl<'env> JNIEnv<'env> {
+
+    /// Get the generated InvocationHandler class, defining it and registering its native `invoke`
+    /// on first use
+    fn handler_class(&self) -> Result<JClass<'static>> {
+        let mut cls_ptr = HANDLER.lock().expect("InvocationHandler class lock poisoned");
+        if *cls_ptr == 0 {
+            let descriptor = "(Ljava/lang/Object;Ljava/lang/reflect/Method;[Ljava/lang/Object;)Ljava/lang/Object;";
+            let bytes = ClassFileBuilder::new(HANDLER_CLASS)
+                .interface("java.lang.reflect.InvocationHandler")
+                .field("handle", "J")
+                .native_method("invoke", descriptor, false)
+                .build();
+            let cls = self.define_class_in_system_loader(HANDLER_CLASS, &bytes)?;
+            let invoke = JNINativeMethod::new::<*mut ffi::JObject>("invoke", descriptor, invoke_closure as *mut _);
+            self.register_natives(&cls, &[invoke])?;
+
+            let global = self.new_global_ref((&cls).downcast())?.leak();
+            // SAFETY: Internal pointer use, the global reference is deleted by release_library_refs
+            *cls_ptr = unsafe { global.borrow_ptr() } as usize;
+        }
+
+        JClass::new(*cls_ptr as *mut ffi::JClass)
+    }
+
+    /// Get the shared `Cleaner`, creating it on first use. Returns None on JVMs without one
+    fn functional_cleaner(&self) -> Result<Option<JObject<'static>>> {
+        let mut cleaner_ptr = CLEANER.lock().expect("Cleaner lock poisoned");
+        if *cleaner_ptr == 0 {
+            let cleaner_cls = match self.find_class("java.lang.ref.Cleaner") {
+                Ok(cls) => cls,
+                Err(_) => {
+                    self.exception_clear()?;
+                    return Ok(None)
+                }
+            };
+            let create = self.get_static_method_id(&cleaner_cls, "create", "() -> java.lang.ref.Cleaner")?;
+            let cleaner = self.call_static_method(&cleaner_cls, &create, &vec![])?
+                .ok_or_else(|| Error::new("Unexpected void result", JNI_ERR))?
+                .into_obj()?
+                .ok_or_else(|| Error::new_null("Cleaner.create"))?;
+
+            let global = self.new_global_ref(&cleaner)?.leak();
+            // SAFETY: Internal pointer use, the global reference is deleted by release_library_refs
+            *cleaner_ptr = unsafe { global.borrow_ptr() } as usize;
+        }
+
+        JObject::new(*cleaner_ptr as *mut ffi::JObject).map(Some)
+    }
+
+    /// Delete the global references to the generated InvocationHandler class and the `Cleaner`,
+    /// if they were created, so a library unloading doesn't leak them
+    pub(super) fn release_functional_refs(&self) {
+        for lock in &[&HANDLER, &CLEANER] {
+            let mut ptr = lock.lock().expect("Functional global lock poisoned");
+            if *ptr != 0 {
+                if let Ok(obj) = JObject::new(*ptr as *mut ffi::JObject) {
+                    self.delete_global_ref(obj);
+                }
+                *ptr = 0;
+            }
+        }
+    }
+
+    /// Free the closure of a handler that will never be handed out, clearing its handle first so
+    /// the handler can't reach it. If clearing fails the closure is leaked instead
+    fn reclaim_functional_body(&self, handler: &JObject, handle_id: &JFieldID, handle: usize) {
+        if self.set_field_long(handler, handle_id, 0).is_ok() {
+            // SAFETY: The handle was cleared above, so the unused handler can't reach it
+            drop(unsafe { Box::from_raw(handle as *mut FunctionalBody) });
+        }
+    }
+
+    /// Run a call made through a proxy from [new_functional][Self::new_functional]. Methods
+    /// declared by `Object` are answered for the proxy itself, and every other method calls the
+    /// handler's closure
+    fn invoke_functional(
+        &self,
+        handler: &JObject,
+        proxy: &JObject,
+        method: &JObject,
+        args: Option<&JObjectArray>
+    ) -> Result<Option<JObject<'env>>> {
+        let obj_cls = get_cls!(self, "java.lang.Object");
+        let method_cls = get_cls!(self, "java.lang.reflect.Method");
+        let get_declaring = get_method_id!(self, method_cls, "getDeclaringClass", "() -> java.lang.Class");
+        let get_name = get_method_id!(self, method_cls, "getName", "() -> java.lang.String");
+
+        let args = match args {
+            Some(args) => self.object_array_to_vec(args, NullPolicy::AsNone)?,
+            None => Vec::new()
+        };
+
+        let declaring = self.call_value_method(method, get_declaring, &vec![])?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Method.getDeclaringClass"))?;
+        if self.is_same_object(&declaring, obj_cls.downcast()) {
+            let name = self.call_value_method(method, get_name, &vec![])?
+                .into_obj()?
+                .ok_or_else(|| Error::new_null("Method.getName"))?;
+            // SAFETY: Guaranteed safe upcast, getName returns a String
+            let name: String = self.get_string_chars(&unsafe { name.upcast_raw() })?.into_iter().collect();
+            return self.invoke_object_method(proxy, &name, &args)
+        }
+
+        // The handler class is defined before any handler exists, so the lookup from its own
+        // native finds it
+        let handler_cls = get_cls!(self, "rustjni.RustInvocationHandler");
+        let handle_id = get_field_id!(self, handler_cls, "handle", "long");
+        let handle = self.get_field_long(handler, handle_id)?;
+        if handle == 0 {
+            return Err(Error::new("Functional object called after its closure was freed", JNI_ERR))
+        }
+
+        // SAFETY: Handles are only created from a boxed body by new_functional, and only freed
+        //         once the handler is unreachable
+        let body = unsafe { &*(handle as *const FunctionalBody) };
+        body(self, &args)
+    }
+
+    /// Answer a call of one of the `Object` methods a proxy forwards, `equals`, `hashCode` and
+    /// `toString`, by the identity of the proxy
+    fn invoke_object_method(&self, proxy: &JObject, name: &str, args: &[Option<JObject>]) -> Result<Option<JObject<'env>>> {
+        let system_cls = get_cls!(self, "java.lang.System");
+        let identity_hash = get_static_method_id!(self, system_cls, "identityHashCode", "(java.lang.Object) -> int");
+        let hash = self.call_static_method(system_cls, identity_hash, &vec![proxy.into()])?
+            .ok_or_else(|| Error::new("Unexpected void result", JNI_ERR))?
+            .into_int()?;
+
+        match name {
+            "equals" => {
+                let bool_cls = get_cls!(self, "java.lang.Boolean");
+                let value_of = get_static_method_id!(self, bool_cls, "valueOf", "(boolean) -> java.lang.Boolean");
+                let same = match args.first() {
+                    Some(Some(other)) => self.is_same_object(proxy, other),
+                    _ => false
+                };
+                self.call_static_method(bool_cls, value_of, &vec![JValue::Bool(same)])?
+                    .ok_or_else(|| Error::new("Unexpected void result", JNI_ERR))?
+                    .into_obj()
+            }
+            "hashCode" => {
+                let int_cls = get_cls!(self, "java.lang.Integer");
+                let value_of = get_static_method_id!(self, int_cls, "valueOf", "(int) -> java.lang.Integer");
+                self.call_static_method(int_cls, value_of, &vec![JValue::Int(hash)])?
+                    .ok_or_else(|| Error::new("Unexpected void result", JNI_ERR))?
+                    .into_obj()
+            }
+            "toString" => {
+                let (_, cls_name) = self.object_class_and_name(proxy)?;
+                let str = self.new_string_utf(&format!("{}@{:x}", cls_name, hash))?;
+                Ok(Some(str.downcast()))
+            }
+            _ => Err(Error::new(&format!("Unexpected Object method {} on a functional proxy", name), JNI_ERR))
+        }
+    }
+
+    /// Count the abstract methods of an interface, leaving out redeclared public `Object` methods
+    /// such as `Comparator.equals`, as the Java language does for functional interfaces
+    fn count_abstract_methods(&self, iface: &JClass) -> Result<usize> {
+        let cls_cls = get_cls!(self, "java.lang.Class");
+        let method_cls = get_cls!(self, "java.lang.reflect.Method");
+        let get_methods = get_method_id!(self, cls_cls, "getMethods", "() -> java.lang.reflect.Method[]");
+        let get_modifiers = get_method_id!(self, method_cls, "getModifiers", "() -> int");
+        let get_name = get_method_id!(self, method_cls, "getName", "() -> java.lang.String");
+        let get_param_count = get_method_id!(self, method_cls, "getParameterCount", "() -> int");
+
+        let methods = self.call_value_method(iface.downcast(), get_methods, &vec![])?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Class.getMethods"))?;
+        // SAFETY: Internal pointer use, getMethods returns a Method[]
+        let method_arr = JObjectArray::new(unsafe { methods.borrow_ptr() } as _)?;
+
+        let mut count = 0;
+        for idx in 0..self.get_array_length((&method_arr).downcast()) {
+            let method = self.get_object_array_element(&method_arr, idx)?;
+            let modifiers = self.call_value_method(&method, get_modifiers, &vec![])?
+                .into_int()?;
+            if modifiers & MODIFIER_ABSTRACT != 0 {
+                let name = self.call_value_method(&method, get_name, &vec![])?
+                    .into_obj()?
+                    .ok_or_else(|| Error::new_null("Method.getName"))?;
+                // SAFETY: Guaranteed safe upcast, getName returns a String
+                let name: String = self.get_string_chars(&unsafe { name.upcast_raw() })?.into_iter().collect();
+                let params = self.call_value_method(&method, get_param_count, &vec![])?
+                    .into_int()?;
+
+                let object_method = matches!((name.as_str(), params), ("equals", 1) | ("hashCode", 0) | ("toString", 0));
+                if !object_method {
+                    count += 1;
+                }
+            }
+            self.delete_local_ref(method);
+        }
+
+        self.delete_local_ref(methods);
+        Ok(count)
+    }
+
+    /// Create an object implementing a functional interface, such as `java.lang.Runnable` or
+    /// `java.util.function.Function`, whose single abstract method calls a rust closure. The
+    /// closure gets the environment of the calling thread and the call's arguments, and returns
+    /// the method's result. As the object is a `java.lang.reflect.Proxy`, primitive arguments
+    /// arrive boxed, and primitive results must be returned boxed, with None for void methods.
+    /// An Err returned by the closure is thrown as by [throw_error][Self::throw_error], unless it
+    /// left an exception pending. `equals`, `hashCode` and `toString` use the object's identity.
+    ///
+    /// The closure may be called from any thread, and is dropped once the object is garbage
+    /// collected. JVMs before 9 have no `Cleaner` to notice that, so there the closure is leaked.
+    /// Returns Err if the interface doesn't have exactly one abstract method
+    pub fn new_functional<F>(&self, interface: &str, f: F) -> Result<JObject<'env>>
+        where
+            F: for<'a> Fn(&JNIEnv<'a>, &[Option<JObject<'a>>]) -> Result<Option<JObject<'a>>> + Send + Sync + 'static
+    {
+        let iface = self.find_class(interface)?;
+        let cls_cls = get_cls!(self, "java.lang.Class");
+        let is_interface = get_method_id!(self, cls_cls, "isInterface", "() -> boolean");
+        let get_loader = get_method_id!(self, cls_cls, "getClassLoader", "() -> java.lang.ClassLoader");
+        if !self.call_value_method(iface.as_object(), is_interface, &vec![])?.into_bool()? {
+            return Err(Error::new(&format!("{} isn't an interface", interface), JNI_ERR))
+        }
+        let abstract_methods = self.count_abstract_methods(&iface)?;
+        if abstract_methods != 1 {
+            return Err(Error::new(
+                &format!("{} isn't a functional interface, it has {} abstract methods", interface, abstract_methods),
+                JNI_ERR
+            ))
+        }
+
+        let handler_cls = self.handler_class()?;
+        let handler_con = self.get_method_id(&handler_cls, "<init>", "() -> void")?;
+        let handle_id = self.get_field_id(&handler_cls, "handle", "long")?;
+        let handler = self.new_object(&handler_cls, &handler_con, &vec![])?;
+
+        let body: FunctionalBody = Box::new(f);
+        let handle = Box::into_raw(Box::new(body)) as usize;
+        if let Err(err) = self.set_field_long(&handler, &handle_id, handle as i64) {
+            // SAFETY: The handle was created above, and never stored
+            drop(unsafe { Box::from_raw(handle as *mut FunctionalBody) });
+            return Err(err)
+        }
+
+        // Free the closure once the handler, only reachable through the proxy, is collected. The
+        // cleaning action mustn't reference the handler, so it only holds the handle
+        let cleaner = match self.functional_cleaner() {
+            Ok(cleaner) => cleaner,
+            Err(err) => {
+                self.reclaim_functional_body(&handler, &handle_id, handle);
+                return Err(err)
+            }
+        };
+        if let Some(cleaner) = cleaner {
+            let cleaner_cls = get_cls!(self, "java.lang.ref.Cleaner");
+            let register = get_method_id!(self, cleaner_cls, "register", "(java.lang.Object, java.lang.Runnable) -> java.lang.ref.Cleaner$Cleanable");
+
+            let action = self.new_runnable(Box::new(move |_| {
+                // SAFETY: The handler holding the handle is unreachable, so no call can be using it
+                drop(unsafe { Box::from_raw(handle as *mut FunctionalBody) });
+            }));
+            let action = match action {
+                Ok(action) => action,
+                Err(err) => {
+                    self.reclaim_functional_body(&handler, &handle_id, handle);
+                    return Err(err)
+                }
+            };
+            if let Err(err) = self.call_method(&cleaner, register, &vec![(&handler).into(), (&action).into()]) {
+                self.free_runnable(&action);
+                self.reclaim_functional_body(&handler, &handle_id, handle);
+                return Err(err)
+            }
+        }
+
+        let loader = self.call_value_method(iface.as_object(), get_loader, &vec![])?
+            .into_obj()?;
+        let interfaces = self.new_object_array(1, cls_cls, Some(iface.as_object()))?;
+
+        let proxy_cls = get_cls!(self, "java.lang.reflect.Proxy");
+        let new_proxy = get_static_method_id!(self, proxy_cls, "newProxyInstance", "(java.lang.ClassLoader, java.lang.Class[], java.lang.reflect.InvocationHandler) -> java.lang.Object");
+        let args = vec![JValue::Object(loader), interfaces.as_object().into(), (&handler).into()];
+        self.call_static_method(proxy_cls, new_proxy, &args)?
+            .ok_or_else(|| Error::new("Unexpected void result", JNI_ERR))?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Proxy.newProxyInstance"))
+    }
+}
@@ -0,0 +1,102 @@
+//!
+//! Module containing a scoped guard over an object's monitor, for Java's intrinsic locking.
+//!
+
+use crate::types::{JValue, JObject};
+use crate::error::Result;
+use crate::{get_cls, get_method_id};
+use super::JNIEnv;
+use super::threads::take_interrupted;
+
+///
+/// A held monitor on an object, created by [JNIEnv::lock_monitor]. The monitor is exited when the
+/// guard is dropped, or by [MonitorGuard::exit] to see whether exiting failed. `Object.wait` and
+/// `notify` must only be called while holding the monitor, so they're provided here rather than on
+/// the environment.
+///
+pub struct MonitorGuard<'a, 'env> {
+    env: &'a JNIEnv<'env>,
+    obj: &'a JObject<'a>
+}
+
+impl<'a, 'env> MonitorGuard<'a, 'env> {
+
+    /// Wait on the monitor until notified, as `Object.wait` would, giving up the monitor while
+    /// waiting and holding it again on return. With a timeout, also returns once that many
+    /// milliseconds have passed. Java treats a timeout of zero as no timeout, so a zero timeout
+    /// waits for one millisecond instead. Returns [Error::Interrupted][crate::Error::Interrupted]
+    /// if the thread was interrupted while waiting, clearing the `InterruptedException`
+    pub fn wait(&self, timeout_ms: Option<u64>) -> Result<()> {
+        let env = self.env;
+        let cls = get_cls!(env, "java.lang.Object");
+        let wait = get_method_id!(env, cls, "wait", "(long) -> void");
+
+        let timeout = match timeout_ms {
+            Some(ms) => ms.clamp(1, i64::MAX as u64) as i64,
+            None => 0
+        };
+
+        take_interrupted(env.call_method(self.obj, wait, &vec![JValue::Long(timeout)]))?;
+        Ok(())
+    }
+
+    /// Wake a single thread waiting on the monitor, as `Object.notify` would
+    pub fn notify(&self) -> Result<()> {
+        let env = self.env;
+        let cls = get_cls!(env, "java.lang.Object");
+        let notify = get_method_id!(env, cls, "notify", "() -> void");
+
+        env.call_method(self.obj, notify, &vec![])?;
+        Ok(())
+    }
+
+    /// Wake every thread waiting on the monitor, as `Object.notifyAll` would
+    pub fn notify_all(&self) -> Result<()> {
+        let env = self.env;
+        let cls = get_cls!(env, "java.lang.Object");
+        let notify_all = get_method_id!(env, cls, "notifyAll", "() -> void");
+
+        env.call_method(self.obj, notify_all, &vec![])?;
+        Ok(())
+    }
+
+    /// Exit the monitor now, reporting any failure. Dropping the guard can only log a failure to
+    /// exit, so use this where the caller needs to know
+    pub fn exit(self) -> Result<()> {
+        let result = self.env.monitor_exit(self.obj);
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl Drop for MonitorGuard<'_, '_> {
+    fn drop(&mut self) {
+        // Panicking here could abort while already unwinding, so the failure is only logged
+        if let Err(err) = self.env.monitor_exit(self.obj) {
+            eprintln!("rust_jni: couldn't exit monitor: {}", err);
+        }
+    }
+}
+
+impl<'env> JNIEnv<'env> {
+
+    /// Enter the monitor of an object, returning a guard that exits it when dropped. Monitors are
+    /// reentrant, so a thread may lock the same object more than once
+    pub fn lock_monitor<'a>(&'a self, obj: &'a JObject<'a>) -> Result<MonitorGuard<'a, 'env>> {
+        self.monitor_enter(obj)?;
+        Ok(MonitorGuard {
+            env: self,
+            obj
+        })
+    }
+
+    /// Run a closure while holding the monitor of an object, as a Java `synchronized` block would.
+    /// The monitor is exited once the closure returns, or if it panics. Returns Err without running
+    /// the closure if the monitor couldn't be entered, or if it couldn't be exited afterwards
+    pub fn synchronized<T>(&self, obj: &JObject, f: impl FnOnce() -> T) -> Result<T> {
+        let guard = self.lock_monitor(obj)?;
+        let result = f();
+        guard.exit()?;
+        Ok(result)
+    }
+}
@@ -0,0 +1,724 @@
+//!
+//! Module containing reflection helpers, for working with class tokens and reflected members.
+//!
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::{JNativeType, JType, ClassKind};
+use crate::ffi::constants::JNI_ERR;
+use crate::types::{JValue, JObject, JClass, JString, JObjectArray, JMethodID, JavaDownCast, JavaUpCast};
+use crate::error::{Error, Result};
+use crate::mangling::TypeSignature;
+use crate::{get_cls, get_method_id, get_static_method_id};
+use super::{JNIEnv, MODIFIER_PUBLIC, MODIFIER_STATIC, MODIFIER_FINAL};
+
+/// Get the name of the wrapper class of a primitive type name, or None if the name isn't primitive
+fn wrapper_class_name(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "boolean" => "java.lang.Boolean",
+        "byte" => "java.lang.Byte",
+        "char" => "java.lang.Character",
+        "short" => "java.lang.Short",
+        "int" => "java.lang.Integer",
+        "long" => "java.lang.Long",
+        "float" => "java.lang.Float",
+        "double" => "java.lang.Double",
+        "void" => "java.lang.Void",
+        _ => return None
+    })
+}
+
+/// Write a string to JSON output as a quoted string literal, escaping as JSON requires
+fn write_json_string(out: &mut String, str: &str) {
+    out.push('"');
+    for c in str.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+}
+
+/// Write a number to JSON output. JSON has no NaN or infinities, so those are written as strings
+fn write_json_number(out: &mut String, num: &str) {
+    match num {
+        "NaN" | "Infinity" | "-Infinity" | "inf" | "-inf" => write_json_string(out, num),
+        _ => out.push_str(num)
+    }
+}
+
+impl<'env> JNIEnv<'env> {
+
+    /// Get the class token for a type name, for passing to APIs taking a `Class<T>`. Primitive
+    /// names such as `int` give the primitive class, while any other name is found as a class, so
+    /// `int` and `java.lang.Integer` give distinct tokens
+    pub fn class_token(&self, name: &str) -> Result<JValue<'env>> {
+        let name = name.trim();
+        let cls = match wrapper_class_name(name) {
+            Some(wrapper) => {
+                let wrapper = self.find_class(wrapper)?;
+                let id = self.get_static_field_id(&wrapper, "TYPE", "java.lang.Class")?;
+                let cls = self.get_static_field(&wrapper, &id)?
+                    .into_obj()?
+                    .ok_or_else(|| Error::new_null("Primitive class token"))?;
+                // SAFETY: Guaranteed safe upcast, TYPE is a Class
+                unsafe { cls.upcast_raw() }
+            }
+            None => self.find_class(name)?
+        };
+
+        Ok(JValue::Object(Some(cls.downcast())))
+    }
+
+    /// Get the wrapper class of a primitive type, such as `java.lang.Integer` for `int`
+    pub fn primitive_wrapper_class(&self, ty: JNativeType) -> Result<JClass<'env>> {
+        let name = match ty {
+            JNativeType::Boolean => "java.lang.Boolean",
+            JNativeType::Byte => "java.lang.Byte",
+            JNativeType::Char => "java.lang.Character",
+            JNativeType::Short => "java.lang.Short",
+            JNativeType::Int => "java.lang.Integer",
+            JNativeType::Long => "java.lang.Long",
+            JNativeType::Float => "java.lang.Float",
+            JNativeType::Double => "java.lang.Double"
+        };
+
+        self.find_class(name)
+    }
+
+    /// Classify a class as a primitive type, an array of some kind, or one of the kinds of class
+    /// declaration, as `Class.isPrimitive`, `isArray`, `isAnnotation`, `isInterface` and `isEnum`
+    /// tell apart. The classes of enum constants with their own bodies aren't enum classes
+    /// themselves, so are a plain [ClassKind::Class]
+    pub fn class_kind(&self, cls: &JClass) -> Result<ClassKind> {
+        let cls_cls = get_cls!(self, "java.lang.Class");
+        let is_primitive = get_method_id!(self, cls_cls, "isPrimitive", "() -> boolean");
+        let is_array = get_method_id!(self, cls_cls, "isArray", "() -> boolean");
+        let is_annotation = get_method_id!(self, cls_cls, "isAnnotation", "() -> boolean");
+        let is_interface = get_method_id!(self, cls_cls, "isInterface", "() -> boolean");
+        let is_enum = get_method_id!(self, cls_cls, "isEnum", "() -> boolean");
+        let get_component = get_method_id!(self, cls_cls, "getComponentType", "() -> java.lang.Class");
+
+        let check = |id| self.call_value_method(cls.downcast(), id, &vec![])?.into_bool();
+
+        if check(is_primitive)? {
+            Ok(ClassKind::Primitive(JType::from_name(&self.class_name(cls)?)))
+        } else if check(is_array)? {
+            let component = self.call_value_method(cls.downcast(), get_component, &vec![])?
+                .into_obj()?
+                .ok_or_else(|| Error::new_null("Class.getComponentType"))?;
+            // SAFETY: Guaranteed safe upcast, getComponentType returns a Class
+            let component: JClass = unsafe { component.upcast_raw() };
+            let kind = self.class_kind(&component);
+            self.delete_local_ref(component.downcast());
+            Ok(ClassKind::Array(Box::new(kind?)))
+        } else if check(is_annotation)? {
+            Ok(ClassKind::Annotation)
+        } else if check(is_interface)? {
+            Ok(ClassKind::Interface)
+        } else if check(is_enum)? {
+            Ok(ClassKind::Enum)
+        } else {
+            Ok(ClassKind::Class)
+        }
+    }
+
+    /// Set whether a reflected field or method skips Java's access checks, so private members
+    /// obtained through `to_reflected_*` can be used. On JVMs with strong encapsulation, members
+    /// of modules that aren't open to the caller can't be made accessible, in which case the
+    /// thrown `InaccessibleObjectException` is cleared and reported as an Err. Some JVMs also
+    /// can't change accessibility at all from a native thread with no Java caller frame, which is
+    /// reported as [Error::Unsupported]
+    pub fn set_accessible(&self, member: &JObject, accessible: bool) -> Result<()> {
+        let acc_cls = self.find_class("java.lang.reflect.AccessibleObject")?;
+        let set_acc = self.get_method_id(&acc_cls, "setAccessible", "(boolean) -> void")?;
+
+        match self.call_method(member, &set_acc, &vec![accessible.into()]) {
+            Ok(_) => Ok(()),
+            // InaccessibleObjectException only exists from Java 9 onwards
+            Err(Error::Exception(name, _)) if name == "java.lang.reflect.InaccessibleObjectException" => {
+                Err(Error::new("Member can't be made accessible, its module isn't open to the caller", JNI_ERR))
+            }
+            // Older module-aware JVMs assume a caller class exists
+            Err(Error::Exception(name, _)) if name == "java.lang.NullPointerException" => {
+                Err(Error::new_unsupported("Accessibility can't be changed without a Java caller frame"))
+            }
+            Err(err) => Err(err)
+        }
+    }
+
+    /// Get a method ID as [get_method_id][Self::get_method_id] does, but if the direct lookup
+    /// fails with a `NoSuchMethodError` or `IllegalAccessError`, retry through the public methods
+    /// of the class with `Class.getMethod`. This finds methods the JNI lookup misses on some JVMs,
+    /// at the cost of extra calls on failure, so it's kept separate from the plain lookup. Using
+    /// the fallback is noted on stderr in debug builds. If neither lookup finds the method, the
+    /// original error is returned and its exception left pending
+    pub fn get_method_id_with_fallback(&self, cls: &JClass, name: &str, sig: &str) -> Result<JMethodID> {
+        let err = match self.get_method_id(cls, name, sig) {
+            Ok(id) => return Ok(id),
+            Err(err) => err
+        };
+
+        if !self.exception_check() {
+            return Err(err)
+        }
+
+        let exc = self.exception_occurred()?;
+        self.exception_clear()?;
+
+        let is_exc = |name: &str| match self.find_class(name) {
+            Ok(cls) => Ok(self.is_instance_of((&exc).downcast(), &cls)),
+            Err(_) => self.exception_clear().map(|_| false)
+        };
+        if !is_exc("java.lang.NoSuchMethodError")? && !is_exc("java.lang.IllegalAccessError")? {
+            self.throw(&exc)?;
+            return Err(err)
+        }
+
+        match self.reflected_method_id(cls, name, sig) {
+            Ok(id) => {
+                if cfg!(debug_assertions) {
+                    eprintln!("rust_jni: get_method_id of {} {} fell back to Class.getMethod", name, sig);
+                }
+                Ok(id)
+            }
+            Err(_) => {
+                if self.exception_check() {
+                    self.exception_clear()?;
+                }
+                self.throw(&exc)?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Get a method ID through `Class.getMethod`, which only finds public instance methods
+    pub(super) fn reflected_method_id(&self, cls: &JClass, name: &str, sig: &str) -> Result<JMethodID> {
+        let sig = self.parse_signature(sig);
+        let args = match &sig.sig {
+            TypeSignature::Method(args, _) => args,
+            _ => return Err(Error::new("Expected method signature", JNI_ERR))
+        };
+
+        let cls_cls = get_cls!(self, "java.lang.Class");
+        let get_method = get_method_id!(self, cls_cls, "getMethod", "(java.lang.String, java.lang.Class[]) -> java.lang.reflect.Method");
+        let meth_cls = get_cls!(self, "java.lang.reflect.Method");
+        let get_mods = get_method_id!(self, meth_cls, "getModifiers", "() -> int");
+
+        // Each intermediate reference is deleted however the lookup ends
+        let params = self.new_object_array(args.len(), cls_cls, None)?;
+        let result = (|| -> Result<JMethodID> {
+            for (idx, arg) in args.iter().enumerate() {
+                let token = self.auto_local(self.class_token(&arg.pretty())?
+                    .into_obj()?
+                    .ok_or_else(|| Error::new_null("Parameter class token"))?);
+                self.set_object_array_element(&params, idx, &token)?;
+            }
+
+            let name_str = self.auto_local(self.new_string_utf(name)?.downcast());
+            let method = self.auto_local(self.call_value_method(cls.downcast(), get_method, &vec![(&*name_str).into(), params.as_object().into()])?
+                .into_obj()?
+                .ok_or_else(|| Error::new_null("Class.getMethod result"))?);
+
+            let mods = self.call_value_method(&method, get_mods, &vec![])?
+                .into_int()?;
+            if mods & MODIFIER_STATIC != 0 {
+                return Err(Error::new(&format!("Method {} is static", name), JNI_ERR))
+            }
+
+            self.from_reflected_method(&method)
+        })();
+        self.delete_local_ref(params.downcast());
+        result
+    }
+
+    /// Make an independent copy of an object. Objects implementing `Cloneable` are copied with
+    /// their own `clone`, called through the JNI so the usual access checks don't apply, which may
+    /// be shallow. If that's unsupported, objects implementing `Serializable` are copied through a
+    /// serialization round trip, which copies the whole object graph. Returns Err if neither
+    /// applies
+    pub fn clone_object(&self, obj: &JObject) -> Result<JObject<'env>> {
+        // Every class, stream and intermediate array made along the way is freed with the frame
+        self.with_local_frame(16, |frame| {
+            let cloneable = frame.find_class("java.lang.Cloneable")?;
+            if frame.is_instance_of(obj, &cloneable) {
+                let obj_cls = frame.get_object_class(obj)?;
+                let clone_id = match frame.get_method_id(&obj_cls, "clone", "() -> java.lang.Object") {
+                    Ok(id) => id,
+                    Err(err) => {
+                        if frame.exception_check() {
+                            return Err(frame.call_exception_error())
+                        }
+                        return Err(err)
+                    }
+                };
+
+                match frame.call_value_method(obj, &clone_id, &vec![]) {
+                    Ok(result) => {
+                        return result.into_obj()?
+                            .ok_or_else(|| Error::new_null("Object.clone"))
+                            .map(Some)
+                    }
+                    Err(Error::Exception(name, _)) if name == "java.lang.CloneNotSupportedException" => (),
+                    Err(err) => return Err(err)
+                }
+            }
+
+            let serializable = frame.find_class("java.io.Serializable")?;
+            if !frame.is_instance_of(obj, &serializable) {
+                return Err(Error::new("Object to clone is neither Cloneable nor Serializable", JNI_ERR))
+            }
+
+            let bytes_out_cls = frame.find_class("java.io.ByteArrayOutputStream")?;
+            let bytes_out_new = frame.get_method_id(&bytes_out_cls, "<init>", "() -> void")?;
+            let to_array = frame.get_method_id(&bytes_out_cls, "toByteArray", "() -> byte[]")?;
+            let obj_out_cls = frame.find_class("java.io.ObjectOutputStream")?;
+            let obj_out_new = frame.get_method_id(&obj_out_cls, "<init>", "(java.io.OutputStream) -> void")?;
+            let write_obj = frame.get_method_id(&obj_out_cls, "writeObject", "(java.lang.Object) -> void")?;
+            let close_out = frame.get_method_id(&obj_out_cls, "close", "() -> void")?;
+
+            let bytes_out = frame.new_object(&bytes_out_cls, &bytes_out_new, &vec![])?;
+            let obj_out = frame.new_object(&obj_out_cls, &obj_out_new, &vec![(&bytes_out).into()])?;
+            frame.call_method(&obj_out, &write_obj, &vec![obj.into()])?;
+            frame.call_method(&obj_out, &close_out, &vec![])?;
+            let bytes = frame.call_value_method(&bytes_out, &to_array, &vec![])?
+                .into_obj()?
+                .ok_or_else(|| Error::new_null("ByteArrayOutputStream.toByteArray"))?;
+
+            let bytes_in_cls = frame.find_class("java.io.ByteArrayInputStream")?;
+            let bytes_in_new = frame.get_method_id(&bytes_in_cls, "<init>", "(byte[]) -> void")?;
+            let obj_in_cls = frame.find_class("java.io.ObjectInputStream")?;
+            let obj_in_new = frame.get_method_id(&obj_in_cls, "<init>", "(java.io.InputStream) -> void")?;
+            let read_obj = frame.get_method_id(&obj_in_cls, "readObject", "() -> java.lang.Object")?;
+
+            let bytes_in = frame.new_object(&bytes_in_cls, &bytes_in_new, &vec![(&bytes).into()])?;
+            let obj_in = frame.new_object(&obj_in_cls, &obj_in_new, &vec![(&bytes_in).into()])?;
+            frame.call_value_method(&obj_in, &read_obj, &vec![])?
+                .into_obj()?
+                .ok_or_else(|| Error::new_null("ObjectInputStream.readObject"))
+                .map(Some)
+        })?.ok_or_else(|| Error::new_null("clone_object result"))
+    }
+
+    /// Get the constant of a Java enum with the given name, as `Enum.valueOf` would. Returns the
+    /// `IllegalArgumentException` as an [Error::Exception] if the class isn't an enum or has no
+    /// constant of that name
+    pub fn enum_constant(&self, enum_cls: &JClass, name: &str) -> Result<JObject<'env>> {
+        let enum_base = get_cls!(self, "java.lang.Enum");
+        let value_of = get_static_method_id!(self, enum_base, "valueOf", "(java.lang.Class, java.lang.String) -> java.lang.Enum");
+
+        let name = self.new_string_utf(name)?;
+        let result = self.call_static_method(enum_base, value_of, &vec![enum_cls.into(), name.as_object().into()]);
+        self.delete_local_ref(name.downcast());
+
+        result?
+            .ok_or_else(|| Error::new("Unexpected void result", JNI_ERR))?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Enum.valueOf"))
+    }
+
+    /// Get the declared name of a Java enum constant, as `Enum.name` would. Returns Err if the
+    /// object isn't an enum constant
+    pub fn enum_name(&self, constant: &JObject) -> Result<String> {
+        let enum_base = get_cls!(self, "java.lang.Enum");
+        let name_id = get_method_id!(self, enum_base, "name", "() -> java.lang.String");
+        if !self.is_instance_of(constant, enum_base) {
+            return Err(Error::new("Expected an instance of java.lang.Enum", JNI_ERR))
+        }
+
+        let name = self.call_value_method(constant, name_id, &vec![])?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Enum.name"))?;
+
+        // SAFETY: Guaranteed safe upcast, name returns a String
+        let name: JString = unsafe { name.upcast_raw() };
+        let result = self.get_string_chars(&name).map(|chars| chars.into_iter().collect());
+        self.delete_local_ref(name.downcast());
+        result
+    }
+
+    /// Get the position of a Java enum constant in its declaration, as `Enum.ordinal` would.
+    /// Returns Err if the object isn't an enum constant
+    pub fn enum_ordinal(&self, constant: &JObject) -> Result<i32> {
+        let enum_base = get_cls!(self, "java.lang.Enum");
+        let ordinal = get_method_id!(self, enum_base, "ordinal", "() -> int");
+        if !self.is_instance_of(constant, enum_base) {
+            return Err(Error::new("Expected an instance of java.lang.Enum", JNI_ERR))
+        }
+
+        self.call_value_method(constant, ordinal, &vec![])?
+            .into_int()
+    }
+
+    /// Read every public static final field of a type declared by a class, converting each value
+    /// with a function which may skip it by returning None. Each field is handled in its own local
+    /// frame, so classes with many fields don't exhaust local references
+    fn load_constants<T, F>(&self, cls: &JClass, ty: &JClass, read: F) -> Result<HashMap<String, T>>
+        where
+            F: Fn(JValue) -> Result<Option<T>>
+    {
+        let cls_cls = get_cls!(self, "java.lang.Class");
+        let get_fields = get_method_id!(self, cls_cls, "getDeclaredFields", "() -> java.lang.reflect.Field[]");
+        let field_cls = get_cls!(self, "java.lang.reflect.Field");
+        let get_modifiers = get_method_id!(self, field_cls, "getModifiers", "() -> int");
+        let get_type = get_method_id!(self, field_cls, "getType", "() -> java.lang.Class");
+        let get_name = get_method_id!(self, field_cls, "getName", "() -> java.lang.String");
+
+        let fields = self.call_value_method(cls.downcast(), get_fields, &vec![])?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Class.getDeclaredFields"))?;
+        // SAFETY: Internal pointer use, getDeclaredFields returns a Field[]
+        let field_arr = JObjectArray::new(unsafe { fields.borrow_ptr() } as _)?;
+        let constant = MODIFIER_PUBLIC | MODIFIER_STATIC | MODIFIER_FINAL;
+
+        let mut out = HashMap::new();
+        let result = (|| -> Result<()> {
+            for idx in 0..self.get_array_length((&field_arr).downcast()) {
+                // The field and everything read from it, such as its type token, go with the frame
+                self.push_local_frame(8)?;
+                let entry = (|| -> Result<Option<(String, T)>> {
+                    let field = self.get_object_array_element(&field_arr, idx)?;
+                    let modifiers = self.call_value_method(&field, get_modifiers, &vec![])?
+                        .into_int()?;
+                    if modifiers & constant != constant {
+                        return Ok(None)
+                    }
+
+                    let field_ty = self.call_value_method(&field, get_type, &vec![])?
+                        .into_obj()?
+                        .ok_or_else(|| Error::new_null("Field.getType"))?;
+                    if !self.is_same_object(&field_ty, ty.downcast()) {
+                        return Ok(None)
+                    }
+
+                    let name = self.call_value_method(&field, get_name, &vec![])?
+                        .into_obj()?
+                        .ok_or_else(|| Error::new_null("Field.getName"))?;
+                    // SAFETY: Guaranteed safe upcast, getName returns a String
+                    let name: String = self.get_string_chars(&unsafe { name.upcast_raw() })?.into_iter().collect();
+
+                    let id = self.from_reflected_field(&field)?;
+                    let val = self.get_static_field(cls, &id)?;
+                    Ok(read(val)?.map(|val| (name, val)))
+                })();
+                self.pop_local_frame(None);
+
+                if let Some((name, val)) = entry? {
+                    out.insert(name, val);
+                }
+            }
+            Ok(())
+        })();
+
+        self.delete_local_ref(fields);
+        result.map(|_| out)
+    }
+
+    /// Read every public static final int constant declared by a class into a map from field name
+    /// to value, such as the flag values of a Java API. Inherited fields aren't included
+    pub fn load_int_constants(&self, cls: &JClass) -> Result<HashMap<String, i32>> {
+        let int_cls = self.class_token("int")?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("Primitive class token"))?;
+        // SAFETY: Guaranteed safe upcast, class tokens are Classes
+        let int_cls: JClass = unsafe { int_cls.upcast_raw() };
+
+        let result = self.load_constants(cls, &int_cls, |val| val.into_int().map(Some));
+        self.delete_local_ref(int_cls.downcast());
+        result
+    }
+
+    /// Read every public static final String constant declared by a class into a map from field
+    /// name to value. Inherited fields aren't included, and fields holding null are skipped
+    pub fn load_string_constants(&self, cls: &JClass) -> Result<HashMap<String, String>> {
+        let str_cls = get_cls!(self, "java.lang.String");
+
+        self.load_constants(cls, str_cls, |val| {
+            match val.into_obj()? {
+                Some(str) => {
+                    // SAFETY: Guaranteed safe upcast, the field's type is String
+                    let str: String = self.get_string_chars(&unsafe { str.upcast_raw() })?.into_iter().collect();
+                    Ok(Some(str))
+                }
+                None => Ok(None)
+            }
+        })
+    }
+
+    /// Render an object as a JSON-like string, for logging and debugging. Strings, boxed
+    /// primitives and enum constants become JSON values, arrays and `Iterable`s become lists, and
+    /// `Map`s become objects keyed by the `toString` of each key. Any other object becomes a JSON
+    /// object of its non-static fields, including inherited ones, read without access checks.
+    /// Objects nested more than `max_depth` levels deep are written as a string naming their class
+    /// instead of being expanded, which also bounds the output of cyclic object graphs
+    pub fn object_to_json(&self, obj: &JObject, max_depth: usize) -> Result<String> {
+        let mut out = String::new();
+        self.write_json(Some(obj), max_depth, &mut out)?;
+        Ok(out)
+    }
+
+    /// Write a field or element value as JSON, expanding objects at most `depth` levels deep
+    fn write_json_value(&self, val: JValue, depth: usize, out: &mut String) -> Result<()> {
+        match val {
+            JValue::Bool(val) => out.push_str(if val { "true" } else { "false" }),
+            JValue::Byte(val) => out.push_str(&val.to_string()),
+            JValue::Char(val) => write_json_string(out, &val.to_string()),
+            JValue::Short(val) => out.push_str(&val.to_string()),
+            JValue::Int(val) => out.push_str(&val.to_string()),
+            JValue::Long(val) => out.push_str(&val.to_string()),
+            JValue::Float(val) => write_json_number(out, &val.to_string()),
+            JValue::Double(val) => write_json_number(out, &val.to_string()),
+            JValue::Object(obj) => return self.write_json(obj.as_ref(), depth, out)
+        }
+        Ok(())
+    }
+
+    /// Get the result of calling `toString` on an object
+    fn json_to_string(&self, obj: &JObject) -> Result<String> {
+        let obj_cls = get_cls!(self, "java.lang.Object");
+        let to_string = get_method_id!(self, obj_cls, "toString", "() -> java.lang.String");
+
+        match self.call_value_method(obj, to_string, &vec![])?.into_obj()? {
+            // SAFETY: Guaranteed safe upcast, toString returns a String
+            Some(str) => Ok(self.get_string_chars(&unsafe { str.upcast_raw() })?.into_iter().collect()),
+            None => Ok(String::from("null"))
+        }
+    }
+
+    /// Write each element of a sequence as a JSON list, getting elements with a function which
+    /// returns None once the sequence is done. Each element is written in its own local frame
+    fn write_json_list<F>(&self, depth: usize, out: &mut String, next: F) -> Result<()>
+        where
+            F: Fn() -> Result<Option<JValue<'env>>>
+    {
+        out.push('[');
+        let mut first = true;
+        loop {
+            self.push_local_frame(16)?;
+            let result = next().and_then(|elem| match elem {
+                Some(elem) => {
+                    if !first {
+                        out.push(',');
+                    }
+                    self.write_json_value(elem, depth, out).map(|_| true)
+                }
+                None => Ok(false)
+            });
+            self.pop_local_frame(None);
+
+            if !result? {
+                break
+            }
+            first = false;
+        }
+        out.push(']');
+        Ok(())
+    }
+
+    /// Write a possibly null object as JSON, expanding objects at most `depth` levels deep
+    fn write_json(&self, obj: Option<&JObject>, depth: usize, out: &mut String) -> Result<()> {
+        let obj = match obj {
+            Some(obj) => obj,
+            None => {
+                out.push_str("null");
+                return Ok(())
+            }
+        };
+
+        let string_cls = get_cls!(self, "java.lang.String");
+        let number_cls = get_cls!(self, "java.lang.Number");
+        let bool_cls = get_cls!(self, "java.lang.Boolean");
+        let char_cls = get_cls!(self, "java.lang.Character");
+        let enum_cls = get_cls!(self, "java.lang.Enum");
+
+        // Values which are never expanded
+        if self.is_instance_of(obj, string_cls) {
+            // SAFETY: Guaranteed safe upcast, the object is a String
+            let str: &JString = unsafe { obj.upcast_raw() };
+            let str: String = self.get_string_chars(str)?.into_iter().collect();
+            write_json_string(out, &str);
+            return Ok(())
+        } else if self.is_instance_of(obj, number_cls) {
+            write_json_number(out, &self.json_to_string(obj)?);
+            return Ok(())
+        } else if self.is_instance_of(obj, bool_cls) {
+            out.push_str(&self.json_to_string(obj)?);
+            return Ok(())
+        } else if self.is_instance_of(obj, char_cls) {
+            write_json_string(out, &self.json_to_string(obj)?);
+            return Ok(())
+        } else if self.is_instance_of(obj, enum_cls) {
+            write_json_string(out, &self.enum_name(obj)?);
+            return Ok(())
+        }
+
+        let (cls, name) = self.object_class_and_name(obj)?;
+        // The class is deleted however writing the object ends
+        let result = (|| -> Result<()> {
+            if depth == 0 {
+                write_json_string(out, &format!("<{}>", name));
+                return Ok(())
+            }
+            let depth = depth - 1;
+
+            let map_cls = get_cls!(self, "java.util.Map");
+            let iterable_cls = get_cls!(self, "java.lang.Iterable");
+            let iter_cls = get_cls!(self, "java.util.Iterator");
+            let iterator = get_method_id!(self, iterable_cls, "iterator", "() -> java.util.Iterator");
+            let has_next = get_method_id!(self, iter_cls, "hasNext", "() -> boolean");
+            let next = get_method_id!(self, iter_cls, "next", "() -> java.lang.Object");
+
+            if name.starts_with('[') {
+                let array_cls = get_cls!(self, "java.lang.reflect.Array");
+                let get = get_static_method_id!(self, array_cls, "get", "(java.lang.Object, int) -> java.lang.Object");
+
+                // SAFETY: Guaranteed safe upcast, the object's class is an array class
+                let len = self.get_array_length(unsafe { obj.upcast_raw() });
+                let idx = std::cell::Cell::new(0);
+                self.write_json_list(depth, out, || {
+                    let cur = idx.get();
+                    if cur >= len {
+                        return Ok(None)
+                    }
+                    idx.set(cur + 1);
+                    // Array.get boxes primitive elements, which are then written as their value
+                    let elem = self.call_static_method(array_cls, get, &vec![obj.into(), JValue::Int(cur as i32)])?
+                        .ok_or_else(|| Error::new("Unexpected void result", JNI_ERR))?;
+                    Ok(Some(elem))
+                })?;
+            } else if self.is_instance_of(obj, map_cls) {
+                let entry_cls = get_cls!(self, "java.util.Map$Entry");
+                let entry_set = get_method_id!(self, map_cls, "entrySet", "() -> java.util.Set");
+                let get_key = get_method_id!(self, entry_cls, "getKey", "() -> java.lang.Object");
+                let get_value = get_method_id!(self, entry_cls, "getValue", "() -> java.lang.Object");
+
+                let entries = self.auto_local(self.call_value_method(obj, entry_set, &vec![])?
+                    .into_obj()?
+                    .ok_or_else(|| Error::new_null("Map.entrySet"))?);
+                let iter = self.auto_local(self.call_value_method(&entries, iterator, &vec![])?
+                    .into_obj()?
+                    .ok_or_else(|| Error::new_null("Set.iterator"))?);
+
+                out.push('{');
+                let mut first = true;
+                while self.call_value_method(&iter, has_next, &vec![])?.into_bool()? {
+                    self.push_local_frame(16)?;
+                    let result = (|| -> Result<()> {
+                        let entry = self.call_value_method(&iter, next, &vec![])?
+                            .into_obj()?
+                            .ok_or_else(|| Error::new_null("Map entry"))?;
+                        let key = match self.call_value_method(&entry, get_key, &vec![])?.into_obj()? {
+                            Some(key) => self.json_to_string(&key)?,
+                            None => String::from("null")
+                        };
+                        let value = self.call_value_method(&entry, get_value, &vec![])?;
+
+                        if !first {
+                            out.push(',');
+                        }
+                        write_json_string(out, &key);
+                        out.push(':');
+                        self.write_json_value(value, depth, out)
+                    })();
+                    self.pop_local_frame(None);
+                    result?;
+                    first = false;
+                }
+                out.push('}');
+            } else if self.is_instance_of(obj, iterable_cls) {
+                let iter = self.auto_local(self.call_value_method(obj, iterator, &vec![])?
+                    .into_obj()?
+                    .ok_or_else(|| Error::new_null("Iterable.iterator"))?);
+
+                self.write_json_list(depth, out, || {
+                    if !self.call_value_method(&iter, has_next, &vec![])?.into_bool()? {
+                        return Ok(None)
+                    }
+                    Ok(Some(self.call_value_method(&iter, next, &vec![])?))
+                })?;
+            } else {
+                self.write_json_fields(obj, &cls, depth, out)?;
+            }
+            Ok(())
+        })();
+        self.delete_local_ref(cls.downcast());
+        result
+    }
+
+    /// Write the non-static fields of an object as a JSON object, superclass fields first
+    fn write_json_fields(&self, obj: &JObject, cls: &JClass, depth: usize, out: &mut String) -> Result<()> {
+        let obj_cls = get_cls!(self, "java.lang.Object");
+        let cls_cls = get_cls!(self, "java.lang.Class");
+        let field_cls = get_cls!(self, "java.lang.reflect.Field");
+        let get_fields = get_method_id!(self, cls_cls, "getDeclaredFields", "() -> java.lang.reflect.Field[]");
+        let get_modifiers = get_method_id!(self, field_cls, "getModifiers", "() -> int");
+        let get_name = get_method_id!(self, field_cls, "getName", "() -> java.lang.String");
+
+        // Collect the class hierarchy below Object, to write it from the top down
+        let mut chain = Vec::new();
+        let mut cur = self.get_superclass(cls).ok();
+        while let Some(super_cls) = cur {
+            if self.is_same_object((&super_cls).downcast(), obj_cls.downcast()) {
+                self.delete_local_ref(super_cls.downcast());
+                break
+            }
+            cur = self.get_superclass(&super_cls).ok();
+            chain.push(super_cls);
+        }
+
+        out.push('{');
+        let mut first = true;
+        for decl_cls in chain.iter().rev().chain(std::iter::once(cls)) {
+            let fields = self.call_value_method(decl_cls.downcast(), get_fields, &vec![])?
+                .into_obj()?
+                .ok_or_else(|| Error::new_null("Class.getDeclaredFields"))?;
+            // SAFETY: Internal pointer use, getDeclaredFields returns a Field[]
+            let field_arr = JObjectArray::new(unsafe { fields.borrow_ptr() } as _)?;
+
+            for idx in 0..self.get_array_length((&field_arr).downcast()) {
+                self.push_local_frame(16)?;
+                let result = (|| -> Result<()> {
+                    let field = self.get_object_array_element(&field_arr, idx)?;
+                    let modifiers = self.call_value_method(&field, get_modifiers, &vec![])?
+                        .into_int()?;
+                    if modifiers & MODIFIER_STATIC != 0 {
+                        return Ok(())
+                    }
+
+                    let name = self.call_value_method(&field, get_name, &vec![])?
+                        .into_obj()?
+                        .ok_or_else(|| Error::new_null("Field.getName"))?;
+                    // SAFETY: Guaranteed safe upcast, getName returns a String
+                    let name: String = self.get_string_chars(&unsafe { name.upcast_raw() })?.into_iter().collect();
+                    let id = self.from_reflected_field(&field)?;
+                    let value = self.get_field(obj, &id)?;
+
+                    if !first {
+                        out.push(',');
+                    }
+                    first = false;
+                    write_json_string(out, &name);
+                    out.push(':');
+                    self.write_json_value(value, depth, out)
+                })();
+                self.pop_local_frame(None);
+                result?;
+            }
+
+            self.delete_local_ref(fields);
+        }
+        out.push('}');
+
+        for super_cls in chain {
+            self.delete_local_ref(super_cls.downcast());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+//!
+//! Best-effort accounting of the local references created through the wrapper, to help hunt down
+//! reference leaks. JNI provides no way to query this, so only references the wrapper itself
+//! creates or deletes are counted. Counting is only active with the `leak-check` or
+//! `runtime-checks` features, otherwise all counts stay at zero.
+//!
+
+use std::cell::RefCell;
+
+const ENABLED: bool = cfg!(any(feature = "leak-check", feature = "runtime-checks"));
+
+/// Snapshot of the local references the wrapper has seen created and deleted on the current thread
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct LocalRefStats {
+    /// Number of local references created since the last reset
+    pub created: usize,
+    /// Number of local references deleted since the last reset, either directly or by popping
+    /// the frame they were created in
+    pub deleted: usize,
+    /// Number of local references created but not yet deleted
+    pub outstanding: usize
+}
+
+#[derive(Default)]
+struct Counters {
+    created: usize,
+    deleted: usize,
+    frames: Vec<(usize, usize)>
+}
+
+thread_local! {
+    static COUNTERS: RefCell<Counters> = RefCell::new(Counters::default());
+}
+
+/// Record a new local reference
+pub(crate) fn record_created() {
+    if ENABLED {
+        COUNTERS.with(|counters| counters.borrow_mut().created += 1)
+    }
+}
+
+/// Record a deleted local reference
+pub(crate) fn record_deleted() {
+    if ENABLED {
+        COUNTERS.with(|counters| counters.borrow_mut().deleted += 1)
+    }
+}
+
+/// Record that a local frame was pushed
+pub(crate) fn record_push_frame() {
+    if ENABLED {
+        COUNTERS.with(|counters| {
+            let mut counters = counters.borrow_mut();
+            let start = (counters.created, counters.deleted);
+            counters.frames.push(start);
+        })
+    }
+}
+
+/// Record that a local frame was popped, freeing all references created within it. Returns the
+/// statistics of the popped frame
+pub(crate) fn record_pop_frame() -> Option<LocalRefStats> {
+    if !ENABLED {
+        return None
+    }
+
+    COUNTERS.with(|counters| {
+        let mut counters = counters.borrow_mut();
+        let (start_created, start_deleted) = counters.frames.pop()?;
+
+        let created = counters.created.saturating_sub(start_created);
+        let deleted = counters.deleted.saturating_sub(start_deleted);
+        let outstanding = created.saturating_sub(deleted);
+        counters.deleted += outstanding;
+
+        Some(LocalRefStats { created, deleted, outstanding })
+    })
+}
+
+/// Get the current statistics for this thread
+pub(crate) fn snapshot() -> LocalRefStats {
+    COUNTERS.with(|counters| {
+        let counters = counters.borrow();
+        LocalRefStats {
+            created: counters.created,
+            deleted: counters.deleted,
+            outstanding: counters.created.saturating_sub(counters.deleted)
+        }
+    })
+}
+
+/// Reset the statistics for this thread. Frames that are still pushed start counting from zero
+pub(crate) fn reset() {
+    COUNTERS.with(|counters| {
+        let mut counters = counters.borrow_mut();
+        counters.created = 0;
+        counters.deleted = 0;
+        for frame in counters.frames.iter_mut() {
+            *frame = (0, 0);
+        }
+    })
+}
@@ -0,0 +1,215 @@
+//!
+//! Module containing string conversions beyond the core JNI string functions, such as raw UTF-16
+//! access and conversion between rust strings and char arrays.
+//!
+
+use std::convert::TryFrom;
+use std::slice;
+
+use crate::ffi::constants::{JNI_ERR, JNI_FALSE};
+use crate::types::{JString, JCharArray, JObjectArray, NullPolicy, JavaDownCast, JavaUpCast};
+use crate::error::{Error, Result};
+use crate::get_cls;
+use super::JNIEnv;
+
+impl<'env> JNIEnv<'env> {
+
+    /// Create a new [String][JString] object directly from UTF-16 code units. Unlike the char
+    /// based APIs, the units don't need to be valid UTF-16, as Java strings permit lone surrogates
+    pub fn new_string_utf16(&self, units: &[u16]) -> Result<JString<'env>> {
+        let env = self.internal_env();
+
+        let len = i32::try_from(units.len())
+            .map_err(|_| Error::new("String too long for a Java string", JNI_ERR))?;
+
+        let result = self.track_local(env.new_string(units.as_ptr(), len));
+        if result.is_null() {
+            Err(Error::new("Couldn't create new string", JNI_ERR))
+        } else {
+            Ok(JString::new(result)?)
+        }
+    }
+
+    /// Create a new [String][JString] object from UTF-16 code units, as
+    /// [new_string_utf16][Self::new_string_utf16] does, but checking they're valid UTF-16 first.
+    /// Unpaired surrogates are an Err naming the index of the first one, for when the units should
+    /// be text and a broken string would only fail later
+    pub fn new_string_utf16_checked(&self, units: &[u16]) -> Result<JString<'env>> {
+        let mut idx = 0;
+        for c in std::char::decode_utf16(units.iter().copied()) {
+            match c {
+                Ok(c) => idx += c.len_utf16(),
+                Err(err) => return Err(Error::new(
+                    &format!("Unpaired surrogate 0x{:04X} at index {}", err.unpaired_surrogate(), idx),
+                    JNI_ERR
+                ))
+            }
+        }
+
+        self.new_string_utf16(units)
+    }
+
+    /// Get the UTF-16 code units of a [String][JString], without decoding them
+    pub fn get_string_utf16(&self, str: &JString) -> Result<Vec<u16>> {
+        let env = self.internal_env();
+        let mut is_copy = JNI_FALSE;
+
+        // SAFETY: Internal pointer use
+        let chars = unsafe { env.get_string_chars(str.borrow_ptr(), &mut is_copy) };
+
+        if chars.is_null() {
+            return Err(Error::new("Couldn't get string characters", JNI_ERR))
+        }
+
+        // SAFETY: Java verifies returned pointer will be valid until release_string_chars is called
+        let out = unsafe { slice::from_raw_parts(chars, self.get_string_length(str)) }.to_vec();
+
+        // SAFETY: Internal pointer use
+        unsafe {
+            env.release_string_chars(str.borrow_ptr(), chars)
+        }
+
+        Ok(out)
+    }
+
+    /// Get a [String][JString] as a rust [String], replacing unpaired surrogates with the
+    /// replacement character
+    pub fn get_string_lossy(&self, str: &JString) -> Result<String> {
+        let units = self.get_string_utf16(str)?;
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    /// Get a [String][JString] as a rust [String]. Unpaired surrogates are an Err naming the index
+    /// of the first one, the same as [get_string_chars][Self::get_string_chars]
+    pub fn get_string_strict(&self, str: &JString) -> Result<String> {
+        Ok(self.get_string_chars(str)?.into_iter().collect())
+    }
+
+    /// Measure a [String][JString] three ways at once, for diagnosing encoding bugs. Returns the
+    /// number of UTF-16 units, the number of modified UTF-8 bytes, and the number of rust chars,
+    /// counting unpaired surrogates as one char each as in
+    /// [char_boundaries][Self::char_boundaries]. Returns Err if the units read back don't match
+    /// the length the JVM reports
+    pub fn string_metrics(&self, str: &JString) -> Result<(usize, usize, usize)> {
+        let units = self.get_string_utf16(str)?;
+        let len = self.get_string_length(str);
+        if units.len() != len {
+            return Err(Error::new(
+                &format!("String has length {}, but {} UTF-16 units were read", len, units.len()),
+                JNI_ERR
+            ))
+        }
+
+        let chars = std::char::decode_utf16(units.iter().copied()).count();
+        Ok((len, self.get_string_utf_length(str), chars))
+    }
+
+    /// Get the UTF-16 offset at which each character of a [String][JString] starts, so the nth
+    /// rust char of the string starts at unit `boundaries[n]`. Astral characters take two units,
+    /// so offsets for [get_string_region][Self::get_string_region] can be found from char
+    /// positions. Unpaired surrogates count as one character each, as in
+    /// [get_string_lossy][Self::get_string_lossy]
+    pub fn char_boundaries(&self, str: &JString) -> Result<Vec<usize>> {
+        let units = self.get_string_utf16(str)?;
+
+        let mut out = Vec::with_capacity(units.len());
+        let mut idx = 0;
+        while idx < units.len() {
+            out.push(idx);
+            let paired = (0xD800..0xDC00).contains(&units[idx])
+                && units.get(idx + 1).map_or(false, |next| (0xDC00..0xE000).contains(next));
+            idx += if paired { 2 } else { 1 };
+        }
+
+        Ok(out)
+    }
+
+    /// Create a new [String][JString] from a rust `&str`, and intern it. The returned string is the
+    /// canonical instance, so equal strings created this way will be the same object
+    pub fn new_interned_string(&self, str: &str) -> Result<JString<'env>> {
+        let str_cls = self.find_class("java.lang.String")?;
+        let intern_id = self.get_method_id(&str_cls, "intern", "() -> java.lang.String")?;
+
+        let new_str = self.new_string_utf(str)?;
+        let interned = self.call_value_method(&new_str.downcast(), &intern_id, &vec![])?
+            .into_obj()?
+            .ok_or_else(|| Error::new_null("String.intern result"))?;
+
+        // SAFETY: Guaranteed safe upcast, intern always returns a String
+        Ok(unsafe { interned.upcast_raw() })
+    }
+
+    /// Get the elements of an array of strings as rust [String]s, treating null elements as the
+    /// policy says. [NullPolicy::DefaultOf] gives the empty string. Returns Err if an element
+    /// isn't a String, or holds unpaired surrogates
+    pub fn get_string_array(&self, arr: &JObjectArray, nulls: NullPolicy) -> Result<Vec<Option<String>>> {
+        let str_cls = get_cls!(self, "java.lang.String");
+
+        let elems = self.object_array_to_vec(arr, NullPolicy::AsNone)?;
+        let mut out = Vec::with_capacity(elems.len());
+        for (idx, elem) in elems.into_iter().enumerate() {
+            let val = match elem {
+                Some(elem) => {
+                    if !self.is_instance_of(&elem, str_cls) {
+                        return Err(Error::new(&format!("get_string_array element {} isn't a String", idx), JNI_ERR))
+                    }
+                    // SAFETY: Checked to be a String above
+                    let str = unsafe { elem.upcast_raw() };
+                    let val = self.get_string_chars(&str)?.into_iter().collect();
+                    self.delete_local_ref(str.downcast());
+                    Some(val)
+                }
+                None => None
+            };
+            out.push(nulls.apply(val, "get_string_array", idx, || Some(String::new()))?);
+        }
+
+        Ok(out)
+    }
+
+    /// Read the UTF-16 code units of a char array in a single region copy
+    fn read_char_array(&self, arr: &JCharArray) -> Result<Vec<u16>> {
+        let env = self.internal_env();
+        let len = self.get_array_length(arr.downcast());
+        let mut units = vec![0; len];
+
+        // SAFETY: Internal pointer use, the buffer holds exactly the array's length
+        unsafe {
+            env.get_char_array_region(arr.borrow_ptr(), 0, len as i32, units.as_mut_ptr());
+        }
+
+        if self.exception_check() {
+            Err(Error::new("Couldn't read char array", JNI_ERR))
+        } else {
+            Ok(units)
+        }
+    }
+
+    /// Decode a char array holding UTF-16 into a rust [String]. Errors if the array contains
+    /// unpaired surrogates
+    pub fn char_array_to_string(&self, arr: &JCharArray) -> Result<String> {
+        let units = self.read_char_array(arr)?;
+        String::from_utf16(&units).map_err(|err| Error::from(Box::new(err)))
+    }
+
+    /// Decode a char array holding UTF-16 into a rust [String], replacing unpaired surrogates
+    /// with the replacement character
+    pub fn char_array_to_string_lossy(&self, arr: &JCharArray) -> Result<String> {
+        let units = self.read_char_array(arr)?;
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    /// Encode a rust `&str` as UTF-16 into a new char array
+    pub fn string_to_char_array(&self, str: &str) -> Result<JCharArray<'env>> {
+        let env = self.internal_env();
+        let units: Vec<u16> = str.encode_utf16().collect();
+
+        let arr = self.track_local(env.new_char_array(units.len() as i32));
+        if arr.is_null() {
+            return Err(Error::new("Couldn't create new char array", JNI_ERR))
+        }
+
+        env.set_char_array_region(arr, 0, units.len() as i32, units.as_ptr());
+        Ok(JCharArray::new(arr)?)
+    }
+}
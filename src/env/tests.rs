@@ -1,24 +1,32 @@
 use super::*;
 use crate::tests::with_env;
+use crate::classgen::{ClassFileBuilder, ClassConstant};
+use crate::types::{AsJObjectPtr, JArrayAny, ClassKind};
 
 #[test]
 fn test_get_version() {
     with_env(|env| {
-        assert_eq!(env.get_version(), JNIVersion::Ver18);
+        // The test JVM is created asking for 1.8, but newer JVMs provide a newer version
+        let version = env.get_version().unwrap();
+        assert!(version >= JNIVersion::Ver18, "{:?}", version);
+
+        let vm = env.get_jvm().unwrap();
+        let same = vm.get_local_env().unwrap();
+        assert_eq!(same.get_version().unwrap(), version);
+
+        // Raw versions this crate doesn't know are an Err rather than a panic, and are treated as
+        // the newest known version below them
+        assert_eq!(JNIVersion::try_from(0x0019_0000).ok(), None);
+        assert_eq!(JNIVersion::at_most(0x0019_0000), Some(JNIVersion::Ver24));
+        assert_eq!(JNIVersion::at_most(0x0016_0000), Some(JNIVersion::Ver21));
+        assert_eq!(JNIVersion::at_most(0), None);
     })
 }
 
 #[test]
 fn test_define_class() {
     with_env(|env| {
-        let cls_ldr_cls = env.find_class("java.lang.ClassLoader").unwrap();
-        let get_ldr_id = env.get_static_method_id(&cls_ldr_cls, "getSystemClassLoader", "() -> java.lang.ClassLoader").unwrap();
-        let cls_ldr = env.call_method(&cls_ldr_cls.downcast(), &get_ldr_id, &vec![])
-            .unwrap()
-            .unwrap()
-            .into_obj()
-            .unwrap()
-            .unwrap();
+        let cls_ldr = env.system_class_loader().unwrap();
 
         env.define_class(
             "TestClass",
@@ -113,6 +121,480 @@ fn test_is_assignable_from() {
     })
 }
 
+#[test]
+fn test_class_is_interface() {
+    with_env(|env| {
+        let iter_cls = env.find_class("java.lang.Iterable").unwrap();
+        let list_cls = env.find_class("java.util.ArrayList").unwrap();
+
+        assert!(env.class_is_interface(&iter_cls).unwrap());
+        assert!(!env.class_is_interface(&list_cls).unwrap());
+    })
+}
+
+#[test]
+#[cfg(feature = "strings-ext")]
+fn test_new_interned_string() {
+    with_env(|env| {
+        let first = env.new_interned_string("rust_jni interned").unwrap().downcast();
+        let second = env.new_interned_string("rust_jni interned").unwrap();
+        assert!(env.is_same_object(&first, &second.downcast()));
+
+        let plain = env.new_string_utf("rust_jni interned").unwrap();
+        assert!(!env.is_same_object(&first, &plain.downcast()));
+    });
+}
+
+#[test]
+#[cfg(feature = "arrays-ext")]
+fn test_as_native_array() {
+    with_env(|env| {
+        let arr = env.new_native_array(4, JNativeType::Int).unwrap();
+        // SAFETY: Pointer comes from a live array, and is only used as an object
+        let obj = JObject::new(unsafe { arr.as_jarray().borrow_ptr() } as _).unwrap();
+
+        match env.as_native_array(obj).unwrap() {
+            Some(JNativeArray::Int(_)) => (),
+            _ => panic!("Expected an int array")
+        }
+
+        let str = env.new_string_utf("not an array").unwrap();
+        assert!(env.as_native_array(str.downcast()).unwrap().is_none());
+
+        let cls = env.find_class("java.lang.Object").unwrap();
+        let obj_arr = env.new_object_array(2, &cls, None).unwrap();
+        assert!(env.as_native_array(obj_arr.downcast()).unwrap().is_none());
+    });
+}
+
+#[test]
+#[cfg(feature = "strings-ext")]
+fn test_char_array_string() {
+    with_env(|env| {
+        let text = "surrogates: \u{1D11E} and \u{1F600}, plain: abc";
+        let arr = env.string_to_char_array(text).unwrap();
+        assert_eq!(env.get_array_length((&arr).downcast()), text.encode_utf16().count());
+        assert_eq!(env.char_array_to_string(&arr).unwrap(), text);
+
+        let arr = env.new_native_array(3, JNativeType::Char).unwrap();
+        env.set_native_array_region(&arr, 0, 3, &JNativeVec::Char(vec![0x61, 0xD800, 0x62])).unwrap();
+        let arr = match arr {
+            JNativeArray::Char(arr) => arr,
+            _ => unreachable!()
+        };
+        assert!(env.char_array_to_string(&arr).is_err());
+        assert_eq!(env.char_array_to_string_lossy(&arr).unwrap(), "a\u{FFFD}b");
+    });
+}
+
+#[test]
+fn test_call_resource_exhausted() {
+    with_env(|env| {
+        // A list containing itself recurses until the stack runs out when hashed
+        let list_cls = env.find_class("java.util.ArrayList").unwrap();
+        let con_id = env.get_method_id(&list_cls, "<init>", "() -> void").unwrap();
+        let add_id = env.get_method_id(&list_cls, "add", "(java.lang.Object) -> boolean").unwrap();
+        let hash_id = env.get_method_id(&list_cls, "hashCode", "() -> int").unwrap();
+
+        let list = env.new_object(&list_cls, &con_id, &vec![]).unwrap();
+        env.call_method(&list, &add_id, &vec![(&list).into()]).unwrap();
+
+        match env.call_method(&list, &hash_id, &vec![]) {
+            Err(Error::ResourceExhausted(name)) => assert_eq!(name, "java.lang.StackOverflowError"),
+            other => panic!("Expected stack overflow, got {:?}", other)
+        }
+        assert!(!env.exception_check());
+
+        // Oversized arrays are rejected up front, without actually exhausting the heap
+        let arrays_cls = env.find_class("java.util.Arrays").unwrap();
+        let copy_id = env.get_static_method_id(&arrays_cls, "copyOf", "(int[], int) -> int[]").unwrap();
+        let arr = env.new_native_array(1, JNativeType::Int).unwrap();
+        // SAFETY: Pointer comes from a live array, and is only used as an object
+        let arr = JObject::new(unsafe { arr.as_jarray().borrow_ptr() } as _).unwrap();
+
+        match env.call_static_method(&arrays_cls, &copy_id, &vec![(&arr).into(), JValue::Int(i32::MAX)]) {
+            Err(Error::ResourceExhausted(name)) => assert_eq!(name, "java.lang.OutOfMemoryError"),
+            other => panic!("Expected out of memory, got {:?}", other)
+        }
+        assert!(!env.exception_check());
+
+        // Other exceptions are captured as they were thrown
+        let int_cls = env.find_class("java.lang.Integer").unwrap();
+        let parse_id = env.get_static_method_id(&int_cls, "parseInt", "(java.lang.String) -> int").unwrap();
+        let bad = env.new_string_utf("not a number").unwrap();
+        match env.call_static_method(&int_cls, &parse_id, &vec![bad.downcast().into()]) {
+            Err(Error::Exception(name, _)) => assert_eq!(name, "java.lang.NumberFormatException"),
+            other => panic!("Expected the thrown exception, got {:?}", other)
+        }
+        assert!(!env.exception_check());
+    });
+}
+
+#[test]
+fn test_method_call_builder() {
+    with_env(|env| {
+        let sb_cls = env.find_class("java.lang.StringBuilder").unwrap();
+        let to_string = env.get_method_id(&sb_cls, "toString", "() -> java.lang.String").unwrap();
+
+        // Manual path
+        let con_id = env.get_method_id(&sb_cls, "<init>", "(java.lang.String) -> void").unwrap();
+        let insert_str = env.get_method_id(&sb_cls, "insert", "(int, java.lang.String) -> java.lang.StringBuilder").unwrap();
+        let insert_char = env.get_method_id(&sb_cls, "insert", "(int, char) -> java.lang.StringBuilder").unwrap();
+        let insert_long = env.get_method_id(&sb_cls, "insert", "(int, long) -> java.lang.StringBuilder").unwrap();
+
+        let world = env.new_string_utf("world").unwrap();
+        let manual = env.new_object(&sb_cls, &con_id, &vec![world.downcast().into()]).unwrap();
+        let hello = env.new_string_utf("hello ").unwrap();
+        env.call_method(&manual, &insert_str, &vec![JValue::Int(0), hello.downcast().into()]).unwrap();
+        env.call_method(&manual, &insert_char, &vec![JValue::Int(5), JValue::Char(',')]).unwrap();
+        env.call_method(&manual, &insert_long, &vec![JValue::Int(0), JValue::Long(42)]).unwrap();
+
+        // Builder path
+        let built: JObject = env.constructor(&sb_cls, "(java.lang.String) -> void").unwrap()
+            .arg_str("world").unwrap()
+            .invoke_returning()
+            .unwrap();
+        env.method(&built, "insert", "(int, java.lang.String) -> java.lang.StringBuilder").unwrap()
+            .arg(0)
+            .arg_str("hello ").unwrap()
+            .invoke()
+            .unwrap();
+        env.method(&built, "insert", "(int, char) -> java.lang.StringBuilder").unwrap()
+            .arg(5)
+            .arg(',')
+            .invoke()
+            .unwrap();
+        env.method(&built, "insert", "(int, long) -> java.lang.StringBuilder").unwrap()
+            .arg(0)
+            .arg(42i64)
+            .invoke()
+            .unwrap();
+
+        let manual_str = env.call_method(&manual, &to_string, &vec![]).unwrap().unwrap().into_obj().unwrap().unwrap();
+        let built_str = env.call_method(&built, &to_string, &vec![]).unwrap().unwrap().into_obj().unwrap().unwrap();
+        // SAFETY: Guaranteed safe upcast, toString returns a String
+        let (manual_str, built_str) = unsafe { (manual_str.upcast_raw(), built_str.upcast_raw()) };
+        let manual_chars: String = env.get_string_chars(&manual_str).unwrap().into_iter().collect();
+        let built_chars: String = env.get_string_chars(&built_str).unwrap().into_iter().collect();
+        assert_eq!(manual_chars, "42hello, world");
+        assert_eq!(built_chars, manual_chars);
+
+        let len: i32 = env.method(&built, "length", "() -> int").unwrap()
+            .invoke_returning()
+            .unwrap();
+        assert_eq!(len, 14);
+
+        let int_cls = env.find_class("java.lang.Integer").unwrap();
+        let max: i32 = env.static_method(&int_cls, "max", "(int, int) -> int").unwrap()
+            .arg(3)
+            .arg(7)
+            .invoke_returning()
+            .unwrap();
+        assert_eq!(max, 7);
+
+        // Wrong count and wrong types are caught before calling
+        assert!(env.static_method(&int_cls, "max", "(int, int) -> int").unwrap().arg(3).invoke().is_err());
+        assert!(env.static_method(&int_cls, "max", "(int, int) -> int").unwrap().arg(3).arg(true).invoke().is_err());
+        assert!(!env.exception_check());
+    });
+}
+
+#[test]
+fn test_jvalue_try_into() {
+    use std::convert::TryInto;
+
+    let val: bool = JValue::Bool(true).try_into().unwrap();
+    assert!(val);
+    let val: i8 = JValue::Byte(-2).try_into().unwrap();
+    assert_eq!(val, -2);
+    let val: char = JValue::Char('x').try_into().unwrap();
+    assert_eq!(val, 'x');
+    let val: i16 = JValue::Short(300).try_into().unwrap();
+    assert_eq!(val, 300);
+    let val: i32 = JValue::Int(70000).try_into().unwrap();
+    assert_eq!(val, 70000);
+    let val: i64 = JValue::Long(1 << 40).try_into().unwrap();
+    assert_eq!(val, 1 << 40);
+    let val: f32 = JValue::Float(1.5).try_into().unwrap();
+    assert_eq!(val, 1.5);
+    let val: f64 = JValue::Double(-0.25).try_into().unwrap();
+    assert_eq!(val, -0.25);
+
+    // No widening or narrowing between types
+    let err: std::result::Result<i64, Error> = JValue::Int(1).try_into();
+    assert!(err.is_err());
+    let err: std::result::Result<i32, Error> = JValue::Object(None).try_into();
+    assert!(err.is_err());
+
+    with_env(|env| {
+        let str = env.new_string_utf("converted").unwrap();
+        let val: String = String::from_java(JValue::from((&str).downcast()), env).unwrap();
+        assert_eq!(val, "converted");
+
+        let upper: String = env.method((&str).downcast(), "toUpperCase", "() -> java.lang.String").unwrap()
+            .invoke_returning()
+            .unwrap();
+        assert_eq!(upper, "CONVERTED");
+
+        // Objects other than strings, and null, are refused
+        let obj_cls = env.find_class("java.lang.Object").unwrap();
+        assert!(String::from_java(JValue::from(&obj_cls), env).is_err());
+        assert!(String::from_java(JValue::Object(None), env).is_err());
+        assert!(String::from_java(JValue::Int(1), env).is_err());
+    })
+}
+
+#[test]
+#[cfg(feature = "reflect")]
+fn test_set_accessible() {
+    with_env(|env| {
+        // public class RustJniAccessible { private int secret = 7; }
+        let cls = env.define_class_in_system_loader(
+            "RustJniAccessible",
+            b"\xca\xfe\xba\xbe\x00\x00\x00\x34\x00\x0e\x0a\x00\x02\x00\x03\x07\x00\x04\x0c\
+\x00\x05\x00\x06\x01\x00\x10\x6a\x61\x76\x61\x2f\x6c\x61\x6e\x67\x2f\x4f\x62\x6a\x65\x63\x74\x01\
+\x00\x06\x3c\x69\x6e\x69\x74\x3e\x01\x00\x03\x28\x29\x56\x09\x00\x08\x00\x09\x07\x00\x0a\x0c\x00\
+\x0b\x00\x0c\x01\x00\x11\x52\x75\x73\x74\x4a\x6e\x69\x41\x63\x63\x65\x73\x73\x69\x62\x6c\x65\x01\
+\x00\x06\x73\x65\x63\x72\x65\x74\x01\x00\x01\x49\x01\x00\x04\x43\x6f\x64\x65\x00\x21\x00\x08\x00\
+\x02\x00\x00\x00\x01\x00\x02\x00\x0b\x00\x0c\x00\x00\x00\x01\x00\x01\x00\x05\x00\x06\x00\x01\x00\
+\x0d\x00\x00\x00\x17\x00\x02\x00\x01\x00\x00\x00\x0b\x2a\xb7\x00\x01\x2a\x10\x07\xb5\x00\x07\xb1\
+\x00\x00\x00\x00\x00\x00"
+        ).expect("Couldn't define accessibility test class");
+
+        let con_id = env.get_method_id(&cls, "<init>", "() -> void").unwrap();
+        let obj = env.new_object(&cls, &con_id, &vec![]).unwrap();
+
+        let secret_id = env.get_field_id(&cls, "secret", "int").unwrap();
+        let field = env.to_reflected_field(&cls, &secret_id, false).unwrap();
+        let field_cls = env.find_class("java.lang.reflect.Field").unwrap();
+        let get_int = env.get_method_id(&field_cls, "getInt", "(java.lang.Object) -> int").unwrap();
+
+        assert!(env.call_method(&field, &get_int, &vec![(&obj).into()]).is_err());
+        assert!(!env.exception_check());
+
+        // Java 9 through 17 can't handle setAccessible with no Java caller frame
+        let sys_cls = env.find_class("java.lang.System").unwrap();
+        let get_prop = env.get_static_method_id(&sys_cls, "getProperty", "(java.lang.String) -> java.lang.String").unwrap();
+        let key = env.new_string_utf("java.specification.version").unwrap();
+        let version = env.call_static_method(&sys_cls, &get_prop, &vec![key.downcast().into()])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        // SAFETY: Guaranteed safe upcast, getProperty returns a String
+        let version: String = env.get_string_chars(&unsafe { version.upcast_raw() }).unwrap().into_iter().collect();
+        let null_caller_unsupported = matches!(version.parse::<u32>(), Ok(9..=17));
+
+        match env.set_accessible(&field, true) {
+            Ok(()) => {
+                assert!(!null_caller_unsupported);
+                let secret = env.call_method(&field, &get_int, &vec![(&obj).into()]).unwrap().unwrap().into_int().unwrap();
+                assert_eq!(secret, 7);
+            }
+            Err(Error::Unsupported(_)) => assert!(null_caller_unsupported),
+            Err(err) => panic!("Unexpected error {:?}", err)
+        }
+        assert!(!env.exception_check());
+
+        // jdk.internal.misc isn't exported, so this is refused and the exception captured
+        let unsafe_cls = env.find_class("jdk.internal.misc.Unsafe").unwrap();
+        let unsafe_id = env.get_static_field_id(&unsafe_cls, "theUnsafe", "jdk.internal.misc.Unsafe").unwrap();
+        let unsafe_field = env.to_reflected_field(&unsafe_cls, &unsafe_id, true).unwrap();
+        assert!(env.set_accessible(&unsafe_field, true).is_err());
+        assert!(!env.exception_check());
+    });
+}
+
+#[test]
+#[cfg(feature = "reflect")]
+fn test_enum_constant() {
+    with_env(|env| {
+        let day_cls = env.find_class("java.time.DayOfWeek").unwrap();
+        let monday = env.enum_constant(&day_cls, "MONDAY").unwrap();
+        assert_eq!(env.enum_name(&monday).unwrap(), "MONDAY");
+        assert_eq!(env.enum_ordinal(&monday).unwrap(), 0);
+
+        let sunday = env.enum_constant(&day_cls, "SUNDAY").unwrap();
+        assert_eq!(env.enum_ordinal(&sunday).unwrap(), 6);
+
+        match env.enum_constant(&day_cls, "FUNDAY") {
+            Err(Error::Exception(name, _)) => assert_eq!(name, "java.lang.IllegalArgumentException"),
+            other => panic!("Expected IllegalArgumentException, got {:?}", other.map(|_| ()))
+        }
+        assert!(!env.exception_check());
+
+        let str = env.new_string_utf("MONDAY").unwrap();
+        assert!(env.enum_name((&str).downcast()).is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+#[cfg(feature = "reflect")]
+fn test_load_constants() {
+    with_env(|env| {
+        let cal_cls = env.find_class("java.util.Calendar").unwrap();
+        let ints = env.load_int_constants(&cal_cls).unwrap();
+        assert_eq!(ints["JANUARY"], 0);
+        assert_eq!(ints["DECEMBER"], 11);
+        assert_eq!(ints["DAY_OF_WEEK"], 7);
+        // Package private and non-int constants are left out
+        assert!(!ints.contains_key("ERA_MASK"));
+        assert!(!ints.contains_key("serialVersionUID"));
+
+        let bytes = ClassFileBuilder::new("RustJniStringConstants")
+            .constant("FIRST", ClassConstant::String(String::from("one")))
+            .constant("SECOND", ClassConstant::String(String::from("two")))
+            .constant("COUNT", ClassConstant::Int(2))
+            .static_field("MUTABLE", "Ljava/lang/String;")
+            .field("instance", "Ljava/lang/String;")
+            .build();
+        let cls = env.define_class_in_system_loader("RustJniStringConstants", &bytes).unwrap();
+
+        let strs = env.load_string_constants(&cls).unwrap();
+        assert_eq!(strs.len(), 2);
+        assert_eq!(strs["FIRST"], "one");
+        assert_eq!(strs["SECOND"], "two");
+        let ints = env.load_int_constants(&cls).unwrap();
+        assert_eq!(ints.len(), 1);
+        assert_eq!(ints["COUNT"], 2);
+    })
+}
+
+#[test]
+#[cfg(feature = "reflect")]
+fn test_clone_object() {
+    with_env(|env| {
+        let list_cls = env.find_class("java.util.ArrayList").unwrap();
+        let list_new = env.get_method_id(&list_cls, "<init>", "() -> void").unwrap();
+        let add = env.get_method_id(&list_cls, "add", "(java.lang.Object) -> boolean").unwrap();
+        let size = env.get_method_id(&list_cls, "size", "() -> int").unwrap();
+
+        // ArrayList is Cloneable
+        let list = env.new_object(&list_cls, &list_new, &vec![]).unwrap();
+        let str = env.new_string_utf("first").unwrap();
+        env.call_method(&list, &add, &vec![(&str).downcast().into()]).unwrap();
+        let copy = env.clone_object(&list).unwrap();
+        assert!(!env.is_same_object(&list, &copy));
+        env.call_method(&list, &add, &vec![(&str).downcast().into()]).unwrap();
+        assert_eq!(env.call_method(&list, &size, &vec![]).unwrap().unwrap().into_int().unwrap(), 2);
+        assert_eq!(env.call_method(&copy, &size, &vec![]).unwrap().unwrap().into_int().unwrap(), 1);
+
+        // ConcurrentHashMap is only Serializable
+        let map_cls = env.find_class("java.util.concurrent.ConcurrentHashMap").unwrap();
+        let map_new = env.get_method_id(&map_cls, "<init>", "() -> void").unwrap();
+        let put = env.get_method_id(&map_cls, "put", "(java.lang.Object, java.lang.Object) -> java.lang.Object").unwrap();
+        let map_size = env.get_method_id(&map_cls, "size", "() -> int").unwrap();
+
+        let map = env.new_object(&map_cls, &map_new, &vec![]).unwrap();
+        env.call_method(&map, &put, &vec![(&str).downcast().into(), (&list).into()]).unwrap();
+        let copy = env.clone_object(&map).unwrap();
+        let key = env.new_string_utf("second").unwrap();
+        env.call_method(&map, &put, &vec![(&key).downcast().into(), (&list).into()]).unwrap();
+        assert_eq!(env.call_method(&map, &map_size, &vec![]).unwrap().unwrap().into_int().unwrap(), 2);
+        assert_eq!(env.call_method(&copy, &map_size, &vec![]).unwrap().unwrap().into_int().unwrap(), 1);
+
+        // Thread is neither
+        let thread_cls = env.find_class("java.lang.Thread").unwrap();
+        let thread_new = env.get_method_id(&thread_cls, "<init>", "() -> void").unwrap();
+        let thread = env.new_object(&thread_cls, &thread_new, &vec![]).unwrap();
+        assert!(env.clone_object(&thread).is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+#[cfg(feature = "reflect")]
+fn test_class_kind() {
+    with_env(|env| {
+        let int_cls = env.class_token("int").unwrap().into_obj().unwrap().unwrap();
+        // SAFETY: Guaranteed safe upcast, class tokens are Classes
+        let int_cls: JClass = unsafe { int_cls.upcast_raw() };
+        assert_eq!(env.class_kind(&int_cls).unwrap(), ClassKind::Primitive(JType::Int));
+
+        let int_arr = env.find_class("int[]").unwrap();
+        assert_eq!(env.class_kind(&int_arr).unwrap(), ClassKind::Array(Box::new(ClassKind::Primitive(JType::Int))));
+        let str_arr_arr = env.find_class("java.lang.String[][]").unwrap();
+        assert_eq!(
+            env.class_kind(&str_arr_arr).unwrap(),
+            ClassKind::Array(Box::new(ClassKind::Array(Box::new(ClassKind::Class))))
+        );
+
+        let runnable = env.find_class("java.lang.Runnable").unwrap();
+        assert_eq!(env.class_kind(&runnable).unwrap(), ClassKind::Interface);
+        let string = env.find_class("java.lang.String").unwrap();
+        assert_eq!(env.class_kind(&string).unwrap(), ClassKind::Class);
+        let time_unit = env.find_class("java.util.concurrent.TimeUnit").unwrap();
+        assert_eq!(env.class_kind(&time_unit).unwrap(), ClassKind::Enum);
+        let deprecated = env.find_class("java.lang.Deprecated").unwrap();
+        assert_eq!(env.class_kind(&deprecated).unwrap(), ClassKind::Annotation);
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+#[cfg(feature = "reflect")]
+fn test_object_to_json() {
+    with_env(|env| {
+        let bytes = ClassFileBuilder::new("RustJniJson")
+            .field("count", "I")
+            .field("flag", "Z")
+            .field("label", "Ljava/lang/String;")
+            .field("values", "[I")
+            .static_field("INSTANCES", "I")
+            .build();
+        let cls = env.define_class_in_system_loader("RustJniJson", &bytes).unwrap();
+
+        let con_id = env.get_method_id(&cls, "<init>", "() -> void").unwrap();
+        let obj = env.new_object(&cls, &con_id, &vec![]).unwrap();
+        let count = env.get_field_id(&cls, "count", "int").unwrap();
+        let flag = env.get_field_id(&cls, "flag", "boolean").unwrap();
+        env.set_field_int(&obj, &count, 42).unwrap();
+        env.set_field_bool(&obj, &flag, true).unwrap();
+
+        assert_eq!(
+            env.object_to_json(&obj, 1).unwrap(),
+            r#"{"count":42,"flag":true,"label":null,"values":null}"#
+        );
+
+        // Nested objects are expanded up to the depth limit
+        let label = env.get_field_id(&cls, "label", "java.lang.String").unwrap();
+        let values = env.get_field_id(&cls, "values", "int[]").unwrap();
+        let str = env.new_string_utf("say \"hi\"").unwrap();
+        let arr = env.new_native_array(2, JNativeType::Int).unwrap();
+        env.set_native_array_region(&arr, 0, 2, &JNativeVec::Int(vec![1, 2])).unwrap();
+        env.set_field(&obj, &label, str.as_object().into()).unwrap();
+        env.set_field(&obj, &values, arr.as_jarray().downcast().into()).unwrap();
+
+        assert_eq!(
+            env.object_to_json(&obj, 2).unwrap(),
+            r#"{"count":42,"flag":true,"label":"say \"hi\"","values":[1,2]}"#
+        );
+        assert_eq!(
+            env.object_to_json(&obj, 1).unwrap(),
+            r#"{"count":42,"flag":true,"label":"say \"hi\"","values":"<[I>"}"#
+        );
+        assert_eq!(env.object_to_json(&obj, 0).unwrap(), r#""<RustJniJson>""#);
+
+        // Collections are written as lists and maps as objects
+        let map_cls = env.find_class("java.util.TreeMap").unwrap();
+        let map_new = env.get_method_id(&map_cls, "<init>", "() -> void").unwrap();
+        let put = env.get_method_id(&map_cls, "put", "(java.lang.Object, java.lang.Object) -> java.lang.Object").unwrap();
+        let list_cls = env.find_class("java.util.ArrayList").unwrap();
+        let list_new = env.get_method_id(&list_cls, "<init>", "() -> void").unwrap();
+        let add = env.get_method_id(&list_cls, "add", "(java.lang.Object) -> boolean").unwrap();
+
+        let list = env.new_object(&list_cls, &list_new, &vec![]).unwrap();
+        env.call_method(&list, &add, &vec![str.as_object().into()]).unwrap();
+        env.call_method(&list, &add, &vec![JValue::Object(None)]).unwrap();
+        let map = env.new_object(&map_cls, &map_new, &vec![]).unwrap();
+        env.call_method(&map, &put, &vec![str.as_object().into(), (&list).into()]).unwrap();
+        assert_eq!(env.object_to_json(&map, 2).unwrap(), r#"{"say \"hi\"":["say \"hi\"",null]}"#);
+        assert!(!env.exception_check());
+    })
+}
+
 #[test]
 fn test_throw_family() {
     with_env(|env| {
@@ -137,6 +619,25 @@ fn test_throw_family() {
     });
 }
 
+#[test]
+fn test_throw_new_encoding() {
+    with_env(|env| {
+        let exc_cls = env.find_class("java.lang.IllegalStateException").unwrap();
+
+        // Characters outside the BMP and NUL are both encoded specially in modified UTF-8
+        let msg = "Broken \u{1F4A5} at \u{e9}t\u{e9}\0end";
+        env.throw_new(&exc_cls, msg).expect("Couldn't throw new exception");
+        match env.propagate_if_pending() {
+            Err(Error::Exception(name, Some(thrown))) => {
+                assert_eq!(name, "java.lang.IllegalStateException");
+                assert_eq!(thrown, msg);
+            }
+            other => panic!("Expected the thrown exception, got {:?}", other)
+        }
+        assert!(!env.exception_check());
+    });
+}
+
 // Can't test fatal_error, it exits the program?
 
 #[test]
@@ -147,15 +648,102 @@ fn test_ensure_local_capacity() {
 }
 
 #[test]
-#[ignore = "Not yet implemented"]
 fn test_local_frame() {
-    todo!()
+    with_env(|env| {
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let len_id = env.get_method_id(&str_cls, "length", "() -> int").unwrap();
+
+        // The returned object is promoted out of the frame, and everything else is freed
+        let kept = env.with_local_frame(4, |frame| {
+            let _freed = frame.new_string_utf("freed").unwrap();
+            let kept = frame.new_string_utf("kept").unwrap();
+            Ok(Some(kept.downcast()))
+        }).unwrap().unwrap();
+        assert_eq!(env.get_object_ref_type(&kept), JRefType::Local);
+        let len = env.call_method(&kept, &len_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+        assert_eq!(len, 4);
+
+        let none = env.with_local_frame(1, |_| Ok(None)).unwrap();
+        assert!(none.is_none());
+
+        // Closure errors are returned after the frame is popped
+        let err = env.with_local_frame(1, |_| Err(Error::new("closure failed", JNI_ERR))).unwrap_err();
+        assert_eq!(err.to_string(), Error::new("closure failed", JNI_ERR).to_string());
+
+        // A frame the JVM can't make is an error, without running the closure
+        let mut ran = false;
+        assert!(env.with_local_frame(-1, |_| { ran = true; Ok(None) }).is_err());
+        assert!(!ran);
+        if env.exception_check() {
+            env.exception_clear().unwrap();
+        }
+
+        // The outer frame is still usable afterwards
+        let len = env.call_method(&kept, &len_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+        assert_eq!(len, 4);
+    })
 }
 
 #[test]
-#[ignore = "Not yet implemented"]
 fn test_global_ref() {
-    todo!()
+    with_env(|env| {
+        let local: JObject = env.new_string_utf("global").unwrap().downcast();
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let len_id = env.get_method_id(&str_cls, "length", "() -> int").unwrap();
+
+        // Dropped at the end of the block, deleting the reference
+        {
+            let global = env.new_global_ref(&local).unwrap();
+            assert_eq!(env.get_object_ref_type(global.as_obj()), JRefType::Global);
+            assert!(env.is_same_object(global.as_obj(), &local));
+            let len = env.call_method(global.as_obj(), &len_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+            assert_eq!(len, 6);
+        }
+
+        // Leaked references outlive the owner, until deleted by hand
+        let leaked = env.new_global_ref(&local).unwrap().leak();
+        assert_eq!(env.get_object_ref_type(&leaked), JRefType::Global);
+        let len = env.call_method(&leaked, &len_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+        assert_eq!(len, 6);
+        env.delete_global_ref(leaked);
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_global_cross_thread() {
+    crate::tests::with_vm(|vm| {
+        let vm: &JavaVM = vm;
+        let env = vm.attach_current_thread().expect("Couldn't attach test thread");
+        let local: JObject = env.new_string_utf("sent").unwrap().downcast();
+        let shared = std::sync::Arc::new(env.new_global(&local).unwrap());
+        let sent = env.new_global(&local).unwrap();
+        assert_eq!(env.get_object_ref_type(shared.as_obj()), JRefType::Global);
+
+        std::thread::scope(|scope| {
+            // Shared between attached threads, each using it through their own environment
+            for _ in 0..2 {
+                let shared = shared.clone();
+                scope.spawn(move || {
+                    let guard = vm.attach_current_thread_guarded().expect("Couldn't attach thread");
+                    let str_cls = guard.find_class("java.lang.String").unwrap();
+                    let len_id = guard.get_method_id(&str_cls, "length", "() -> int").unwrap();
+                    let len = guard.call_method(shared.as_obj(), &len_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+                    assert_eq!(len, 4);
+                });
+            }
+
+            // Dropped on a thread that isn't attached, which attaches just to delete it
+            scope.spawn(move || drop(sent));
+        });
+
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let len_id = env.get_method_id(&str_cls, "length", "() -> int").unwrap();
+        let len = env.call_method(shared.as_obj(), &len_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+        assert_eq!(len, 4);
+        assert!(env.is_same_object(shared.as_obj(), &local));
+        assert!(!env.exception_check());
+    })
 }
 
 #[test]
@@ -163,3 +751,2289 @@ fn test_global_ref() {
 fn test_local_ref() {
     todo!()
 }
+
+#[test]
+fn test_auto_local() {
+    with_env(|env| {
+        const LEN: usize = 100_000;
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let len_id = env.get_method_id(&str_cls, "length", "() -> int").unwrap();
+        let init = env.new_string_utf("element").unwrap();
+        let arr = env.new_object_array(LEN, &str_cls, Some(init.as_object())).unwrap();
+
+        // One reference per element would overflow the table without being deleted
+        let before = env.local_ref_stats();
+        let mut total = 0;
+        for idx in 0..LEN {
+            let elem = env.auto_local(env.get_object_array_element(&arr, idx).unwrap());
+            total += env.call_method(&elem, &len_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+        }
+        assert_eq!(total, 7 * LEN as i32);
+        assert!(!env.exception_check());
+        assert_eq!(env.local_ref_stats().outstanding, before.outstanding);
+
+        // Forgotten references are kept alive
+        let kept = env.auto_local(env.get_object_array_element(&arr, 0).unwrap()).forget();
+        assert_eq!(env.get_object_ref_type(&kept), JRefType::Local);
+        assert!(env.is_same_object(&kept, init.as_object()));
+        if cfg!(any(feature = "leak-check", feature = "runtime-checks")) {
+            assert_eq!(env.local_ref_stats().outstanding, before.outstanding + 1);
+        }
+        env.delete_local_ref(kept);
+    })
+}
+
+#[test]
+fn test_call_default_method() {
+    with_env(|env| {
+        let list_cls = env.find_class("java.util.ArrayList").unwrap();
+        let iter_cls = env.find_class("java.lang.Iterable").unwrap();
+        let split_cls = env.find_class("java.util.Spliterator").unwrap();
+        let con_id = env.get_method_id(&list_cls, "<init>", "() -> void").unwrap();
+        let split_id = env.get_method_id(&iter_cls, "spliterator", "() -> java.util.Spliterator").unwrap();
+        let chars_id = env.get_method_id(&split_cls, "characteristics", "() -> int").unwrap();
+
+        let list = env.new_object(&list_cls, &con_id, &vec![]).unwrap();
+
+        // ArrayList overrides spliterator with one that knows its size
+        let split = env.call_method(&list, &split_id, &vec![])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        let chars = env.call_method(&split, &chars_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+        assert_ne!(chars, 0);
+
+        // The Iterable default knows nothing about the collection, so reports no characteristics
+        let split = env.call_default_method(&list, &iter_cls, "spliterator", "() -> java.util.Spliterator", &vec![])
+            .expect("Couldn't call Iterable.spliterator default method")
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        let chars = env.call_method(&split, &chars_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+        assert_eq!(chars, 0);
+
+        assert!(env.call_default_method(&list, &list_cls, "spliterator", "() -> java.util.Spliterator", &vec![]).is_err());
+    });
+}
+
+#[test]
+fn test_module_can_access() {
+    with_env(|env| {
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let log_cls = env.find_class("java.util.logging.Logger").unwrap();
+        let base = env.get_module(&str_cls).expect("Couldn't get java.base module");
+        let logging = env.get_module(&log_cls).expect("Couldn't get java.logging module");
+
+        assert!(env.module_can_access(&logging, "java.lang", &base).unwrap());
+        assert!(env.module_can_access(&base, "java.lang", &base).unwrap());
+        assert!(!env.module_can_access(&logging, "com.example.missing", &base).unwrap());
+    });
+}
+
+#[test]
+#[cfg(any(feature = "leak-check", feature = "runtime-checks"))]
+fn test_local_ref_stats() {
+    with_env(|env| {
+        env.reset_stats();
+
+        for idx in 0..100 {
+            env.new_string_utf(&format!("leak {}", idx)).unwrap();
+        }
+        let stats = env.local_ref_stats();
+        assert!(stats.outstanding >= 100);
+        assert_eq!(stats.created - stats.deleted, stats.outstanding);
+
+        let before = env.local_ref_stats();
+        env.push_local_frame(16).unwrap();
+        for idx in 0..10 {
+            env.new_string_utf(&format!("framed {}", idx)).unwrap();
+        }
+        assert_eq!(env.local_ref_stats().outstanding, before.outstanding + 10);
+        env.pop_local_frame(None);
+
+        let after = env.local_ref_stats();
+        assert_eq!(after.created, before.created + 10);
+        assert_eq!(after.deleted, before.deleted + 10);
+        assert_eq!(after.outstanding, before.outstanding);
+    })
+}
+
+#[test]
+fn test_clone_env() {
+    with_env(|env| {
+        let other = env.clone();
+
+        // Warm the lookups cached on first use, so only the references made below are counted
+        let warm_cls = env.find_class("java.lang.String").unwrap();
+        let warm_str = env.new_string_utf("warm").unwrap();
+        let len_id = env.get_method_id(&warm_cls, "length", "() -> int").unwrap();
+        env.call_method((&warm_str).downcast(), &len_id, &vec![]).unwrap();
+        env.is_same_object(&warm_cls.downcast(), &warm_str.downcast());
+        let before = env.local_ref_stats();
+
+        let str_cls = other.find_class("java.lang.String").unwrap();
+        let len_id = env.get_method_id(&str_cls, "length", "() -> int").unwrap();
+        let str = other.new_string_utf("shared").unwrap();
+        assert_eq!(env.call_method((&str).downcast(), &len_id, &vec![]).unwrap().unwrap().into_int().unwrap(), 6);
+        assert!(other.is_same_object(&str_cls.downcast(), &env.find_class("java.lang.String").unwrap().downcast()));
+
+        // Frames pushed through one handle are popped through the other
+        env.push_local_frame(4).unwrap();
+        other.new_string_utf("framed").unwrap();
+        other.pop_local_frame(None);
+
+        // Both handles see the same accounting, rather than keeping their own
+        assert_eq!(env.local_ref_stats(), other.local_ref_stats());
+        if cfg!(any(feature = "leak-check", feature = "runtime-checks")) {
+            assert_eq!(env.local_ref_stats().created, before.created + 4);
+        }
+    })
+}
+
+#[test]
+#[cfg(feature = "collections")]
+fn test_unwrap_optional() {
+    with_env(|env| {
+        let opt_cls = env.find_class("java.util.Optional").unwrap();
+        let of_id = env.get_static_method_id(&opt_cls, "of", "(java.lang.Object) -> java.util.Optional").unwrap();
+        let empty_id = env.get_static_method_id(&opt_cls, "empty", "() -> java.util.Optional").unwrap();
+
+        let str = env.new_string_utf("present").unwrap().downcast();
+        let opt = env.call_static_method(&opt_cls, &of_id, &vec![(&str).into()])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        let val = env.unwrap_optional(&opt).unwrap().expect("Optional was empty");
+        assert!(env.is_same_object(&str, &val));
+
+        let empty = env.call_static_method(&opt_cls, &empty_id, &vec![])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        assert!(env.unwrap_optional(&empty).unwrap().is_none());
+    })
+}
+
+#[test]
+#[cfg(feature = "arrays-ext")]
+fn test_call_native_array_method() {
+    with_env(|env| {
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let chars_id = env.get_method_id(&str_cls, "chars", "() -> java.util.stream.IntStream").unwrap();
+        let stream_cls = env.find_class("java.util.stream.IntStream").unwrap();
+        let to_array = env.get_method_id(&stream_cls, "toArray", "() -> int[]").unwrap();
+
+        let str = env.new_string_utf("abc").unwrap();
+        let chars = || env.call_method((&str).downcast(), &chars_id, &vec![])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+
+        match env.call_native_array_method(&chars(), &to_array, &vec![], JNativeType::Int).unwrap() {
+            Some(JNativeVec::Int(vals)) => assert_eq!(vals, vec![0x61, 0x62, 0x63]),
+            _ => panic!("Expected an int vec")
+        }
+
+        assert!(env.call_native_array_method(&chars(), &to_array, &vec![], JNativeType::Double).is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+#[cfg(feature = "arrays-ext")]
+fn test_with_native_array_elements() {
+    with_env(|env| {
+        let arr = env.new_native_array(3, JNativeType::Int).unwrap();
+        env.set_native_array_region(&arr, 0, 3, &JNativeVec::Int(vec![1, 2, 3])).unwrap();
+        let read = || match env.get_native_array_region(&arr, 0, 3).unwrap() {
+            JNativeVec::Int(vals) => vals,
+            _ => panic!("Expected an int vec")
+        };
+
+        // Abort discards the changes
+        env.with_native_array_elements(&arr, ReleaseMode::Abort, |elems| {
+            if let JNativeSlice::Int(vals) = &mut **elems {
+                vals[0] = 10;
+            }
+        }).unwrap();
+        assert_eq!(read(), vec![1, 2, 3]);
+
+        // CopyFree writes them back
+        let sum = env.with_native_array_elements(&arr, ReleaseMode::CopyFree, |elems| {
+            match &mut **elems {
+                JNativeSlice::Int(vals) => {
+                    vals[1] = 20;
+                    vals.iter().sum::<i32>()
+                }
+                _ => panic!("Expected an int slice")
+            }
+        }).unwrap();
+        assert_eq!(sum, 24);
+        assert_eq!(read(), vec![1, 20, 3]);
+
+        // A commit is kept even if the rest is aborted
+        env.with_native_array_elements(&arr, ReleaseMode::Abort, |elems| {
+            if let JNativeSlice::Int(vals) = &mut **elems {
+                vals[0] = 100;
+            }
+            elems.commit().unwrap();
+            if let JNativeSlice::Int(vals) = &mut **elems {
+                vals[2] = 300;
+            }
+        }).unwrap();
+        assert_eq!(read(), vec![100, 20, 3]);
+    })
+}
+
+#[test]
+#[cfg(feature = "collections")]
+fn test_optional_conversions() {
+    with_env(|env| {
+        let str = env.new_string_utf("present").unwrap().downcast();
+        let opt = env.rust_to_optional(Some(&str)).unwrap();
+        let val = env.optional_to_rust(&opt).unwrap().expect("Optional was empty");
+        assert!(env.is_same_object(&str, &val));
+
+        let empty = env.rust_to_optional(None).unwrap();
+        assert!(env.optional_to_rust(&empty).unwrap().is_none());
+
+        // Anything other than an Optional is refused
+        assert!(env.optional_to_rust(&str).is_err());
+        assert!(env.optional_int_to_rust(&opt).is_err());
+        assert!(!env.exception_check());
+
+        // Primitive specializations, as made by Java's own streams
+        let int_stream = env.find_class("java.util.stream.IntStream").unwrap();
+        let of_id = env.get_static_method_id(&int_stream, "of", "(int) -> java.util.stream.IntStream").unwrap();
+        let first_id = env.get_method_id(&int_stream, "findFirst", "() -> java.util.OptionalInt").unwrap();
+        let stream = env.call_static_method(&int_stream, &of_id, &vec![JValue::Int(1)])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        let first = env.call_method(&stream, &first_id, &vec![])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        assert_eq!(env.optional_int_to_rust(&first).unwrap(), Some(1));
+
+        for &val in &[Some(7), None] {
+            let opt = env.rust_to_optional_int(val).unwrap();
+            assert_eq!(env.optional_int_to_rust(&opt).unwrap(), val);
+        }
+        for &val in &[Some(-7_000_000_000), None] {
+            let opt = env.rust_to_optional_long(val).unwrap();
+            assert_eq!(env.optional_long_to_rust(&opt).unwrap(), val);
+        }
+        for &val in &[Some(2.5), None] {
+            let opt = env.rust_to_optional_double(val).unwrap();
+            assert_eq!(env.optional_double_to_rust(&opt).unwrap(), val);
+        }
+    })
+}
+
+#[test]
+#[cfg(feature = "collections")]
+fn test_list_to_vec() {
+    with_env(|env| {
+        let list_cls = env.find_class("java.util.ArrayList").unwrap();
+        let list_new = env.get_method_id(&list_cls, "<init>", "() -> void").unwrap();
+        let add = env.get_method_id(&list_cls, "add", "(java.lang.Object) -> boolean").unwrap();
+
+        let list = env.new_object(&list_cls, &list_new, &vec![]).unwrap();
+        assert_eq!(env.list_len(&list).unwrap(), 0);
+        assert!(env.list_to_vec(&list).unwrap().is_empty());
+
+        let strs: Vec<JObject> = ["zero", "one", "two"].iter()
+            .map(|s| env.new_string_utf(s).unwrap().downcast())
+            .collect();
+        for str in &strs {
+            env.call_method(&list, &add, &vec![str.into()]).unwrap();
+        }
+        env.call_method(&list, &add, &vec![JValue::Object(None)]).unwrap();
+
+        assert_eq!(env.list_len(&list).unwrap(), 4);
+        let elems = env.list_to_vec(&list).unwrap();
+        assert_eq!(elems.len(), 4);
+        for (elem, str) in elems.iter().zip(&strs) {
+            assert!(env.is_same_object(elem.as_ref().unwrap(), str));
+        }
+        assert!(elems[3].is_none());
+
+        assert!(env.list_len(&strs[0]).is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+#[cfg(feature = "collections")]
+fn test_map_entries() {
+    with_env(|env| {
+        let map_cls = env.find_class("java.util.HashMap").unwrap();
+        let map_new = env.get_method_id(&map_cls, "<init>", "() -> void").unwrap();
+        let put = env.get_method_id(&map_cls, "put", "(java.lang.Object, java.lang.Object) -> java.lang.Object").unwrap();
+
+        let map = env.new_object(&map_cls, &map_new, &vec![]).unwrap();
+        assert!(env.map_entries(&map).unwrap().is_empty());
+
+        let one: JObject = env.new_string_utf("one").unwrap().downcast();
+        let two: JObject = env.new_string_utf("two").unwrap().downcast();
+        let first: JObject = env.new_string_utf("first").unwrap().downcast();
+        env.call_method(&map, &put, &vec![(&one).into(), (&first).into()]).unwrap();
+        env.call_method(&map, &put, &vec![(&two).into(), JValue::Object(None)]).unwrap();
+
+        let entries = env.map_entries(&map).unwrap();
+        assert_eq!(entries.len(), 2);
+        let one_entry = entries.iter()
+            .find(|(key, _)| env.is_same_object(key.as_ref().unwrap(), &one))
+            .unwrap();
+        assert!(env.is_same_object(one_entry.1.as_ref().unwrap(), &first));
+        let two_entry = entries.iter()
+            .find(|(key, _)| env.is_same_object(key.as_ref().unwrap(), &two))
+            .unwrap();
+        assert!(two_entry.1.is_none());
+
+        assert!(env.map_entries(&one).is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+#[cfg(all(unix, feature = "buffers"))]
+fn test_file_descriptor_pipe() {
+    use std::io::Write;
+    use std::os::unix::io::IntoRawFd;
+
+    with_env(|env| {
+        let (reader, mut writer) = std::io::pipe().unwrap();
+        let read_fd = reader.into_raw_fd();
+
+        let fd_obj = env.raw_to_file_descriptor(read_fd).unwrap();
+        assert_eq!(env.file_descriptor_to_raw(&fd_obj).unwrap(), read_fd);
+
+        let stream_cls = env.find_class("java.io.FileInputStream").unwrap();
+        let con_id = env.get_method_id(&stream_cls, "<init>", "(java.io.FileDescriptor) -> void").unwrap();
+        let read_id = env.get_method_id(&stream_cls, "read", "() -> int").unwrap();
+        let close_id = env.get_method_id(&stream_cls, "close", "() -> void").unwrap();
+        let stream = env.new_object(&stream_cls, &con_id, &vec![(&fd_obj).into()]).unwrap();
+
+        writer.write_all(b"jni").unwrap();
+        drop(writer);
+
+        let mut read = Vec::new();
+        loop {
+            let byte = env.call_method(&stream, &read_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+            if byte < 0 {
+                break
+            }
+            read.push(byte as u8);
+        }
+        assert_eq!(read, b"jni");
+
+        // Java owns the descriptor now, so it does the closing
+        env.call_method(&stream, &close_id, &vec![]).unwrap();
+    })
+}
+
+#[test]
+fn test_boolean_field() {
+    with_env(|env| {
+        let bits_cls = env.find_class("java.util.BitSet").unwrap();
+        let con_id = env.get_method_id(&bits_cls, "<init>", "() -> void").unwrap();
+        let bits = env.new_object(&bits_cls, &con_id, &vec![]).unwrap();
+        let sticky = env.get_field_id(&bits_cls, "sizeIsSticky", "boolean").unwrap();
+
+        for &val in &[true, false, true] {
+            env.set_field(&bits, &sticky, JValue::Bool(val)).unwrap();
+            assert_eq!(env.get_field(&bits, &sticky).unwrap().into_bool().unwrap(), val);
+        }
+    })
+}
+
+#[test]
+fn test_field_batch() {
+    with_env(|env| {
+        let bytes = ClassFileBuilder::new("RustJniFields")
+            .field("flag", "Z")
+            .field("letter", "C")
+            .field("count", "I")
+            .field("total", "J")
+            .field("ratio", "D")
+            .field("name", "Ljava/lang/String;")
+            .build();
+        let cls = env.define_class_in_system_loader("RustJniFields", &bytes)
+            .expect("Couldn't define field test class");
+
+        let con_id = env.get_method_id(&cls, "<init>", "() -> void").unwrap();
+        let obj = env.new_object(&cls, &con_id, &vec![]).unwrap();
+
+        let flag = env.get_field_id(&cls, "flag", "boolean").unwrap();
+        let letter = env.get_field_id(&cls, "letter", "char").unwrap();
+        let count = env.get_field_id(&cls, "count", "int").unwrap();
+        let total = env.get_field_id(&cls, "total", "long").unwrap();
+        let ratio = env.get_field_id(&cls, "ratio", "double").unwrap();
+        let name = env.get_field_id(&cls, "name", "java.lang.String").unwrap();
+
+        let str = env.new_string_utf("batched").unwrap().downcast();
+        FieldBatch::new()
+            .set(&flag, true)
+            .set(&letter, 'q')
+            .set(&count, 42)
+            .set(&total, 1i64 << 40)
+            .set(&ratio, 0.5)
+            .set(&name, &str)
+            .apply(env, &obj)
+            .unwrap();
+
+        assert!(env.get_field(&obj, &flag).unwrap().into_bool().unwrap());
+        assert_eq!(env.get_field(&obj, &letter).unwrap().into_char().unwrap(), 'q');
+        assert_eq!(env.get_field(&obj, &count).unwrap().into_int().unwrap(), 42);
+        assert_eq!(env.get_field(&obj, &total).unwrap().into_long().unwrap(), 1 << 40);
+        assert_eq!(env.get_field(&obj, &ratio).unwrap().into_double().unwrap(), 0.5);
+        let name_val = env.get_field(&obj, &name).unwrap().into_obj().unwrap().unwrap();
+        assert!(env.is_same_object(&name_val, &str));
+
+        // The mismatched long is caught before the earlier int write happens
+        let batch = FieldBatch::new()
+            .set(&count, 7)
+            .set(&total, 3);
+        assert_eq!(batch.len(), 2);
+        assert!(batch.apply(env, &obj).is_err());
+        assert_eq!(env.get_field(&obj, &count).unwrap().into_int().unwrap(), 42);
+    })
+}
+
+#[test]
+fn test_check_cancelled() {
+    with_env(|env| {
+        assert!(!env.thread_interrupted().unwrap());
+        env.check_cancelled().unwrap();
+
+        env.interrupt_current().unwrap();
+        assert!(env.thread_interrupted().unwrap());
+        assert!(env.clear_interrupt().unwrap());
+        assert!(!env.thread_interrupted().unwrap());
+
+        // Interrupt the thread from Java partway through a native loop
+        let thread_cls = env.find_class("java.lang.Thread").unwrap();
+        let current_id = env.get_static_method_id(&thread_cls, "currentThread", "() -> java.lang.Thread").unwrap();
+        let interrupt_id = env.get_method_id(&thread_cls, "interrupt", "() -> void").unwrap();
+        let thread = env.call_static_method(&thread_cls, &current_id, &vec![])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+
+        let native_loop = || -> Result<u32> {
+            for i in 0..1000 {
+                env.check_cancelled()?;
+                if i == 100 {
+                    env.call_method(&thread, &interrupt_id, &vec![])?;
+                }
+            }
+            Ok(1000)
+        };
+
+        let err = native_loop().expect_err("Loop wasn't cancelled");
+        assert!(matches!(err, Error::Interrupted));
+        assert!(!env.thread_interrupted().unwrap());
+
+        env.throw_error(&err).unwrap();
+        let exc = env.exception_occurred().unwrap();
+        env.exception_clear().unwrap();
+        let exc_cls = env.find_class("java.lang.InterruptedException").unwrap();
+        assert!(env.is_instance_of(&exc.downcast(), &exc_cls));
+    })
+}
+
+#[test]
+fn test_call_super_method() {
+    with_env(|env| {
+        let int_cls = env.find_class("java.lang.Integer").unwrap();
+        let int = env.static_method(&int_cls, "valueOf", "(int) -> java.lang.Integer").unwrap()
+            .arg(5)
+            .invoke_returning::<JObject>()
+            .unwrap();
+
+        let to_str = |val: Option<JValue>| -> String {
+            let str = val.unwrap().into_obj().unwrap().unwrap();
+            // SAFETY: Guaranteed safe upcast, toString returns a String
+            env.get_string_chars(&unsafe { str.upcast_raw() }).unwrap().into_iter().collect()
+        };
+
+        // Integer overrides toString, but Number doesn't, so the super call reaches Object's
+        let own = env.method(&int, "toString", "() -> java.lang.String").unwrap().invoke().unwrap();
+        assert_eq!(to_str(own), "5");
+        let sup = env.call_super_method(&int, "toString", "() -> java.lang.String", &vec![]).unwrap();
+        assert!(to_str(sup).starts_with("java.lang.Integer@"));
+
+        // Object has no superclass
+        let obj_cls = env.find_class("java.lang.Object").unwrap();
+        let obj = env.alloc_object(&obj_cls).unwrap();
+        assert!(env.call_super_method(&obj, "toString", "() -> java.lang.String", &vec![]).is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_new_record() {
+    with_env(|env| {
+        let stats = env.new_record("java.util.IntSummaryStatistics", &[
+            ("long", JValue::Long(3)),
+            ("int", JValue::Int(1)),
+            ("int", JValue::Int(5)),
+            ("long", JValue::Long(9))
+        ]).unwrap();
+        let sum: i64 = env.method(&stats, "getSum", "() -> long").unwrap().invoke_returning().unwrap();
+        assert_eq!(sum, 9);
+        let max: i32 = env.method(&stats, "getMax", "() -> int").unwrap().invoke_returning().unwrap();
+        assert_eq!(max, 5);
+
+        let key: JObject = env.new_string_utf("key").unwrap().downcast();
+        let entry = env.new_record("java.util.AbstractMap$SimpleImmutableEntry", &[
+            ("java.lang.Object", JValue::from(&key)),
+            ("java.lang.Object", JValue::Object(None))
+        ]).unwrap();
+        let entry_key: JObject = env.method(&entry, "getKey", "() -> java.lang.Object").unwrap().invoke_returning().unwrap();
+        assert!(env.is_same_object(&entry_key, &key));
+
+        // Values must match their component types
+        let err = env.new_record("java.util.IntSummaryStatistics", &[
+            ("long", JValue::Int(3)),
+            ("int", JValue::Int(1)),
+            ("int", JValue::Int(5)),
+            ("long", JValue::Long(9))
+        ]);
+        assert!(err.is_err());
+
+        assert!(env.new_record("java.util.IntSummaryStatistics", &[("double", JValue::Double(1.0))]).is_err());
+        env.exception_clear().unwrap();
+    })
+}
+
+#[test]
+fn test_char_sequence_args() {
+    with_env(|env| {
+        let sb_cls = env.find_class("java.lang.StringBuilder").unwrap();
+        let sb: JObject = env.constructor(&sb_cls, "() -> void").unwrap()
+            .invoke_returning()
+            .unwrap();
+
+        env.method(&sb, "append", "(java.lang.CharSequence) -> java.lang.StringBuilder").unwrap()
+            .arg("hello")
+            .invoke()
+            .unwrap();
+        env.method(&sb, "append", "(java.lang.CharSequence) -> java.lang.StringBuilder").unwrap()
+            .arg(String::from(", world"))
+            .invoke()
+            .unwrap();
+
+        // Strings are passed as modified UTF-8, so an embedded NUL reaches Java intact
+        env.method(&sb, "append", "(java.lang.CharSequence) -> java.lang.StringBuilder").unwrap()
+            .arg("\0")
+            .invoke()
+            .unwrap();
+
+        let str: JObject = env.method(&sb, "toString", "() -> java.lang.String").unwrap()
+            .invoke_returning()
+            .unwrap();
+        // SAFETY: Guaranteed safe upcast, toString returns a String
+        let chars: String = env.get_string_chars(&unsafe { str.upcast_raw() }).unwrap().into_iter().collect();
+        assert_eq!(chars, "hello, world\0");
+    })
+}
+
+#[test]
+#[cfg(feature = "strings-ext")]
+fn test_string_utf16() {
+    with_env(|env| {
+        // A lone high surrogate is a valid Java string, but not a valid rust one
+        let units = [0x0048, 0xD800, 0x0069];
+        let str = env.new_string_utf16(&units).unwrap();
+        assert_eq!(env.get_string_length(&str), 3);
+        assert_eq!(env.get_string_utf16(&str).unwrap(), units);
+
+        let text = "h\u{e9}llo";
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let from_units = env.new_string_utf16(&units).unwrap();
+        let from_chars = env.new_string(&text.chars().collect::<Vec<_>>()).unwrap();
+        assert_eq!(env.get_string_chars(&from_units).unwrap(), env.get_string_chars(&from_chars).unwrap());
+        assert_eq!(env.get_string_utf16(&from_chars).unwrap(), units);
+
+        let empty = env.new_string_utf16(&[]).unwrap();
+        assert!(env.get_string_utf16(&empty).unwrap().is_empty());
+    })
+}
+
+#[test]
+#[cfg(feature = "strings-ext")]
+fn test_string_utf16_validation() {
+    with_env(|env| {
+        let text = "a\u{1F600}b";
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let paired = env.new_string_utf16_checked(&units).unwrap();
+        assert_eq!(env.get_string_strict(&paired).unwrap(), text);
+
+        let err = env.new_string_utf16_checked(&[0x61, 0xD83D, 0x62]).unwrap_err();
+        assert!(err.to_string().contains("Unpaired surrogate 0xD83D at index 1"), "{}", err);
+        let err = env.new_string_utf16_checked(&[0x61, 0x62, 0xDE00]).unwrap_err();
+        assert!(err.to_string().contains("Unpaired surrogate 0xDE00 at index 2"), "{}", err);
+        assert!(env.new_string_utf16_checked(&[0xD83D]).is_err());
+
+        // Without validation the units reach Java unchanged
+        let lone = env.new_string_utf16(&[0x61, 0xD83D, 0x62]).unwrap();
+        assert_eq!(env.get_string_utf16(&lone).unwrap(), vec![0x61, 0xD83D, 0x62]);
+    })
+}
+
+#[test]
+fn test_get_string() {
+    with_env(|env| {
+        for text in &["a\0b", "\u{1F600}", "h\u{e9}llo \u{20AC}\0\u{1D11E}", ""] {
+            let str = env.new_string_utf(text).unwrap();
+            assert_eq!(env.get_string(&str).unwrap(), *text);
+            let chars: String = env.get_string_chars(&str).unwrap().into_iter().collect();
+            assert_eq!(chars, *text);
+        }
+
+        // The raw bytes are modified UTF-8, not standard UTF-8
+        let nul = env.new_string_utf("\0").unwrap();
+        assert_eq!(env.get_string_utf_chars(&nul).unwrap(), vec![0xC0, 0x80]);
+        let emoji = env.new_string_utf("\u{1F600}").unwrap();
+        assert_eq!(env.get_string_utf_chars(&emoji).unwrap(), vec![0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+
+        assert!(crate::mutf8::units_from_mutf8(&[0x61, 0x00]).is_err());
+        assert!(crate::mutf8::units_from_mutf8(&[0xF0, 0x9F, 0x98, 0x80]).is_err());
+        assert!(crate::mutf8::units_from_mutf8(&[0x61, 0xE2, 0x82]).is_err());
+
+        // Overlong forms are rejected, except the two byte NUL
+        assert_eq!(crate::mutf8::units_from_mutf8(&[0xC0, 0x80]).unwrap(), vec![0]);
+        assert!(crate::mutf8::units_from_mutf8(&[0xC1, 0x81]).is_err());
+        assert!(crate::mutf8::units_from_mutf8(&[0xC0, 0x81]).is_err());
+        assert!(crate::mutf8::units_from_mutf8(&[0xE0, 0x80, 0x80]).is_err());
+        assert!(crate::mutf8::units_from_mutf8(&[0xE0, 0x81, 0x81]).is_err());
+        assert!(crate::mutf8::units_from_mutf8(&[0xE0, 0x9F, 0xBF]).is_err());
+        assert_eq!(crate::mutf8::units_from_mutf8(&[0xE0, 0xA0, 0x80]).unwrap(), vec![0x800]);
+    })
+}
+
+#[test]
+#[cfg(feature = "strings-ext")]
+fn test_char_boundaries() {
+    with_env(|env| {
+        let text = "a\u{1F600}b\u{20AC}\u{1D11E}";
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let str = env.new_string_utf16(&units).unwrap();
+        let boundaries = env.char_boundaries(&str).unwrap();
+        assert_eq!(boundaries, vec![0, 1, 3, 4, 5]);
+        assert_eq!(boundaries.len(), text.chars().count());
+        assert_eq!(env.get_string_length(&str), 7);
+
+        // Region reads starting at a boundary give back the rust chars from that position
+        // SAFETY: Internal pointer use, the region read takes a second handle to the same string
+        let alias = JString::new(unsafe { str.borrow_ptr() }).unwrap();
+        let region = env.get_string_region(alias, boundaries[3], 3).unwrap();
+        assert_eq!(region, vec!['\u{20AC}', '\u{1D11E}']);
+
+        // An out of range region is the thrown exception, cleared so the JNI can be used again
+        // SAFETY: Internal pointer use, as above
+        let alias = JString::new(unsafe { str.borrow_ptr() }).unwrap();
+        match env.get_string_region(alias, boundaries[3], 100) {
+            Err(Error::Exception(name, _)) => assert_eq!(name, "java.lang.StringIndexOutOfBoundsException"),
+            other => panic!("Expected the thrown exception, got {:?}", other)
+        }
+        assert!(!env.exception_check());
+
+        let lone = env.new_string_utf16(&[0x61, 0xD800, 0x62, 0xDC00]).unwrap();
+        assert_eq!(env.char_boundaries(&lone).unwrap(), vec![0, 1, 2, 3]);
+
+        let empty = env.new_string_utf("").unwrap();
+        assert!(env.char_boundaries(&empty).unwrap().is_empty());
+    })
+}
+
+#[test]
+#[cfg(feature = "strings-ext")]
+fn test_unpaired_surrogate_policy() {
+    with_env(|env| {
+        let str = env.new_string_utf16(&[0x0048, 0xD800, 0x0069]).unwrap();
+        assert_eq!(env.get_string_lossy(&str).unwrap(), "H\u{FFFD}i");
+        let err = env.get_string_strict(&str).unwrap_err();
+        assert!(err.to_string().contains("Unpaired surrogate 0xD800 at index 1"), "{}", err);
+        assert!(env.get_string_chars(&str).is_err());
+
+        // Surrogate pairs decode to a single char under both policies
+        let text = "a\u{1F600}b";
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let paired = env.new_string_utf16(&units).unwrap();
+        assert_eq!(env.get_string_strict(&paired).unwrap(), text);
+        assert_eq!(env.get_string_lossy(&paired).unwrap(), text);
+        assert_eq!(env.get_string_chars(&paired).unwrap(), text.chars().collect::<Vec<_>>());
+
+        // A lone surrogate char returned from Java is an Err, not a panic
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let char_at = env.get_method_id(&str_cls, "charAt", "(int) -> char").unwrap();
+        assert!(env.call_method((&str).downcast(), &char_at, &vec![JValue::Int(1)]).is_err());
+        assert_eq!(env.call_method((&str).downcast(), &char_at, &vec![JValue::Int(2)]).unwrap().unwrap().into_char().unwrap(), 'i');
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+#[cfg(any(debug_assertions, feature = "runtime-checks"))]
+fn test_method_id_not_class() {
+    with_env(|env| {
+        let str = env.new_string_utf("not a class").unwrap();
+        // SAFETY: Deliberately wrong upcast, to check it's caught
+        let not_cls: JClass = unsafe { str.downcast().upcast_raw() };
+
+        let err = env.get_method_id(&not_cls, "length", "() -> int").expect_err("Instance accepted as class");
+        assert!(err.to_string().contains("get_method_id expected a java.lang.Class, but was passed an instance of java.lang.String"), "{}", err);
+        assert!(env.get_static_method_id(&not_cls, "valueOf", "(int) -> java.lang.String").is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+#[cfg(feature = "reflect")]
+fn test_class_token() {
+    with_env(|env| {
+        let int_token = env.class_token("int").unwrap().into_obj().unwrap().unwrap();
+        let integer_token = env.class_token("java.lang.Integer").unwrap().into_obj().unwrap().unwrap();
+        assert!(!env.is_same_object(&int_token, &integer_token));
+
+        let wrapper = env.primitive_wrapper_class(JNativeType::Int).unwrap();
+        assert!(env.is_same_object(&integer_token, &wrapper.downcast()));
+
+        // SAFETY: Guaranteed safe upcast, class_token gives a Class
+        let int_cls: JClass = unsafe { int_token.upcast_raw() };
+        let cls_cls = env.find_class("java.lang.Class").unwrap();
+        let is_primitive = env.get_method_id(&cls_cls, "isPrimitive", "() -> boolean").unwrap();
+        assert!(env.call_method(&(&int_cls).downcast(), &is_primitive, &vec![]).unwrap().unwrap().into_bool().unwrap());
+
+        let enum_set_cls = env.find_class("java.util.EnumSet").unwrap();
+        let all_of = env.get_static_method_id(&enum_set_cls, "allOf", "(java.lang.Class) -> java.util.EnumSet").unwrap();
+        let days = env.call_static_method(&enum_set_cls, &all_of, &vec![env.class_token("java.time.DayOfWeek").unwrap()])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        let size: i32 = env.method(&days, "size", "() -> int").unwrap().invoke_returning().unwrap();
+        assert_eq!(size, 7);
+
+        assert!(env.class_token("not.a.RealClass").is_err());
+        env.exception_clear().unwrap();
+    })
+}
+
+/// The minimal profile, built with `--no-default-features`, turns every convenience layer off.
+/// This only exists in that build, checking the core call and field APIs stand on their own
+#[test]
+#[cfg(not(any(
+    feature = "arrays-ext",
+    feature = "strings-ext",
+    feature = "collections",
+    feature = "reflect",
+    feature = "buffers"
+)))]
+fn test_minimal_core() {
+    with_env(|env| {
+        let sb_cls = env.find_class("java.lang.StringBuilder").unwrap();
+        let con_id = env.get_method_id(&sb_cls, "<init>", "(java.lang.String) -> void").unwrap();
+        let str = env.new_string_utf("core").unwrap();
+        let sb = env.new_object(&sb_cls, &con_id, &vec![str.downcast().into()]).unwrap();
+
+        let len: i32 = env.method(&sb, "length", "() -> int").unwrap().invoke_returning().unwrap();
+        assert_eq!(len, 4);
+
+        let arr = env.new_native_array(3, JNativeType::Int).unwrap();
+        assert_eq!(env.get_array_length(arr.as_jarray()), 3);
+    })
+}
+
+#[test]
+fn test_nonstandard_boolean() {
+    use crate::ffi::constants::JNI_TRUE;
+
+    with_env(|env| {
+        // Java booleans are only required to be non-zero for true, so 2 must read as true
+        let arr = env.new_native_array(3, JNativeType::Boolean).unwrap();
+        let mut slice = env.get_native_array_elements(&arr).unwrap();
+        if let JNativeSlice::Boolean(vals) = &mut slice {
+            vals.copy_from_slice(&[2, 0, 1]);
+        }
+        env.release_native_array_elements(&arr, slice, ReleaseMode::CopyFree).unwrap();
+
+        match env.get_native_array_region(&arr, 0, 3).unwrap() {
+            JNativeVec::Boolean(vals) => assert_eq!(vals, vec![true, false, true]),
+            _ => panic!("Expected a boolean vec")
+        }
+
+        env.set_native_array_region(&arr, 0, 2, &JNativeVec::Boolean(vec![false, true])).unwrap();
+        let slice = env.get_native_array_elements(&arr).unwrap();
+        if let JNativeSlice::Boolean(vals) = &slice {
+            assert_eq!(vals, &[JNI_FALSE, JNI_TRUE, 1]);
+        }
+        env.release_native_array_elements(&arr, slice, ReleaseMode::Abort).unwrap();
+    })
+}
+
+#[test]
+fn test_read_region_into() {
+    use crate::ffi::constants::JNI_TRUE;
+
+    with_env(|env| {
+        const LEN: usize = 64;
+        let arr = env.new_native_array(LEN, JNativeType::Int).unwrap();
+        let vals: Vec<i32> = (0..LEN as i32).map(|val| val * val - 100).collect();
+        env.set_native_array_region(&arr, 0, LEN, &JNativeVec::Int(vals.clone())).unwrap();
+        let int_arr = match &arr {
+            JNativeArray::Int(arr) => arr,
+            _ => unreachable!()
+        };
+
+        // The same buffer is reused for every region, and matches the allocating path
+        let mut buf = [0; 16];
+        for start in (0..=LEN - buf.len()).step_by(4) {
+            env.read_int_region_into(int_arr, start, &mut buf).unwrap();
+            match env.get_native_array_region(&arr, start, buf.len()).unwrap() {
+                JNativeVec::Int(region) => assert_eq!(region, buf),
+                _ => panic!("Expected an int vec")
+            }
+            assert_eq!(&buf[..], &vals[start..start + buf.len()]);
+        }
+
+        // Out of bounds regions are refused without reaching the JVM
+        let err = env.read_int_region_into(int_arr, LEN - 8, &mut buf).unwrap_err();
+        assert_eq!(err.to_string(), "Error in JVM: message \"Array region 56..72 out of bounds for length 64\", code -1");
+        assert!(env.read_int_region_into(int_arr, usize::MAX, &mut buf).is_err());
+        assert!(env.get_native_array_region(&arr, LEN, 1).is_err());
+        assert!(!env.exception_check());
+        // An empty region at the end is fine
+        env.read_int_region_into(int_arr, LEN, &mut []).unwrap();
+
+        let arr = env.new_native_array(3, JNativeType::Boolean).unwrap();
+        env.set_native_array_region(&arr, 0, 3, &JNativeVec::Boolean(vec![true, false, true])).unwrap();
+        let mut flags = [0; 2];
+        if let JNativeArray::Boolean(bool_arr) = &arr {
+            env.read_boolean_region_into(bool_arr, 1, &mut flags).unwrap();
+        }
+        assert_eq!(flags, [JNI_FALSE, JNI_TRUE]);
+
+        let arr = env.new_native_array(4, JNativeType::Double).unwrap();
+        env.set_native_array_region(&arr, 0, 4, &JNativeVec::Double(vec![0.5, 1.5, 2.5, 3.5])).unwrap();
+        let mut nums = [0.0; 3];
+        if let JNativeArray::Double(double_arr) = &arr {
+            env.read_double_region_into(double_arr, 1, &mut nums).unwrap();
+        }
+        assert_eq!(nums, [1.5, 2.5, 3.5]);
+    })
+}
+
+#[test]
+fn test_register_natives_checked() {
+    use crate::ffi::JInt;
+
+    extern "system" fn first(_env: *mut ffi::JNIEnv, _cls: *mut ffi::JClass) -> JInt {
+        1
+    }
+
+    extern "system" fn second(_env: *mut ffi::JNIEnv, _cls: *mut ffi::JClass) -> JInt {
+        2
+    }
+
+    with_env(|env| {
+        let bytes = ClassFileBuilder::new("RustJniDupNatives")
+            .native_method("value", "()I", true)
+            .native_method("other", "()I", true)
+            .build();
+        let cls = env.define_class_in_system_loader("RustJniDupNatives", &bytes).unwrap();
+        let value = env.get_static_method_id(&cls, "value", "() -> int").unwrap();
+
+        let methods = [
+            JNINativeMethod::new::<JInt>("value", "()I", first as *mut _),
+            JNINativeMethod::new::<JInt>("other", "()I", first as *mut _),
+            JNINativeMethod::new::<JInt>("value", "()I", second as *mut _)
+        ];
+        let err = env.register_natives_checked(&cls, &methods).unwrap_err();
+        assert_eq!(err.to_string(), "Error in JVM: message \"Native methods registered more than once: value()I\", code -1");
+
+        // Nothing was registered, so calls still fail to link
+        assert!(env.call_static_method(&cls, &value, &vec![]).is_err());
+        assert!(!env.exception_check());
+
+        env.register_natives_checked(&cls, &methods[..2]).unwrap();
+        let val = env.call_static_method(&cls, &value, &vec![]).unwrap().unwrap();
+        assert_eq!(val.into_int().unwrap(), 1);
+    })
+}
+
+#[test]
+fn test_new_array_zeroed() {
+    with_env(|env| {
+        const LEN: usize = 64;
+        let types = [
+            JNativeType::Boolean, JNativeType::Byte, JNativeType::Char, JNativeType::Short,
+            JNativeType::Int, JNativeType::Long, JNativeType::Float, JNativeType::Double
+        ];
+
+        for &ty in &types {
+            let arr = env.new_native_array(LEN, ty).unwrap();
+            let slice = env.get_native_array_elements(&arr).unwrap();
+            let zeroed = match &slice {
+                JNativeSlice::Boolean(vals) => vals.len() == LEN && vals.iter().all(|&v| v == 0),
+                JNativeSlice::Byte(vals) => vals.len() == LEN && vals.iter().all(|&v| v == 0),
+                JNativeSlice::Char(vals) => vals.len() == LEN && vals.iter().all(|&v| v == 0),
+                JNativeSlice::Short(vals) => vals.len() == LEN && vals.iter().all(|&v| v == 0),
+                JNativeSlice::Int(vals) => vals.len() == LEN && vals.iter().all(|&v| v == 0),
+                JNativeSlice::Long(vals) => vals.len() == LEN && vals.iter().all(|&v| v == 0),
+                JNativeSlice::Float(vals) => vals.len() == LEN && vals.iter().all(|&v| v.to_bits() == 0),
+                JNativeSlice::Double(vals) => vals.len() == LEN && vals.iter().all(|&v| v.to_bits() == 0)
+            };
+            env.release_native_array_elements(&arr, slice, ReleaseMode::Abort).unwrap();
+            assert!(zeroed, "New {:?} array wasn't zeroed", ty);
+        }
+
+        let obj_cls = env.find_class("java.lang.Object").unwrap();
+        let obj_arr = env.new_object_array(LEN, &obj_cls, None).unwrap();
+        // Null elements are reported as an Err, with no exception thrown
+        for idx in 0..LEN {
+            assert!(env.get_object_array_element(&obj_arr, idx).is_err(), "Element {} wasn't null", idx);
+            assert!(!env.exception_check());
+        }
+    })
+}
+
+#[test]
+fn test_cached_call() {
+    with_env(|env| {
+        let int_cls = env.find_class("java.lang.Integer").unwrap();
+        let value_of = env.get_static_method_id(&int_cls, "valueOf", "(int) -> java.lang.Integer").unwrap();
+
+        let mut cache = CachedCall::new(value_of, |args: &[JValue]| match args[0] {
+            JValue::Int(n) => n,
+            _ => unreachable!()
+        });
+        assert!(cache.is_empty());
+
+        // 1000 is outside Integer's own cache, so Java gives a new object for every call
+        let first = cache.call_static(env, &int_cls, &[JValue::Int(1000)])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        let second = cache.call_static(env, &int_cls, &[JValue::Int(1000)])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        assert!(env.is_same_object(&first, &second));
+        assert_eq!(cache.len(), 1);
+
+        cache.call_static(env, &int_cls, &[JValue::Int(2000)]).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        cache.clear(env);
+        assert!(cache.is_empty());
+    })
+}
+
+#[test]
+fn test_object_return_null_or_throw() {
+    with_env(|env| {
+        let map_cls = env.find_class("java.util.HashMap").unwrap();
+        let map_con = env.get_method_id(&map_cls, "<init>", "() -> void").unwrap();
+        let map = env.new_object(&map_cls, &map_con, &vec![]).unwrap();
+        let get_id = env.get_method_id(&map_cls, "get", "(java.lang.Object) -> java.lang.Object").unwrap();
+        let key = env.new_string_utf("missing").unwrap();
+
+        let sys_cls = env.find_class("java.lang.System").unwrap();
+        let get_prop = env.get_static_method_id(&sys_cls, "getProperty", "(java.lang.String) -> java.lang.String").unwrap();
+
+        // Legitimate null returns
+        let result = env.call_method(&map, &get_id, &vec![(&key).downcast().into()]);
+        assert!(matches!(result, Ok(Some(JValue::Object(None)))));
+        let result = env.call_nonvirtual_method(&map, &map_cls, &get_id, &vec![(&key).downcast().into()]);
+        assert!(matches!(result, Ok(Some(JValue::Object(None)))));
+        let result = env.call_static_method(&sys_cls, &get_prop, &vec![(&key).downcast().into()]);
+        assert!(matches!(result, Ok(Some(JValue::Object(None)))));
+        assert!(!env.exception_check());
+
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let substr_id = env.get_method_id(&str_cls, "substring", "(int) -> java.lang.String").unwrap();
+        let int_cls = env.find_class("java.lang.Integer").unwrap();
+        let value_of = env.get_static_method_id(&int_cls, "valueOf", "(java.lang.String) -> java.lang.Integer").unwrap();
+
+        // Throwing methods, whose exception is captured in the error and cleared
+        let check_thrown = |result: Result<Option<JValue>>, exc_name: &str| {
+            match result {
+                Err(Error::Exception(name, _)) => assert_eq!(name, exc_name),
+                other => panic!("Expected {}, got {:?}", exc_name, other.map(|_| ()))
+            }
+            assert!(!env.exception_check());
+        };
+
+        let bounds = "java.lang.StringIndexOutOfBoundsException";
+        check_thrown(env.call_method((&key).downcast(), &substr_id, &vec![JValue::Int(100)]), bounds);
+        check_thrown(env.call_nonvirtual_method((&key).downcast(), &str_cls, &substr_id, &vec![JValue::Int(100)]), bounds);
+        check_thrown(env.call_static_method(&int_cls, &value_of, &vec![(&key).downcast().into()]), "java.lang.NumberFormatException");
+    })
+}
+
+#[test]
+fn test_propagate_if_pending() {
+    fn parse(env: &JNIEnv, str: &str) -> Result<i32> {
+        let int_cls = env.find_class("java.lang.Integer")?;
+        let parse_id = env.get_static_method_id(&int_cls, "parseInt", "(java.lang.String) -> int")?;
+        let str = env.new_string_utf(str)?;
+
+        let result = env.call_static_method(&int_cls, &parse_id, &vec![str.downcast().into()]);
+        env.propagate_if_pending()?;
+        result?.expect("Unexpected void result").into_int()
+    }
+
+    with_env(|env| {
+        assert!(env.propagate_if_pending().is_ok());
+        assert_eq!(parse(env, "42").unwrap(), 42);
+
+        match parse(env, "forty-two") {
+            Err(Error::Exception(name, msg)) => {
+                assert_eq!(name, "java.lang.NumberFormatException");
+                assert_eq!(msg.as_deref(), Some("For input string: \"forty-two\""));
+            }
+            other => panic!("Expected a captured exception, got {:?}", other)
+        }
+        assert!(!env.exception_check());
+
+        // Captured exceptions can be thrown again as themselves
+        let err = Error::Exception(String::from("java.lang.IllegalStateException"), None);
+        env.throw_error(&err).unwrap();
+        let exc = env.exception_occurred().unwrap();
+        let state_cls = env.find_class("java.lang.IllegalStateException").unwrap();
+        assert!(env.is_instance_of(&exc.downcast(), &state_cls));
+        env.exception_clear().unwrap();
+    })
+}
+
+#[test]
+fn test_raw_object_interop() {
+    with_env(|env| {
+        let str: JObject = env.new_string_utf("raw").unwrap().downcast();
+        // SAFETY: The pointer is only compared against
+        let expected = unsafe { str.borrow_ptr() };
+        let ptr = str.into_raw();
+        assert_eq!(ptr as *mut ffi::JObject, expected);
+
+        let obj = JObject::from_raw(ptr).unwrap();
+        let class = env.get_object_class(&obj).unwrap();
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        assert!(env.is_same_object(&class.downcast(), &str_cls.downcast()));
+        assert!(JObject::from_raw(std::ptr::null_mut()).is_err());
+    })
+}
+
+#[test]
+fn test_arg_count_mismatch() {
+    with_env(|env| {
+        let args = [JValue::Int(1), JValue::Int(2), JValue::Int(3)];
+
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let substr_id = env.get_method_id(&str_cls, "substring", "(int, int) -> java.lang.String").unwrap();
+        let str = env.new_string_utf("mismatch").unwrap();
+        let err = env.call_method((&str).downcast(), &substr_id, &args).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error in JVM: message \"call_method expected 2 arguments for a method returning object, but was passed 3\", code -1"
+        );
+        let err = env.call_nonvirtual_method((&str).downcast(), &str_cls, &substr_id, &args).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error in JVM: message \"call_nonvirtual_method expected 2 arguments for a method returning object, but was passed 3\", code -1"
+        );
+
+        let math_cls = env.find_class("java.lang.Math").unwrap();
+        let max_id = env.get_static_method_id(&math_cls, "max", "(int, int) -> int").unwrap();
+        let err = env.call_static_method(&math_cls, &max_id, &args).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error in JVM: message \"call_static_method expected 2 arguments for a method returning int, but was passed 3\", code -1"
+        );
+
+        let map_cls = env.find_class("java.util.HashMap").unwrap();
+        let con_id = env.get_method_id(&map_cls, "<init>", "(int, float) -> void").unwrap();
+        let err = env.new_object(&map_cls, &con_id, &args).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error in JVM: message \"new_object expected 2 arguments for a method returning void, but was passed 3\", code -1"
+        );
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_define_class_with() {
+    with_env(|env| {
+        let cls_ldr = env.system_class_loader().unwrap();
+
+        let check_magic = |bytes: &[u8]| {
+            if bytes.starts_with(b"\xca\xfe\xba\xbe") {
+                Ok(())
+            } else {
+                Err(Error::new("Not a class file", -1))
+            }
+        };
+
+        let mut bytes = ClassFileBuilder::new("RustJniVerified").build();
+        bytes[0] = 0;
+        let err = env.define_class_with("RustJniVerified", &cls_ldr, &bytes, check_magic).unwrap_err();
+        assert_eq!(err.to_string(), "Error in JVM: message \"Not a class file\", code -1");
+        // The JVM never saw the class
+        assert!(!env.exception_check());
+
+        bytes[0] = 0xca;
+        let cls = env.define_class_with("RustJniVerified", &cls_ldr, &bytes, check_magic).unwrap();
+        env.get_method_id(&cls, "<init>", "() -> void").unwrap();
+    })
+}
+
+#[test]
+fn test_define_class_errors() {
+    with_env(|env| {
+        let cls_ldr = env.system_class_loader().unwrap();
+
+        let sys_cls = env.find_class("java.lang.System").unwrap();
+        let get_prop = env.get_static_method_id(&sys_cls, "getProperty", "(java.lang.String) -> java.lang.String").unwrap();
+        let key = env.new_string_utf("java.class.version").unwrap();
+        let supported = env.call_static_method(&sys_cls, &get_prop, &vec![(&key).downcast().into()])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        // SAFETY: Guaranteed safe upcast, getProperty returns a String
+        let supported: String = env.get_string_chars(&unsafe { supported.upcast_raw() }).unwrap().into_iter().collect();
+        let supported: u16 = supported.split('.').next().unwrap().parse().unwrap();
+
+        // A class from a newer JDK is refused before reaching the JVM
+        let mut bytes = ClassFileBuilder::new("RustJniTooNew").build();
+        bytes[6..8].copy_from_slice(&(supported + 4).to_be_bytes());
+        let err = env.define_class("RustJniTooNew", &cls_ldr, &bytes).unwrap_err().to_string();
+        let expected = format!("Class RustJniTooNew has class file version {}, but the JVM (Java ", supported + 4);
+        assert!(err.contains(&expected), "{}", err);
+        assert!(err.contains(&format!("only supports up to version {}", supported)), "{}", err);
+        assert!(!env.exception_check());
+
+        // Errors from the JVM itself carry the thrown error
+        let bytes = ClassFileBuilder::new("RustJniTruncated").build();
+        match env.define_class("RustJniTruncated", &cls_ldr, &bytes[..bytes.len() - 4]) {
+            Err(Error::Exception(name, _)) => assert_eq!(name, "java.lang.ClassFormatError"),
+            other => panic!("Expected ClassFormatError, got {:?}", other.map(|_| ()))
+        }
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_classgen_constants() {
+    with_env(|env| {
+        let bytes = ClassFileBuilder::new("rustjni.gen.RustJniConstants")
+            .static_field("counter", "I")
+            .constant("ENABLED", ClassConstant::Boolean(true))
+            .constant("SMALL", ClassConstant::Short(-7))
+            .constant("BIG", ClassConstant::Long(1 << 50))
+            .constant("HALF", ClassConstant::Float(0.5))
+            .constant("PI", ClassConstant::Double(std::f64::consts::PI))
+            .constant("GREETING", ClassConstant::String(String::from("h\u{e9}llo\0")))
+            .build();
+        let cls = env.define_class_in_system_loader("rustjni/gen/RustJniConstants", &bytes)
+            .expect("Couldn't define constants test class");
+
+        let get = |name: &str, sig: &str| {
+            let id = env.get_static_field_id(&cls, name, sig).unwrap();
+            env.get_static_field(&cls, &id).unwrap()
+        };
+        assert_eq!(get("counter", "int").into_int().unwrap(), 0);
+        assert!(get("ENABLED", "boolean").into_bool().unwrap());
+        assert_eq!(get("SMALL", "short").into_short().unwrap(), -7);
+        assert_eq!(get("BIG", "long").into_long().unwrap(), 1 << 50);
+        assert_eq!(get("HALF", "float").into_float().unwrap(), 0.5);
+        assert_eq!(get("PI", "double").into_double().unwrap(), std::f64::consts::PI);
+
+        let greeting = get("GREETING", "java.lang.String").into_obj().unwrap().unwrap();
+        // SAFETY: Guaranteed safe upcast, the field is a String
+        let greeting: String = unsafe { env.get_string_chars(&greeting.upcast_raw()) }.unwrap().into_iter().collect();
+        assert_eq!(greeting, "h\u{e9}llo\0");
+
+        // Without a constructor, instances can still be allocated
+        let bytes = ClassFileBuilder::new("RustJniNoCtor").constructor(false).field("x", "I").build();
+        let cls = env.define_class_in_system_loader("RustJniNoCtor", &bytes).unwrap();
+        assert!(env.get_method_id(&cls, "<init>", "() -> void").is_err());
+        env.exception_clear().unwrap();
+        let obj = env.alloc_object(&cls).unwrap();
+        let x = env.get_field_id(&cls, "x", "int").unwrap();
+        assert_eq!(env.get_field(&obj, &x).unwrap().into_int().unwrap(), 0);
+    })
+}
+
+#[test]
+#[cfg(feature = "arrays-ext")]
+fn test_byte_array_critical() {
+    with_env(|env| {
+        const LEN: usize = 1 << 20;
+        let arr = env.new_native_array(LEN, JNativeType::Byte).unwrap();
+        let values: Vec<i8> = (0..LEN).map(|idx| (idx % 251) as i8).collect();
+        env.set_native_array_region(&arr, 0, LEN, &JNativeVec::Byte(values)).unwrap();
+        let arr = match arr {
+            JNativeArray::Byte(arr) => arr,
+            _ => panic!("Expected a byte array")
+        };
+
+        let expected: u64 = (0..LEN).map(|idx| (idx % 251) as i8 as u8 as u64).sum();
+        let (len, sum) = env.with_byte_array_critical(&arr, |bytes| {
+            (bytes.len(), bytes.iter().map(|&b| b as u64).sum::<u64>())
+        }).unwrap();
+        assert_eq!(len, LEN);
+        assert_eq!(sum, expected);
+
+        // The elements were released, so the array can be borrowed again
+        let first = env.with_byte_array_critical(&arr, |bytes| bytes[..4].to_vec()).unwrap();
+        assert_eq!(first, vec![0, 1, 2, 3]);
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_field_acquire_release() {
+    crate::tests::with_vm(|vm| {
+        let vm: &JavaVM = vm;
+        let env = vm.attach_current_thread().expect("Couldn't attach test thread");
+
+        // AtomicInteger keeps its value in a private volatile field
+        let atomic_cls = env.find_class("java.util.concurrent.atomic.AtomicInteger").unwrap();
+        let con_id = env.get_method_id(&atomic_cls, "<init>", "(int) -> void").unwrap();
+        let get_id = env.get_method_id(&atomic_cls, "get", "() -> int").unwrap();
+        let inc_id = env.get_method_id(&atomic_cls, "incrementAndGet", "() -> int").unwrap();
+        let value_id = env.get_field_id(&atomic_cls, "value", "int").unwrap();
+
+        let atomic = env.new_object(&atomic_cls, &con_id, &vec![JValue::Int(5)]).unwrap();
+        assert_eq!(env.get_field_acquire(&atomic, &value_id).unwrap().into_int().unwrap(), 5);
+        env.set_field_release(&atomic, &value_id, JValue::Int(7)).unwrap();
+        assert_eq!(env.call_method(&atomic, &get_id, &vec![]).unwrap().unwrap().into_int().unwrap(), 7);
+        env.call_method(&atomic, &inc_id, &vec![]).unwrap();
+        assert_eq!(env.get_field_acquire(&atomic, &value_id).unwrap().into_int().unwrap(), 8);
+
+        // A value published with release ordering is seen by an acquiring reader on another thread,
+        // along with what was written before it
+        let bytes = ClassFileBuilder::new("RustJniVolatile")
+            .field("payload", "I")
+            .field("ready", "Z")
+            .build();
+        let cls = env.define_class_in_system_loader("RustJniVolatile", &bytes).unwrap();
+        let obj = env.alloc_object(&cls).unwrap();
+        let shared = env.new_global_ref(&obj).unwrap();
+
+        std::thread::scope(|scope| {
+            let reader = scope.spawn(|| {
+                let guard = vm.attach_current_thread_guarded().expect("Couldn't attach thread");
+                let obj = shared.as_obj();
+                let cls = guard.get_object_class(obj).unwrap();
+                let payload_id = guard.get_field_id(&cls, "payload", "int").unwrap();
+                let ready_id = guard.get_field_id(&cls, "ready", "boolean").unwrap();
+                while !guard.get_field_acquire(obj, &ready_id).unwrap().into_bool().unwrap() {
+                    std::thread::yield_now();
+                }
+                guard.get_field(obj, &payload_id).unwrap().into_int().unwrap()
+            });
+
+            let payload_id = env.get_field_id(&cls, "payload", "int").unwrap();
+            let ready_id = env.get_field_id(&cls, "ready", "boolean").unwrap();
+            env.set_field(&obj, &payload_id, JValue::Int(42)).unwrap();
+            env.set_field_release(&obj, &ready_id, JValue::Bool(true)).unwrap();
+            assert_eq!(reader.join().unwrap(), 42);
+        });
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_synchronized() {
+    with_env(|env| {
+        let thread_cls = env.find_class("java.lang.Thread").unwrap();
+        let holds_id = env.get_static_method_id(&thread_cls, "holdsLock", "(java.lang.Object) -> boolean").unwrap();
+        let holds_lock = |obj: &JObject| {
+            env.call_static_method(&thread_cls, &holds_id, &vec![obj.into()]).unwrap().unwrap().into_bool().unwrap()
+        };
+
+        let outer: JObject = env.new_string_utf("outer").unwrap().downcast();
+        let inner: JObject = env.new_string_utf("inner").unwrap().downcast();
+
+        let result = env.synchronized(&outer, || {
+            assert!(holds_lock(&outer));
+            assert!(!holds_lock(&inner));
+            env.synchronized(&inner, || {
+                assert!(holds_lock(&outer));
+                assert!(holds_lock(&inner));
+                5
+            }).unwrap() + 1
+        }).unwrap();
+        assert_eq!(result, 6);
+        assert!(!holds_lock(&outer));
+        assert!(!holds_lock(&inner));
+
+        // The monitor is still exited if the closure panics
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            env.synchronized(&outer, || panic!("Panic while synchronized")).unwrap()
+        }));
+        assert!(panicked.is_err());
+        assert!(!holds_lock(&outer));
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_monitor_wait_notify() {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    crate::tests::with_vm(|vm| {
+        let vm: &JavaVM = vm;
+        let env = vm.attach_current_thread().expect("Couldn't attach test thread");
+        let obj_cls = env.find_class("java.lang.Object").unwrap();
+        let con_id = env.get_method_id(&obj_cls, "<init>", "() -> void").unwrap();
+        let local = env.new_object(&obj_cls, &con_id, &vec![]).unwrap();
+        let global = env.new_global_ref(&local).unwrap();
+        let lock = global.as_obj();
+
+        // Waiting with a timeout and no notifier returns once the timeout passes
+        {
+            let guard = env.lock_monitor(lock).unwrap();
+            guard.wait(Some(1)).unwrap();
+            guard.notify_all().unwrap();
+        }
+
+        // Global references are valid on any thread, but pointers aren't Send
+        // SAFETY: Pointer is only passed to another thread, which rebuilds the global reference
+        let lock_ptr = unsafe { lock.borrow_ptr() } as usize;
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (done_tx, done_rx) = mpsc::channel();
+
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                let guard = vm.attach_current_thread_guarded().expect("Couldn't attach thread");
+                let lock = JObject::new(lock_ptr as *mut _).unwrap();
+                let monitor = guard.lock_monitor(&lock).unwrap();
+                // Signalled while holding the monitor, so the notify can't happen before the wait
+                ready_tx.send(()).unwrap();
+                monitor.wait(Some(10_000)).unwrap();
+                monitor.exit().unwrap();
+                done_tx.send(()).unwrap();
+            });
+
+            ready_rx.recv_timeout(Duration::from_secs(10)).expect("Waiting thread never started");
+            let monitor = env.lock_monitor(lock).unwrap();
+            monitor.notify().unwrap();
+            drop(monitor);
+            done_rx.recv_timeout(Duration::from_secs(5)).expect("Waiting thread was never notified");
+        });
+
+        // Interrupts while waiting become Error::Interrupted
+        {
+            let guard = env.lock_monitor(lock).unwrap();
+            env.interrupt_current().unwrap();
+            let err = guard.wait(None).unwrap_err();
+            assert!(matches!(err, Error::Interrupted));
+            assert!(!env.exception_check());
+        }
+    })
+}
+
+#[test]
+fn test_upgrade_weak() {
+    with_env(|env| {
+        let obj_cls = env.find_class("java.lang.Object").unwrap();
+        let con_id = env.get_method_id(&obj_cls, "<init>", "() -> void").unwrap();
+        let sys_cls = env.find_class("java.lang.System").unwrap();
+        let gc_id = env.get_static_method_id(&sys_cls, "gc", "() -> void").unwrap();
+
+        let obj = env.new_object(&obj_cls, &con_id, &vec![]).unwrap();
+        let weak = env.new_weak_global_ref(&obj).unwrap();
+        assert!(!weak.is_collected(env));
+
+        let strong = env.upgrade_weak(&weak).unwrap().expect("Referent collected while strongly held");
+        assert_eq!(env.get_object_ref_type(&strong), JRefType::Local);
+        assert!(env.is_same_object(&strong, &obj));
+        env.delete_local_ref(strong);
+
+        // GC isn't guaranteed to collect on request, so retry a few times
+        env.delete_local_ref(obj);
+        let mut collected = false;
+        for _ in 0..20 {
+            env.call_static_method(&sys_cls, &gc_id, &vec![]).unwrap();
+            match env.upgrade_weak(&weak).unwrap() {
+                Some(strong) => env.delete_local_ref(strong),
+                None => {
+                    collected = true;
+                    break;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(collected, "Referent was never collected");
+        assert!(weak.is_collected(env));
+        assert!(!env.exception_check());
+
+        env.delete_weak_global_ref(weak);
+    })
+}
+
+#[test]
+fn test_weak_calls() {
+    with_env(|env| {
+        let point_cls = env.find_class("java.awt.Point").unwrap();
+        let con_id = env.get_method_id(&point_cls, "<init>", "(int, int) -> void").unwrap();
+        let get_x = env.get_method_id(&point_cls, "getX", "() -> double").unwrap();
+        let y_id = env.get_field_id(&point_cls, "y", "int").unwrap();
+        let sys_cls = env.find_class("java.lang.System").unwrap();
+        let gc_id = env.get_static_method_id(&sys_cls, "gc", "() -> void").unwrap();
+
+        let point = env.new_object(&point_cls, &con_id, &vec![JValue::Int(3), JValue::Int(4)]).unwrap();
+        let weak = env.new_weak_global_ref(&point).unwrap();
+
+        // The strong reference keeps the referent alive
+        let x = env.call_method_weak(&weak, &get_x, &vec![]).unwrap()
+            .expect("Referent collected while strongly held")
+            .unwrap()
+            .into_double()
+            .unwrap();
+        assert_eq!(x, 3.0);
+        let y = env.get_field_weak(&weak, &y_id).unwrap()
+            .expect("Referent collected while strongly held")
+            .into_int()
+            .unwrap();
+        assert_eq!(y, 4);
+
+        // GC isn't guaranteed to collect on request, so retry a few times
+        env.delete_local_ref(point);
+        let mut collected = false;
+        for _ in 0..20 {
+            env.call_static_method(&sys_cls, &gc_id, &vec![]).unwrap();
+            if env.call_method_weak(&weak, &get_x, &vec![]).unwrap().is_none() {
+                collected = true;
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(collected, "Referent was never collected");
+        assert!(env.get_field_weak(&weak, &y_id).unwrap().is_none());
+        assert!(!env.exception_check());
+
+        env.delete_weak_global_ref(weak);
+    })
+}
+
+#[test]
+fn test_object_creation_errors() {
+    with_env(|env| {
+        let list_cls = env.find_class("java.util.ArrayList").unwrap();
+        let con_id = env.get_method_id(&list_cls, "<init>", "(int) -> void").unwrap();
+        // The constructor's exception is captured and cleared, as for method calls
+        match env.new_object(&list_cls, &con_id, &vec![JValue::Int(-1)]) {
+            Err(Error::Exception(name, msg)) => {
+                assert_eq!(name, "java.lang.IllegalArgumentException");
+                assert_eq!(msg.as_deref(), Some("Illegal Capacity: -1"));
+            }
+            other => panic!("Expected IllegalArgumentException, got {:?}", other.map(|_| ()))
+        }
+        assert!(!env.exception_check());
+        let iae_cls = env.find_class("java.lang.IllegalArgumentException").unwrap();
+
+        // The JVM can only give null without throwing when it couldn't allocate the object
+        match env.created_object(std::ptr::null_mut()) {
+            Err(Error::ResourceExhausted(name)) => assert_eq!(name, "java.lang.OutOfMemoryError"),
+            other => panic!("Expected exhausted resources, got {:?}", other.map(|_| ()))
+        }
+        assert!(!env.exception_check());
+
+        // A pending exception takes priority over the result
+        let str = env.new_string_utf("pending").unwrap();
+        env.throw_new(&iae_cls, "thrown").unwrap();
+        // SAFETY: Internal pointer use, the string outlives the check
+        let err = env.created_object(unsafe { str.borrow_ptr() } as _).unwrap_err();
+        assert_eq!(err.to_string(), "Java exception thrown: java.lang.IllegalArgumentException: thrown");
+        assert!(!env.exception_check());
+
+        let runnable_cls = env.find_class("java.lang.Runnable").unwrap();
+        let err = env.alloc_object(&runnable_cls).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error in JVM: message \"Can't allocate an instance of java.lang.Runnable, it's an interface\", code -1"
+        );
+        let abstract_cls = env.find_class("java.util.AbstractList").unwrap();
+        let err = env.alloc_object(&abstract_cls).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error in JVM: message \"Can't allocate an instance of java.util.AbstractList, it's an abstract class\", code -1"
+        );
+        assert!(!env.exception_check());
+
+        env.alloc_object(&list_cls).unwrap();
+    })
+}
+
+#[test]
+#[cfg(any(debug_assertions, feature = "runtime-checks"))]
+fn test_cross_thread_local() {
+    crate::tests::with_vm(|vm| {
+        let vm: &JavaVM = vm;
+        let env = vm.attach_current_thread().expect("Couldn't attach test thread");
+        let local: JObject = env.new_string_utf("thread local").unwrap().downcast();
+        let global_ref = env.new_global_ref(&local).unwrap();
+        let global = global_ref.as_obj();
+
+        // Objects aren't Send, so smuggle references across as addresses to simulate misuse
+        let local_addr = &local as *const JObject as usize;
+        let global_addr = global as *const JObject as usize;
+
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                let guard = vm.attach_current_thread_guarded().expect("Couldn't attach thread");
+                // SAFETY: The referenced objects outlive the scoped thread
+                let (local, global) = unsafe {
+                    (&*(local_addr as *const JObject), &*(global_addr as *const JObject))
+                };
+
+                let str_cls = guard.find_class("java.lang.String").unwrap();
+                let len_id = guard.get_method_id(&str_cls, "length", "() -> int").unwrap();
+                let eq_id = guard.get_method_id(&str_cls, "equals", "(java.lang.Object) -> boolean").unwrap();
+
+                let err = guard.call_method(local, &len_id, &vec![]).unwrap_err();
+                let msg = err.to_string();
+                assert!(msg.contains("call_method was passed a local reference created on thread"), "{}", msg);
+
+                let err = guard.call_method(global, &eq_id, &vec![local.into()]).unwrap_err();
+                assert!(err.to_string().contains("created on thread"));
+
+                // Global references are valid on any thread
+                let len = guard.call_method(global, &len_id, &vec![]).unwrap().unwrap().into_int().unwrap();
+                assert_eq!(len, 12);
+                assert!(!guard.exception_check());
+            });
+        });
+    })
+}
+
+#[test]
+fn test_reference_downcasts() {
+    with_env(|env| {
+        let arr = match env.new_native_array(3, JNativeType::Int).unwrap() {
+            JNativeArray::Int(arr) => arr,
+            _ => panic!("Expected an int array")
+        };
+        let arr: crate::types::JIntArray = arr;
+        let as_arr: &JArray = (&arr).downcast();
+        let as_obj: &JObject = as_arr.downcast();
+        assert_eq!(env.get_array_length(as_arr), 3);
+        assert!(env.is_same_object(as_obj, arr.as_object()));
+        let direct: &JObject = (&arr).downcast();
+        assert!(env.is_same_object(direct, as_obj));
+
+        // The array is still usable after the borrowed casts
+        let int_arr_cls = env.find_class("int[]").unwrap();
+        assert!(env.is_instance_of(arr.as_object(), &int_arr_cls));
+
+        let str = env.new_string_utf("as object").unwrap();
+        let obj_cls = env.find_class("java.lang.Object").unwrap();
+        assert!(env.is_instance_of(str.as_object(), &obj_cls));
+        assert!(env.is_instance_of(obj_cls.as_object(), &env.find_class("java.lang.Class").unwrap()));
+
+        let weak = env.new_weak_global_ref(str.as_object()).unwrap();
+        let weak_obj: &JObject = (&weak).downcast();
+        assert!(env.is_same_object(weak_obj, str.as_object()));
+        env.delete_weak_global_ref(weak);
+    })
+}
+
+#[test]
+fn test_upcasts() {
+    with_env(|env| {
+        let str: JObject = env.new_string_utf("upcast").unwrap().downcast();
+        let cls: JObject = env.find_class("java.lang.String").unwrap().downcast();
+        let arr: JObject = env.new_object_array(2, &env.find_class("java.lang.Object").unwrap(), None).unwrap().downcast();
+
+        let as_str: &JString = (&str).upcast(env).unwrap();
+        assert_eq!(env.get_string_utf_chars(as_str).unwrap(), b"upcast");
+        let _: &JClass = (&cls).upcast(env).unwrap();
+        let _: &JArray = (&arr).upcast(env).unwrap();
+        let owned: JString = env.new_local_ref(&str).unwrap().upcast(env).unwrap();
+        assert!(env.is_same_object(owned.as_object(), &str));
+
+        assert!(JavaUpCast::<&JClass>::upcast(&str, env).is_err());
+        assert!(JavaUpCast::<&JString>::upcast(&cls, env).is_err());
+        assert!(JavaUpCast::<&JArray>::upcast(&str, env).is_err());
+        assert!(JavaUpCast::<&JThrowable>::upcast(&arr, env).is_err());
+        assert!(JavaUpCast::<&JWeak>::upcast(&str, env).is_err());
+
+        let weak = env.new_weak_global_ref(&str).unwrap();
+        let weak_obj: &JObject = (&weak).downcast();
+        let _: &JWeak = weak_obj.upcast(env).unwrap();
+        env.delete_weak_global_ref(weak);
+        assert!(!env.exception_check());
+
+        // After the first cast, the target class is cached, leaving only the object's class to be
+        // looked up and deleted each time
+        env.reset_stats();
+        for _ in 0..100 {
+            let _: &JString = (&str).upcast(env).unwrap();
+        }
+        let stats = env.local_ref_stats();
+        if cfg!(any(feature = "leak-check", feature = "runtime-checks")) {
+            assert_eq!(stats.created, 100);
+            assert_eq!(stats.deleted, 100);
+        }
+        assert_eq!(stats.outstanding, 0);
+    })
+}
+
+#[test]
+fn test_direct_buffer_version() {
+    with_env(|env| {
+        let mut bytes = [1, 2, 3];
+        let buffer = env.new_direct_byte_buffer(&mut bytes).unwrap();
+
+        // An environment claiming an old version, over the real pointer
+        // SAFETY: The pointer is valid for this thread, and only used for the version check
+        let old = JNIEnv::from_cached(unsafe { env.borrow_ptr() }, JNIVersion::Ver12);
+        let mut other = [4, 5];
+        match old.new_direct_byte_buffer(&mut other) {
+            Err(Error::Unsupported(msg)) => {
+                assert_eq!(msg, "Direct buffers require JNI 1.4 or newer, but the environment is Ver12")
+            }
+            other => panic!("Expected an unsupported error, got {:?}", other)
+        }
+        assert!(matches!(old.get_direct_buffer_slice(&buffer), Err(Error::Unsupported(_))));
+
+        assert_eq!(env.get_direct_buffer_slice(&buffer).unwrap(), &[1, 2, 3]);
+    })
+}
+
+#[test]
+fn test_spawn_java_thread() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc;
+
+    with_env(|env| {
+        let ran = Arc::new(AtomicBool::new(false));
+        let (send, recv) = mpsc::channel();
+
+        let thread_ran = ran.clone();
+        let thread = env.spawn_java_thread("rust-jni-worker", Box::new(move |env| {
+            let thread_cls = env.find_class("java.lang.Thread").unwrap();
+            let current = env.get_static_method_id(&thread_cls, "currentThread", "() -> java.lang.Thread").unwrap();
+            let get_name = env.get_method_id(&thread_cls, "getName", "() -> java.lang.String").unwrap();
+
+            let this = env.call_static_method(&thread_cls, &current, &vec![])
+                .unwrap()
+                .unwrap()
+                .into_obj()
+                .unwrap()
+                .unwrap();
+            let name = env.call_method(&this, &get_name, &vec![])
+                .unwrap()
+                .unwrap()
+                .into_obj()
+                .unwrap()
+                .unwrap();
+            // SAFETY: Guaranteed safe upcast, getName returns a String
+            let name: String = env.get_string_chars(&unsafe { name.upcast_raw() }).unwrap().into_iter().collect();
+
+            thread_ran.store(true, Ordering::SeqCst);
+            send.send(name).unwrap();
+        })).unwrap();
+
+        assert!(env.join_java_thread(&thread, None).unwrap());
+        assert!(ran.load(Ordering::SeqCst));
+        assert_eq!(recv.recv().unwrap(), "rust-jni-worker");
+
+        // A thread still running isn't finished after a short join
+        let (release, wait) = mpsc::channel::<()>();
+        let blocked = env.spawn_java_thread("rust-jni-blocked", Box::new(move |_| {
+            wait.recv().unwrap();
+        })).unwrap();
+        assert!(!env.join_java_thread(&blocked, Some(10)).unwrap());
+        release.send(()).unwrap();
+        assert!(env.join_java_thread(&blocked, None).unwrap());
+
+        // A panic ends the thread with an exception, rather than unwinding into the JVM
+        let panicked = env.spawn_java_thread("rust-jni-panics", Box::new(|_| {
+            panic!("Worker failed")
+        })).unwrap();
+        assert!(env.join_java_thread(&panicked, None).unwrap());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_call_depth_limit() {
+    use std::sync::Mutex;
+    use crate::ffi::JInt;
+
+    static LIMIT_ERR: Mutex<Option<String>> = Mutex::new(None);
+
+    // Calls itself through JNI until a call fails
+    extern "system" fn recurse(env: *mut ffi::JNIEnv, cls: *mut ffi::JClass) {
+        let env = JNIEnv::new(env).unwrap();
+        let cls = JClass::new(cls).unwrap();
+        let id = env.get_static_method_id(&cls, "recurse", "() -> void").unwrap();
+
+        if let Err(err) = env.call_static_method(&cls, &id, &vec![]) {
+            if !env.exception_check() {
+                *LIMIT_ERR.lock().unwrap() = Some(format!("{} at depth {}", err, env.call_depth()));
+            }
+        }
+    }
+
+    with_env(|env| {
+        let bytes = ClassFileBuilder::new("RustJniRecursion")
+            .native_method("recurse", "()V", true)
+            .build();
+        let cls = env.define_class_in_system_loader("RustJniRecursion", &bytes).unwrap();
+        let method = JNINativeMethod::new::<JInt>("recurse", "()V", recurse as *mut _);
+        env.register_natives(&cls, &[method]).unwrap();
+        let id = env.get_static_method_id(&cls, "recurse", "() -> void").unwrap();
+
+        env.set_call_depth_limit(Some(5));
+        let result = env.call_static_method(&cls, &id, &vec![]);
+        env.set_call_depth_limit(None);
+
+        result.unwrap();
+        assert_eq!(env.call_depth(), 0);
+        let err = LIMIT_ERR.lock().unwrap().take().expect("The depth limit wasn't reached");
+        assert_eq!(err, "Error in JVM: message \"call_static_method would pass the call depth limit of 5\", code -1 at depth 5");
+    })
+}
+
+#[test]
+#[cfg(feature = "reflect")]
+fn test_method_id_fallback() {
+    with_env(|env| {
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let list_cls = env.find_class("java.util.ArrayList").unwrap();
+        let str = env.new_string_utf("fallback").unwrap();
+
+        // Found directly
+        let id = env.get_method_id_with_fallback(&str_cls, "length", "() -> int").unwrap();
+        let len = env.call_method(str.as_object(), &id, &vec![]).unwrap().unwrap();
+        assert_eq!(len.into_int().unwrap(), 8);
+
+        // Missing from both lookups, the original error is left pending
+        assert!(env.get_method_id_with_fallback(&str_cls, "noSuchMethod", "() -> int").is_err());
+        match env.propagate_if_pending() {
+            Err(Error::Exception(name, _)) => assert_eq!(name, "java.lang.NoSuchMethodError"),
+            other => panic!("Expected a pending exception, got {:?}", other)
+        }
+
+        // The reflective path on its own, as taken when the direct lookup fails
+        let id = env.reflected_method_id(&str_cls, "substring", "(int, int) -> java.lang.String").unwrap();
+        let sub = env.call_method(str.as_object(), &id, &vec![JValue::Int(4), JValue::Int(8)])
+            .unwrap()
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        // SAFETY: Guaranteed safe upcast, substring returns a String
+        let sub: String = env.get_string_chars(&unsafe { sub.upcast_raw() }).unwrap().into_iter().collect();
+        assert_eq!(sub, "back");
+
+        // Inherited methods with object and array parameters
+        let list = env.new_object(&list_cls, &env.get_method_id(&list_cls, "<init>", "() -> void").unwrap(), &vec![]).unwrap();
+        let add = env.reflected_method_id(&list_cls, "add", "(java.lang.Object) -> boolean").unwrap();
+        env.call_method(&list, &add, &vec![str.as_object().into()]).unwrap();
+        let to_array = env.reflected_method_id(&list_cls, "toArray", "(java.lang.Object[]) -> java.lang.Object[]").unwrap();
+        assert_eq!(to_array.ret_ty(), JType::Object);
+
+        // Static methods aren't instance method IDs
+        assert!(env.reflected_method_id(&str_cls, "valueOf", "(int) -> java.lang.String").is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+#[cfg(feature = "strings-ext")]
+fn test_null_policy() {
+    with_env(|env| {
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let arr = env.new_object_array(3, &str_cls, None).unwrap();
+        let first = env.new_string_utf("first").unwrap();
+        let last = env.new_string_utf("last").unwrap();
+        env.set_object_array_element(&arr, 0, first.as_object()).unwrap();
+        env.set_object_array_element(&arr, 2, last.as_object()).unwrap();
+
+        match env.get_string_array(&arr, NullPolicy::Error) {
+            Err(Error::NullPointer(ctx)) => assert_eq!(ctx, "get_string_array element 1"),
+            other => panic!("Expected a null element error, got {:?}", other)
+        }
+        assert_eq!(
+            env.get_string_array(&arr, NullPolicy::AsNone).unwrap(),
+            vec![Some(String::from("first")), None, Some(String::from("last"))]
+        );
+        assert_eq!(
+            env.get_string_array(&arr, NullPolicy::DefaultOf).unwrap(),
+            vec![Some(String::from("first")), Some(String::new()), Some(String::from("last"))]
+        );
+
+        assert!(env.object_array_to_vec(&arr, NullPolicy::Error).is_err());
+        let elems = env.object_array_to_vec(&arr, NullPolicy::DefaultOf).unwrap();
+        assert!(env.is_same_object(elems[0].as_ref().unwrap(), first.as_object()));
+        assert!(elems[1].is_none());
+
+        // Non-string elements are rejected rather than misread
+        let obj_cls = env.find_class("java.lang.Object").unwrap();
+        let objs = env.new_object_array(1, &obj_cls, Some(&obj_cls.as_object())).unwrap();
+        assert!(env.get_string_array(&objs, NullPolicy::AsNone).is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_call_method_prevalidated() {
+    with_env(|env| {
+        let str = env.new_string_utf("prevalidated").unwrap();
+        let str_cls = env.get_object_class(str.as_object()).unwrap();
+        let char_at = env.get_method_id(&str_cls, "charAt", "(int) -> char").unwrap();
+        let index_of = env.get_method_id(&str_cls, "indexOf", "(java.lang.String) -> int").unwrap();
+
+        // Both paths agree over many calls, with primitive and object arguments
+        for idx in 0..1000 {
+            let idx = idx % 12;
+            let args = vec![JValue::Int(idx)];
+            let safe = env.call_method(str.as_object(), &char_at, &args).unwrap();
+            let ffi_args = JValue::make_ffi_vec(&args);
+            // SAFETY: charAt takes a single int
+            let fast = unsafe { env.call_method_prevalidated(str.as_object(), &char_at, &ffi_args) }.unwrap();
+            assert_eq!(safe.unwrap().into_char().unwrap(), fast.unwrap().into_char().unwrap());
+        }
+
+        let needle = env.new_string_utf("valid").unwrap();
+        let args = vec![needle.as_object().into()];
+        let ffi_args = JValue::make_ffi_vec(&args);
+        // SAFETY: indexOf takes a single String, which is live until the end of the test
+        let found = unsafe { env.call_method_prevalidated(str.as_object(), &index_of, &ffi_args) }.unwrap();
+        assert_eq!(found.unwrap().into_int().unwrap(), 3);
+
+        // Exceptions are still reported
+        let ffi_args = JValue::make_ffi_vec(&[JValue::Int(100)]);
+        // SAFETY: charAt takes a single int
+        let result = unsafe { env.call_method_prevalidated(str.as_object(), &char_at, &ffi_args) };
+        assert!(result.is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_object_class_and_name() {
+    with_env(|env| {
+        let str = env.new_string_utf("named").unwrap();
+        let (cls, name) = env.object_class_and_name(str.as_object()).unwrap();
+        assert_eq!(name, "java.lang.String");
+        assert!(env.is_same_object(cls.as_object(), env.find_class("java.lang.String").unwrap().as_object()));
+
+        let arr = env.new_native_array(1, JNativeType::Int).unwrap();
+        let (_, name) = env.object_class_and_name(arr.as_jarray().as_object()).unwrap();
+        assert_eq!(name, "[I");
+    })
+}
+
+#[test]
+fn test_native_array_checked() {
+    with_env(|env| {
+        let arr = env.new_native_array(4, JNativeType::Int).unwrap();
+        // SAFETY: Internal pointer use, the array outlives the checked handles
+        let ptr = unsafe { arr.as_jarray().borrow_ptr() };
+
+        let checked = unsafe { JNativeArray::new_checked(env, ptr, JNativeType::Int) }.unwrap();
+        assert_eq!(checked.jtype(), JNativeType::Int);
+
+        let err = unsafe { JNativeArray::new_checked(env, ptr, JNativeType::Long) }.err().unwrap();
+        assert!(err.to_string().contains("Array of class [I isn't a Long array"), "{}", err);
+
+        // The unchecked path trusts the caller
+        let trusted = unsafe { JNativeArray::new_raw(ptr, JNativeType::Int) }.unwrap();
+        assert_eq!(trusted.jtype(), JNativeType::Int);
+
+        #[cfg(feature = "arrays-ext")]
+        {
+            let str = env.new_string_utf("not an array").unwrap();
+            assert!(env.as_native_array(str.downcast()).unwrap().is_none());
+            let arr = env.new_native_array(2, JNativeType::Double).unwrap();
+            // SAFETY: Internal pointer use
+            let obj = JObject::new(unsafe { arr.as_jarray().borrow_ptr() } as *mut _).unwrap();
+            assert_eq!(env.as_native_array(obj).unwrap().unwrap().jtype(), JNativeType::Double);
+        }
+    })
+}
+
+#[test]
+fn test_call_value_method() {
+    with_env(|env| {
+        let str = env.new_string_utf("value").unwrap();
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let length = env.get_method_id(&str_cls, "length", "() -> int").unwrap();
+        let to_upper = env.get_method_id(&str_cls, "toUpperCase", "() -> java.lang.String").unwrap();
+
+        let len = env.call_value_method(str.as_object(), &length, &vec![]).unwrap();
+        assert_eq!(len.into_int().unwrap(), 5);
+        let upper = env.call_value_method(str.as_object(), &to_upper, &vec![]).unwrap();
+        assert!(upper.into_obj().unwrap().is_some());
+
+        let obj_cls = env.find_class("java.lang.Object").unwrap();
+        let notify = env.get_method_id(&obj_cls, "notify", "() -> void").unwrap();
+        let err = env.call_value_method(str.as_object(), &notify, &vec![]).unwrap_err();
+        assert!(err.to_string().contains("can't call a void method"), "{}", err);
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_get_static_field_as() {
+    with_env(|env| {
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        // Declared as a Comparator, which has no wrapper, so read as a plain object
+        let order: JObject = env.get_static_field_as(&str_cls, "CASE_INSENSITIVE_ORDER").unwrap().unwrap();
+        let cmp_cls = env.find_class("java.util.Comparator").unwrap();
+        assert!(env.is_instance_of(&order, &cmp_cls));
+
+        let file_cls = env.find_class("java.io.File").unwrap();
+        let sep: JString = env.get_static_field_as(&file_cls, "separator").unwrap().unwrap();
+        assert_eq!(env.get_string_chars(&sep).unwrap(), vec!['/']);
+
+        // Boolean.TYPE holds a Class, not a String
+        let bool_cls = env.find_class("java.lang.Boolean").unwrap();
+        let err = env.get_static_field_as::<JString>(&bool_cls, "TYPE").unwrap_err();
+        assert!(err.to_string().contains("Static field TYPE isn't a java.lang.String"), "{}", err);
+        assert!(env.get_static_field_as::<JClass>(&bool_cls, "TYPE").unwrap().is_some());
+
+        let int_cls = env.find_class("java.lang.Integer").unwrap();
+        assert_eq!(env.get_static_field_primitive::<i32>(&int_cls, "MAX_VALUE").unwrap(), i32::MAX);
+        assert!(env.get_static_field_as::<JObject>(&int_cls, "MAX_VALUE").is_err());
+        assert!(!env.exception_check());
+
+        // A missing field fails the fallback lookup too, without leaving an exception pending
+        match env.get_static_field_as::<JObject>(&int_cls, "MISSING") {
+            Err(Error::Exception(name, _)) => assert_eq!(name, "java.lang.NoSuchFieldException"),
+            other => panic!("Expected the fallback to fail, got {:?}", other.map(|_| ()))
+        }
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+#[cfg(feature = "strings-ext")]
+fn test_string_metrics() {
+    with_env(|env| {
+        // Modified UTF-8 encodes NUL in two bytes, and each half of a surrogate pair in three
+        let units: Vec<u16> = "a\u{e9}\u{1F600}\0".encode_utf16().collect();
+        let str = env.new_string_utf16_checked(&units).unwrap();
+        assert_eq!(env.string_metrics(&str).unwrap(), (5, 11, 4));
+
+        let lone = env.new_string_utf16(&[0x61, 0xD800]).unwrap();
+        assert_eq!(env.string_metrics(&lone).unwrap(), (2, 4, 2));
+
+        let empty = env.new_string_utf("").unwrap();
+        assert_eq!(env.string_metrics(&empty).unwrap(), (0, 0, 0));
+    })
+}
+
+#[test]
+fn test_typed_field_accessors() {
+    with_env(|env| {
+        let bytes = ClassFileBuilder::new("RustJniTypedFields")
+            .field("flag", "Z")
+            .field("letter", "C")
+            .field("count", "I")
+            .field("ratio", "D")
+            .static_field("total", "J")
+            .build();
+        let cls = env.define_class_in_system_loader("RustJniTypedFields", &bytes)
+            .expect("Couldn't define typed field test class");
+        let obj = env.alloc_object(&cls).unwrap();
+
+        let flag = env.get_field_id(&cls, "flag", "boolean").unwrap();
+        let letter = env.get_field_id(&cls, "letter", "char").unwrap();
+        let count = env.get_field_id(&cls, "count", "int").unwrap();
+        let ratio = env.get_field_id(&cls, "ratio", "double").unwrap();
+        let total = env.get_static_field_id(&cls, "total", "long").unwrap();
+
+        env.set_field_bool(&obj, &flag, true).unwrap();
+        env.set_field_char(&obj, &letter, 'z').unwrap();
+        env.set_field_int(&obj, &count, -12).unwrap();
+        env.set_field_double(&obj, &ratio, 0.25).unwrap();
+        env.set_static_field_long(&cls, &total, 1 << 50).unwrap();
+
+        assert!(env.get_field_bool(&obj, &flag).unwrap());
+        assert_eq!(env.get_field_char(&obj, &letter).unwrap(), 'z');
+        assert_eq!(env.get_field_int(&obj, &count).unwrap(), -12);
+        assert_eq!(env.get_field_double(&obj, &ratio).unwrap(), 0.25);
+        assert_eq!(env.get_static_field_long(&cls, &total).unwrap(), 1 << 50);
+
+        // The generic accessors agree, as they're built on the typed ones
+        assert_eq!(env.get_field(&obj, &count).unwrap().into_int().unwrap(), -12);
+        env.set_field(&obj, &count, JValue::Int(99)).unwrap();
+        assert_eq!(env.get_field_int(&obj, &count).unwrap(), 99);
+
+        // With checks, a mismatched ID is rejected before touching the field
+        #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+        {
+            let err = env.get_field_long(&obj, &count).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Error in JVM: message \"get_field_long expected a field of type Long, but the ID is for a Int field\", code -1"
+            );
+            assert!(env.set_field_float(&obj, &ratio, 1.0).is_err());
+            assert_eq!(env.get_field_double(&obj, &ratio).unwrap(), 0.25);
+            assert!(env.get_static_field_int(&cls, &total).is_err());
+            assert!(!env.exception_check());
+        }
+    })
+}
+
+#[test]
+fn test_future_get() {
+    with_env(|env| {
+        let future_cls = env.find_class("java.util.concurrent.CompletableFuture").unwrap();
+        let con = env.get_method_id(&future_cls, "<init>", "() -> void").unwrap();
+        let complete = env.get_method_id(&future_cls, "complete", "(java.lang.Object) -> boolean").unwrap();
+        let complete_exc = env.get_method_id(&future_cls, "completeExceptionally", "(java.lang.Throwable) -> boolean").unwrap();
+        let cancel = env.get_method_id(&future_cls, "cancel", "(boolean) -> boolean").unwrap();
+
+        let future = env.new_object(&future_cls, &con, &vec![]).unwrap();
+        let val = env.new_string_utf("done").unwrap();
+        env.call_method(&future, &complete, &vec![val.as_object().into()]).unwrap();
+        let result = env.future_get(&future).unwrap().unwrap();
+        assert!(env.is_same_object(&result, val.as_object()));
+
+        // A null result is None
+        let future = env.new_object(&future_cls, &con, &vec![]).unwrap();
+        env.call_method(&future, &complete, &vec![JValue::Object(None)]).unwrap();
+        assert!(env.future_get(&future).unwrap().is_none());
+
+        // Failures report the cause, not the ExecutionException wrapping it
+        let future = env.new_object(&future_cls, &con, &vec![]).unwrap();
+        let state_cls = env.find_class("java.lang.IllegalStateException").unwrap();
+        let state_con = env.get_method_id(&state_cls, "<init>", "(java.lang.String) -> void").unwrap();
+        let msg = env.new_string_utf("boom").unwrap();
+        let cause = env.new_object(&state_cls, &state_con, &vec![msg.as_object().into()]).unwrap();
+        env.call_method(&future, &complete_exc, &vec![(&cause).into()]).unwrap();
+        match env.future_get(&future) {
+            Err(Error::Exception(name, msg)) => {
+                assert_eq!(name, "java.lang.IllegalStateException");
+                assert_eq!(msg.as_deref(), Some("boom"));
+            }
+            other => panic!("Expected the future's exception, got {:?}", other.map(|_| ()))
+        }
+        assert!(!env.exception_check());
+
+        let future = env.new_object(&future_cls, &con, &vec![]).unwrap();
+        env.call_method(&future, &cancel, &vec![JValue::Bool(true)]).unwrap();
+        match env.future_get(&future) {
+            Err(Error::Exception(name, _)) => assert_eq!(name, "java.util.concurrent.CancellationException"),
+            other => panic!("Expected a cancellation, got {:?}", other.map(|_| ()))
+        }
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_new_string_supplementary() {
+    with_env(|env| {
+        // Characters outside the BMP take two UTF-16 units, and read back as one char
+        let chars: Vec<char> = "a\u{1F600}b".chars().collect();
+        let str = env.new_string(&chars).unwrap();
+        assert_eq!(env.get_string_length(&str), 4);
+        assert_eq!(env.get_string_chars(&str).unwrap(), chars);
+    })
+}
+
+#[test]
+fn test_signature_cache() {
+    with_env(|env| {
+        let parses = || SIGNATURE_PARSES.with(|parses| parses.get());
+
+        // A name no other test looks up, so the first lookup is sure to parse it
+        env.find_class("java.util.concurrent.atomic.LongAdder").unwrap();
+        let after_first = parses();
+        env.find_class("java.util.concurrent.atomic.LongAdder").unwrap();
+        env.find_class("java.util.concurrent.atomic.LongAdder").unwrap();
+        assert_eq!(parses(), after_first);
+
+        // Method and field signatures are shared across lookups on different classes
+        let cls = env.find_class("java.util.concurrent.atomic.LongAdder").unwrap();
+        env.get_method_id(&cls, "add", "(long) -> void").unwrap();
+        let before = parses();
+        let acc_cls = env.find_class("java.util.concurrent.atomic.LongAccumulator").unwrap();
+        env.get_method_id(&acc_cls, "accumulate", "(long) -> void").unwrap();
+        assert_eq!(parses(), before + 1, "Only the new class name should be parsed");
+    })
+}
+
+/// Check two references of any kinds refer to the same object, through their object pointers
+fn same_referent<A: AsJObjectPtr, B: AsJObjectPtr>(env: &JNIEnv, a: &A, b: &B) -> bool {
+    // SAFETY: Internal pointer use, the pointers are only borrowed while their owners live
+    let (a, b) = unsafe { (JObject::new(a.as_object_ptr()).unwrap(), JObject::new(b.as_object_ptr()).unwrap()) };
+    env.is_same_object(&a, &b)
+}
+
+#[test]
+fn test_as_object_ptr() {
+    with_env(|env| {
+        let str = env.new_string_utf("pointer").unwrap();
+        let cls = env.get_object_class(str.as_object()).unwrap();
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let arr = env.new_native_array(1, JNativeType::Int).unwrap();
+
+        assert!(same_referent(env, &str, str.as_object()));
+        assert!(same_referent(env, &cls, &str_cls));
+        assert!(!same_referent(env, &str, &cls));
+        assert!(same_referent(env, &arr, arr.as_jarray()));
+        assert!(!same_referent(env, &arr, &str));
+    })
+}
+
+#[test]
+fn test_new_functional() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    with_env(|env| {
+        // A Runnable run by a Java thread
+        let runs = Arc::new(AtomicI32::new(0));
+        let closure_runs = runs.clone();
+        let runnable = env.new_functional("java.lang.Runnable", move |_, args| {
+            assert!(args.is_empty());
+            closure_runs.fetch_add(1, Ordering::SeqCst);
+            Ok(None)
+        }).unwrap();
+
+        let thread_cls = env.find_class("java.lang.Thread").unwrap();
+        let thread_con = env.get_method_id(&thread_cls, "<init>", "(java.lang.Runnable) -> void").unwrap();
+        let start = env.get_method_id(&thread_cls, "start", "() -> void").unwrap();
+        let thread = env.new_object(&thread_cls, &thread_con, &vec![(&runnable).into()]).unwrap();
+        env.call_method(&thread, &start, &vec![]).unwrap();
+        assert!(env.join_java_thread(&thread, None).unwrap());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        let runnable_cls = env.find_class("java.lang.Runnable").unwrap();
+        let run = env.get_method_id(&runnable_cls, "run", "() -> void").unwrap();
+        env.call_method(&runnable, &run, &vec![]).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+
+        // A Function taking and returning objects
+        let function = env.new_functional("java.util.function.Function", |env, args| {
+            let arg = args[0].as_ref().expect("Null argument");
+            let str_cls = env.find_class("java.lang.String")?;
+            let concat = env.get_method_id(&str_cls, "concat", "(java.lang.String) -> java.lang.String")?;
+            let suffix = env.new_string_utf(" from rust")?;
+            env.call_value_method(arg, &concat, &vec![suffix.as_object().into()])?.into_obj()
+        }).unwrap();
+        let function_cls = env.find_class("java.util.function.Function").unwrap();
+        let apply = env.get_method_id(&function_cls, "apply", "(java.lang.Object) -> java.lang.Object").unwrap();
+        let str = env.new_string_utf("hello").unwrap();
+        let result = env.call_value_method(&function, &apply, &vec![str.as_object().into()])
+            .unwrap()
+            .into_obj()
+            .unwrap()
+            .unwrap();
+        // SAFETY: Guaranteed safe upcast, the closure returns a String
+        let result: String = env.get_string_chars(&unsafe { result.upcast_raw() }).unwrap().into_iter().collect();
+        assert_eq!(result, "hello from rust");
+
+        // Object methods use the proxy's identity
+        let obj_cls = env.find_class("java.lang.Object").unwrap();
+        let equals = env.get_method_id(&obj_cls, "equals", "(java.lang.Object) -> boolean").unwrap();
+        assert!(env.call_value_method(&function, &equals, &vec![(&function).into()]).unwrap().into_bool().unwrap());
+        assert!(!env.call_value_method(&function, &equals, &vec![(&runnable).into()]).unwrap().into_bool().unwrap());
+
+        // Errors from the closure are thrown into Java
+        let failing = env.new_functional("java.lang.Runnable", |_, _| {
+            Err(Error::Unsupported(String::from("Not today")))
+        }).unwrap();
+        match env.call_method(&failing, &run, &vec![]) {
+            Err(Error::Exception(name, _)) => assert_eq!(name, "java.lang.UnsupportedOperationException"),
+            other => panic!("Expected the thrown error, got {:?}", other)
+        }
+
+        // Interfaces with more than one abstract method, and classes, are rejected
+        assert!(env.new_functional("java.util.Iterator", |_, _| Ok(None)).is_err());
+        assert!(env.new_functional("java.util.Comparator", |_, _| Ok(None)).is_ok());
+        assert!(env.new_functional("java.lang.Object", |_, _| Ok(None)).is_err());
+        assert!(!env.exception_check());
+    })
+}
+
+#[test]
+fn test_array_any() {
+    with_env(|env| {
+        let ints = env.new_native_array(3, JNativeType::Int).unwrap();
+        env.set_native_array_region(&ints, 0, 3, &JNativeVec::Int(vec![1, 2, 3])).unwrap();
+        let str_cls = env.find_class("java.lang.String").unwrap();
+        let strs = env.new_object_array(3, &str_cls, None).unwrap();
+        let first = env.new_string_utf("first").unwrap();
+        env.set_object_array_element(&strs, 0, first.as_object()).unwrap();
+
+        let ints = JArrayAny::from(ints);
+        let strs = JArrayAny::from(strs);
+
+        // Arrays of unknown kind are classified by their class
+        let any = JArrayAny::from_object(env, ints.as_jarray().as_object().share()).unwrap();
+        assert_eq!(any.jtype(), Some(JNativeType::Int));
+        let any = JArrayAny::from_object(env, strs.as_jarray().as_object().share()).unwrap();
+        assert_eq!(any.jtype(), None);
+        assert_eq!(ints.jtype(), Some(JNativeType::Int));
+        assert_eq!(strs.jtype(), None);
+        assert!(JArrayAny::from_object(env, first.as_object().share()).is_err());
+
+        // The same generic code works over both kinds
+        let last = env.new_string_utf("last").unwrap();
+        for (arr, val) in [(&ints, JValue::Int(4)), (&strs, last.as_object().into())] {
+            assert_eq!(arr.len(env), 3);
+            arr.set(env, 2, val).unwrap();
+            assert!(arr.set(env, 3, JValue::Int(0)).is_err());
+            assert_eq!(arr.to_vec(env).unwrap().len(), 3);
+        }
+
+        let vals: Vec<i32> = ints.to_vec(env).unwrap().into_iter().map(|val| val.into_int().unwrap()).collect();
+        assert_eq!(vals, vec![1, 2, 4]);
+        assert_eq!(ints.get(env, 1).unwrap().into_int().unwrap(), 2);
+        assert!(ints.set(env, 0, JValue::Bool(true)).is_err());
+
+        let vals: Vec<Option<String>> = strs.to_vec(env)
+            .unwrap()
+            .into_iter()
+            .map(|val| val.into_obj().unwrap().map(|obj| {
+                // SAFETY: Guaranteed safe upcast, the array holds Strings
+                env.get_string_chars(&unsafe { obj.upcast_raw() }).unwrap().into_iter().collect()
+            }))
+            .collect();
+        assert_eq!(vals, vec![Some(String::from("first")), None, Some(String::from("last"))]);
+        assert!(strs.get(env, 1).unwrap().into_obj().unwrap().is_none());
+        strs.set(env, 0, JValue::Object(None)).unwrap();
+        assert!(strs.get(env, 0).unwrap().into_obj().unwrap().is_none());
+        assert!(!env.exception_check());
+    })
+}
@@ -0,0 +1,216 @@
+//!
+//! Module containing helpers for running rust code on real Java threads, which are visible to Java
+//! tooling and inherit the spawning thread's group and context class loader.
+//!
+
+use std::sync::Mutex;
+
+use crate::ffi;
+use crate::types::{JValue, JObject, JClass, JNINativeMethod, JavaDownCast, JavaUpCast};
+use crate::error::{Error, Result};
+use crate::classgen::ClassFileBuilder;
+use crate::panic_bridge::catch_panic;
+use crate::{get_cls, get_method_id};
+use super::JNIEnv;
+
+/// Name of the generated Runnable class whose `run` calls a rust closure
+const RUNNABLE_CLASS: &str = "rustjni/RustRunnable";
+
+/// A closure waiting to be run on a Java thread
+type ThreadBody = Box<dyn FnOnce(&JNIEnv) + Send>;
+
+/// Global reference to the generated Runnable class, once defined
+static RUNNABLE: Mutex<usize> = Mutex::new(0);
+
+/// Turn the `InterruptedException` a blocking Java call threw into [Error::Interrupted], passing
+/// any other result through. Shared by every wait that can be interrupted, so they agree on it
+pub(super) fn take_interrupted<T>(result: Result<T>) -> Result<T> {
+    match result {
+        Err(Error::Exception(name, _)) if name == "java.lang.InterruptedException" => Err(Error::Interrupted),
+        result => result
+    }
+}
+
+/// Native `run` of the generated Runnable class. Takes the closure out of the object's handle, so
+/// it runs at most once, and calls it with the environment of the running thread
+extern "system" fn run_closure(env: *mut ffi::JNIEnv, this: *mut ffi::JObject) {
+    let env = match JNIEnv::new(env) {
+        Ok(env) => env,
+        Err(_) => return
+    };
+
+    catch_panic(&env, (), || {
+        let this = JObject::new(this).expect("Null Runnable in native run");
+        let cls = env.runnable_class().expect("Runnable class ran before being defined");
+        let handle_id = env.get_field_id(&cls, "handle", "long").expect("Runnable has no handle field");
+
+        let handle = env.get_field(&this, &handle_id)
+            .and_then(|val| val.into_long())
+            .expect("Couldn't read Runnable handle");
+        if handle == 0 {
+            return
+        }
+        env.set_field(&this, &handle_id, JValue::Long(0)).expect("Couldn't clear Runnable handle");
+
+        // SAFETY: Handles are only created from a boxed body by spawn_java_thread, and cleared above
+        //         so this is the only owner
+        let body = unsafe { Box::from_raw(handle as *mut ThreadBody) };
+        body(&env)
+    })
+}
+
+impl<'env> JNIEnv<'env> {
+
+    /// Get the generated Runnable class, defining it and registering its native `run` on first use
+    fn runnable_class(&self) -> Result<JClass<'static>> {
+        let mut cls_ptr = RUNNABLE.lock().expect("Runnable class lock poisoned");
+        if *cls_ptr == 0 {
+            let bytes = ClassFileBuilder::new(RUNNABLE_CLASS)
+                .interface("java.lang.Runnable")
+                .field("handle", "J")
+                .native_method("run", "()V", false)
+                .build();
+            let cls = self.define_class_in_system_loader(RUNNABLE_CLASS, &bytes)?;
+            let run = JNINativeMethod::new::<ffi::JInt>("run", "()V", run_closure as *mut _);
+            self.register_natives(&cls, &[run])?;
+
+            let global = self.new_global_ref((&cls).downcast())?.leak();
+            // SAFETY: Internal pointer use, the global reference is never deleted
+            *cls_ptr = unsafe { global.borrow_ptr() } as usize;
+        }
+
+        JClass::new(*cls_ptr as *mut ffi::JClass)
+    }
+
+    /// Delete the global reference to the generated Runnable class, if it was defined, so a
+    /// library unloading doesn't leak it
+    pub(super) fn release_runnable_class(&self) {
+        let mut cls_ptr = RUNNABLE.lock().expect("Runnable class lock poisoned");
+        if *cls_ptr != 0 {
+            if let Ok(cls) = JObject::new(*cls_ptr as *mut ffi::JObject) {
+                self.delete_global_ref(cls);
+            }
+            *cls_ptr = 0;
+        }
+    }
+
+    /// Create an instance of the generated Runnable class whose `run` calls a closure once, with
+    /// the environment of the thread running it
+    pub(super) fn new_runnable(&self, f: ThreadBody) -> Result<JObject<'env>> {
+        let runnable_cls = self.runnable_class()?;
+        let runnable_con = self.get_method_id(&runnable_cls, "<init>", "() -> void")?;
+        let handle_id = self.get_field_id(&runnable_cls, "handle", "long")?;
+
+        let runnable = self.new_object(&runnable_cls, &runnable_con, &vec![])?;
+        let handle = Box::into_raw(Box::new(f));
+        if let Err(err) = self.set_field(&runnable, &handle_id, JValue::Long(handle as i64)) {
+            // SAFETY: The handle was created above, and never stored
+            drop(unsafe { Box::from_raw(handle) });
+            return Err(err)
+        }
+        Ok(runnable)
+    }
+
+    /// Free the closure of a Runnable from [new_runnable][Self::new_runnable] that will never run,
+    /// such as when starting its thread failed
+    pub(super) fn free_runnable(&self, runnable: &JObject) {
+        let handle_id = match self.runnable_class().and_then(|cls| self.get_field_id(&cls, "handle", "long")) {
+            Ok(id) => id,
+            Err(_) => return
+        };
+
+        if let Ok(JValue::Long(left)) = self.get_field(runnable, &handle_id) {
+            if left != 0 && self.set_field(runnable, &handle_id, JValue::Long(0)).is_ok() {
+                // SAFETY: The handle was created by new_runnable, and never taken by a run
+                drop(unsafe { Box::from_raw(left as *mut ThreadBody) });
+            }
+        }
+    }
+
+    /// Run a closure on a new Java thread with the given name, returning the started `Thread`. The
+    /// closure gets the environment of the new thread. A panic in the closure is thrown into the
+    /// thread as a `RuntimeException`, ending it through Java's uncaught exception handling. Use
+    /// [join_java_thread][Self::join_java_thread] to wait for it to finish
+    pub fn spawn_java_thread(&self, name: &str, f: Box<dyn FnOnce(&JNIEnv) + Send>) -> Result<JObject<'env>> {
+        let thread_cls = get_cls!(self, "java.lang.Thread");
+        let thread_con = get_method_id!(self, thread_cls, "<init>", "(java.lang.Runnable, java.lang.String) -> void");
+        let start = get_method_id!(self, thread_cls, "start", "() -> void");
+
+        let runnable = self.new_runnable(f)?;
+        let result = self.new_string_utf(name).and_then(|name| {
+            let thread = self.new_object(thread_cls, thread_con, &vec![(&runnable).into(), name.downcast().into()])?;
+            self.call_method(&thread, start, &vec![])?;
+            Ok(thread)
+        });
+
+        if result.is_err() {
+            // The thread never started, so the closure is still ours to free
+            self.free_runnable(&runnable);
+        }
+        result
+    }
+
+    /// Wait for a Java thread to finish, as `Thread.join` would. With a timeout, gives up once that
+    /// many milliseconds have passed, a zero timeout waiting one millisecond as in
+    /// [MonitorGuard::wait][super::MonitorGuard::wait]. Returns whether the thread has finished.
+    /// Returns [Error::Interrupted] if this thread was interrupted while waiting, clearing the
+    /// `InterruptedException`
+    pub fn join_java_thread(&self, thread: &JObject, timeout_ms: Option<u64>) -> Result<bool> {
+        let thread_cls = get_cls!(self, "java.lang.Thread");
+        let join = get_method_id!(self, thread_cls, "join", "(long) -> void");
+        let is_alive = get_method_id!(self, thread_cls, "isAlive", "() -> boolean");
+
+        let timeout = match timeout_ms {
+            Some(ms) => ms.clamp(1, i64::MAX as u64) as i64,
+            None => 0
+        };
+
+        take_interrupted(self.call_method(thread, join, &vec![JValue::Long(timeout)]))?;
+
+        let alive = self.call_value_method(thread, is_alive, &vec![])?
+            .into_bool()?;
+        Ok(!alive)
+    }
+
+    /// Wait for a `Future`, such as a `CompletableFuture`, to complete, as `Future.get` would, and
+    /// return its result. A future completed exceptionally returns its cause as an
+    /// [Error::Exception], and a cancelled future the `CancellationException`. Returns
+    /// [Error::Interrupted] if this thread was interrupted while waiting. The Java exception is
+    /// cleared in every case
+    pub fn future_get(&self, future: &JObject) -> Result<Option<JObject<'env>>> {
+        let future_cls = get_cls!(self, "java.util.concurrent.Future");
+        let get = get_method_id!(self, future_cls, "get", "() -> java.lang.Object");
+
+        let env = self.internal_env();
+
+        // Called directly rather than through call_method, as the thrown exception is needed to
+        // find its cause
+        // SAFETY: Internal pointer use, Future.get takes no arguments
+        let result = unsafe { self.track_local(env.call_object_method(future.borrow_ptr(), get.borrow_ptr(), std::ptr::null())) };
+        if !self.exception_check() {
+            return if result.is_null() { Ok(None) } else { JObject::new(result).map(Some) }
+        }
+
+        let exc = self.exception_occurred()?;
+        self.exception_clear()?;
+
+        // Failures are wrapped in an ExecutionException, report what the future failed with. The
+        // cause being an InterruptedException means the task was interrupted, not this thread
+        let execution = get_cls!(self, "java.util.concurrent.ExecutionException");
+        if self.is_instance_of((&exc).downcast(), execution) {
+            let throwable_cls = get_cls!(self, "java.lang.Throwable");
+            let get_cause = get_method_id!(self, throwable_cls, "getCause", "() -> java.lang.Throwable");
+            let exc = match self.call_value_method((&exc).downcast(), get_cause, &vec![])?.into_obj()? {
+                // SAFETY: Guaranteed safe upcast, getCause returns a Throwable
+                Some(cause) => unsafe { cause.upcast_raw() },
+                None => exc
+            };
+
+            let (name, msg) = self.describe_exception(&exc)?;
+            return Err(Error::Exception(name, msg))
+        }
+
+        let (name, msg) = self.describe_exception(&exc)?;
+        take_interrupted(Err(Error::Exception(name, msg)))
+    }
+}
@@ -0,0 +1,135 @@
+//!
+//! Module tracking the global state a native library leaves in the JVM, so it can be released when
+//! the library is unloaded. See [jni_on_unload][crate::jni_on_unload].
+//!
+
+use std::sync::Mutex;
+
+use crate::ffi;
+use crate::types::{JClass, JavaDownCast, JavaUpCast};
+use crate::types::object::JWeak;
+use crate::error::Result;
+use super::JNIEnv;
+
+/// Weak global references to the classes natives have been registered on. Weak, so registering
+/// natives doesn't keep the class, and with it the class loader and this library, from unloading
+static NATIVE_CLASSES: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// Statics the [get_cls][crate::get_cls] macro caches a global class reference in
+static CACHED_CLASSES: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// Statics the ID caching macros, such as [get_method_id][crate::get_method_id], cache an ID in,
+/// with the function resetting each
+static CACHED_IDS: Mutex<Vec<(usize, unsafe fn(usize))>> = Mutex::new(Vec::new());
+
+/// Empty a static cached by one of the ID caching macros
+///
+/// # Safety
+///
+/// The slot must point to an `Option<T>` static
+unsafe fn reset_slot<T>(slot: usize) {
+    *(slot as *mut Option<T>) = None;
+}
+
+impl<'env> JNIEnv<'env> {
+
+    /// Remember a class natives are about to be registered on, so they can be unregistered on
+    /// unload. Classes already remembered aren't added twice, which takes a scan of those already
+    /// remembered, but registering is rare and the list is one entry per class. Classes that have
+    /// since been collected are dropped during the scan. A class stays remembered even if
+    /// registering then fails, which only means an unneeded unregister later
+    pub(super) fn record_natives(&self, cls: &JClass) -> Result<()> {
+        let mut classes = NATIVE_CLASSES.lock().expect("Native class list poisoned");
+        let mut known = false;
+        let mut kept = Vec::with_capacity(classes.len() + 1);
+        for &ptr in classes.iter() {
+            let weak = JWeak::new(ptr as *mut ffi::JWeak)?;
+            if self.is_weak_collected(&weak) {
+                self.delete_weak_global_ref(weak);
+                continue
+            }
+            known |= self.is_same_object((&weak).downcast(), cls.downcast());
+            kept.push(ptr);
+        }
+
+        if !known {
+            let weak = self.new_weak_global_ref(cls.downcast())?;
+            // SAFETY: Internal pointer use, the weak reference is deleted by release_library_refs
+            kept.push(unsafe { weak.borrow_ptr() } as usize);
+        }
+        *classes = kept;
+        Ok(())
+    }
+
+    /// Remember a static [get_cls][crate::get_cls] cached a global class reference in, so the
+    /// reference can be deleted and the cache emptied on unload. Only meant for use by that macro
+    ///
+    /// # Safety
+    ///
+    /// The slot must be a static only written by that macro
+    #[doc(hidden)]
+    pub unsafe fn track_cached_class(&self, slot: *mut Option<JClass<'static>>) {
+        CACHED_CLASSES.lock().expect("Cached class list poisoned").push(slot as usize);
+    }
+
+    /// Remember a static one of the ID caching macros cached an ID in, so the cache can be emptied
+    /// on unload. Only meant for use by those macros
+    ///
+    /// # Safety
+    ///
+    /// The slot must be a static only written by those macros
+    #[doc(hidden)]
+    pub unsafe fn track_cached_id<T>(&self, slot: *mut Option<T>) {
+        CACHED_IDS.lock().expect("Cached ID list poisoned").push((slot as usize, reset_slot::<T>));
+    }
+
+    /// Release what this library holds in the JVM: natives registered through
+    /// [register_natives][Self::register_natives] are unregistered, the global references cached
+    /// by this crate are deleted, and the caches of the caching macros are emptied, so later uses
+    /// look their class or ID up again. Every entry is released even if some fail, returning the
+    /// first error.
+    ///
+    /// This is run by [jni_on_unload][crate::jni_on_unload] after the user's cleanup.
+    ///
+    /// # Safety
+    ///
+    /// The caches are plain statics, so no other thread may be calling into the JVM through this
+    /// library while they're emptied, and references returned by [get_cls][crate::get_cls] before
+    /// this call mustn't be used after it
+    pub unsafe fn release_library_refs(&self) -> Result<()> {
+        let mut result = Ok(());
+
+        let natives = std::mem::take(&mut *NATIVE_CLASSES.lock().expect("Native class list poisoned"));
+        for ptr in natives {
+            let weak = JWeak::new(ptr as *mut ffi::JWeak)?;
+            match self.upgrade_weak(&weak) {
+                Ok(Some(obj)) => {
+                    let cls: JClass = obj.upcast_raw();
+                    if let Err(err) = self.unregister_natives(&cls) {
+                        result = result.and(Err(err));
+                    }
+                    self.delete_local_ref(cls.downcast());
+                }
+                Ok(None) => (),
+                Err(err) => result = result.and(Err(err))
+            }
+            self.delete_weak_global_ref(weak);
+        }
+
+        let cached = std::mem::take(&mut *CACHED_CLASSES.lock().expect("Cached class list poisoned"));
+        for slot in cached {
+            if let Some(cls) = (*(slot as *mut Option<JClass<'static>>)).take() {
+                self.delete_global_ref(cls.downcast());
+            }
+        }
+
+        let ids = std::mem::take(&mut *CACHED_IDS.lock().expect("Cached ID list poisoned"));
+        for (slot, reset) in ids {
+            reset(slot);
+        }
+
+        self.release_runnable_class();
+        self.release_functional_refs();
+        result
+    }
+}
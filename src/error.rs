@@ -5,16 +5,30 @@
 use std::fmt::{Display, Formatter};
 use std::error;
 
+use crate::ffi::JInt;
+use crate::ffi::constants::{JNI_ERR, JNI_EDETACHED, JNI_EVERSION, JNI_ENOMEM, JNI_EEXIST, JNI_EINVAL};
+
 /// Error type for this library. Most often used to represent a case where an environment
 /// action caused Java to begin throwing an error
 #[derive(Debug)]
 pub enum Error {
     /// JNI error returned from a different error type
-    Induced(Box<dyn error::Error>),
+    Induced(Box<dyn error::Error + Send + Sync>),
     /// JNI error returned with a message and code
     General(String, i32),
     /// JNI error returned when a pointer is null
-    NullPointer(String)
+    NullPointer(String),
+    /// Error returned when an operation isn't supported by the running JVM
+    Unsupported(String),
+    /// Java ran out of a resource during a call, such as with a `StackOverflowError` or
+    /// `OutOfMemoryError`. Holds the name of the thrown error class, or the one it stands for if
+    /// the JVM failed without throwing
+    ResourceExhausted(String),
+    /// The current Java thread was interrupted, and native code stopped early
+    Interrupted,
+    /// A Java exception was pending, and has been cleared. Holds the name of the exception's
+    /// class, and its message if it had one
+    Exception(String, Option<String>)
 }
 
 impl Error {
@@ -33,11 +47,39 @@ impl Error {
         Error::NullPointer(String::from(ctx))
     }
 
+    /// Create a new unsupported-operation error, with a message
+    pub fn new_unsupported(msg: &str) -> Error {
+        Error::Unsupported(String::from(msg))
+    }
+
     /// Create a new error, based on an existing [error::Error]
-    pub fn from(err: Box<dyn error::Error>) -> Error {
+    pub fn from(err: Box<dyn error::Error + Send + Sync>) -> Error {
         Error::Induced(err)
     }
 
+    /// Create a new error from a JNI return code, with a message describing that code
+    pub fn from_code(code: JInt) -> Error {
+        let msg = match code {
+            JNI_EDETACHED => "Thread detached from the VM",
+            JNI_EVERSION => "JNI version error",
+            JNI_ENOMEM => "Not enough memory",
+            JNI_EEXIST => "VM already created",
+            JNI_EINVAL => "Invalid arguments",
+            _ => "Unknown error"
+        };
+        Error::new(msg, code)
+    }
+
+    /// Get the JNI return code best representing this error. General errors keep the code they
+    /// were created with, running out of memory is `JNI_ENOMEM`, and anything else is `JNI_ERR`
+    pub fn jni_code(&self) -> JInt {
+        match self {
+            Error::General(_, code) => *code,
+            Error::ResourceExhausted(name) if name == "java.lang.OutOfMemoryError" => JNI_ENOMEM,
+            _ => JNI_ERR
+        }
+    }
+
 }
 
 impl Display for Error {
@@ -51,10 +93,37 @@ impl Display for Error {
             Error::NullPointer(context) => {
                 write!(f, "Error in JNI: Pointer was null in {}", context)
             }
+            Error::Unsupported(msg) => {
+                write!(f, "Operation unsupported by JVM: {}", msg)
+            }
+            Error::ResourceExhausted(name) => {
+                write!(f, "JVM resources exhausted during call: {}", name)
+            }
+            Error::Interrupted => {
+                write!(f, "Java thread was interrupted")
+            }
+            Error::Exception(name, Some(msg)) => {
+                write!(f, "Java exception thrown: {}: {}", name, msg)
+            }
+            Error::Exception(name, None) => {
+                write!(f, "Java exception thrown: {}", name)
+            }
         }
     }
 }
 
+impl From<&Error> for JInt {
+    fn from(err: &Error) -> JInt {
+        err.jni_code()
+    }
+}
+
+impl From<JInt> for Error {
+    fn from(code: JInt) -> Error {
+        Error::from_code(code)
+    }
+}
+
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         if let Error::Induced(err) = self {
@@ -67,3 +136,33 @@ impl error::Error for Error {
 
 /// Common result type using the local error type
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jni_code() {
+        assert_eq!(Error::new("", JNI_EINVAL).jni_code(), JNI_EINVAL);
+        assert_eq!(Error::new("", 7).jni_code(), 7);
+        assert_eq!(Error::new_null("").jni_code(), JNI_ERR);
+        assert_eq!(Error::new_unsupported("").jni_code(), JNI_ERR);
+        assert_eq!(Error::ResourceExhausted("java.lang.OutOfMemoryError".into()).jni_code(), JNI_ENOMEM);
+        assert_eq!(Error::ResourceExhausted("java.lang.StackOverflowError".into()).jni_code(), JNI_ERR);
+        assert_eq!(Error::Interrupted.jni_code(), JNI_ERR);
+        assert_eq!(Error::Exception("java.lang.RuntimeException".into(), None).jni_code(), JNI_ERR);
+        assert_eq!(Error::from(Box::new(std::fmt::Error)).jni_code(), JNI_ERR);
+
+        assert_eq!(JInt::from(&Error::new("", JNI_EDETACHED)), JNI_EDETACHED);
+    }
+
+    #[test]
+    fn test_from_code() {
+        for &code in &[JNI_ERR, JNI_EDETACHED, JNI_EVERSION, JNI_ENOMEM, JNI_EEXIST, JNI_EINVAL, -100] {
+            let err: Error = code.into();
+            assert!(matches!(err, Error::General(_, _)));
+            assert_eq!(err.jni_code(), code);
+        }
+        assert_eq!(Error::from_code(JNI_ENOMEM).to_string(), "Error in JVM: message \"Not enough memory\", code -4");
+    }
+}
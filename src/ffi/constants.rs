@@ -16,6 +16,14 @@ pub const JNI_VERSION_1_8: i32 = 0x00010008;
 pub const JNI_VERSION_9: i32 = 0x00090000;
 /// Version value for Java 10
 pub const JNI_VERSION_10: i32 = 0x000a0000;
+/// Version value for Java 19
+pub const JNI_VERSION_19: i32 = 0x00130000;
+/// Version value for Java 20
+pub const JNI_VERSION_20: i32 = 0x00140000;
+/// Version value for Java 21
+pub const JNI_VERSION_21: i32 = 0x00150000;
+/// Version value for Java 24
+pub const JNI_VERSION_24: i32 = 0x00180000;
 
 /// Value passed for FALSE in numeric contexts
 pub const JNI_FALSE: u8 = 0;
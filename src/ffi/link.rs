@@ -5,7 +5,7 @@
 use crate::ffi::types::*;
 
 #[link(name = "jvm")]
-extern "stdcall" {
+extern "system" {
     #[link_name = "JNI_GetDefaultJavaVMInitArgs"]
     pub fn get_default_jvm_init_args(args: *mut JavaVMInitArgs) -> JInt;
     #[link_name = "JNI_CreateJavaVM"]
@@ -10,6 +10,9 @@ use crate::ffi::types::*;
 /// A struct representing the method table backing the JNI environment, the only part of the
 /// environment which isn't opaque to the user.
 ///
+/// The variadic (`...` and `va_list`) slots are kept as opaque pointers, as calling them needs
+/// nightly-only features. The `A` forms taking a value array are used instead.
+///
 #[repr(C)]
 pub struct JNINativeInterface {
     reserved0: *const c_void,
@@ -50,8 +53,8 @@ pub struct JNINativeInterface {
 
     alloc_object: extern "system" fn(*const JNIEnv, *mut JClass) -> *mut JObject,
 
-    new_object: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...) -> *mut JObject,
-    new_object_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList) -> *mut JObject,
+    new_object: *const c_void,
+    new_object_v: *const c_void,
     new_object_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue) -> *mut JObject,
 
     get_object_class: extern "system" fn(*const JNIEnv, *mut JObject) -> *mut JClass,
@@ -59,84 +62,84 @@ pub struct JNINativeInterface {
 
     get_method_id: extern "system" fn(*const JNIEnv, *mut JClass, *const i8, *const i8) -> *const JMethodID,
 
-    call_object_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *const JMethodID, ...) -> *mut JObject,
-    call_object_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, std::ffi::VaList) -> *mut JObject,
+    call_object_method: *const c_void,
+    call_object_method_v: *const c_void,
     call_object_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, *const JValue) -> *mut JObject,
 
-    call_boolean_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *const JMethodID, ...) -> JBoolean,
-    call_boolean_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, std::ffi::VaList) -> JBoolean,
+    call_boolean_method: *const c_void,
+    call_boolean_method_v: *const c_void,
     call_boolean_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, *const JValue) -> JBoolean,
 
-    call_byte_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *const JMethodID, ...) -> JByte,
-    call_byte_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, std::ffi::VaList) -> JByte,
+    call_byte_method: *const c_void,
+    call_byte_method_v: *const c_void,
     call_byte_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, *const JValue) -> JByte,
 
-    call_char_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *const JMethodID, ...) -> JChar,
-    call_char_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, std::ffi::VaList) -> JChar,
+    call_char_method: *const c_void,
+    call_char_method_v: *const c_void,
     call_char_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, *const JValue) -> JChar,
 
-    call_short_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *const JMethodID, ...) -> JShort,
-    call_short_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, std::ffi::VaList) -> JShort,
+    call_short_method: *const c_void,
+    call_short_method_v: *const c_void,
     call_short_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, *const JValue) -> JShort,
 
-    call_int_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *const JMethodID, ...) -> JInt,
-    call_int_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, std::ffi::VaList) -> JInt,
+    call_int_method: *const c_void,
+    call_int_method_v: *const c_void,
     call_int_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, *const JValue) -> JInt,
 
-    call_long_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *const JMethodID, ...) -> JLong,
-    call_long_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, std::ffi::VaList) -> JLong,
+    call_long_method: *const c_void,
+    call_long_method_v: *const c_void,
     call_long_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, *const JValue) -> JLong,
 
-    call_float_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *const JMethodID, ...) -> JFloat,
-    call_float_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, std::ffi::VaList) -> JFloat,
+    call_float_method: *const c_void,
+    call_float_method_v: *const c_void,
     call_float_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, *const JValue) -> JFloat,
 
-    call_double_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *const JMethodID, ...) -> JDouble,
-    call_double_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, std::ffi::VaList) -> JDouble,
+    call_double_method: *const c_void,
+    call_double_method_v: *const c_void,
     call_double_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, *const JValue) -> JDouble,
 
-    call_void_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *const JMethodID, ...),
-    call_void_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, std::ffi::VaList),
+    call_void_method: *const c_void,
+    call_void_method_v: *const c_void,
     call_void_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *const JMethodID, *const JValue),
 
-    call_nonvirtual_object_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, ...) -> *mut JObject,
-    call_nonvirtual_object_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, std::ffi::VaList) -> *mut JObject,
+    call_nonvirtual_object_method: *const c_void,
+    call_nonvirtual_object_method_v: *const c_void,
     call_nonvirtual_object_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, *const JValue) -> *mut JObject,
 
-    call_nonvirtual_boolean_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, ...) -> JBoolean,
-    call_nonvirtual_boolean_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, std::ffi::VaList) -> JBoolean,
+    call_nonvirtual_boolean_method: *const c_void,
+    call_nonvirtual_boolean_method_v: *const c_void,
     call_nonvirtual_boolean_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, *const JValue) -> JBoolean,
 
-    call_nonvirtual_byte_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, ...) -> JByte,
-    call_nonvirtual_byte_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, std::ffi::VaList) -> JByte,
+    call_nonvirtual_byte_method: *const c_void,
+    call_nonvirtual_byte_method_v: *const c_void,
     call_nonvirtual_byte_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, *const JValue) -> JByte,
 
-    call_nonvirtual_char_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, ...) -> JChar,
-    call_nonvirtual_char_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, std::ffi::VaList) -> JChar,
+    call_nonvirtual_char_method: *const c_void,
+    call_nonvirtual_char_method_v: *const c_void,
     call_nonvirtual_char_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, *const JValue) -> JChar,
 
-    call_nonvirtual_short_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, ...) -> JShort,
-    call_nonvirtual_short_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, std::ffi::VaList) -> JShort,
+    call_nonvirtual_short_method: *const c_void,
+    call_nonvirtual_short_method_v: *const c_void,
     call_nonvirtual_short_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, *const JValue) -> JShort,
 
-    call_nonvirtual_int_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, ...) -> JInt,
-    call_nonvirtual_int_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, std::ffi::VaList) -> JInt,
+    call_nonvirtual_int_method: *const c_void,
+    call_nonvirtual_int_method_v: *const c_void,
     call_nonvirtual_int_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, *const JValue) -> JInt,
 
-    call_nonvirtual_long_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, ...) -> JLong,
-    call_nonvirtual_long_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, std::ffi::VaList) -> JLong,
+    call_nonvirtual_long_method: *const c_void,
+    call_nonvirtual_long_method_v: *const c_void,
     call_nonvirtual_long_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, *const JValue) -> JLong,
 
-    call_nonvirtual_float_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, ...) -> JFloat,
-    call_nonvirtual_float_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, std::ffi::VaList) -> JFloat,
+    call_nonvirtual_float_method: *const c_void,
+    call_nonvirtual_float_method_v: *const c_void,
     call_nonvirtual_float_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, *const JValue) -> JFloat,
 
-    call_nonvirtual_double_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, ...) -> JDouble,
-    call_nonvirtual_double_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, std::ffi::VaList) -> JDouble,
+    call_nonvirtual_double_method: *const c_void,
+    call_nonvirtual_double_method_v: *const c_void,
     call_nonvirtual_double_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, *const JValue) -> JDouble,
 
-    call_nonvirtual_void_method: extern "cdecl" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, ...),
-    call_nonvirtual_void_method_v: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, std::ffi::VaList),
+    call_nonvirtual_void_method: *const c_void,
+    call_nonvirtual_void_method_v: *const c_void,
     call_nonvirtual_void_method_a: extern "system" fn(*const JNIEnv, *mut JObject, *mut JClass, *const JMethodID, *const JValue),
 
     get_field_id: extern "system" fn(*const JNIEnv, *mut JClass, *const i8, *const i8) -> *const JFieldID,
@@ -163,44 +166,44 @@ pub struct JNINativeInterface {
 
     get_static_method_id: extern "system" fn(*const JNIEnv, *mut JClass, *const i8, *const i8) -> *const JMethodID,
 
-    call_static_object_method: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...) -> *mut JObject,
-    call_static_object_method_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList) -> *mut JObject,
+    call_static_object_method: *const c_void,
+    call_static_object_method_v: *const c_void,
     call_static_object_method_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue) -> *mut JObject,
 
-    call_static_boolean_method: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...) -> JBoolean,
-    call_static_boolean_method_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList) -> JBoolean,
+    call_static_boolean_method: *const c_void,
+    call_static_boolean_method_v: *const c_void,
     call_static_boolean_method_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue) -> JBoolean,
 
-    call_static_byte_method: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...) -> JByte,
-    call_static_byte_method_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList) -> JByte,
+    call_static_byte_method: *const c_void,
+    call_static_byte_method_v: *const c_void,
     call_static_byte_method_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue) -> JByte,
 
-    call_static_char_method: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...) -> JChar,
-    call_static_char_method_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList) -> JChar,
+    call_static_char_method: *const c_void,
+    call_static_char_method_v: *const c_void,
     call_static_char_method_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue) -> JChar,
 
-    call_static_short_method: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...) -> JShort,
-    call_static_short_method_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList) -> JShort,
+    call_static_short_method: *const c_void,
+    call_static_short_method_v: *const c_void,
     call_static_short_method_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue) -> JShort,
 
-    call_static_int_method: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...) -> JInt,
-    call_static_int_method_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList) -> JInt,
+    call_static_int_method: *const c_void,
+    call_static_int_method_v: *const c_void,
     call_static_int_method_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue) -> JInt,
 
-    call_static_long_method: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...) -> JLong,
-    call_static_long_method_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList) -> JLong,
+    call_static_long_method: *const c_void,
+    call_static_long_method_v: *const c_void,
     call_static_long_method_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue) -> JLong,
 
-    call_static_float_method: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...) -> JFloat,
-    call_static_float_method_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList) -> JFloat,
+    call_static_float_method: *const c_void,
+    call_static_float_method_v: *const c_void,
     call_static_float_method_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue) -> JFloat,
 
-    call_static_double_method: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...) -> JDouble,
-    call_static_double_method_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList) -> JDouble,
+    call_static_double_method: *const c_void,
+    call_static_double_method_v: *const c_void,
     call_static_double_method_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue) -> JDouble,
 
-    call_static_void_method: extern "cdecl" fn(*const JNIEnv, *mut JClass, *const JMethodID, ...),
-    call_static_void_method_v: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, std::ffi::VaList),
+    call_static_void_method: *const c_void,
+    call_static_void_method_v: *const c_void,
     call_static_void_method_a: extern "system" fn(*const JNIEnv, *mut JClass, *const JMethodID, *const JValue),
 
     get_static_field_id: extern "system" fn(*const JNIEnv, *mut JClass, *const i8, *const i8) -> *const JFieldID,
@@ -453,11 +456,6 @@ impl JNIEnv {
         (self.get_functions().new_object_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->NewObjectV(...)
-    pub fn new_object_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> *mut JObject {
-        (self.get_functions().new_object_v)(self, cls, id, args)
-    }
-
     /// Wrapper for env->GetObjectClass(...)
     pub fn get_object_class(&self, obj: *mut JObject) -> *mut JClass {
         (self.get_functions().get_object_class)(self, obj)
@@ -479,220 +477,120 @@ impl JNIEnv {
         (self.get_functions().call_object_method_a)(self, obj, id, args)
     }
 
-    /// Wrapper for env->CallObjectMethodV(...)
-    pub fn call_object_method_v(&self, obj: *mut JObject, id: *const JMethodID, args: std::ffi::VaList) -> *mut JObject {
-        (self.get_functions().call_object_method_v)(self, obj, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallBooleanMethodA(...)
     pub fn call_boolean_method(&self, obj: *mut JObject, id: *const JMethodID, args: *const JValue) -> JBoolean {
         (self.get_functions().call_boolean_method_a)(self, obj, id, args)
     }
 
-    /// Wrapper for env->CallBooleanMethodV(...)
-    pub fn call_boolean_method_v(&self, obj: *mut JObject, id: *const JMethodID, args: std::ffi::VaList) -> JBoolean {
-        (self.get_functions().call_boolean_method_v)(self, obj, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallByteMethodA(...)
     pub fn call_byte_method(&self, obj: *mut JObject, id: *const JMethodID, args: *const JValue) -> JByte {
         (self.get_functions().call_byte_method_a)(self, obj, id, args)
     }
 
-    /// Wrapper for env->CallByteMethodV(...)
-    pub fn call_byte_method_v(&self, obj: *mut JObject, id: *const JMethodID, args: std::ffi::VaList) -> JByte {
-        (self.get_functions().call_byte_method_v)(self, obj, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallCharMethodA(...)
     pub fn call_char_method(&self, obj: *mut JObject, id: *const JMethodID, args: *const JValue) -> JChar {
         (self.get_functions().call_char_method_a)(self, obj, id, args)
     }
 
-    /// Wrapper for env->CallCharMethodV(...)
-    pub fn call_char_method_v(&self, obj: *mut JObject, id: *const JMethodID, args: std::ffi::VaList) -> JChar {
-        (self.get_functions().call_char_method_v)(self, obj, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallShortMethodA(...)
     pub fn call_short_method(&self, obj: *mut JObject, id: *const JMethodID, args: *const JValue) -> JShort {
         (self.get_functions().call_short_method_a)(self, obj, id, args)
     }
 
-    /// Wrapper for env->CallShortMethodV(...)
-    pub fn call_short_method_v(&self, obj: *mut JObject, id: *const JMethodID, args: std::ffi::VaList) -> JShort {
-        (self.get_functions().call_short_method_v)(self, obj, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallIntMethodA(...)
     pub fn call_int_method(&self, obj: *mut JObject, id: *const JMethodID, args: *const JValue) -> JInt {
         (self.get_functions().call_int_method_a)(self, obj, id, args)
     }
 
-    /// Wrapper for env->CallIntMethodV(...)
-    pub fn call_int_method_v(&self, obj: *mut JObject, id: *const JMethodID, args: std::ffi::VaList) -> JInt {
-        (self.get_functions().call_int_method_v)(self, obj, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallLongMethodA(...)
     pub fn call_long_method(&self, obj: *mut JObject, id: *const JMethodID, args: *const JValue) -> JLong {
         (self.get_functions().call_long_method_a)(self, obj, id, args)
     }
 
-    /// Wrapper for env->CallLongMethodV(...)
-    pub fn call_long_method_v(&self, obj: *mut JObject, id: *const JMethodID, args: std::ffi::VaList) -> JLong {
-        (self.get_functions().call_long_method_v)(self, obj, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallFloatMethodA(...)
     pub fn call_float_method(&self, obj: *mut JObject, id: *const JMethodID, args: *const JValue) -> JFloat {
         (self.get_functions().call_float_method_a)(self, obj, id, args)
     }
 
-    /// Wrapper for env->CallFloatMethodV(...)
-    pub fn call_float_method_v(&self, obj: *mut JObject, id: *const JMethodID, args: std::ffi::VaList) -> JFloat {
-        (self.get_functions().call_float_method_v)(self, obj, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallDoubleMethodA(...)
     pub fn call_double_method(&self, obj: *mut JObject, id: *const JMethodID, args: *const JValue) -> JDouble {
         (self.get_functions().call_double_method_a)(self, obj, id, args)
     }
 
-    /// Wrapper for env->CallDoubleMethodV(...)
-    pub fn call_double_method_v(&self, obj: *mut JObject, id: *const JMethodID, args: std::ffi::VaList) -> JDouble {
-        (self.get_functions().call_double_method_v)(self, obj, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallVoidMethodA(...)
     pub fn call_void_method(&self, obj: *mut JObject, id: *const JMethodID, args: *const JValue) {
         (self.get_functions().call_void_method_a)(self, obj, id, args)
     }
 
-    /// Wrapper for env->CallVoidMethodV(...)
-    pub fn call_void_method_v(&self, obj: *mut JObject, id: *const JMethodID, args: std::ffi::VaList) {
-        (self.get_functions().call_void_method_v)(self, obj, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallNonvirtualObjectMethodA(...)
     pub fn call_nonvirtual_object_method(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> *mut JObject {
         (self.get_functions().call_nonvirtual_object_method_a)(self, obj, cls, id, args)
     }
 
-    /// Wrapper for env->CallNonvirtualObjectMethodV(...)
-    pub fn call_nonvirtual_object_method_v(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> *mut JObject {
-        (self.get_functions().call_nonvirtual_object_method_v)(self, obj, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallNonvirtualBooleanMethodA(...)
     pub fn call_nonvirtual_boolean_method(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JBoolean {
         (self.get_functions().call_nonvirtual_boolean_method_a)(self, obj, cls, id, args)
     }
 
-    /// Wrapper for env->CallNonvirtualBooleanMethodV(...)
-    pub fn call_nonvirtual_boolean_method_v(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JBoolean {
-        (self.get_functions().call_nonvirtual_boolean_method_v)(self, obj, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// /// Wrapper for env->CallNonvirtualByteMethodA(...)
     pub fn call_nonvirtual_byte_method(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JByte {
         (self.get_functions().call_nonvirtual_byte_method_a)(self, obj, cls, id, args)
     }
 
-    /// Wrapper for env->CallNonvirtualByteMethodV(...)
-    pub fn call_nonvirtual_byte_method_v(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JByte {
-        (self.get_functions().call_nonvirtual_byte_method_v)(self, obj, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallNonvirtualCharMethodA(...)
     pub fn call_nonvirtual_char_method(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JChar {
         (self.get_functions().call_nonvirtual_char_method_a)(self, obj, cls, id, args)
     }
 
-    /// Wrapper for env->CallNonvirtualCharMethodV(...)
-    pub fn call_nonvirtual_char_method_v(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JChar {
-        (self.get_functions().call_nonvirtual_char_method_v)(self, obj, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallNonvirtualShortMethodA(...)
     pub fn call_nonvirtual_short_method(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JShort {
         (self.get_functions().call_nonvirtual_short_method_a)(self, obj, cls, id, args)
     }
 
-    /// Wrapper for env->CallNonvirtualShortMethodV(...)
-    pub fn call_nonvirtual_short_method_v(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JShort {
-        (self.get_functions().call_nonvirtual_short_method_v)(self, obj, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallNonvirtualIntMethodA(...)
     pub fn call_nonvirtual_int_method(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JInt {
         (self.get_functions().call_nonvirtual_int_method_a)(self, obj, cls, id, args)
     }
 
-    /// Wrapper for env->CallNonvirtualIntMethodV(...)
-    pub fn call_nonvirtual_int_method_v(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JInt {
-        (self.get_functions().call_nonvirtual_int_method_v)(self, obj, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallNonvirtualLongMethodA(...)
     pub fn call_nonvirtual_long_method(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JLong {
         (self.get_functions().call_nonvirtual_long_method_a)(self, obj, cls, id, args)
     }
 
-    /// Wrapper for env->CallNonvirtualLongMethodV(...)
-    pub fn call_nonvirtual_long_method_v(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JLong {
-        (self.get_functions().call_nonvirtual_long_method_v)(self, obj, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallNonvirtualFloatMethodA(...)
     pub fn call_nonvirtual_float_method(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JFloat {
         (self.get_functions().call_nonvirtual_float_method_a)(self, obj, cls, id, args)
     }
 
-    /// Wrapper for env->CallNonvirtualFloatMethodV(...)
-    pub fn call_nonvirtual_float_method_v(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JFloat {
-        (self.get_functions().call_nonvirtual_float_method_v)(self, obj, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallNonvirtualDoubleMethodA(...)
     pub fn call_nonvirtual_double_method(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JDouble {
         (self.get_functions().call_nonvirtual_double_method_a)(self, obj, cls, id, args)
     }
 
-    /// Wrapper for env->CallNonvirtualDoubleMethodV(...)
-    pub fn call_nonvirtual_double_method_v(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JDouble {
-        (self.get_functions().call_nonvirtual_double_method_v)(self, obj, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallNonvirtualVoidMethodA(...)
     pub fn call_nonvirtual_void_method(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: *const JValue) {
         (self.get_functions().call_nonvirtual_void_method_a)(self, obj, cls, id, args)
     }
 
-    /// Wrapper for env->CallNonvirtualVoidMethodV(...)
-    pub fn call_nonvirtual_void_method_v(&self, obj: *mut JObject, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) {
-        (self.get_functions().call_nonvirtual_void_method_v)(self, obj, cls, id, args)
-    }
-
     /// Wrapper for env->GetFieldId(...)
     pub fn get_field_id(&self, cls: *mut JClass, name: *const i8, sig: *const i8) -> *const JFieldID {
         (self.get_functions().get_field_id)(self, cls, name, sig)
@@ -799,110 +697,60 @@ impl JNIEnv {
         (self.get_functions().call_static_object_method_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->CallStaticObjectMethodV(...)
-    pub fn call_static_object_method_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> *mut JObject {
-        (self.get_functions().call_static_object_method_v)(self, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallStaticBooleanMethodA(...)
     pub fn call_static_boolean_method(&self, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JBoolean {
         (self.get_functions().call_static_boolean_method_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->CallStaticBooleanMethodV(...)
-    pub fn call_static_boolean_method_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JBoolean {
-        (self.get_functions().call_static_boolean_method_v)(self, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallStaticByteMethodA(...)
     pub fn call_static_byte_method(&self, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JByte {
         (self.get_functions().call_static_byte_method_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->CallStaticByteMethodV(...)
-    pub fn call_static_byte_method_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JByte {
-        (self.get_functions().call_static_byte_method_v)(self, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallStaticCharMethodA(...)
     pub fn call_static_char_method(&self, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JChar {
         (self.get_functions().call_static_char_method_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->CallStaticCharMethodV(...)
-    pub fn call_static_char_method_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JChar {
-        (self.get_functions().call_static_char_method_v)(self, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallStaticShortMethodA(...)
     pub fn call_static_short_method(&self, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JShort {
         (self.get_functions().call_static_short_method_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->CallStaticShortMethodV(...)
-    pub fn call_static_short_method_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JShort {
-        (self.get_functions().call_static_short_method_v)(self, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallStaticIntMethodA(...)
     pub fn call_static_int_method(&self, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JInt {
         (self.get_functions().call_static_int_method_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->CallStaticIntMethodV(...)
-    pub fn call_static_int_method_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JInt {
-        (self.get_functions().call_static_int_method_v)(self, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallStaticLongMethodA(...)
     pub fn call_static_long_method(&self, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JLong {
         (self.get_functions().call_static_long_method_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->CallStaticLongMethodV(...)
-    pub fn call_static_long_method_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JLong {
-        (self.get_functions().call_static_long_method_v)(self, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallStaticFloatMethodA(...)
     pub fn call_static_float_method(&self, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JFloat {
         (self.get_functions().call_static_float_method_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->CallStaticFloatMethodV(...)
-    pub fn call_static_float_method_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JFloat {
-        (self.get_functions().call_static_float_method_v)(self, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallStaticDoubleMethodA(...)
     pub fn call_static_double_method(&self, cls: *mut JClass, id: *const JMethodID, args: *const JValue) -> JDouble {
         (self.get_functions().call_static_double_method_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->CallStaticDoubleMethodV(...)
-    pub fn call_static_double_method_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) -> JDouble {
-        (self.get_functions().call_static_double_method_v)(self, cls, id, args)
-    }
-
     /// Default to the value array form, becauses rust doesn't support variadics
     /// Wrapper for env->CallStaticVoidMethodA(...)
     pub fn call_static_void_method(&self, cls: *mut JClass, id: *const JMethodID, args: *const JValue) {
         (self.get_functions().call_static_void_method_a)(self, cls, id, args)
     }
 
-    /// Wrapper for env->CallStaticVoidMethodV(...)
-    pub fn call_static_void_method_v(&self, cls: *mut JClass, id: *const JMethodID, args: std::ffi::VaList) {
-        (self.get_functions().call_static_void_method_v)(self, cls, id, args)
-    }
-
     /// Wrapper for env->GetStaticFieldId(...)
     pub fn get_static_field_id(&self, cls: *mut JClass, name: *const i8, sig: *const i8) -> *const JFieldID {
         (self.get_functions().get_static_field_id)(self, cls, name, sig)
@@ -1353,3 +1201,41 @@ impl JNIEnv {
         (self.get_functions().get_module)(self, cls)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::{size_of, MaybeUninit};
+
+    /// Get the slot index of a table field, from its byte offset
+    macro_rules! slot {
+        ($base:ident, $field:ident) => {
+            // SAFETY: Only takes the address of the field, never reading it
+            unsafe {
+                (std::ptr::addr_of!((*$base).$field) as usize - $base as usize) / size_of::<*const c_void>()
+            }
+        }
+    }
+
+    #[test]
+    fn test_table_layout() {
+        assert_eq!(size_of::<JNINativeInterface>(), 234 * size_of::<*const c_void>());
+
+        let table = MaybeUninit::<JNINativeInterface>::uninit();
+        let base = table.as_ptr();
+
+        // Indices from the JNI specification's function table
+        assert_eq!(slot!(base, get_version), 4);
+        assert_eq!(slot!(base, new_object), 28);
+        assert_eq!(slot!(base, new_object_v), 29);
+        assert_eq!(slot!(base, new_object_a), 30);
+        assert_eq!(slot!(base, call_object_method), 34);
+        assert_eq!(slot!(base, call_void_method_a), 63);
+        assert_eq!(slot!(base, call_nonvirtual_object_method), 64);
+        assert_eq!(slot!(base, call_static_object_method), 114);
+        assert_eq!(slot!(base, call_static_void_method_a), 143);
+        assert_eq!(slot!(base, get_static_field_id), 144);
+        assert_eq!(slot!(base, exception_check), 228);
+        assert_eq!(slot!(base, get_module), 233);
+    }
+}
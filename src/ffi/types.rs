@@ -32,8 +32,9 @@ pub type JInt = i32;
 /// Real type for JLong on the JVM
 pub type JLong = i64;
 
-/// Real type for JBoolean on the JVM
-pub type JBoolean = bool;
+/// Real type for JBoolean on the JVM. Any non-zero value is true, so this is kept as an integer
+/// rather than a rust `bool`, which may only be 0 or 1
+pub type JBoolean = u8;
 
 /// Real type for JChar on the JVM
 pub type JChar = u16;
@@ -199,6 +200,28 @@ pub struct JavaVMOption {
     extra_info: *mut c_void
 }
 
+impl JavaVMOption {
+
+    /// Create a new JavaVMOption from an option string and extra info pointer
+    pub fn new(option_string: *mut i8, extra_info: *mut c_void) -> JavaVMOption {
+        JavaVMOption {
+            option_string,
+            extra_info
+        }
+    }
+
+    /// Get the option string of this option
+    pub fn option_string(&self) -> *const i8 {
+        self.option_string
+    }
+
+    /// Get the extra info pointer of this option
+    pub fn extra_info(&self) -> *mut c_void {
+        self.extra_info
+    }
+
+}
+
 impl Debug for JavaVMOption {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
         let option_string = unsafe {
@@ -220,7 +243,10 @@ pub struct JavaVMInitArgs {
     version: JInt,
     num_options: JInt,
     options: *mut JavaVMOption,
-    ignore_unrecognized: JBoolean
+    ignore_unrecognized: JBoolean,
+    /// Whether the options array was allocated by these args, rather than filled in by the JVM.
+    /// Not part of the JNI struct, the JVM only reads and writes the fields before it
+    owns_options: bool
 }
 
 impl JavaVMInitArgs {
@@ -231,38 +257,103 @@ impl JavaVMInitArgs {
             version,
             num_options: 0,
             options: std::ptr::null_mut(),
-            ignore_unrecognized: false
+            ignore_unrecognized: constants::JNI_FALSE,
+            owns_options: false
         }
     }
 
-    /// Add a startup option to these initialization args
-    pub fn add_option(&mut self, option: JavaVMOption) {
-        let layout = Layout::new::<JavaVMOption>();
+    /// Get the JNI version of these initialization args
+    pub fn version(&self) -> JInt {
+        self.version
+    }
 
-        self.num_options += 1;
-        if self.options == std::ptr::null_mut() {
-            // SAFETY: Full size of allocation will be initialized by the set later
-            unsafe {
-                self.options = std::alloc::alloc(layout).cast();
-            }
+    /// Get whether the JVM should ignore unrecognized options
+    pub fn ignore_unrecognized(&self) -> bool {
+        self.ignore_unrecognized != constants::JNI_FALSE
+    }
+
+    /// Set whether the JVM should ignore unrecognized options
+    pub fn set_ignore_unrecognized(&mut self, ignore: bool) {
+        self.ignore_unrecognized = JBoolean::from(ignore)
+    }
+
+    /// Get the startup options of these initialization args. Unsafe, as the options pointer may
+    /// have been filled in by the JVM, and must point to at least as many options as it claims
+    pub unsafe fn options(&self) -> &[JavaVMOption] {
+        if self.options.is_null() || self.num_options <= 0 {
+            &[]
         } else {
-            // SAFETY: Full size of allocation is initialized, or will be by the set later
+            slice::from_raw_parts(self.options, self.num_options as usize)
+        }
+    }
+
+    /// Set the startup options of these initialization args, replacing any existing ones. The
+    /// options are copied into an array owned by these args. Unsafe, as the option strings must
+    /// outlive every use of these args
+    pub unsafe fn set_options(&mut self, options: &[JavaVMOption]) {
+        let new = Self::alloc_options(options.len());
+        options.as_ptr().copy_to_nonoverlapping(new, options.len());
+        self.replace_options(new, options.len());
+    }
+
+    /// Layout of an options array holding `len` options
+    fn options_layout(len: usize) -> Layout {
+        Layout::array::<JavaVMOption>(len).expect("Option array size overflowed")
+    }
+
+    /// Allocate an uninitialized array of `len` options, or a dangling pointer if it's empty
+    fn alloc_options(len: usize) -> *mut JavaVMOption {
+        if len == 0 {
+            return std::ptr::NonNull::dangling().as_ptr()
+        }
+
+        let layout = Self::options_layout(len);
+        // SAFETY: The layout isn't zero sized
+        let options = unsafe { std::alloc::alloc(layout) };
+        if options.is_null() {
+            std::alloc::handle_alloc_error(layout)
+        }
+        options.cast()
+    }
+
+    /// Swap in a new options array from [alloc_options][Self::alloc_options], with `len` options
+    /// initialized, freeing the current one if these args own it
+    fn replace_options(&mut self, options: *mut JavaVMOption, len: usize) {
+        if self.owns_options {
+            // SAFETY: Owned arrays are allocated with the layout of the current options
             unsafe {
-                self.options = std::alloc::realloc(
-                    self.options.cast(),
-                    layout,
-                    std::mem::size_of::<JavaVMOption>() * self.num_options as usize
-                ).cast()
+                std::alloc::dealloc(self.options.cast(), Self::options_layout(self.num_options as usize))
             }
         }
 
-        // SAFETY: Initializes any possibly uninit memory. Offset will always be less than array size
+        if len == 0 {
+            self.options = std::ptr::null_mut();
+            self.owns_options = false;
+        } else {
+            self.options = options;
+            self.owns_options = true;
+        }
+        self.num_options = len as JInt;
+    }
+
+    /// Add a startup option to these initialization args. The existing options are copied into a
+    /// new array owned by these args
+    pub fn add_option(&mut self, option: JavaVMOption) {
+        // SAFETY: The options are either owned by these args, or were filled in by the JVM
+        let current = unsafe { self.options() };
+        let len = current.len();
+        let new = Self::alloc_options(len + 1);
+
+        // SAFETY: The new array holds one more option than the current one, and doesn't overlap it
         unsafe {
-            *self.options.offset(self.num_options as isize - 1) = option;
+            current.as_ptr().copy_to_nonoverlapping(new, len);
+            new.add(len).write(option);
         }
+        self.replace_options(new, len + 1);
     }
 
-    /// Remove a startup option from these initialization args by index
+    /// Remove a startup option from these initialization args by index. The remaining options are
+    /// copied into a new array owned by these args
     pub fn remove_option(&mut self, idx: i32) -> Result<(), Error>{
         if idx >= self.num_options || idx < 0 {
             return Err(Error::new(
@@ -271,37 +362,30 @@ impl JavaVMInitArgs {
             ));
         }
 
-        let layout = Layout::new::<JavaVMOption>();
+        // SAFETY: The options are either owned by these args, or were filled in by the JVM
+        let current = unsafe { self.options() };
+        let len = current.len() - 1;
+        let idx = idx as usize;
+        let new = Self::alloc_options(len);
 
-        self.num_options -= 1;
-        if self.num_options == 0 {
-            // SAFETY: If num_options is zero, this array will never be accessed
-            unsafe {
-                std::alloc::dealloc(self.options.cast(), layout);
-            }
-        } else {
-            // SAFETY: This will never overflow the end of the array, so will always be copying
-            //         initialized values
-            unsafe {
-                self.options
-                    .offset((idx + 1) as isize)
-                    .copy_to(self.options.offset(idx as isize), (self.num_options - idx) as usize);
-            }
-
-            // SAFETY: Shrinks past the now discarded end of the array.
-            unsafe {
-                self.options = std::alloc::realloc(
-                    self.options.cast(),
-                    layout,
-                    std::mem::size_of::<JavaVMOption>() * self.num_options as usize
-                ).cast();
-            }
+        // SAFETY: The options before and after the removed one exactly fill the new array, which
+        //         doesn't overlap the current one
+        unsafe {
+            current.as_ptr().copy_to_nonoverlapping(new, idx);
+            current.as_ptr().add(idx + 1).copy_to_nonoverlapping(new.add(idx), len - idx);
         }
+        self.replace_options(new, len);
 
         Ok(())
     }
 }
 
+impl Drop for JavaVMInitArgs {
+    fn drop(&mut self) {
+        self.replace_options(std::ptr::null_mut(), 0);
+    }
+}
+
 impl Debug for JavaVMInitArgs {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
         let options;
@@ -353,3 +437,39 @@ unsafe impl IsArray for JLongArray {}
 unsafe impl IsArray for JFloatArray {}
 unsafe impl IsArray for JDoubleArray {}
 unsafe impl IsArray for JObjectArray {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_args_options() {
+        let strings: Vec<CString> = ["-Xss2m", "-Da=1", "-Db=2"].iter()
+            .map(|opt| CString::new(*opt).unwrap())
+            .collect();
+        let options: Vec<_> = strings.iter()
+            .map(|opt| JavaVMOption::new(opt.as_ptr() as *mut _, std::ptr::null_mut()))
+            .collect();
+        let ptrs = |args: &JavaVMInitArgs| {
+            // SAFETY: The options are owned by the args, and the strings outlive them
+            unsafe { args.options() }.iter().map(|opt| opt.option_string()).collect::<Vec<_>>()
+        };
+
+        let mut args = JavaVMInitArgs::new(constants::JNI_VERSION_1_8);
+        // SAFETY: The strings outlive the args
+        unsafe { args.set_options(&options[..2]) };
+        drop(options);
+
+        // Growing and shrinking the copied array leaves the passed one alone
+        args.add_option(JavaVMOption::new(strings[2].as_ptr() as *mut _, std::ptr::null_mut()));
+        assert_eq!(ptrs(&args), strings.iter().map(|opt| opt.as_ptr()).collect::<Vec<_>>());
+        args.remove_option(1).unwrap();
+        assert_eq!(ptrs(&args), vec![strings[0].as_ptr(), strings[2].as_ptr()]);
+        assert!(args.remove_option(2).is_err());
+        args.remove_option(0).unwrap();
+        args.remove_option(0).unwrap();
+        assert!(ptrs(&args).is_empty());
+        args.add_option(JavaVMOption::new(strings[0].as_ptr() as *mut _, std::ptr::null_mut()));
+        assert_eq!(ptrs(&args), vec![strings[0].as_ptr()]);
+    }
+}
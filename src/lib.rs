@@ -23,11 +23,30 @@
 //! }
 //! ```
 //!
+//! # Features
+//!
+//! The convenience layers over the core call and field APIs are behind default-on features, each
+//! of which can be disabled on its own:
+//!
+//! - `arrays-ext`: Array helpers, such as narrowing objects to native arrays
+//! - `strings-ext`: Raw UTF-16 strings, interning, and char array conversions
+//! - `collections`: Helpers for Java collection and container types
+//! - `reflect`: Class tokens and accessibility of reflected members
+//! - `buffers`: I/O interop, such as file descriptor conversion
+//!
+//! Building with `--no-default-features` gives the minimal core API.
+//!
+//! The `classgen` feature, off by default, exposes [classgen], a minimal class file writer for
+//! generating classes to define in tests.
+//!
 //! # Soundness
 //!
 //! - `#[java]` functions must take all types by value, with non-static lifetimes. Why? The pointers
 //!   passed are local, and thus are guaranteed to live for the length of that call, but not any
 //!   longer. (Or until [env::JNIEnv::delete_local_ref] is called, which is why it's unsafe)
+//! - [env::JNIEnv] carries the same call-scope lifetime as the object arguments, and every object
+//!   it creates is bounded by that lifetime. Objects created during a call thus can't escape the
+//!   native frame whose locals they belong to.
 //! - Casts transmute safely because all backing pointers are the same, the JVM just calls them
 //!   different things for type safety (And the casts done are either checked or verified safe)
 //! - [types::JObject::borrow_ptr] is unsafe because external systems may break the pointer
@@ -37,7 +56,8 @@
 //! Macro Rules:
 //! - Output function will have a lifetime param 'a which is used for object arguments,
 //!   forbidding them from outliving the function
-//! - Must take environment reference as first param
+//! - Must take environment reference as first param, constructed with the same lifetime 'a as
+//!   the object arguments (`&JNIEnv<'a>`)
 //! - Arguments and returns must not be aliased
 //! - Possible Arguments:
 //!   - `JBoolean-JDouble`: Takes the native type
@@ -52,15 +72,13 @@
 #![allow(dead_code)]
 
 #![warn(missing_docs)]
-
-#![feature(c_variadic)]
-#![feature(never_type)]
-#![feature(alloc_layout_extra)]
+#![deny(unstable_features)]
 
 // Private modules
 
 #[cfg(test)]
 mod tests;
+mod mutf8;
 
 // Public modules
 
@@ -72,11 +90,23 @@ pub mod env;
 pub mod types;
 pub mod mangling;
 pub mod macros;
+pub mod panic_bridge;
+#[cfg(feature = "migration")]
+pub mod migration;
+#[cfg(any(test, feature = "classgen"))]
+pub mod classgen;
+#[cfg(not(any(test, feature = "classgen")))]
+mod classgen;
 
 // Public re-exports
 
 pub use error::{Error, Result};
 
 pub use types::*;
-pub use vm::JavaVM;
-pub use env::JNIEnv;
+pub use vm::{JavaVM, JvmOptions, AttachGuard};
+#[cfg(feature = "audit")]
+pub use vm::EnvCacheStats;
+pub use env::{JNIEnv, LocalRefStats, MethodCall, IntoJava, FromJava, FieldBatch, CachedCall, MonitorGuard, LocalFrame, AutoLocal};
+#[cfg(feature = "arrays-ext")]
+pub use env::ArrayElements;
+pub use panic_bridge::{install_panic_bridge, catch_panic};
@@ -16,8 +16,9 @@ macro_rules! get_cls {
             static mut CLS: Option<JClass<'static>> = None;
             unsafe {
                 if let None = CLS {
-                    let cls = $env.new_global_ref(&$env.find_class($cls).expect(&format!("Couldn't find class {}", $cls)).downcast()).unwrap().upcast_raw();
+                    let cls = $env.new_global_ref(&$env.find_class($cls).expect(&format!("Couldn't find class {}", $cls)).downcast()).unwrap().leak().upcast_raw();
                     CLS = Some(cls);
+                    $env.track_cached_class(std::ptr::addr_of_mut!(CLS));
                 }
                 CLS.as_ref().unwrap()
             }
@@ -36,7 +37,8 @@ macro_rules! get_method_id {
             unsafe {
                 if let None = ID {
                     let id = $env.get_method_id($cls, $name, $sig).expect(&format!("Couldn't find method {} with signature {}", $name, $sig));
-                    ID = Some(id)
+                    ID = Some(id);
+                    $env.track_cached_id(std::ptr::addr_of_mut!(ID));
                 }
                 ID.as_ref().unwrap()
             }
@@ -55,7 +57,8 @@ macro_rules! get_static_method_id {
             unsafe {
                 if let None = ID {
                     let id = $env.get_static_method_id($cls, $name, $sig).expect(&format!("Couldn't find method {} with signature {}", $name, $sig));
-                    ID = Some(id)
+                    ID = Some(id);
+                    $env.track_cached_id(std::ptr::addr_of_mut!(ID));
                 }
                 ID.as_ref().unwrap()
             }
@@ -74,7 +77,8 @@ macro_rules! get_field_id {
             unsafe {
                 if let None = ID {
                     let id = $env.get_field_id($cls, $name, $ty).expect(&format!("Couldn't find method {} with signature {}", $name, $ty));
-                    ID = Some(id)
+                    ID = Some(id);
+                    $env.track_cached_id(std::ptr::addr_of_mut!(ID));
                 }
                 ID.as_ref().unwrap()
             }
@@ -93,10 +97,73 @@ macro_rules! get_static_field_id {
             unsafe {
                 if let None = ID {
                     let id = $env.get_static_field_id($cls, $name, $ty).expect(&format!("Couldn't find method {} with signature {}", $name, $ty));
-                    ID = Some(id)
+                    ID = Some(id);
+                    $env.track_cached_id(std::ptr::addr_of_mut!(ID));
                 }
                 ID.as_ref().unwrap()
             }
         }
     }
 }
+
+
+/// Define the `JNI_OnLoad` entry point of a native library. The passed function is called with the
+/// loading [JavaVM][crate::vm::JavaVM], and returns the JNI version the library needs. An Err is
+/// reported to the JVM as the error's [JNI code][crate::error::Error::jni_code]
+#[macro_export]
+macro_rules! jni_on_load {
+    ($func:path) => {
+        #[no_mangle]
+        pub extern "system" fn JNI_OnLoad(vm: *mut $crate::ffi::JavaVM, _reserved: *mut std::ffi::c_void) -> $crate::ffi::JInt {
+            let result = $crate::vm::JavaVM::new($crate::types::JNIVersion::Ver16, vm, false)
+                .and_then(|vm| $func(&vm));
+
+            match result {
+                Ok(version) => version.into(),
+                Err(err) => (&err).into()
+            }
+        }
+    }
+}
+
+
+/// Define the `JNI_OnUnload` entry point of a native library, the counterpart of
+/// [jni_on_load][crate::jni_on_load]. The passed function or closure is called with the unloading
+/// [JavaVM][crate::vm::JavaVM] to release the library's own state, then the natives registered
+/// through [register_natives][crate::JNIEnv::register_natives] are unregistered, the global
+/// references cached by this crate deleted and the caching macros' caches emptied, as by
+/// [release_library_refs][crate::JNIEnv::release_library_refs]. Errors can't be reported from
+/// `JNI_OnUnload`, so they're ignored
+///
+/// ```no_run
+/// use rust_jni::{jni_on_load, jni_on_unload, JavaVM, JNIVersion, Result};
+///
+/// fn on_load(vm: &JavaVM) -> Result<JNIVersion> {
+///     let env = vm.get_local_env()?;
+///     env.find_class("java.lang.String")?;
+///     Ok(JNIVersion::Ver18)
+/// }
+///
+/// jni_on_load!(on_load);
+/// jni_on_unload!(|_vm: &JavaVM| {
+///     // Release state the library keeps outside the JVM
+/// });
+/// ```
+#[macro_export]
+macro_rules! jni_on_unload {
+    ($func:expr) => {
+        #[no_mangle]
+        pub extern "system" fn JNI_OnUnload(vm: *mut $crate::ffi::JavaVM, _reserved: *mut std::ffi::c_void) {
+            let vm = match $crate::vm::JavaVM::new($crate::types::JNIVersion::Ver16, vm, false) {
+                Ok(vm) => vm,
+                Err(_) => return
+            };
+            ($func)(&vm);
+
+            if let Ok(env) = vm.get_local_env() {
+                // SAFETY: The library is being unloaded, so no other thread calls into it
+                let _ = unsafe { env.release_library_refs() };
+            }
+        }
+    }
+}
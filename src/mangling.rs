@@ -4,6 +4,8 @@
 //!
 
 use crate::types::JType;
+use crate::error::{Error, Result};
+use crate::ffi::constants::JNI_ERR;
 
 ///
 /// An enum representing a JNI type signature
@@ -169,44 +171,202 @@ fn primitive_symbol(name: &str) -> &str {
 }
 
 
-fn handle_args(args: &str) -> Vec<TypeSignature> {
-    if args.len() > 2 {
-        (&args[1..(args.len() - 1)]).split(",").map(&mangle_class).collect()
+fn handle_args(args: &str) -> std::result::Result<Vec<TypeSignature>, String> {
+    let args = args.trim();
+    let inner = args
+        .strip_prefix("(")
+        .and_then(|args| args.strip_suffix(")"))
+        .ok_or_else(|| String::from("Invalid method arguments to mangle"))?
+        .trim();
+
+    if inner.is_empty() {
+        Ok(Vec::new())
     } else {
-        Vec::new()
+        inner
+            .split(",")
+            .map(|arg| {
+                let arg = arg.trim();
+                validate_arg(arg, args)?;
+                try_mangle_class(arg)
+            })
+            .collect()
     }
 }
 
-/// Take a 'pretty' type signature and convert it into a TypeSignature object
+/// Check a single method argument names a valid type, returning Err naming the offending argument
+/// if not
+fn validate_arg(arg: &str, args: &str) -> std::result::Result<(), String> {
+    let mut base = arg;
+    while let Some(inner) = base.strip_suffix("[]") {
+        base = inner.trim_end();
+    }
+
+    let valid = !base.is_empty() &&
+        base != "void" &&
+        base.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$' || c == '.');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid method argument `{}` in {}", arg, args))
+    }
+}
+
+/// Take a 'pretty' type signature and convert it into a TypeSignature object. Panics if the
+/// signature is malformed
 pub fn mangle_class(name: &str) -> TypeSignature {
+    try_mangle_class(name).unwrap_or_else(|msg| panic!("{}", msg))
+}
+
+/// Take a 'pretty' type signature and convert it into a TypeSignature object, returning Err
+/// describing the problem if it's malformed
+fn try_mangle_class(name: &str) -> std::result::Result<TypeSignature, String> {
     let name = name.trim();
 
     if is_primitive(name) {
-        TypeSignature::Primitive(String::from(name))
+        Ok(TypeSignature::Primitive(String::from(name)))
     } else if name.starts_with("(") {
         if let Some(pos) = name.find("->") {
             let (args, ret) = name.split_at(pos);
             let args = args.trim();
             let ret = ret.trim();
-            let args = handle_args(args);
+            let args = handle_args(args)?;
 
-            let ret = mangle_class(&ret[2..]);
+            let ret = try_mangle_class(&ret[2..])?;
 
-            TypeSignature::Method(args, Box::new(ret))
+            Ok(TypeSignature::Method(args, Box::new(ret)))
         } else {
-            panic!("Invalid class to mangle")
+            Err(String::from("Invalid class to mangle"))
         }
     } else if name.ends_with("[]") {
-        TypeSignature::Array(
+        Ok(TypeSignature::Array(
             Box::new(
-                mangle_class(&name[..(name.len() - 2)])
+                try_mangle_class(&name[..(name.len() - 2)])?
             )
-        )
+        ))
+    } else {
+        Ok(TypeSignature::Class(String::from(name)))
+    }
+}
+
+/// Parse a single mangled type from the start of a signature, returning it and the rest of the
+/// signature
+fn demangle_one(sig: &str) -> Option<(TypeSignature, &str)> {
+    let mut chars = sig.chars();
+    let name = match chars.next()? {
+        'Z' => "boolean",
+        'B' => "byte",
+        'C' => "char",
+        'S' => "short",
+        'I' => "int",
+        'J' => "long",
+        'F' => "float",
+        'D' => "double",
+        'V' => "void",
+        'L' => {
+            let rest = chars.as_str();
+            let end = rest.find(';')?;
+            if end == 0 {
+                return None
+            }
+            return Some((TypeSignature::Class(rest[..end].replace("/", ".")), &rest[end + 1..]))
+        }
+        '[' => {
+            let (inner, rest) = demangle_one(chars.as_str())?;
+            if is_void(&inner) {
+                return None
+            }
+            return Some((TypeSignature::Array(Box::new(inner)), rest))
+        }
+        _ => return None
+    };
+
+    Some((TypeSignature::Primitive(String::from(name)), chars.as_str()))
+}
+
+fn is_void(sig: &TypeSignature) -> bool {
+    match sig {
+        TypeSignature::Primitive(name) => name == "void",
+        _ => false
+    }
+}
+
+/// Parse a mangled type signature, such as `(ILjava/lang/String;)V`, into a TypeSignature. The
+/// opposite of [TypeSignature::mangled]. Returns None if the signature is malformed
+pub fn demangle_signature(sig: &str) -> Option<TypeSignature> {
+    let sig = sig.trim();
+
+    if let Some(rest) = sig.strip_prefix("(") {
+        let end = rest.find(')')?;
+        let mut params = &rest[..end];
+        let mut args = Vec::new();
+        while !params.is_empty() {
+            let (arg, rest) = demangle_one(params)?;
+            if is_void(&arg) {
+                return None
+            }
+            args.push(arg);
+            params = rest;
+        }
+
+        match demangle_one(&rest[end + 1..])? {
+            (ret, "") => Some(TypeSignature::Method(args, Box::new(ret))),
+            _ => None
+        }
     } else {
-        TypeSignature::Class(String::from(name))
+        match demangle_one(sig)? {
+            (ty, "") => Some(ty),
+            _ => None
+        }
     }
 }
 
+/// Escape a class or method name for use in a JNI exported symbol name. ASCII letters and digits
+/// are kept, package separators (`.` or `/`) become `_`, and `_`, `;` and `[` become `_1`, `_2`
+/// and `_3`. Any other character becomes `_0` followed by each of its UTF-16 code units in
+/// lowercase hex, such as `_00024` for `$`
+pub fn escape_symbol_component(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => out.push(c),
+            '.' | '/' => out.push('_'),
+            '_' => out.push_str("_1"),
+            ';' => out.push_str("_2"),
+            '[' => out.push_str("_3"),
+            _ => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    out.push_str(&format!("_0{:04x}", unit));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Get the symbol name the JVM looks up for a native method. The class is a fully qualified name
+/// such as `java.lang.Object`. If the method is overloaded, pass its signature in the syntax
+/// defined in the root documentation, and its argument types are appended to the name as the JVM
+/// expects for overloads. Returns Err if the signature is malformed or isn't a method signature
+pub fn jni_export_name(class: &str, method: &str, overload_sig: Option<&str>) -> Result<String> {
+    let mut out = format!("Java_{}_{}", escape_symbol_component(class), escape_symbol_component(method));
+
+    if let Some(sig) = overload_sig {
+        let args = match try_mangle_class(sig) {
+            Ok(TypeSignature::Method(args, _)) => args,
+            Ok(_) => return Err(Error::new(&format!("Overload signature `{}` isn't a method signature", sig), JNI_ERR)),
+            Err(msg) => return Err(Error::new(&msg, JNI_ERR))
+        };
+        let args: String = args.iter().map(|arg| arg.mangled()).collect();
+
+        out.push_str("__");
+        out.push_str(&escape_symbol_component(&args));
+    }
+
+    Ok(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,4 +382,126 @@ mod tests {
         assert_eq!(mangle_class("() -> int").mangled(), "()I");
     }
 
+    #[test]
+    fn test_mangle_whitespace() {
+        assert_eq!(mangle_class("()->int").mangled(), "()I");
+        assert_eq!(mangle_class("(  int  ,  long ) -> void").mangled(), "(IJ)V");
+        assert_eq!(mangle_class("() -> void").mangled(), "()V");
+        assert_eq!(mangle_class("( ) -> int").mangled(), "()I");
+        assert_eq!(mangle_class("  (java.lang.String)->  int[]  ").mangled(), "(Ljava/lang/String;)[I");
+    }
+
+    #[test]
+    fn test_mangle_array_args() {
+        assert_eq!(mangle_class("(java.lang.String[], int[][], long) -> boolean").mangled(), "([Ljava/lang/String;[[IJ)Z");
+        assert_eq!(mangle_class("( java.lang.Object[][] ,int[] ) -> java.lang.String[]").mangled(), "([[Ljava/lang/Object;[I)[Ljava/lang/String;");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid method argument")]
+    fn test_mangle_empty_arg() {
+        mangle_class("(int, , long) -> void");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid method argument")]
+    fn test_mangle_void_arg() {
+        mangle_class("(void) -> int");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid method argument")]
+    fn test_mangle_generic_arg() {
+        mangle_class("(java.util.Map<java.lang.String, int>) -> void");
+    }
+
+    #[test]
+    fn test_demangle() {
+        let pretty = |sig| demangle_signature(sig).map(|sig| sig.pretty());
+        assert_eq!(pretty("I").unwrap(), "int");
+        assert_eq!(pretty("[[Ljava/lang/String;").unwrap(), "java.lang.String[][]");
+        assert_eq!(pretty("()V").unwrap(), "() -> void");
+        assert_eq!(pretty("(I[JLjava/util/ArrayList;)Ljava/lang/String;").unwrap(), "(int, long[], java.util.ArrayList) -> java.lang.String");
+
+        for sig in ["(Ljava/lang/Object;I)Ljava/lang/String;", "([[Ljava/lang/Object;[I)[Ljava/lang/String;", "Z"] {
+            assert_eq!(mangle_class(&pretty(sig).unwrap()).mangled(), sig);
+        }
+
+        for sig in ["", "Q", "(I", "()", "(V)I", "[V", "L;", "Ljava/lang/String", "II", "()VI"] {
+            assert!(demangle_signature(sig).is_none(), "`{}` shouldn't parse", sig);
+        }
+    }
+
+    #[test]
+    fn test_escape_symbol_component() {
+        assert_eq!(escape_symbol_component("java.lang.String"), "java_lang_String");
+        assert_eq!(escape_symbol_component("java/lang/String"), "java_lang_String");
+        assert_eq!(escape_symbol_component("my_method"), "my_1method");
+        assert_eq!(escape_symbol_component("Ljava/lang/String;"), "Ljava_lang_String_2");
+        assert_eq!(escape_symbol_component("[I"), "_3I");
+        assert_eq!(escape_symbol_component("Outer$Inner"), "Outer_00024Inner");
+        assert_eq!(escape_symbol_component("caf\u{e9}"), "caf_000e9");
+        assert_eq!(escape_symbol_component("\u{1F600}"), "_0d83d_0de00");
+        assert_eq!(escape_symbol_component(""), "");
+    }
+
+    #[test]
+    fn test_jni_export_name() {
+        // The examples given by the JNI specification
+        assert_eq!(jni_export_name("pkg.Cls", "f", None).unwrap(), "Java_pkg_Cls_f");
+        assert_eq!(
+            jni_export_name("pkg.Cls", "f", Some("(int, java.lang.String) -> double")).unwrap(),
+            "Java_pkg_Cls_f__ILjava_lang_String_2"
+        );
+
+        assert_eq!(jni_export_name("com.example.My_Class", "do_it", None).unwrap(), "Java_com_example_My_1Class_do_1it");
+        assert_eq!(jni_export_name("Outer$Inner", "run", Some("() -> void")).unwrap(), "Java_Outer_00024Inner_run__");
+        assert_eq!(
+            jni_export_name("a.B", "g", Some("(int[], java.lang.String[][]) -> void")).unwrap(),
+            "Java_a_B_g___3I_3_3Ljava_lang_String_2"
+        );
+
+        // Field signatures and malformed signatures are an Err rather than a panic
+        assert!(jni_export_name("a.B", "g", Some("int")).is_err());
+        assert!(jni_export_name("a.B", "g", Some("(int, , long) -> void")).is_err());
+        assert!(jni_export_name("a.B", "g", Some("(int")).is_err());
+        assert!(jni_export_name("a.B", "g", Some("int) -> void")).is_err());
+    }
+
+    #[test]
+    fn test_escape_injective() {
+        use std::collections::HashMap;
+
+        // Every name up to 4 characters from a mix of kept, escaped and separator characters. As
+        // in Java names, package components never start with a digit, which is what makes the
+        // escapes unambiguous
+        let alphabet = ['a', '1', '0', '_', ';', '[', '.', '\u{e9}'];
+        let mut names = vec![String::new()];
+        let mut seen = HashMap::new();
+        for _ in 0..4 {
+            let mut next = Vec::new();
+            for name in &names {
+                for &c in &alphabet {
+                    let mut name = name.clone();
+                    name.push(c);
+                    next.push(name);
+                }
+            }
+            names = next;
+
+            for name in &names {
+                let valid = !name.chars().zip(name.chars().skip(1))
+                    .any(|(a, b)| a == '.' && b.is_ascii_digit());
+                if !valid {
+                    continue
+                }
+
+                let escaped = escape_symbol_component(name);
+                if let Some(other) = seen.insert(escaped.clone(), name.clone()) {
+                    panic!("`{}` and `{}` both escape to `{}`", other, name, escaped);
+                }
+            }
+        }
+    }
+
 }
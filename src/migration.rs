@@ -0,0 +1,394 @@
+//!
+//! Module containing a transition aid for code written against the `jni` crate. [JniCompat] wraps
+//! a [JNIEnv] and provides the most used `jni` method names with their argument styles, taking
+//! JNI descriptors such as `(I)Ljava/lang/String;` and class names in slash form, then delegating
+//! to the equivalent calls of this crate. Anything it doesn't cover is reached through `Deref` to
+//! the wrapped environment.
+//!
+//! The names live on a wrapper rather than an extension trait of [JNIEnv], as several of them,
+//! such as `call_method` and `new_string`, are already inherent methods with different arguments,
+//! and inherent methods always take precedence over trait methods.
+//!
+//! Return values differ from `jni` where this crate's types do: method calls give None for void,
+//! and object values are None for null. The [ValueCompat] trait provides `jni`'s short accessors
+//! such as `.i()` and `.l()` on both. New code should use the [JNIEnv] API directly.
+//!
+
+use std::ops::Deref;
+
+use crate::{JNativeArray, JNativeType, JNativeVec};
+use crate::ffi::constants::JNI_ERR;
+use crate::types::{JValue, JObject, JClass, JString, JArray, JByteArray, JObjectArray, JMethodID, JFieldID, JavaDownCast, JavaUpCast};
+use crate::error::{Error, Result};
+use crate::env::JNIEnv;
+use crate::mangling::demangle_signature;
+
+/// Convert a JNI descriptor into the signature syntax defined in the root documentation
+fn pretty_signature(desc: &str) -> Result<String> {
+    demangle_signature(desc)
+        .map(|sig| sig.pretty())
+        .ok_or_else(|| Error::new(&format!("Invalid JNI descriptor {}", desc), JNI_ERR))
+}
+
+///
+/// A class argument in the style of `jni`, either a class name or an already loaded class. Names
+/// may use slashes or dots as the package separator, and arrays may be given as descriptors.
+///
+pub trait ClassDesc<'env> {
+    /// Get the class this describes, as a new local reference the caller is responsible for
+    fn lookup(self, env: &JNIEnv<'env>) -> Result<JClass<'env>>;
+}
+
+impl<'env> ClassDesc<'env> for &str {
+    fn lookup(self, env: &JNIEnv<'env>) -> Result<JClass<'env>> {
+        if self.starts_with('[') {
+            env.find_class(&pretty_signature(self)?)
+        } else {
+            env.find_class(&self.replace("/", "."))
+        }
+    }
+}
+
+impl<'env> ClassDesc<'env> for &JClass<'env> {
+    fn lookup(self, env: &JNIEnv<'env>) -> Result<JClass<'env>> {
+        let cls = env.new_local_ref(self.downcast())?;
+        // SAFETY: Guaranteed safe upcast, the reference is to the same class
+        Ok(unsafe { cls.upcast_raw() })
+    }
+}
+
+///
+/// The short value accessors of `jni`, for the results of calls and field reads. Each returns Err
+/// if the value is of a different type, or for a void result.
+///
+pub trait ValueCompat<'env>: Sized {
+    /// Get the value, or Err if there is none
+    fn value(self) -> Result<JValue<'env>>;
+
+    /// Check the result is void
+    fn v(self) -> Result<()>;
+
+    /// Get an object value, None if null
+    fn l(self) -> Result<Option<JObject<'env>>> {
+        self.value()?.into_obj()
+    }
+
+    /// Get a boolean value
+    fn z(self) -> Result<bool> {
+        self.value()?.into_bool()
+    }
+
+    /// Get a byte value
+    fn b(self) -> Result<i8> {
+        self.value()?.into_byte()
+    }
+
+    /// Get a char value
+    fn c(self) -> Result<char> {
+        self.value()?.into_char()
+    }
+
+    /// Get a short value
+    fn s(self) -> Result<i16> {
+        self.value()?.into_short()
+    }
+
+    /// Get an int value
+    fn i(self) -> Result<i32> {
+        self.value()?.into_int()
+    }
+
+    /// Get a long value
+    fn j(self) -> Result<i64> {
+        self.value()?.into_long()
+    }
+
+    /// Get a float value
+    fn f(self) -> Result<f32> {
+        self.value()?.into_float()
+    }
+
+    /// Get a double value
+    fn d(self) -> Result<f64> {
+        self.value()?.into_double()
+    }
+}
+
+impl<'env> ValueCompat<'env> for JValue<'env> {
+    fn value(self) -> Result<JValue<'env>> {
+        Ok(self)
+    }
+
+    fn v(self) -> Result<()> {
+        Err(Error::new("JValue isn't void", JNI_ERR))
+    }
+}
+
+impl<'env> ValueCompat<'env> for Option<JValue<'env>> {
+    fn value(self) -> Result<JValue<'env>> {
+        self.ok_or_else(|| Error::new("Method returned void", JNI_ERR))
+    }
+
+    fn v(self) -> Result<()> {
+        match self {
+            Some(_) => Err(Error::new("Method didn't return void", JNI_ERR)),
+            None => Ok(())
+        }
+    }
+}
+
+///
+/// A wrapper of [JNIEnv] providing the most used methods of the `jni` crate's environment, under
+/// the same names and with the same argument styles. Methods not provided here are reached
+/// through `Deref`.
+///
+pub struct JniCompat<'a, 'env> {
+    env: &'a JNIEnv<'env>
+}
+
+impl<'a, 'env> JniCompat<'a, 'env> {
+
+    /// Wrap an environment
+    pub fn new(env: &'a JNIEnv<'env>) -> JniCompat<'a, 'env> {
+        JniCompat {
+            env
+        }
+    }
+
+    /// Find a class by name, with slashes or dots as the package separator
+    pub fn find_class(&self, name: &str) -> Result<JClass<'env>> {
+        name.lookup(self.env)
+    }
+
+    /// Run a closure with the class a [ClassDesc] describes, deleting the looked up reference after
+    fn with_class<C, F, T>(&self, cls: C, f: F) -> Result<T>
+        where
+            C: ClassDesc<'env>,
+            F: FnOnce(&JClass<'env>) -> Result<T>
+    {
+        let cls = cls.lookup(self.env)?;
+        let result = f(&cls);
+        self.env.delete_local_ref(cls.downcast());
+        result
+    }
+
+    /// Create a new Java string from a rust string
+    pub fn new_string<S: AsRef<str>>(&self, str: S) -> Result<JString<'env>> {
+        let units: Vec<u16> = str.as_ref().encode_utf16().collect();
+        self.env.new_string_utf16(&units)
+    }
+
+    /// Read a Java string into a rust string, replacing unpaired surrogates
+    pub fn get_string(&self, str: &JString) -> Result<String> {
+        self.env.get_string_lossy(str)
+    }
+
+    /// Create a new byte array holding a copy of a slice
+    pub fn byte_array_from_slice(&self, bytes: &[u8]) -> Result<JByteArray<'env>> {
+        let arr = self.env.new_native_array(bytes.len(), JNativeType::Byte)?;
+        let vals = bytes.iter().map(|byte| *byte as i8).collect();
+        self.env.set_native_array_region(&arr, 0, bytes.len(), &JNativeVec::Byte(vals))?;
+
+        match arr {
+            JNativeArray::Byte(arr) => Ok(arr),
+            _ => unreachable!()
+        }
+    }
+
+    /// Copy the contents of a byte array into a [Vec]
+    pub fn convert_byte_array(&self, arr: &JByteArray) -> Result<Vec<u8>> {
+        let arr_ref: &JArray = arr.downcast();
+        let mut out = vec![0; self.env.get_array_length(arr_ref)];
+        self.env.read_byte_region_into(arr, 0, &mut out)?;
+        Ok(out.into_iter().map(|byte| byte as u8).collect())
+    }
+
+    /// Get the ID of a method from its name and JNI descriptor
+    pub fn get_method_id<C: ClassDesc<'env>>(&self, cls: C, name: &str, desc: &str) -> Result<JMethodID> {
+        self.with_class(cls, |cls| {
+            self.env.get_method_id(cls, name, &pretty_signature(desc)?)
+        })
+    }
+
+    /// Get the ID of a static method from its name and JNI descriptor
+    pub fn get_static_method_id<C: ClassDesc<'env>>(&self, cls: C, name: &str, desc: &str) -> Result<JMethodID> {
+        self.with_class(cls, |cls| {
+            self.env.get_static_method_id(cls, name, &pretty_signature(desc)?)
+        })
+    }
+
+    /// Get the ID of a field from its name and JNI descriptor
+    pub fn get_field_id<C: ClassDesc<'env>>(&self, cls: C, name: &str, desc: &str) -> Result<JFieldID> {
+        self.with_class(cls, |cls| {
+            self.env.get_field_id(cls, name, &pretty_signature(desc)?)
+        })
+    }
+
+    /// Get the ID of a static field from its name and JNI descriptor
+    pub fn get_static_field_id<C: ClassDesc<'env>>(&self, cls: C, name: &str, desc: &str) -> Result<JFieldID> {
+        self.with_class(cls, |cls| {
+            self.env.get_static_field_id(cls, name, &pretty_signature(desc)?)
+        })
+    }
+
+    /// Call a method on an object by name and JNI descriptor. None if the method is void
+    pub fn call_method(&self, obj: &JObject, name: &str, desc: &str, args: &[JValue]) -> Result<Option<JValue<'env>>> {
+        let sig = pretty_signature(desc)?;
+        let cls = self.env.get_object_class(obj)?;
+        let id = self.env.get_method_id(&cls, name, &sig);
+        self.env.delete_local_ref(cls.downcast());
+        let id = id?;
+        self.env.call_method(obj, &id, args)
+    }
+
+    /// Call a static method by name and JNI descriptor. None if the method is void
+    pub fn call_static_method<C: ClassDesc<'env>>(&self, cls: C, name: &str, desc: &str, args: &[JValue]) -> Result<Option<JValue<'env>>> {
+        self.with_class(cls, |cls| {
+            let id = self.env.get_static_method_id(cls, name, &pretty_signature(desc)?)?;
+            self.env.call_static_method(cls, &id, args)
+        })
+    }
+
+    /// Construct an object with the constructor of the given JNI descriptor
+    pub fn new_object<C: ClassDesc<'env>>(&self, cls: C, ctor_desc: &str, args: &[JValue]) -> Result<JObject<'env>> {
+        self.with_class(cls, |cls| {
+            let id = self.env.get_method_id(cls, "<init>", &pretty_signature(ctor_desc)?)?;
+            self.env.new_object(cls, &id, args)
+        })
+    }
+
+    /// Get the value of a field of an object by name and JNI descriptor
+    pub fn get_field(&self, obj: &JObject, name: &str, desc: &str) -> Result<JValue<'env>> {
+        let sig = pretty_signature(desc)?;
+        let cls = self.env.get_object_class(obj)?;
+        let id = self.env.get_field_id(&cls, name, &sig);
+        self.env.delete_local_ref(cls.downcast());
+        let id = id?;
+        self.env.get_field(obj, &id)
+    }
+
+    /// Set the value of a field of an object by name and JNI descriptor
+    pub fn set_field(&self, obj: &JObject, name: &str, desc: &str, val: JValue) -> Result<()> {
+        let sig = pretty_signature(desc)?;
+        let cls = self.env.get_object_class(obj)?;
+        let id = self.env.get_field_id(&cls, name, &sig);
+        self.env.delete_local_ref(cls.downcast());
+        let id = id?;
+        self.env.set_field(obj, &id, val)
+    }
+
+    /// Get the value of a static field by name and JNI descriptor
+    pub fn get_static_field<C: ClassDesc<'env>>(&self, cls: C, name: &str, desc: &str) -> Result<JValue<'env>> {
+        self.with_class(cls, |cls| {
+            let id = self.env.get_static_field_id(cls, name, &pretty_signature(desc)?)?;
+            self.env.get_static_field(cls, &id)
+        })
+    }
+
+    /// Set the value of a static field by name and JNI descriptor
+    pub fn set_static_field<C: ClassDesc<'env>>(&self, cls: C, name: &str, desc: &str, val: JValue) -> Result<()> {
+        self.with_class(cls, |cls| {
+            let id = self.env.get_static_field_id(cls, name, &pretty_signature(desc)?)?;
+            self.env.set_static_field(cls, &id, val)
+        })
+    }
+
+    /// Throw a new exception of the given class with a message
+    pub fn throw_new<C: ClassDesc<'env>>(&self, cls: C, msg: &str) -> Result<()> {
+        self.with_class(cls, |cls| {
+            self.env.throw_new(cls, msg)
+        })
+    }
+
+    /// Check whether an object is an instance of a class
+    pub fn is_instance_of<C: ClassDesc<'env>>(&self, obj: &JObject, cls: C) -> Result<bool> {
+        self.with_class(cls, |cls| {
+            Ok(self.env.is_instance_of(obj, cls))
+        })
+    }
+
+    /// Create a new object array with every element set to an initial value, or null
+    pub fn new_object_array<C: ClassDesc<'env>>(&self, len: i32, cls: C, init: Option<&JObject>) -> Result<JObjectArray<'env>> {
+        if len < 0 {
+            return Err(Error::new(&format!("Negative array length {}", len), JNI_ERR))
+        }
+        self.with_class(cls, |cls| {
+            self.env.new_object_array(len as usize, cls, init)
+        })
+    }
+}
+
+impl<'a, 'env> Deref for JniCompat<'a, 'env> {
+    type Target = JNIEnv<'env>;
+
+    fn deref(&self) -> &JNIEnv<'env> {
+        self.env
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::with_env;
+
+    /// Helpers written as they would be against the `jni` crate
+    mod jni_style {
+        use super::*;
+
+        pub fn greet<'env>(env: &JniCompat<'_, 'env>, name: &JString) -> Result<JString<'env>> {
+            let name = env.get_string(name)?;
+            env.new_string(format!("Hello, {}!", name))
+        }
+
+        pub fn string_length(env: &JniCompat, str: &JString) -> Result<i32> {
+            env.call_method(str.as_object(), "length", "()I", &[])?.i()
+        }
+
+        pub fn boxed_sum(env: &JniCompat, a: i32, b: i32) -> Result<i32> {
+            let sum = env.call_static_method("java/lang/Integer", "sum", "(II)I", &[JValue::Int(a), JValue::Int(b)])?.i()?;
+            let boxed = env.new_object("java/lang/Integer", "(I)V", &[JValue::Int(sum)])?;
+            if !env.is_instance_of(&boxed, "java/lang/Number")? {
+                return Err(Error::new("Integer isn't a Number", JNI_ERR))
+            }
+            env.call_method(&boxed, "intValue", "()I", &[])?.i()
+        }
+
+        pub fn checksum(env: &JniCompat, bytes: &[u8]) -> Result<u32> {
+            let arr = env.byte_array_from_slice(bytes)?;
+            let back = env.convert_byte_array(&arr)?;
+            Ok(back.iter().map(|byte| *byte as u32).sum())
+        }
+
+        pub fn max_value(env: &JniCompat) -> Result<i32> {
+            env.get_static_field("java/lang/Integer", "MAX_VALUE", "I")?.i()
+        }
+
+        pub fn fail(env: &JniCompat, msg: &str) -> Result<()> {
+            env.throw_new("java/lang/IllegalStateException", msg)
+        }
+    }
+
+    #[test]
+    fn test_jni_style() {
+        with_env(|env| {
+            let env = JniCompat::new(env);
+
+            let name = env.new_string("\u{1F600} world").unwrap();
+            let greeting = jni_style::greet(&env, &name).unwrap();
+            assert_eq!(env.get_string(&greeting).unwrap(), "Hello, \u{1F600} world!");
+            assert_eq!(jni_style::string_length(&env, &name).unwrap(), 8);
+
+            assert_eq!(jni_style::boxed_sum(&env, 40, 2).unwrap(), 42);
+            assert_eq!(jni_style::checksum(&env, &[1, 2, 250]).unwrap(), 253);
+            assert_eq!(jni_style::max_value(&env).unwrap(), i32::MAX);
+
+            let arr = env.new_object_array(2, "[Ljava/lang/String;", None).unwrap();
+            assert_eq!(env.get_array_length((&arr).downcast()), 2);
+            assert!(env.call_method(name.as_object(), "length", "(I", &[]).is_err());
+
+            jni_style::fail(&env, "from jni style").unwrap();
+            assert!(env.exception_check());
+            env.exception_clear().unwrap();
+        })
+    }
+}
@@ -0,0 +1,94 @@
+//!
+//! Module containing conversions to and from modified UTF-8, the encoding used by class files and
+//! by JNI functions taking or returning C strings of arbitrary text. It differs from standard UTF-8
+//! in writing NUL as two bytes, so encoded text never contains a zero byte, and in writing
+//! characters outside the BMP as a pair of three byte surrogates.
+//!
+
+use std::ffi::CString;
+
+use crate::ffi::constants::JNI_ERR;
+use crate::error::{Error, Result};
+
+/// Encode a string as modified UTF-8
+pub(crate) fn modified_utf8(str: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(str.len());
+    for unit in str.encode_utf16() {
+        match unit {
+            0x0001..=0x007F => out.push(unit as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                out.push(0xC0 | (unit >> 6) as u8);
+                out.push(0x80 | (unit & 0x3F) as u8);
+            }
+            _ => {
+                out.push(0xE0 | (unit >> 12) as u8);
+                out.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                out.push(0x80 | (unit & 0x3F) as u8);
+            }
+        }
+    }
+    out
+}
+
+/// Encode a string as a `CString` of modified UTF-8, for JNI functions taking C strings of
+/// arbitrary text. Never fails, as modified UTF-8 encodes NUL without a zero byte
+pub(crate) fn mutf8_from_str(str: &str) -> CString {
+    // SAFETY: Every byte written for a unit is either in 0x01..=0x7F or has its top bit set
+    unsafe { CString::from_vec_unchecked(modified_utf8(str)) }
+}
+
+/// Decode modified UTF-8 from the JNI into the UTF-16 code units it encodes. NUL is only valid as
+/// its two byte form, characters outside the BMP are a pair of three byte surrogates, and every
+/// other unit must use its shortest form, so anything else is an Err naming the offset of the bad
+/// byte
+pub(crate) fn units_from_mutf8(bytes: &[u8]) -> Result<Vec<u16>> {
+    let invalid = |idx: usize| Error::new(&format!("Invalid modified UTF-8 at byte {}", idx), JNI_ERR);
+    let cont = |idx: usize| match bytes.get(idx) {
+        Some(&byte) if byte & 0xC0 == 0x80 => Ok((byte & 0x3F) as u16),
+        _ => Err(invalid(idx))
+    };
+
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let byte = bytes[idx];
+        match byte {
+            0x01..=0x7F => {
+                out.push(byte as u16);
+                idx += 1;
+            }
+            0xC0..=0xDF => {
+                let unit = ((byte & 0x1F) as u16) << 6 | cont(idx + 1)?;
+                if let 0x01..=0x7F = unit {
+                    return Err(invalid(idx))
+                }
+                out.push(unit);
+                idx += 2;
+            }
+            0xE0..=0xEF => {
+                let unit = ((byte & 0x0F) as u16) << 12 | cont(idx + 1)? << 6 | cont(idx + 2)?;
+                if unit < 0x800 {
+                    return Err(invalid(idx))
+                }
+                out.push(unit);
+                idx += 3;
+            }
+            _ => return Err(invalid(idx))
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modified_utf8() {
+        assert_eq!(modified_utf8("abc"), b"abc");
+        assert_eq!(modified_utf8("\0"), b"\xc0\x80");
+        assert_eq!(modified_utf8("\u{e9}"), "\u{e9}".as_bytes());
+        assert_eq!(modified_utf8("\u{1F600}"), b"\xed\xa0\xbd\xed\xb8\x80");
+        assert_eq!(mutf8_from_str("a\0b").as_bytes(), b"a\xc0\x80b");
+    }
+}
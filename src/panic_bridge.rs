@@ -0,0 +1,154 @@
+//!
+//! Module containing the translation of rust panics into Java exceptions, for code called by the
+//! JVM. Panics can't unwind across the JNI boundary, so every entry point from Java catches them
+//! with [catch_panic], which throws a `RuntimeException` in their place.
+//!
+
+use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+use crate::env::JNIEnv;
+
+/// Message used when neither the bridge nor the panic payload says what happened
+const UNKNOWN_PANIC: &str = "panic occurred";
+
+static INSTALL: Once = Once::new();
+
+thread_local! {
+    /// The message and backtrace of the latest panic on this thread, captured by the bridge hook
+    static LAST_PANIC: RefCell<Option<(String, Backtrace)>> = RefCell::new(None);
+}
+
+/// Install a process-wide panic hook, which records the message and backtrace of each panic so
+/// [catch_panic] can include them in the exception it throws. Backtraces follow the usual
+/// `RUST_BACKTRACE` setting. The existing hook still runs afterwards. Should be called once from
+/// library load or initialization, further calls do nothing
+pub fn install_panic_bridge() {
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let msg = match info.location() {
+                Some(loc) => format!("{} at {}", payload_message(info.payload()), loc),
+                None => payload_message(info.payload())
+            };
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some((msg, Backtrace::capture())));
+            previous(info)
+        }));
+    });
+}
+
+/// Get the message of a panic payload, for the common `&str` and `String` payloads
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        String::from(*msg)
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        String::from(UNKNOWN_PANIC)
+    }
+}
+
+/// Describe a panic just caught on this thread. With the bridge installed, this is the message
+/// recorded by the hook, with the rust backtrace appended if one was captured. Otherwise it's
+/// only the message from the payload
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match LAST_PANIC.with(|last| last.borrow_mut().take()) {
+        Some((msg, trace)) if trace.status() == BacktraceStatus::Captured => {
+            format!("{}\nRust backtrace:\n{}", msg, trace)
+        }
+        Some((msg, _)) => msg,
+        None => payload_message(payload)
+    }
+}
+
+/// Run the body of a call from the JVM, catching any panic and throwing it as a Java
+/// `RuntimeException` instead. The exception message describes the panic, as recorded by
+/// [install_panic_bridge] if it was called. On a panic, `default` is returned for the JVM to
+/// ignore. Any exception already pending is replaced
+pub fn catch_panic<R, F>(env: &JNIEnv, default: R, f: F) -> R
+    where
+        F: FnOnce() -> R
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(val) => val,
+        Err(payload) => {
+            let msg = format!("Rust panic: {}", panic_message(&*payload));
+            if env.exception_check() {
+                let _ = env.exception_clear();
+            }
+            let thrown = env.find_class("java.lang.RuntimeException")
+                .and_then(|cls| env.throw_new(&cls, &msg));
+            if thrown.is_err() {
+                // Nothing left to report the panic through, and it can't unwind into the JVM
+                std::process::abort()
+            }
+            default
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::with_env;
+    use crate::classgen::ClassFileBuilder;
+    use crate::{ffi, Error, JNINativeMethod, JInt, JValue};
+
+    extern "system" fn native_panic(env: *mut ffi::JNIEnv, _cls: *mut ffi::JClass, val: JInt) -> JInt {
+        let env = JNIEnv::new(env).expect("Null environment in native call");
+        catch_panic(&env, -1, || {
+            if val > 0 {
+                panic!("Native got bad value {}", val)
+            }
+            val
+        })
+    }
+
+    #[test]
+    fn test_panic_bridge() {
+        // The hook and backtrace setting are process-wide, so both are put back before asserting
+        let backtrace = std::env::var_os("RUST_BACKTRACE");
+        let previous = panic::take_hook();
+        std::env::set_var("RUST_BACKTRACE", "1");
+        install_panic_bridge();
+
+        let mut results = None;
+        with_env(|env| {
+            let bytes = ClassFileBuilder::new("RustJniPanics")
+                .native_method("check", "(I)I", true)
+                .build();
+            let cls = env.define_class_in_system_loader("RustJniPanics", &bytes).unwrap();
+            let method = JNINativeMethod::new::<JInt>("check", "(I)I", native_panic as *mut _);
+            env.register_natives(&cls, &[method]).unwrap();
+            let check = env.get_static_method_id(&cls, "check", "(int) -> int").unwrap();
+
+            let val = env.call_static_method(&cls, &check, &vec![JValue::Int(-3)])
+                .map(|val| val.unwrap().into_int().unwrap());
+            let thrown = env.call_static_method(&cls, &check, &vec![JValue::Int(7)]).map(|_| ());
+            results = Some((val, thrown));
+        });
+
+        // Replacing the bridge means installing it again does nothing, no other test installs it
+        drop(panic::take_hook());
+        panic::set_hook(previous);
+        match backtrace {
+            Some(val) => std::env::set_var("RUST_BACKTRACE", val),
+            None => std::env::remove_var("RUST_BACKTRACE")
+        }
+
+        let (val, thrown) = results.unwrap();
+        assert_eq!(val.unwrap(), -3);
+
+        let (name, msg) = match thrown {
+            Err(Error::Exception(name, Some(msg))) => (name, msg),
+            other => panic!("Expected the thrown exception, got {:?}", other)
+        };
+
+        assert_eq!(name, "java.lang.RuntimeException");
+        assert!(msg.starts_with("Rust panic: Native got bad value 7 at src/panic_bridge.rs"), "{}", msg);
+        assert!(msg.contains("rust_jni::panic_bridge::tests::native_panic"), "{}", msg);
+    }
+}
@@ -1,9 +1,21 @@
 use crate::{JNIEnv, JNIVersion, JavaVM};
 
+/// System property set on the test JVM, to check startup options are passed through
+pub const TEST_PROPERTY: &str = "rust_jni.test";
+
 /// Create and return a static reference to a JVM
 fn create_leak_jvm() -> &'static mut JavaVM {
-    let jvm = match JavaVM::create(JNIVersion::Ver18) {
-        Ok(jvm) => jvm.0,
+    let options = JavaVM::default_init_args(JNIVersion::Ver18)
+        .expect("Couldn't get default JVM args")
+        .option(&format!("-D{}=true", TEST_PROPERTY));
+
+    let jvm = match JavaVM::create_with_options(options) {
+        Ok((jvm, env)) => {
+            // Creation stores the version the JVM really provides, rather than the requested one
+            assert_eq!(jvm.version(), env.get_version().unwrap());
+            assert!(jvm.version() >= JNIVersion::Ver18);
+            jvm
+        }
         Err(e) => panic!("{}", e)
     };
 
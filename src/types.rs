@@ -16,22 +16,22 @@ pub mod native_method;
 // Public re-exports
 
 pub use object::{
-    JMethodID, JFieldID,
-    JObject, JThrowable, JString, JClass, JArray, JObjectArray, JBooleanArray, JByteArray,
+    JMethodID, JFieldID, JGlobalRef, JGlobal,
+    JObject, JThrowable, JString, JWeak, JClass, JArray, JObjectArray, JBooleanArray, JByteArray,
     JCharArray, JShortArray, JIntArray, JLongArray, JFloatArray, JDoubleArray
 };
 
 pub use array::{
-    JNativeArray, JNativeSlice, JNativeVec, ReleaseMode
+    JNativeArray, JArrayAny, JNativeSlice, JNativeVec, ReleaseMode
 };
 
-pub use jtype::{JType, JNonVoidType, JNativeType};
+pub use jtype::{JType, JNonVoidType, JNativeType, ClassKind};
 
-pub use value::JValue;
+pub use value::{JValue, FromJValue, JavaPrimitive, NullPolicy};
 
 pub use version::JNIVersion;
 
-pub use cast::{JavaUpCast, JavaDownCast};
+pub use cast::{JavaUpCast, JavaDownCast, JavaClassType, AsJObjectPtr};
 
 pub use native_method::JNINativeMethod;
 
@@ -3,11 +3,14 @@
 //!
 
 
+use std::convert::TryFrom;
+
 use crate::{ffi, JavaDownCast};
-use crate::error::Result;
+use crate::env::JNIEnv;
+use crate::error::{Error, Result};
 use crate::types::{
-    JBooleanArray, JByteArray, JCharArray, JShortArray, JIntArray, JLongArray, JFloatArray,
-    JDoubleArray, JArray,
+    JObject, JBooleanArray, JByteArray, JCharArray, JShortArray, JIntArray, JLongArray, JFloatArray,
+    JDoubleArray, JArray, JObjectArray, JValue, NullPolicy, AsJObjectPtr,
     JBoolean, JByte, JChar, JShort, JInt, JLong, JFloat, JDouble
 };
 use crate::JNativeType;
@@ -68,6 +71,24 @@ impl JNativeArray<'_> {
         }
     }
 
+    /// Create a new JNativeArray from a backing pointer and array type, checking the type against
+    /// the class of the array. Returns Err if the array isn't of the given type. Unsafe, as we
+    /// trust the pointer is a live array reference
+    pub unsafe fn new_checked<'a>(env: &JNIEnv, arr: *mut ffi::JArray, ty: JNativeType) -> Result<JNativeArray<'a>> {
+        let obj = JObject::new(arr as *mut ffi::JObject)?;
+        let (cls, name) = env.object_class_and_name(&obj)?;
+        env.delete_local_ref(cls.downcast());
+
+        if name != ty.array_descriptor() {
+            return Err(Error::new(
+                &format!("Array of class {} isn't a {:?} array", name, ty),
+                ffi::constants::JNI_ERR
+            ))
+        }
+
+        JNativeArray::new_raw(arr, ty)
+    }
+
     /// Get the backing reference of this object as a generic JArray reference
     pub fn as_jarray(&self) -> &JArray {
         match self {
@@ -122,6 +143,185 @@ impl JNativeArray<'_> {
 
 }
 
+impl AsJObjectPtr for JNativeArray<'_> {
+    unsafe fn as_object_ptr(&self) -> *mut ffi::JObject {
+        self.as_jarray().as_object_ptr()
+    }
+}
+
+///
+/// An enum representing any java array, either of a primitive type or of objects. Lets generic
+/// array code use one length and element interface for both kinds, with elements passed as
+/// [JValue]s, only matching on the variant where the kinds really differ
+///
+pub enum JArrayAny<'a> {
+    /// Java primitive array
+    Native(JNativeArray<'a>),
+    /// Java object array
+    Object(JObjectArray<'a>)
+}
+
+impl<'a> JArrayAny<'a> {
+
+    /// Create a new JArrayAny from an array object, picking the variant from the array's class.
+    /// Returns Err if the object isn't an array
+    pub fn from_object(env: &JNIEnv, obj: JObject<'a>) -> Result<JArrayAny<'a>> {
+        let (cls, name) = env.object_class_and_name(&obj)?;
+        env.delete_local_ref(cls.downcast());
+
+        let ty = match JNativeType::from_array_descriptor(&name) {
+            Some(ty) => ty,
+            None if name.starts_with('[') => {
+                // SAFETY: Internal pointer use, the class of the object is an array of objects
+                return Ok(JArrayAny::Object(JObjectArray::new(unsafe { obj.borrow_ptr() } as _)?))
+            }
+            None => return Err(Error::new(&format!("Object of class {} isn't an array", name), ffi::constants::JNI_ERR))
+        };
+
+        // SAFETY: Internal pointer use, the class of the object is an array of this type
+        unsafe { JNativeArray::new_raw(obj.borrow_ptr() as _, ty) }.map(JArrayAny::Native)
+    }
+
+    /// Get the backing reference of this object as a generic JArray reference
+    pub fn as_jarray(&self) -> &JArray<'_> {
+        match self {
+            JArrayAny::Native(arr) => arr.as_jarray(),
+            JArrayAny::Object(arr) => arr.downcast()
+        }
+    }
+
+    /// Get the JNativeType of the elements of this array, or None for an array of objects
+    pub fn jtype(&self) -> Option<JNativeType> {
+        match self {
+            JArrayAny::Native(arr) => Some(arr.jtype()),
+            JArrayAny::Object(_) => None
+        }
+    }
+
+    /// Get the number of elements in this array
+    pub fn len(&self, env: &JNIEnv) -> usize {
+        env.get_array_length(self.as_jarray())
+    }
+
+    /// Check whether this array has no elements
+    pub fn is_empty(&self, env: &JNIEnv) -> bool {
+        self.len(env) == 0
+    }
+
+    /// Get the element at an index. Primitive elements are the matching JValue variant, and
+    /// object elements a [JValue::Object], None if the element is null
+    pub fn get<'e>(&self, env: &JNIEnv<'e>, idx: usize) -> Result<JValue<'e>> {
+        if idx >= self.len(env) {
+            return Err(Error::new("Index outside array bounds", ffi::constants::JNI_ERR))
+        }
+
+        match self {
+            JArrayAny::Native(arr) => {
+                let vals = native_values(env.get_native_array_region(arr, idx, 1)?)?;
+                Ok(vals.into_iter().next().expect("Region of one element was empty"))
+            }
+            JArrayAny::Object(arr) => {
+                // Within bounds, a failure with no exception thrown is a null element
+                match env.get_object_array_element(arr, idx) {
+                    Ok(obj) => Ok(JValue::Object(Some(obj))),
+                    Err(_) if !env.exception_check() => Ok(JValue::Object(None)),
+                    Err(err) => Err(err)
+                }
+            }
+        }
+    }
+
+    /// Set the element at an index. Returns Err if the value doesn't match the element type, with
+    /// any object fitting an array of objects
+    pub fn set(&self, env: &JNIEnv, idx: usize, val: JValue) -> Result<()> {
+        if idx >= self.len(env) {
+            return Err(Error::new("Index outside array bounds", ffi::constants::JNI_ERR))
+        }
+
+        match self {
+            JArrayAny::Native(arr) => {
+                let vec = match arr.jtype() {
+                    JNativeType::Boolean => JNativeVec::Boolean(vec![val.into_bool()?]),
+                    JNativeType::Byte => JNativeVec::Byte(vec![val.into_byte()?]),
+                    JNativeType::Char => {
+                        let c = val.into_char()?;
+                        let unit = JChar::try_from(c as u32)
+                            .map_err(|_| Error::new(&format!("Char {:?} doesn't fit in one Java char", c), ffi::constants::JNI_ERR))?;
+                        JNativeVec::Char(vec![unit])
+                    }
+                    JNativeType::Short => JNativeVec::Short(vec![val.into_short()?]),
+                    JNativeType::Int => JNativeVec::Int(vec![val.into_int()?]),
+                    JNativeType::Long => JNativeVec::Long(vec![val.into_long()?]),
+                    JNativeType::Float => JNativeVec::Float(vec![val.into_float()?]),
+                    JNativeType::Double => JNativeVec::Double(vec![val.into_double()?])
+                };
+                env.set_native_array_region(arr, idx, 1, &vec)
+            }
+            JArrayAny::Object(arr) => {
+                match val.into_obj()? {
+                    Some(obj) => env.set_object_array_element(arr, idx, &obj),
+                    None => env.set_object_array_null(arr, idx)
+                }
+            }
+        }
+    }
+
+    /// Get every element of this array, as [get][Self::get] would. Primitive arrays are read in a
+    /// single region copy
+    pub fn to_vec<'e>(&self, env: &JNIEnv<'e>) -> Result<Vec<JValue<'e>>> {
+        match self {
+            JArrayAny::Native(arr) => native_values(env.get_native_array_region(arr, 0, self.len(env))?),
+            JArrayAny::Object(arr) => {
+                Ok(env.object_array_to_vec(arr, NullPolicy::AsNone)?
+                    .into_iter()
+                    .map(JValue::Object)
+                    .collect())
+            }
+        }
+    }
+}
+
+impl<'a> From<JNativeArray<'a>> for JArrayAny<'a> {
+    fn from(arr: JNativeArray<'a>) -> JArrayAny<'a> {
+        JArrayAny::Native(arr)
+    }
+}
+
+impl<'a> From<JObjectArray<'a>> for JArrayAny<'a> {
+    fn from(arr: JObjectArray<'a>) -> JArrayAny<'a> {
+        JArrayAny::Object(arr)
+    }
+}
+
+impl AsJObjectPtr for JArrayAny<'_> {
+    unsafe fn as_object_ptr(&self) -> *mut ffi::JObject {
+        self.as_jarray().as_object_ptr()
+    }
+}
+
+/// Convert the values of a primitive vector into JValues. Returns Err if a char is an unpaired
+/// surrogate, which has no rust char
+fn native_values<'a>(vec: JNativeVec) -> Result<Vec<JValue<'a>>> {
+    Ok(match vec {
+        JNativeVec::Boolean(vals) => vals.into_iter().map(JValue::Bool).collect(),
+        JNativeVec::Byte(vals) => vals.into_iter().map(JValue::Byte).collect(),
+        JNativeVec::Char(vals) => {
+            vals.into_iter()
+                .map(|unit| {
+                    std::char::from_u32(unit as u32)
+                        .map(JValue::Char)
+                        .ok_or_else(|| Error::new(&format!("Java char 0x{:04X} is an unpaired surrogate", unit), ffi::constants::JNI_ERR))
+                })
+                .collect::<Result<_>>()?
+        }
+        JNativeVec::Short(vals) => vals.into_iter().map(JValue::Short).collect(),
+        JNativeVec::Int(vals) => vals.into_iter().map(JValue::Int).collect(),
+        JNativeVec::Long(vals) => vals.into_iter().map(JValue::Long).collect(),
+        JNativeVec::Float(vals) => vals.into_iter().map(JValue::Float).collect(),
+        JNativeVec::Double(vals) => vals.into_iter().map(JValue::Double).collect()
+    })
+}
+
 ///
 /// An enum representing a slice of a java primitive array
 ///
@@ -200,8 +400,8 @@ pub enum JNativeVec {
     Boolean(Vec<bool>),
     /// Vector of byte values
     Byte(Vec<i8>),
-    /// Vector of char values
-    Char(Vec<char>),
+    /// Vector of char values, as the UTF-16 code units java stores
+    Char(Vec<JChar>),
     /// Vector of short values
     Short(Vec<i16>),
     /// Vector of int values
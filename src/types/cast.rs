@@ -2,8 +2,10 @@
 //! Module containing traits used for up/down-casting JObject types safely
 //!
 
+use crate::ffi;
 use crate::env::JNIEnv;
 use crate::error::Result;
+use crate::types::JObject;
 
 ///
 /// Trait representing the ability for this reference to be 'downcast', and treated as a reference
@@ -26,3 +28,29 @@ pub trait JavaUpCast<T> {
     /// Does an unsafe cast to a Java type that inherits from this type
     unsafe fn upcast_raw(self) -> T;
 }
+
+
+///
+/// Trait for reference types standing for a specific Java class, so values can be checked against
+/// the class before being treated as the type
+///
+pub trait JavaClassType<'a>: Sized {
+    /// The name of the class, in the syntax defined in the root documentation, such as
+    /// `java.lang.String` or `int[]`
+    fn class_name() -> &'static str;
+
+    /// Treat an object as this type without checking its class. Unsafe, as the object must really
+    /// be an instance of the class
+    unsafe fn from_object_unchecked(obj: JObject<'a>) -> Self;
+}
+
+
+///
+/// Trait for every reference type, giving its backing pointer as a plain object pointer. Lets
+/// generic code take any kind of object where the JNI takes a `jobject`
+///
+pub trait AsJObjectPtr {
+    /// Get the backing pointer of this object, as an object pointer. Unsafe, as this pointer may be
+    /// used without the safety provided by this object
+    unsafe fn as_object_ptr(&self) -> *mut ffi::JObject;
+}
@@ -191,6 +191,58 @@ pub enum JNativeType {
     Double
 }
 
+impl JNativeType {
+
+    /// Get the descriptor of an array of this type, which is also the name of the array class,
+    /// such as `[I` for an int array
+    pub fn array_descriptor(&self) -> &'static str {
+        match self {
+            JNativeType::Boolean => "[Z",
+            JNativeType::Byte => "[B",
+            JNativeType::Char => "[C",
+            JNativeType::Short => "[S",
+            JNativeType::Int => "[I",
+            JNativeType::Long => "[J",
+            JNativeType::Float => "[F",
+            JNativeType::Double => "[D"
+        }
+    }
+
+    /// Get the element type of a primitive array from its descriptor, or None if it isn't one
+    pub fn from_array_descriptor(desc: &str) -> Option<JNativeType> {
+        match desc {
+            "[Z" => Some(JNativeType::Boolean),
+            "[B" => Some(JNativeType::Byte),
+            "[C" => Some(JNativeType::Char),
+            "[S" => Some(JNativeType::Short),
+            "[I" => Some(JNativeType::Int),
+            "[J" => Some(JNativeType::Long),
+            "[F" => Some(JNativeType::Float),
+            "[D" => Some(JNativeType::Double),
+            _ => None
+        }
+    }
+}
+
+///
+/// An enum representing the kinds of class a `java.lang.Class` can stand for
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClassKind {
+    /// A primitive type, or void
+    Primitive(JType),
+    /// An array, with the kind of its component type
+    Array(Box<ClassKind>),
+    /// An interface, other than an annotation type
+    Interface,
+    /// A class not of any other kind
+    Class,
+    /// An enum class
+    Enum,
+    /// An annotation type
+    Annotation
+}
+
 ///
 /// A struct representing all the possible JVM reference types
 ///
@@ -31,6 +31,16 @@ impl JNINativeMethod {
         }
     }
 
+    /// Get the name of the method
+    pub fn name(&self) -> &str {
+        self.name.to_str().expect("Name was created from a str")
+    }
+
+    /// Get the signature of the method
+    pub fn signature(&self) -> &str {
+        self.signature.to_str().expect("Signature was created from a str")
+    }
+
     /// Create a vector of the FFI-safe JNINativeMethod type from a slice of JNINativeMethods
     pub fn make_ffi_vec(slice: &[JNINativeMethod]) -> Vec<ffi::JNINativeMethod> {
         let mut out = Vec::new();
@@ -7,9 +7,30 @@
 
 use crate::ffi;
 use crate::error::Error;
+use crate::ffi::constants::JNI_ERR;
 use crate::env::JNIEnv;
-use crate::types::{JavaDownCast, JavaUpCast, JType, JNonVoidType};
+use crate::vm::JavaVM;
+use crate::types::{JavaDownCast, JavaUpCast, JavaClassType, AsJObjectPtr, JType, JNonVoidType};
+use crate::types::jtype::JRefType;
+use crate::{get_cls, get_method_id};
 use std::marker::PhantomData;
+use std::ffi::c_void;
+#[cfg(any(debug_assertions, feature = "runtime-checks"))]
+use std::thread::ThreadId;
+
+
+/// Build a smart object of one type from another of any type, keeping the pointer and the thread
+/// it was created on. Infallible, as smart objects are never null
+macro_rules! retype {
+    ($src:expr, $y:ident) => {
+        $y {
+            backing_ptr: $src.backing_ptr as *mut ffi::$y,
+            #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+            thread: $src.thread,
+            phantom: PhantomData
+        }
+    }
+}
 
 
 macro_rules! smart_obj {
@@ -19,9 +40,13 @@ macro_rules! smart_obj {
         /// A struct representing a pointer to the $x type, with added guarantee of memory-safety
         /// in usage.
         ///
+        // The layout is fixed, as references are cast between the smart types in place
         #[derive(Debug)]
+        #[repr(C)]
         pub struct $x<'a> {
             backing_ptr: *mut ffi::$x,
+            #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+            thread: ThreadId,
             phantom: PhantomData<&'a ffi::$x>
         }
 
@@ -34,11 +59,25 @@ macro_rules! smart_obj {
                 } else {
                     Ok($x {
                         backing_ptr: ptr,
+                        #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+                        thread: std::thread::current().id(),
                         phantom: PhantomData
                     })
                 }
             }
 
+            /// Get another handle to the same reference, keeping the thread it was created on
+            pub(crate) fn share<'b>(&self) -> $x<'b> {
+                retype!(self, $x)
+            }
+
+            /// Get the thread this object was created on. Only tracked with debug assertions or
+            /// the `runtime-checks` feature
+            #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+            pub(crate) fn creating_thread(&self) -> ThreadId {
+                self.thread
+            }
+
             /// Get the java name associated with this type, if one exists
             pub fn get_java_name() -> &'static str {
                 stringify!($y)
@@ -50,6 +89,40 @@ macro_rules! smart_obj {
                 self.backing_ptr
             }
 
+            /// Create a new instance of this struct from an untyped pointer, such as a `jobject`
+            /// from another JNI crate. This is the interop boundary with other crates, so the
+            /// pointer is only checked to be non-null, and must really be a live reference of
+            /// this type
+            pub fn from_raw<'a>(ptr: *mut c_void) -> Result<$x<'a>, Error> {
+                $x::new(ptr as *mut ffi::$x)
+            }
+
+            /// Give up this object, returning its backing pointer untyped, such as for use as a
+            /// `jobject` by another JNI crate. The reference isn't deleted
+            pub fn into_raw(self) -> *mut c_void {
+                self.backing_ptr as *mut c_void
+            }
+
+        }
+
+        impl<'a> $x<'a> {
+
+            /// Borrow this as a plain [JObject], as every reference type is one. The same as
+            /// downcasting a reference, without needing [JavaDownCast] in scope
+            pub fn as_object(&self) -> &JObject<'a> {
+                // SAFETY: All the smart types are repr(C) with the same fields
+                //         This is thus a safe cast
+                unsafe {
+                    &*(self as *const $x as *const JObject)
+                }
+            }
+
+        }
+
+        impl AsJObjectPtr for $x<'_> {
+            unsafe fn as_object_ptr(&self) -> *mut ffi::JObject {
+                self.backing_ptr as *mut ffi::JObject
+            }
         }
 
     }
@@ -60,13 +133,13 @@ macro_rules! extends {
     ($x:ident, $y:ident) => {
         impl<'a> JavaDownCast<$y<'a>> for $x<'a> {
             fn downcast(self) -> $y<'a> {
-                $y::new(self.backing_ptr as *mut ffi::$y).unwrap()
+                retype!(self, $y)
             }
         }
 
         impl<'a, 'b> JavaDownCast<&'b $y<'a>> for &'b $x<'a> {
             fn downcast(self) -> &'b $y<'a> {
-                // SAFETY: All the smart types have the same size + same backing pointer
+                // SAFETY: All the smart types are repr(C) with the same fields
                 //         This is thus a safe cast
                 unsafe {
                     &*(self as *const $x as *const $y)
@@ -78,32 +151,47 @@ macro_rules! extends {
 
 
 macro_rules! upcast {
-    ($x:ident, $y:ident) => {
+    ($x:ident, $y:ident, $cls:literal) => {
+        upcast!($x, $y, |env, obj| {
+            // The target class is only looked up once, as casts are a hot path
+            let cast_cls = get_cls!(env, $cls);
+            let cls = env.get_object_class(obj)?;
+            let assignable = env.is_assignable_from(&cls, cast_cls);
+            env.delete_local_ref(cls.downcast());
+            Ok(assignable)
+        });
+    };
+    ($x:ident, $y:ident, $check:expr) => {
+        impl $y<'_> {
+            /// Check whether an object can be upcast to this type
+            fn can_upcast_from(env: &JNIEnv, obj: &JObject) -> Result<bool, Error> {
+                let check: fn(&JNIEnv, &JObject) -> Result<bool, Error> = $check;
+                check(env, obj)
+            }
+        }
+
         impl<'a> JavaUpCast<$y<'a>> for $x<'a> {
-            fn upcast(self, env: &JNIEnv) -> $crate::error::Result<$y<'a>> {
-                let self_name = Self::get_java_name();
-                let cast_cls = env.find_class(self_name)?;
-                let cls = env.get_object_class(&self)?;
-
-                if !env.is_assignable_from(&cls, &cast_cls) {
-                    Err(
-                        $crate::error::Error::new(&format!("Can't assign to type {}", self_name), -1)
-                    )
+            fn upcast(self, env: &JNIEnv) -> Result<$y<'a>, Error> {
+                if !$y::can_upcast_from(env, self.as_object())? {
+                    Err(Error::new(&format!("Can't assign to type {}", stringify!($y)), JNI_ERR))
                 } else {
-                    $y::new(self.backing_ptr as *mut ffi::$y)
+                    // SAFETY: Checked the object is assignable to the type above
+                    Ok(unsafe { self.upcast_raw() })
                 }
             }
 
             unsafe fn upcast_raw(self) -> $y<'a> {
-                $y::new(self.backing_ptr as *mut ffi::$y).unwrap()
+                retype!(self, $y)
             }
         }
 
         impl<'a, 'b> JavaUpCast<&'b $y<'a>> for &'b $x<'a> {
-            fn upcast(self, _env: &JNIEnv) -> $crate::error::Result<&'b $y<'a>> {
-                // TODO: Do env check
-                unsafe {
-                    Ok(&*(self as *const $x as *const $y))
+            fn upcast(self, env: &JNIEnv) -> Result<&'b $y<'a>, Error> {
+                if !$y::can_upcast_from(env, self.as_object())? {
+                    Err(Error::new(&format!("Can't assign to type {}", stringify!($y)), JNI_ERR))
+                } else {
+                    // SAFETY: Checked the object is assignable to the type above
+                    Ok(unsafe { self.upcast_raw() })
                 }
             }
 
@@ -115,6 +203,19 @@ macro_rules! upcast {
 }
 
 
+macro_rules! class_type {
+    ($x:ident, $name:literal) => {
+        impl<'a> JavaClassType<'a> for $x<'a> {
+            fn class_name() -> &'static str {
+                $name
+            }
+
+            unsafe fn from_object_unchecked(obj: JObject<'a>) -> $x<'a> {
+                retype!(obj, $x)
+            }
+        }
+    }
+}
 ///
 /// A struct representing a Java Method ID. Knows its own return type and the number of args,
 /// preventing memory unsafety while calling methods with it
@@ -197,12 +298,100 @@ impl JFieldID {
     }
 }
 
+
+///
+/// An owned global reference to an object, deleted when dropped. Keeps a handle to the JVM it
+/// belongs to, so it can be deleted from whichever thread drops it. It is `Send + Sync`, as global
+/// references are valid on any attached thread, so it can be shared with or dropped on other
+/// threads
+///
+pub struct JGlobalRef {
+    obj: Option<JObject<'static>>,
+    vm: JavaVM
+}
+
+// SAFETY: Global references are valid on any thread, and only used through an environment, so from
+//         attached threads. The JVM handle doesn't own the JVM, so it's never destroyed from here
+unsafe impl Send for JGlobalRef {}
+unsafe impl Sync for JGlobalRef {}
+
+impl JGlobalRef {
+
+    /// Take ownership of a global reference, to be deleted on drop. Unsafe, as the object must be
+    /// a live global reference of the given JVM, and not deleted anywhere else. The JVM handle
+    /// mustn't own the JVM, such as one from [get_jvm][JNIEnv::get_jvm]
+    pub unsafe fn new(obj: JObject<'static>, vm: JavaVM) -> JGlobalRef {
+        JGlobalRef {
+            obj: Some(obj),
+            vm
+        }
+    }
+
+    /// Borrow the referenced object, to pass it into environment methods
+    pub fn as_obj(&self) -> &JObject<'static> {
+        self.obj.as_ref().expect("JGlobalRef used after being leaked")
+    }
+
+    /// Give up ownership of the reference without deleting it. It is then up to the caller to
+    /// pass it to [delete_global_ref][JNIEnv::delete_global_ref], or let it live as long as the JVM
+    pub fn leak(mut self) -> JObject<'static> {
+        self.obj.take().expect("JGlobalRef used after being leaked")
+    }
+}
+
+/// The owned global reference type returned by [new_global][JNIEnv::new_global]. An alias of
+/// [JGlobalRef], kept as the name global references are usually held under
+pub type JGlobal = JGlobalRef;
+
+impl std::fmt::Debug for JGlobalRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("JGlobalRef").field(&self.obj).finish()
+    }
+}
+
+impl AsJObjectPtr for JGlobalRef {
+    unsafe fn as_object_ptr(&self) -> *mut ffi::JObject {
+        self.as_obj().borrow_ptr()
+    }
+}
+
+impl Drop for JGlobalRef {
+    fn drop(&mut self) {
+        if let Some(obj) = self.obj.take() {
+            drop_global(&self.vm, obj);
+        }
+    }
+}
+
+
+/// Delete an owned global reference from whichever thread it's dropped on, attaching if needed.
+/// Errors are swallowed, as the JVM may be being torn down and there is no one to report to.
+/// Getting or attaching an env fails once it's destroyed, leaving the reference to die with it
+fn drop_global(vm: &JavaVM, obj: JObject<'static>) {
+    if let Ok(env) = vm.get_local_env() {
+        env.delete_global_ref(obj);
+    } else if let Ok(env) = vm.attach_current_thread() {
+        env.delete_global_ref(obj);
+        let _ = vm.detach_current_thread(env);
+    }
+}
+
 smart_obj!(JObject, "[Ljava/lang/Object;");
 
 smart_obj!(JThrowable, "[Ljava/lang/Throwable;");
 smart_obj!(JClass, "[Ljava/lang/Class;");
 smart_obj!(JString, "[Ljava/lang/String;");
 smart_obj!(JWeak, "[Ljava/lang/ref/WeakReference;");
+
+impl JWeak<'_> {
+
+    /// Check whether the referent of this weak global reference has been collected. One that
+    /// hasn't may still be collected at any time, so use [upgrade_weak][JNIEnv::upgrade_weak] to
+    /// keep it alive while using it
+    pub fn is_collected(&self, env: &JNIEnv) -> bool {
+        env.is_weak_collected(self)
+    }
+}
 smart_obj!(JArray, "");
 
 smart_obj!(JObjectArray, "[Ljava/lang/Object;");
@@ -215,11 +404,22 @@ smart_obj!(JLongArray, "[J");
 smart_obj!(JFloatArray, "[F");
 smart_obj!(JDoubleArray, "[D");
 
-upcast!(JObject, JThrowable);
-upcast!(JObject, JClass);
-upcast!(JObject, JString);
-upcast!(JObject, JWeak);
-upcast!(JObject, JArray);
+// Thread tracking is compiled out without runtime checks, leaving objects a bare pointer
+#[cfg(not(any(debug_assertions, feature = "runtime-checks")))]
+const _: () = assert!(std::mem::size_of::<JObject>() == std::mem::size_of::<*mut ffi::JObject>());
+
+upcast!(JObject, JThrowable, "java.lang.Throwable");
+upcast!(JObject, JClass, "java.lang.Class");
+upcast!(JObject, JString, "java.lang.String");
+upcast!(JObject, JWeak, |env, obj| Ok(env.get_object_ref_type(obj) == JRefType::WeakGlobal));
+upcast!(JObject, JArray, |env, obj| {
+    let cls_cls = get_cls!(env, "java.lang.Class");
+    let is_array = get_method_id!(env, cls_cls, "isArray", "() -> boolean");
+    let cls = env.get_object_class(obj)?;
+    let result = env.call_method(cls.as_object(), is_array, &vec![]);
+    env.delete_local_ref(cls.downcast());
+    result?.ok_or_else(|| Error::new_null("Class.isArray result"))?.into_bool()
+});
 
 extends!(JThrowable, JObject);
 
@@ -227,6 +427,8 @@ extends!(JClass, JObject);
 
 extends!(JString, JObject);
 
+extends!(JWeak, JObject);
+
 extends!(JArray, JObject);
 
 extends!(JObjectArray, JObject);
@@ -255,3 +457,17 @@ extends!(JFloatArray, JArray);
 
 extends!(JDoubleArray, JObject);
 extends!(JDoubleArray, JArray);
+
+class_type!(JObject, "java.lang.Object");
+class_type!(JThrowable, "java.lang.Throwable");
+class_type!(JClass, "java.lang.Class");
+class_type!(JString, "java.lang.String");
+class_type!(JObjectArray, "java.lang.Object[]");
+class_type!(JBooleanArray, "boolean[]");
+class_type!(JByteArray, "byte[]");
+class_type!(JCharArray, "char[]");
+class_type!(JShortArray, "short[]");
+class_type!(JIntArray, "int[]");
+class_type!(JLongArray, "long[]");
+class_type!(JFloatArray, "float[]");
+class_type!(JDoubleArray, "double[]");
@@ -3,8 +3,10 @@
 //! their return, as well as for getting/setting fields.
 //!
 
+use std::convert::TryFrom;
+
 use crate::ffi;
-use crate::types::JObject;
+use crate::types::{JObject, JClass, JavaDownCast};
 use crate::error::Error;
 
 ///
@@ -134,28 +136,28 @@ impl<'a> JValue<'a> {
     pub unsafe fn as_ffi(&self) -> ffi::JValue {
         match self {
             JValue::Bool(bool) => {
-                ffi::JValue { z: *bool as ffi::JBoolean }
+                ffi::JValue { z: ffi::JBoolean::from(*bool) }
             }
             JValue::Byte(byte) => {
-                ffi::JValue { b: *byte as ffi::JByte }
+                ffi::JValue { b: *byte }
             }
             JValue::Char(char) => {
                 ffi::JValue { c: *char as ffi::JChar }
             }
             JValue::Short(short) => {
-                ffi::JValue { s: *short as ffi::JShort }
+                ffi::JValue { s: *short }
             }
             JValue::Int(int) => {
-                ffi::JValue { i: *int as ffi::JInt }
+                ffi::JValue { i: *int }
             }
             JValue::Long(long) => {
-                ffi::JValue { j: *long as ffi::JLong }
+                ffi::JValue { j: *long }
             }
             JValue::Float(float) => {
-                ffi::JValue { f: *float as ffi::JFloat }
+                ffi::JValue { f: *float }
             }
             JValue::Double(double) => {
-                ffi::JValue { d: *double as ffi::JDouble }
+                ffi::JValue { d: *double }
             }
             JValue::Object(obj) => {
                 // SAFETY: Internal pointer use
@@ -224,3 +226,137 @@ impl<'a> From<Option<JObject<'a>>> for JValue<'a> {
         return JValue::Object(val)
     }
 }
+
+impl<'a> From<&JObject<'a>> for JValue<'a> {
+    fn from(val: &JObject<'a>) -> Self {
+        // The new handle shares the lifetime of the borrowed one
+        return JValue::Object(Some(val.share()))
+    }
+}
+
+impl<'a> From<&JClass<'a>> for JValue<'a> {
+    fn from(val: &JClass<'a>) -> Self {
+        let obj: &JObject<'a> = val.downcast();
+        JValue::from(obj)
+    }
+}
+
+///
+/// How conversion helpers treat Java nulls, such as null elements of an array or list. Helpers
+/// taking a policy return Option entries, which are only None under [AsNone][NullPolicy::AsNone]
+/// or when the default is itself null
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NullPolicy {
+    /// Fail on the first null, naming its index
+    Error,
+    /// Give None for nulls
+    AsNone,
+    /// Replace nulls with the default value of the converted type, such as the empty string for
+    /// strings. Java object references default to null, so plain objects are still None
+    DefaultOf
+}
+
+impl NullPolicy {
+
+    /// Apply the policy to a converted value, which is None if the Java value was null. `ctx`
+    /// names the helper and `idx` the position of the value, for the error. `default` gives the
+    /// default value of the type
+    pub(crate) fn apply<T, F>(self, val: Option<T>, ctx: &str, idx: usize, default: F) -> Result<Option<T>, Error>
+        where
+            F: FnOnce() -> Option<T>
+    {
+        match (val, self) {
+            (Some(val), _) => Ok(Some(val)),
+            (None, NullPolicy::Error) => Err(Error::new_null(&format!("{} element {}", ctx, idx))),
+            (None, NullPolicy::AsNone) => Ok(None),
+            (None, NullPolicy::DefaultOf) => Ok(default())
+        }
+    }
+}
+
+///
+/// A trait for types that can be extracted from a [JValue], checking the value holds the right
+/// type at runtime
+///
+pub trait FromJValue<'a>: Sized {
+    /// Extract this type from a JValue, or Err if the value holds a different type
+    fn from_jvalue(val: JValue<'a>) -> Result<Self, Error>;
+}
+
+macro_rules! from_jvalue {
+    ($ty:ty, $conv:ident) => {
+        impl<'a> FromJValue<'a> for $ty {
+            fn from_jvalue(val: JValue<'a>) -> Result<Self, Error> {
+                val.$conv()
+            }
+        }
+
+        impl<'a> TryFrom<JValue<'a>> for $ty {
+            type Error = Error;
+
+            fn try_from(val: JValue<'a>) -> Result<Self, Error> {
+                val.$conv()
+            }
+        }
+    }
+}
+
+from_jvalue!(bool, into_bool);
+from_jvalue!(i8, into_byte);
+from_jvalue!(char, into_char);
+from_jvalue!(i16, into_short);
+from_jvalue!(i32, into_int);
+from_jvalue!(i64, into_long);
+from_jvalue!(f32, into_float);
+from_jvalue!(f64, into_double);
+from_jvalue!(Option<JObject<'a>>, into_obj);
+
+mod private {
+    /// Keeps [JavaPrimitive][super::JavaPrimitive] to the primitive types
+    pub trait Sealed {}
+}
+
+///
+/// A trait for the rust types of Java primitives, knowing the name of their Java type. Sealed, as
+/// only the eight primitive types have a Java counterpart.
+///
+pub trait JavaPrimitive<'a>: FromJValue<'a> + private::Sealed {
+    /// The name of the Java type, such as `int`
+    fn java_name() -> &'static str;
+}
+
+macro_rules! java_primitive {
+    ($ty:ty, $name:literal) => {
+        impl private::Sealed for $ty {}
+
+        impl<'a> JavaPrimitive<'a> for $ty {
+            fn java_name() -> &'static str {
+                $name
+            }
+        }
+    }
+}
+
+java_primitive!(bool, "boolean");
+java_primitive!(i8, "byte");
+java_primitive!(char, "char");
+java_primitive!(i16, "short");
+java_primitive!(i32, "int");
+java_primitive!(i64, "long");
+java_primitive!(f32, "float");
+java_primitive!(f64, "double");
+
+impl<'a> FromJValue<'a> for JObject<'a> {
+    fn from_jvalue(val: JValue<'a>) -> Result<Self, Error> {
+        val.into_obj()?.ok_or_else(|| Error::new_null("JValue object"))
+    }
+}
+
+impl<'a> TryFrom<JValue<'a>> for JObject<'a> {
+    type Error = Error;
+
+    fn try_from(val: JValue<'a>) -> Result<Self, Error> {
+        JObject::from_jvalue(val)
+    }
+}
@@ -2,7 +2,10 @@
 //! Module containing an enum representing possible JNI versions
 //!
 
+use std::convert::TryFrom;
+
 use crate::ffi::constants;
+use crate::error::Error;
 
 ///
 /// An enum containing variants representing all the supported JNI versions
@@ -22,12 +25,44 @@ pub enum JNIVersion {
     /// JNI 9
     Ver9,
     /// JNI 10
-    Ver10
+    Ver10,
+    /// JNI 19
+    Ver19,
+    /// JNI 20
+    Ver20,
+    /// JNI 21
+    Ver21,
+    /// JNI 24
+    Ver24
 }
 
-impl From<i32> for JNIVersion {
-    fn from(val: i32) -> Self {
-        match val {
+impl JNIVersion {
+
+    /// Every known version, oldest first
+    const ALL: [JNIVersion; 11] = [
+        JNIVersion::Ver11, JNIVersion::Ver12, JNIVersion::Ver14, JNIVersion::Ver16, JNIVersion::Ver18,
+        JNIVersion::Ver9, JNIVersion::Ver10, JNIVersion::Ver19, JNIVersion::Ver20, JNIVersion::Ver21,
+        JNIVersion::Ver24
+    ];
+
+    /// Get the newest known version no newer than a raw version. A JVM reporting a version this
+    /// enum doesn't know, such as one released after this crate, still supports every older
+    /// version, so is treated as the newest one it's known to support. None if the raw version is
+    /// older than any JNI version
+    pub(crate) fn at_most(val: i32) -> Option<JNIVersion> {
+        JNIVersion::ALL
+            .iter()
+            .rev()
+            .copied()
+            .find(|ver| i32::from(*ver) <= val)
+    }
+}
+
+impl TryFrom<i32> for JNIVersion {
+    type Error = Error;
+
+    fn try_from(val: i32) -> Result<Self, Error> {
+        Ok(match val {
             constants::JNI_VERSION_1_1 => {
                 JNIVersion::Ver11
             }
@@ -49,10 +84,22 @@ impl From<i32> for JNIVersion {
             constants::JNI_VERSION_10 => {
                 JNIVersion::Ver10
             }
+            constants::JNI_VERSION_19 => {
+                JNIVersion::Ver19
+            }
+            constants::JNI_VERSION_20 => {
+                JNIVersion::Ver20
+            }
+            constants::JNI_VERSION_21 => {
+                JNIVersion::Ver21
+            }
+            constants::JNI_VERSION_24 => {
+                JNIVersion::Ver24
+            }
             _ => {
-                panic!("Invalid value for JNIVersion")
+                return Err(Error::new_unsupported(&format!("Unknown JNI version 0x{:08X}", val)))
             }
-        }
+        })
     }
 }
 
@@ -80,6 +127,18 @@ impl From<JNIVersion> for i32 {
             JNIVersion::Ver10 => {
                 constants::JNI_VERSION_10
             }
+            JNIVersion::Ver19 => {
+                constants::JNI_VERSION_19
+            }
+            JNIVersion::Ver20 => {
+                constants::JNI_VERSION_20
+            }
+            JNIVersion::Ver21 => {
+                constants::JNI_VERSION_21
+            }
+            JNIVersion::Ver24 => {
+                constants::JNI_VERSION_24
+            }
         }
     }
 }
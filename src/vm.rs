@@ -4,11 +4,125 @@
 //! ensure safety while doing so.
 //!
 
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "async")]
+use std::task::{Context, Poll, Waker};
+
 use crate::{env, ffi};
 use crate::error::Error;
-use crate::ffi::{JavaVMInitArgs, JavaVMAttachArgs};
+use crate::ffi::{JavaVMInitArgs, JavaVMAttachArgs, JavaVMOption};
+use crate::ffi::constants::{JNI_OK, JNI_ERR, JNI_EDETACHED};
 use crate::types::JNIVersion;
 use crate::env::JNIEnv;
+#[cfg(feature = "async")]
+use crate::panic_bridge::panic_message;
+
+/// Environment cached for the current thread by [JavaVM::current_env]
+#[derive(Copy, Clone)]
+struct CachedEnv {
+    vm: *mut ffi::JavaVM,
+    env: *mut ffi::JNIEnv,
+    version: JNIVersion,
+    generation: u64
+}
+
+thread_local! {
+    static ENV_CACHE: Cell<Option<CachedEnv>> = Cell::new(None);
+}
+
+/// Set once an owned JVM has been destroyed. Only one JVM can exist per process, so after this
+/// any JVM or environment pointer still around points into freed memory
+static VM_DESTROYED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the JVM has been destroyed, after which the crate-managed paths refuse to call into it
+pub(crate) fn vm_destroyed() -> bool {
+    VM_DESTROYED.load(Ordering::Acquire)
+}
+
+/// Return Err if the JVM has been destroyed
+fn check_alive() -> Result<(), Error> {
+    if vm_destroyed() {
+        Err(Error::new("JVM has been destroyed", JNI_ERR))
+    } else {
+        Ok(())
+    }
+}
+
+/// Hit statistics of the current thread's environment cache, used by [JavaVM::current_env]
+#[cfg(feature = "audit")]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct EnvCacheStats {
+    /// Number of lookups answered from the cache
+    pub hits: usize,
+    /// Number of lookups that had to ask the JVM
+    pub misses: usize
+}
+
+#[cfg(feature = "audit")]
+thread_local! {
+    static ENV_CACHE_STATS: Cell<EnvCacheStats> = Cell::new(EnvCacheStats::default());
+}
+
+///
+/// Owned options for creating a JVM. Can start empty, or from the JVM's defaults through
+/// [JavaVM::default_init_args], and be extended before being passed to
+/// [JavaVM::create_with_options]
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct JvmOptions {
+    version: JNIVersion,
+    options: Vec<String>,
+    ignore_unrecognized: bool
+}
+
+impl JvmOptions {
+
+    /// Create a new set of options with no startup options, for a given JNI version
+    pub fn new(version: JNIVersion) -> JvmOptions {
+        JvmOptions {
+            version,
+            options: Vec::new(),
+            ignore_unrecognized: false
+        }
+    }
+
+    /// Add a startup option, such as `-Xmx512m` or `-Dkey=value`
+    pub fn option(mut self, option: &str) -> Self {
+        self.options.push(String::from(option));
+        self
+    }
+
+    /// Set whether the JVM should ignore startup options it doesn't recognize
+    pub fn ignore_unrecognized(mut self, ignore: bool) -> Self {
+        self.ignore_unrecognized = ignore;
+        self
+    }
+
+    /// Get the JNI version these options are for
+    pub fn version(&self) -> JNIVersion {
+        self.version
+    }
+
+    /// Get the startup options, in the order they were added
+    pub fn options(&self) -> &[String] {
+        &self.options
+    }
+
+    /// Get whether the JVM will ignore startup options it doesn't recognize
+    pub fn ignores_unrecognized(&self) -> bool {
+        self.ignore_unrecognized
+    }
+}
 
 /// Higher-level construct representing a JVM
 pub struct JavaVM {
@@ -32,32 +146,82 @@ impl JavaVM {
         }
     }
 
+    /// Get the JNI version of this JVM. For a JVM created through this crate, this is the version
+    /// the JVM really provides, which may be newer than the one requested
+    pub fn version(&self) -> JNIVersion {
+        self.version
+    }
+
+    /// Get the JVM's default initialization args for a version, as options which can be extended
+    /// and passed to [create_with_options][Self::create_with_options]. Any options the JVM fills
+    /// in are copied, not taken
+    pub fn default_init_args(version: JNIVersion) -> Result<JvmOptions, Error> {
+        let mut args = JavaVMInitArgs::new(version.into());
+
+        // SAFETY: The JVM only fills in the passed args, and returns an error code on bad input
+        let result = unsafe { ffi::get_default_jvm_init_args(&mut args) };
+        if result != 0 {
+            return Err(Error::new("Couldn't get default JVM args", result))
+        }
+
+        // SAFETY: The options array was either left empty, or filled in by the JVM
+        let options = unsafe { args.options() }
+            .iter()
+            .filter(|opt| !opt.option_string().is_null())
+            // SAFETY: Non-null option strings are valid C strings, which are copied here
+            .map(|opt| unsafe { CStr::from_ptr(opt.option_string()) }.to_string_lossy().into_owned())
+            .collect();
+
+        Ok(JvmOptions {
+            // A version the JVM fills in that isn't known is left as the one asked for
+            version: JNIVersion::try_from(args.version()).unwrap_or(version),
+            options,
+            ignore_unrecognized: args.ignore_unrecognized()
+        })
+    }
+
     /// Create a new JVM. Initializes an entirely new JVM, with the current thread
-    /// as the main thread. This object will call the JVM destroy function when it is dropped
-    pub fn create(version: JNIVersion) -> Result<(JavaVM, JNIEnv), Error> {
+    /// as the main thread. This object will call the JVM destroy function when it is dropped. The
+    /// version is the minimum required, the JVM and environment store the one actually provided
+    pub fn create(version: JNIVersion) -> Result<(JavaVM, JNIEnv<'static>), Error> {
+        JavaVM::create_with_options(JavaVM::default_init_args(version)?)
+    }
+
+    /// Create a new JVM from a set of options, otherwise the same as [create][Self::create]
+    pub fn create_with_options(options: JvmOptions) -> Result<(JavaVM, JNIEnv<'static>), Error> {
         let mut main_vm = std::ptr::null_mut();
         let mut main_env = std::ptr::null_mut();
-        let mut args = JavaVMInitArgs::new(version.into());
 
-        // SAFETY: The FFI functions called here only rely on user input in checked cases, and
-        //         will return error codes if the input provided here isn't right, which will be
-        //         propagated as Err results.
-        unsafe {
-            let result = ffi::get_default_jvm_init_args(&mut args);
-            if result != 0 {
-                return Err(Error::new("Couldn't get default JVM args", result))
-            }
-
-            let result = ffi::create_jvm(&mut main_vm, &mut main_env, &mut args);
-            if result != 0 {
-                return Err(Error::new("Couldn't create JVM", result))
-            }
+        let c_options = options.options
+            .iter()
+            .map(|opt| CString::new(opt.as_str()).map_err(|err| Error::from(Box::new(err))))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let raw_options: Vec<_> = c_options
+            .iter()
+            .map(|opt| JavaVMOption::new(opt.as_ptr() as *mut _, std::ptr::null_mut()))
+            .collect();
+
+        let mut args = JavaVMInitArgs::new(options.version.into());
+        args.set_ignore_unrecognized(options.ignore_unrecognized);
+
+        // SAFETY: The option strings outlive the args, which are only used for creation.
+        //         The FFI function returns an error code if the input isn't right, which is
+        //         propagated as an Err result.
+        let result = unsafe {
+            args.set_options(&raw_options);
+            ffi::create_jvm(&mut main_vm, &mut main_env, &mut args)
+        };
+        if result != 0 {
+            return Err(Error::new("Couldn't create JVM", result))
         }
 
         if main_vm.is_null() || main_env.is_null() {
             Err(Error::new("Main VM or Global Environment null, despite successful JVM creation", ffi::constants::JNI_ERR))
         } else {
+            // The JVM may support a newer version than requested, store the one it really provides
+            // if it's known, or the requested one if not
             let main_env = env::JNIEnv::new(main_env)?;
+            let version = main_env.get_version().unwrap_or(options.version);
             Ok((JavaVM { version, main_vm, owned: true }, main_env))
         }
     }
@@ -98,8 +262,37 @@ impl JavaVM {
         }
     }
 
+    /// Destroy this JVM, first waiting for any other attached non-daemon threads to finish. Once
+    /// destroyed, getting or attaching an environment through any [JavaVM] returns an error, and
+    /// deleting global references does nothing, as they died with the JVM. Environments and
+    /// references already held must not be used otherwise. Returns Err if this doesn't own the
+    /// JVM, as with handles from [get_existing][Self::get_existing]
+    pub fn destroy(mut self) -> Result<(), Error> {
+        if !self.owned {
+            return Err(Error::new("Can't destroy a JVM that isn't owned", JNI_ERR))
+        }
+        // Destroyed or not, Drop shouldn't try again
+        self.owned = false;
+        self.destroy_vm()
+    }
+
+    /// Destroy the JVM and mark it destroyed, regardless of ownership
+    fn destroy_vm(&self) -> Result<(), Error> {
+        check_alive()?;
+
+        let result = self.internal_vm().destroy_java_vm();
+        if result != 0 {
+            return Err(Error::new("JVM failed to shut down", result))
+        }
+
+        VM_DESTROYED.store(true, Ordering::Release);
+        ENV_CACHE.with(|cache| cache.set(None));
+        Ok(())
+    }
+
     /// Get an owned object for the local thread's environment
-    pub fn get_local_env(&self) -> Result<env::JNIEnv, Error> {
+    pub fn get_local_env(&self) -> Result<env::JNIEnv<'_>, Error> {
+        check_alive()?;
         let vm = self.internal_vm();
 
         let mut ffi_env = std::ptr::null_mut();
@@ -111,8 +304,61 @@ impl JavaVM {
         env::JNIEnv::new(ffi_env)
     }
 
+    /// Get the environment for the current thread, attaching it if it isn't already. The result is
+    /// cached per-thread, so repeat calls are only a thread-local read. Detaching through this
+    /// crate invalidates the cache, detaches done directly through JNI aren't seen
+    pub fn current_env(&self) -> Result<env::JNIEnv<'_>, Error> {
+        check_alive()?;
+        let generation = env::attach_generation();
+
+        if let Some(cached) = ENV_CACHE.with(|cache| cache.get()) {
+            if cached.vm == self.main_vm && cached.generation == generation {
+                #[cfg(feature = "audit")]
+                ENV_CACHE_STATS.with(|stats| {
+                    let mut new = stats.get();
+                    new.hits += 1;
+                    stats.set(new)
+                });
+                return Ok(env::JNIEnv::from_cached(cached.env, cached.version))
+            }
+        }
+
+        #[cfg(feature = "audit")]
+        ENV_CACHE_STATS.with(|stats| {
+            let mut new = stats.get();
+            new.misses += 1;
+            stats.set(new)
+        });
+
+        let vm = self.internal_vm();
+        let mut ffi_env = std::ptr::null_mut();
+        let result = vm.get_env(&mut ffi_env, self.version.into());
+        let env = match result {
+            JNI_OK => env::JNIEnv::new(ffi_env)?,
+            JNI_EDETACHED => self.attach_current_thread()?,
+            _ => return Err(Error::new("Couldn't get local environment", result))
+        };
+
+        ENV_CACHE.with(|cache| cache.set(Some(CachedEnv {
+            vm: self.main_vm,
+            // SAFETY: Internal pointer use, the cache is invalidated on detach
+            env: unsafe { env.borrow_ptr() },
+            version: env.get_version().unwrap_or(self.version),
+            generation: env::attach_generation()
+        })));
+
+        Ok(env)
+    }
+
+    /// Get the hit statistics of the current thread's [current_env][Self::current_env] cache
+    #[cfg(feature = "audit")]
+    pub fn env_cache_stats() -> EnvCacheStats {
+        ENV_CACHE_STATS.with(|stats| stats.get())
+    }
+
     /// Attach the current thread, and get an owned instance of the environment for it
-    pub fn attach_current_thread(&self) -> Result<env::JNIEnv, Error> {
+    pub fn attach_current_thread(&self) -> Result<env::JNIEnv<'_>, Error> {
+        check_alive()?;
         let args = JavaVMAttachArgs::new(self.version.into());
         let vm = self.internal_vm();
 
@@ -127,7 +373,8 @@ impl JavaVM {
     }
 
     /// Attach the current thread as a daemon, and get an owned instance of the environment for it
-    pub fn attach_current_thread_daemon(&self) -> Result<env::JNIEnv, Error> {
+    pub fn attach_current_thread_daemon(&self) -> Result<env::JNIEnv<'_>, Error> {
+        check_alive()?;
         let args = JavaVMAttachArgs::new(self.version.into());
         let vm = self.internal_vm();
 
@@ -141,26 +388,185 @@ impl JavaVM {
         }
     }
 
+    /// Attach the current thread, returning a guard which detaches it again when dropped. If the
+    /// thread was already attached, the guard wraps the existing environment and leaves it attached
+    pub fn attach_current_thread_guarded(&self) -> Result<AttachGuard<'_>, Error> {
+        self.attach_guarded(Self::attach_current_thread)
+    }
+
+    /// Attach the current thread as a daemon, returning a guard which detaches it again when
+    /// dropped. The JVM doesn't wait for daemon threads before shutting down, so they suit
+    /// background work that shouldn't hold the JVM open. Daemons are often left attached until
+    /// they exit, but threads reused by a worker pool should still detach between jobs, as an
+    /// attached thread keeps its Java `Thread` object and any local references alive. If the
+    /// thread was already attached, the guard wraps the existing environment and leaves it
+    /// attached, without making it a daemon
+    pub fn attach_current_thread_daemon_guarded(&self) -> Result<AttachGuard<'_>, Error> {
+        self.attach_guarded(Self::attach_current_thread_daemon)
+    }
+
+    /// Build a guard for the current thread, attaching it with the given function if it isn't
+    /// already attached
+    fn attach_guarded<'a, F>(&'a self, attach: F) -> Result<AttachGuard<'a>, Error>
+        where
+            F: FnOnce(&'a Self) -> Result<env::JNIEnv<'a>, Error>
+    {
+        check_alive()?;
+        let vm = self.internal_vm();
+
+        let mut ffi_env = std::ptr::null_mut();
+        let result = vm.get_env(&mut ffi_env, self.version.into());
+        match result {
+            JNI_OK => Ok(AttachGuard {
+                vm: self,
+                env: env::JNIEnv::new(ffi_env)?,
+                detach: false
+            }),
+            JNI_EDETACHED => Ok(AttachGuard {
+                vm: self,
+                env: attach(self)?,
+                detach: true
+            }),
+            _ => Err(Error::new("Couldn't check whether current thread is attached", result))
+        }
+    }
+
+    /// Run a function on a new thread attached to this JVM, returning a future which resolves
+    /// with its result. This keeps blocking JNI calls off of async executors, without depending
+    /// on any particular runtime. The thread holds the JVM open until it finishes, as destroying
+    /// a JVM waits for attached threads
+    #[cfg(feature = "async")]
+    pub fn spawn_blocking<F, T>(&self, f: F) -> impl Future<Output = Result<T, Error>>
+        where
+            F: FnOnce(&JNIEnv<'_>) -> Result<T, Error> + Send + 'static,
+            T: Send + 'static
+    {
+        let state = Arc::new(Mutex::new(BlockingState { result: None, waker: None }));
+        let thread_state = state.clone();
+        let version = self.version;
+        // Pointers aren't Send, but the JVM pointer is valid on any thread
+        let vm_ptr = self.main_vm as usize;
+
+        std::thread::spawn(move || {
+            let call = std::panic::AssertUnwindSafe(|| {
+                let vm = JavaVM::new(version, vm_ptr as *mut ffi::JavaVM, false)?;
+                let guard = vm.attach_current_thread_guarded()?;
+                f(&guard)
+            });
+            let result = std::panic::catch_unwind(call)
+                .unwrap_or_else(|payload| {
+                    let msg = format!("Blocking call panicked: {}", panic_message(&*payload));
+                    Err(Error::new(&msg, ffi::constants::JNI_ERR))
+                });
+
+            let mut state = thread_state.lock().expect("Blocking call state poisoned");
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+        BlockingCall { state }
+    }
+
     /// Detach the current thread, and give up the associated owned environment
-    pub fn detach_current_thread(&self, _env: env::JNIEnv) -> Result<(), Error> {
+    pub fn detach_current_thread(&self, _env: env::JNIEnv<'_>) -> Result<(), Error> {
+        check_alive()?;
         let vm = self.internal_vm();
         let result = vm.detach_current_thread();
 
         if result != 0 {
             Err(Error::new("Couldn't detach current thread from JVM", result))
         } else {
+            env::bump_attach_generation();
             Ok(())
         }
     }
 }
 
+/// Result slot shared between a [JavaVM::spawn_blocking] thread and its future
+#[cfg(feature = "async")]
+struct BlockingState<T> {
+    result: Option<Result<T, Error>>,
+    waker: Option<Waker>
+}
+
+/// Future resolving with the result of a [JavaVM::spawn_blocking] call
+#[cfg(feature = "async")]
+struct BlockingCall<T> {
+    state: Arc<Mutex<BlockingState<T>>>
+}
+
+#[cfg(feature = "async")]
+impl<T> Future for BlockingCall<T> {
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().expect("Blocking call state poisoned");
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Guard over an attached thread's environment. Dereferences to the [JNIEnv], and detaches the
+/// current thread when dropped if the guard was the one to attach it
+pub struct AttachGuard<'a> {
+    vm: &'a JavaVM,
+    env: env::JNIEnv<'a>,
+    detach: bool
+}
+
+impl<'a> Deref for AttachGuard<'a> {
+    type Target = env::JNIEnv<'a>;
+
+    fn deref(&self) -> &env::JNIEnv<'a> {
+        &self.env
+    }
+}
+
+impl<'a> AttachGuard<'a> {
+    /// Detach the current thread now if the guard attached it, reporting any failure. Dropping
+    /// the guard can only log a failure to detach, so use this where the caller needs to know
+    pub fn detach(mut self) -> Result<(), Error> {
+        self.detach_inner()
+    }
+
+    fn detach_inner(&mut self) -> Result<(), Error> {
+        // Destroying the JVM already detached every thread
+        if !std::mem::replace(&mut self.detach, false) || vm_destroyed() {
+            return Ok(())
+        }
+
+        let result = self.vm.internal_vm().detach_current_thread();
+        if result != 0 {
+            return Err(Error::new("Couldn't detach current thread from JVM", result))
+        }
+        env::bump_attach_generation();
+        Ok(())
+    }
+}
+
+impl<'a> Drop for AttachGuard<'a> {
+    fn drop(&mut self) {
+        // Panicking here could abort while already unwinding, so the failure is only logged
+        if let Err(err) = self.detach_inner() {
+            eprintln!("rust_jni: {}", err);
+        }
+    }
+}
+
 impl Drop for JavaVM {
     fn drop(&mut self) {
-        if self.owned {
-            let vm = self.internal_vm();
-            let result = vm.destroy_java_vm();
-            if result != 0 {
-                panic!(format!("JVM failed to shut down: {}", result));
+        if self.owned && !vm_destroyed() {
+            // Panicking here could abort while already unwinding, so the failure is only logged.
+            // Calling destroy instead returns it
+            if let Err(err) = self.destroy_vm() {
+                eprintln!("rust_jni: couldn't destroy JVM: {}", err);
             }
         }
     }
@@ -174,7 +580,7 @@ mod tests {
     use super::*;
     use rust_jni_proc::java;
     use crate::tests::with_vm;
-    use crate::types::{JavaDownCast, JObject, JClass, JString};
+    use crate::types::{JavaDownCast, JavaUpCast, JObject, JClass, JString};
     use crate::env::JNIEnv;
     use crate::JThrowable;
 
@@ -243,4 +649,310 @@ mod tests {
             }
         });
     }
+
+    /// Environment variable telling [destroyed_vm_child] it's running in its own process
+    const DESTROY_CHILD_VAR: &str = "RUST_JNI_DESTROY_CHILD";
+
+    /// Destroys a JVM, so it has to run in a process of its own, started by [test_destroyed_vm]
+    #[test]
+    #[ignore]
+    fn destroyed_vm_child() {
+        if std::env::var_os(DESTROY_CHILD_VAR).is_none() {
+            return
+        }
+
+        let (jvm, env) = JavaVM::create(JNIVersion::Ver18).expect("Couldn't create JVM");
+        let str = env.new_string_utf("outlives the JVM").unwrap();
+        let global = env.new_global_ref(str.as_object()).unwrap();
+        let weak = env.new_weak_global_ref(str.as_object()).unwrap();
+        let handle = JavaVM::new(jvm.version(), jvm.main_vm, false).unwrap();
+
+        jvm.destroy().expect("Couldn't destroy JVM");
+
+        // Deleting references, or dropping an owned one, is a no-op rather than a use of freed memory
+        drop(global);
+        env.delete_weak_global_ref(weak);
+
+        let is_destroyed = |result: Result<(), Error>| match result {
+            Err(Error::General(msg, _)) => msg == "JVM has been destroyed",
+            _ => false
+        };
+        assert!(is_destroyed(handle.current_env().map(|_| ())));
+        assert!(is_destroyed(handle.get_local_env().map(|_| ())));
+        assert!(is_destroyed(handle.attach_current_thread().map(|_| ())));
+        assert!(is_destroyed(handle.attach_current_thread_guarded().map(|_| ())));
+        assert!(handle.destroy().is_err());
+    }
+
+    #[test]
+    fn test_destroyed_vm() {
+        let exe = std::env::current_exe().expect("Couldn't find test executable");
+        let out = std::process::Command::new(exe)
+            .args(&["--ignored", "--exact", "vm::tests::destroyed_vm_child", "--test-threads=1"])
+            .env(DESTROY_CHILD_VAR, "1")
+            .output()
+            .expect("Couldn't run child test");
+
+        let stdout = String::from_utf8_lossy(&out.stdout);
+        assert!(out.status.success(), "Child test failed:\n{}\n{}", stdout, String::from_utf8_lossy(&out.stderr));
+        assert!(stdout.contains("1 passed"), "Child test didn't run:\n{}", stdout);
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "runtime-checks"))]
+    fn test_env_after_detach() {
+        with_vm(|vm| {
+            let vm: &JavaVM = vm;
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    let guard = vm.attach_current_thread_guarded().expect("Couldn't attach thread");
+                    guard.find_class("java.lang.String").unwrap();
+
+                    // SAFETY: The pointer is only used to build an environment that should be rejected
+                    let stale = JNIEnv::new(unsafe { guard.borrow_ptr() }).unwrap();
+                    let use_env = move || { let _ = stale.get_version(); };
+                    drop(guard);
+
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(use_env));
+                    let err = result.expect_err("Stale JNIEnv use didn't panic");
+                    let msg = err.downcast_ref::<String>().expect("Unexpected panic payload");
+                    assert!(msg.starts_with("JNIEnv used after its thread was detached"), "{}", msg);
+                });
+            });
+        });
+    }
+
+    #[test]
+    fn test_guard_already_attached() {
+        with_vm(|vm| {
+            let env = vm.attach_current_thread().expect("Couldn't attach test thread");
+            {
+                let guard = vm.attach_current_thread_guarded().expect("Couldn't get guard");
+                guard.find_class("java.lang.Object").unwrap();
+            }
+            env.find_class("java.lang.Object").unwrap();
+        });
+    }
+
+    #[test]
+    fn test_guard_detaches() {
+        with_vm(|vm| {
+            let vm: &JavaVM = vm;
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    assert!(vm.get_local_env().is_err());
+                    {
+                        let guard = vm.attach_current_thread_guarded().expect("Couldn't attach thread");
+                        assert!(vm.get_local_env().is_ok());
+
+                        // A guard over an attached thread leaves it attached when dropped
+                        {
+                            let nested = vm.attach_current_thread_guarded().expect("Couldn't get nested guard");
+                            nested.find_class("java.lang.Object").unwrap();
+                        }
+                        assert!(vm.get_local_env().is_ok());
+                        guard.find_class("java.lang.Object").unwrap();
+                    }
+
+                    // Dropping the guard that attached the thread detached it
+                    assert!(vm.get_local_env().is_err());
+
+                    // Detaching explicitly reports success
+                    let guard = vm.attach_current_thread_guarded().expect("Couldn't attach thread");
+                    guard.detach().unwrap();
+                    assert!(vm.get_local_env().is_err());
+                });
+            });
+        });
+    }
+
+    #[test]
+    fn test_daemon_guard() {
+        with_vm(|vm| {
+            let vm: &JavaVM = vm;
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    {
+                        let guard = vm.attach_current_thread_daemon_guarded().expect("Couldn't attach daemon");
+                        let thread_cls = guard.find_class("java.lang.Thread").unwrap();
+                        let current = guard.get_static_method_id(&thread_cls, "currentThread", "() -> java.lang.Thread").unwrap();
+                        let is_daemon = guard.get_method_id(&thread_cls, "isDaemon", "() -> boolean").unwrap();
+
+                        let thread = guard.call_static_method(&thread_cls, &current, &vec![])
+                            .unwrap()
+                            .unwrap()
+                            .into_obj()
+                            .unwrap()
+                            .unwrap();
+                        let daemon = guard.call_method(&thread, &is_daemon, &vec![]).unwrap().unwrap();
+                        assert!(daemon.into_bool().unwrap());
+                    }
+
+                    // Dropping the guard detached the thread
+                    assert!(vm.get_local_env().is_err());
+                });
+            });
+        });
+    }
+
+    #[test]
+    fn test_current_env_cycles() {
+        with_vm(|vm| {
+            let vm: &JavaVM = vm;
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    for _ in 0..3 {
+                        let guard = vm.attach_current_thread_guarded().expect("Couldn't attach thread");
+
+                        #[cfg(feature = "audit")]
+                        let before = JavaVM::env_cache_stats();
+
+                        let first = vm.current_env().unwrap();
+                        let second = vm.current_env().unwrap();
+                        // SAFETY: Pointers are only compared
+                        unsafe {
+                            assert_eq!(first.borrow_ptr(), guard.borrow_ptr());
+                            assert_eq!(second.borrow_ptr(), guard.borrow_ptr());
+                        }
+                        second.find_class("java.lang.String").unwrap();
+
+                        #[cfg(feature = "audit")]
+                        {
+                            let after = JavaVM::env_cache_stats();
+                            assert_eq!(after.misses, before.misses + 1);
+                            assert_eq!(after.hits, before.hits + 1);
+                        }
+
+                        drop(first);
+                        drop(second);
+                    }
+
+                    // With no guard, the lookup attaches the thread itself
+                    let env = vm.current_env().unwrap();
+                    env.find_class("java.lang.String").unwrap();
+                    vm.detach_current_thread(env).unwrap();
+                });
+            });
+        });
+    }
+
+    #[test]
+    fn test_default_init_args() {
+        let defaults = JavaVM::default_init_args(JNIVersion::Ver18).unwrap();
+        assert_eq!(defaults.version(), JNIVersion::Ver18);
+
+        let extended = defaults.clone().option("-Xss2m");
+        assert_eq!(extended.options().len(), defaults.options().len() + 1);
+        assert_eq!(extended.options().last().unwrap(), "-Xss2m");
+
+        // The test JVM is created from the defaults with a property added, check it made it through
+        with_vm(|vm| {
+            let env = vm.attach_current_thread().expect("Couldn't attach test thread");
+            let sys_cls = env.find_class("java.lang.System").unwrap();
+            let get_prop = env.get_static_method_id(&sys_cls, "getProperty", "(java.lang.String) -> java.lang.String").unwrap();
+            let key = env.new_string_utf(crate::tests::TEST_PROPERTY).unwrap();
+            let val = env.call_static_method(&sys_cls, &get_prop, &vec![key.downcast().into()])
+                .unwrap()
+                .unwrap()
+                .into_obj()
+                .unwrap()
+                .expect("Test property wasn't set");
+            // SAFETY: Guaranteed safe upcast, getProperty returns a String
+            let val: String = env.get_string_chars(&unsafe { val.upcast_raw() }).unwrap().into_iter().collect();
+            assert_eq!(val, "true");
+        });
+    }
+
+    #[test]
+    fn test_error_codes() {
+        with_vm(|vm| {
+            let vm: &JavaVM = vm;
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    let err = vm.get_local_env().err().expect("Unattached thread got an environment");
+                    assert_eq!(err.jni_code(), JNI_EDETACHED);
+                });
+            });
+        });
+    }
+
+    fn on_load(vm: &JavaVM) -> Result<JNIVersion, Error> {
+        let env = vm.get_local_env()?;
+        env.find_class("java.lang.String")?;
+        Ok(JNIVersion::Ver18)
+    }
+
+    crate::jni_on_load!(on_load);
+    crate::jni_on_unload!(|vm: &JavaVM| {
+        assert!(vm.get_local_env().is_ok());
+    });
+
+    #[test]
+    fn test_jni_on_load() {
+        with_vm(|vm| {
+            let vm: &JavaVM = vm;
+            std::thread::scope(|scope| {
+                scope.spawn(|| {
+                    assert_eq!(JNI_OnLoad(vm.main_vm, std::ptr::null_mut()), JNI_EDETACHED);
+
+                    let env = vm.attach_current_thread().unwrap();
+                    assert_eq!(JNI_OnLoad(vm.main_vm, std::ptr::null_mut()), JNIVersion::Ver18.into());
+                    vm.detach_current_thread(env).unwrap();
+                });
+            });
+        });
+    }
+
+    #[test]
+    fn test_jni_on_unload() {
+        // Running the hook would release the classes other tests have cached, so only check it's
+        // exported with the signature the JVM calls. The native library integration test checks
+        // what releasing actually frees, in a process of its own
+        let hook: extern "system" fn(*mut ffi::JavaVM, *mut std::ffi::c_void) = JNI_OnUnload;
+        let load: extern "system" fn(*mut ffi::JavaVM, *mut std::ffi::c_void) -> ffi::JInt = JNI_OnLoad;
+        assert_ne!(hook as usize, load as usize);
+    }
+
+    /// Minimal executor, polling a future on the current thread until it resolves
+    #[cfg(feature = "async")]
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake, Waker};
+
+        struct ThreadWaker(std::thread::Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark()
+            }
+        }
+
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = Box::pin(fut);
+        loop {
+            match fut.as_mut().poll(&mut cx) {
+                Poll::Ready(out) => return out,
+                Poll::Pending => std::thread::park()
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_spawn_blocking() {
+        with_vm(|vm| {
+            let len = block_on(vm.spawn_blocking(|env| {
+                let str = env.new_string_utf("hello async")?;
+                env.method(&str.downcast(), "length", "() -> int")?.invoke_returning::<i32>()
+            }));
+            assert_eq!(len.unwrap(), 11);
+
+            let err = block_on(vm.spawn_blocking(|env| env.find_class("not.a.RealClass").map(|_| ())));
+            assert!(err.is_err());
+
+            let panicked = block_on(vm.spawn_blocking(|_| -> Result<(), Error> { panic!("Expected panic") }));
+            assert!(panicked.is_err());
+        });
+    }
 }
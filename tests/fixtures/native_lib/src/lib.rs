@@ -0,0 +1,87 @@
+//!
+//! Native library loaded by the JVM in the `integration_native` test. It implements the natives of
+//! the `rustjni.fixture.Natives` class three ways: through the `#[java]` macro, as a hand-mangled
+//! export, and registered from `JNI_OnLoad`.
+//!
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use rust_jni::*;
+use rust_jni_proc::java;
+
+/// Name of the fixture class declaring the natives
+const CLASS: &str = "rustjni.fixture.Natives";
+
+/// Round trip a string through rust, appending a marker so the test can tell it was seen
+#[java(class = "rustjni.fixture.Natives")]
+fn echo(env: &JNIEnv, _this: JObject, msg: JObject) -> JObject {
+    // SAFETY: The fixture class declares echo as taking a String
+    let msg = unsafe { msg.upcast_raw() };
+    let msg = env.get_string_strict(&msg).expect("Couldn't read echo string");
+
+    // Built from chars, which goes through UTF-16 and so keeps supplementary characters intact
+    let chars: Vec<char> = format!("{} (via rust)", msg).chars().collect();
+    env.new_string(&chars)
+        .expect("Couldn't create echo string")
+        .downcast()
+}
+
+/// Exported under its mangled name by hand, catching changes to the escaping of underscores
+#[no_mangle]
+pub extern "system" fn Java_rustjni_fixture_Natives_add_1one(_env: *mut ffi::JNIEnv, _this: *mut ffi::JObject, val: ffi::JInt) -> ffi::JInt {
+    val + 1
+}
+
+/// Registered native throwing an `IllegalArgumentException` with the passed message, through the
+/// crate's error mapping
+extern "system" fn fail(env: *mut ffi::JNIEnv, _this: *mut ffi::JObject, msg: *mut ffi::JString) {
+    let env = match JNIEnv::new(env) {
+        Ok(env) => env,
+        Err(_) => return
+    };
+
+    catch_panic(&env, (), || {
+        let msg = JString::new(msg).expect("Null message passed to fail");
+        let msg = env.get_string_strict(&msg).expect("Couldn't read fail message");
+        let err = Error::Exception("java.lang.IllegalArgumentException".into(), Some(msg));
+        env.throw_error(&err).expect("Couldn't throw error");
+    })
+}
+
+/// Registered native running a rust closure on a new Java thread, which calls back into this
+/// library, and returning what the closure computed
+extern "system" fn call_closure(env: *mut ffi::JNIEnv, _this: *mut ffi::JObject, val: ffi::JInt) -> ffi::JInt {
+    let env = match JNIEnv::new(env) {
+        Ok(env) => env,
+        Err(_) => return 0
+    };
+
+    catch_panic(&env, 0, || {
+        let out = Arc::new(AtomicI32::new(0));
+        let thread_out = out.clone();
+        let thread = env.spawn_java_thread("rust-jni-fixture", Box::new(move |_| {
+            thread_out.store(val * 2, Ordering::SeqCst);
+        })).expect("Couldn't spawn closure thread");
+
+        env.join_java_thread(&thread, None).expect("Couldn't join closure thread");
+        out.load(Ordering::SeqCst)
+    })
+}
+
+fn on_load(vm: &JavaVM) -> Result<JNIVersion> {
+    let env = vm.get_local_env()?;
+    let cls = env.find_class(CLASS)?;
+
+    env.register_natives(&cls, &[
+        JNINativeMethod::new::<ffi::JInt>("fail", "(Ljava/lang/String;)V", fail as *mut _),
+        JNINativeMethod::new::<ffi::JInt>("callClosure", "(I)I", call_closure as *mut _),
+    ])?;
+
+    Ok(JNIVersion::Ver18)
+}
+
+jni_on_load!(on_load);
+
+// The library keeps no state of its own, unloading only releases what rust_jni registered
+jni_on_unload!(|_vm: &JavaVM| {});
@@ -0,0 +1,205 @@
+//!
+//! Integration test of the "library loaded by Java" direction. Builds the fixture library in
+//! `tests/fixtures/native_lib`, starts a JVM, defines a class declaring its natives, loads it with
+//! `System.load`, and calls each native. This catches ABI and name mangling regressions the unit
+//! tests, which never leave one process image, can't see. Having the process to itself, it also
+//! checks what releasing the library's references frees.
+//!
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rust_jni::*;
+use rust_jni::classgen::ClassFileBuilder;
+use rust_jni::mangling::jni_export_name;
+
+/// Name of the fixture class, as passed to the class file writer
+const CLASS: &str = "rustjni/fixture/Natives";
+
+/// Name of the fixture library, without the platform's prefix and suffix
+const LIBRARY: &str = "rust_jni_fixture";
+
+/// Build the fixture library with the cargo running this test, returning the directory holding it
+fn build_fixture() -> PathBuf {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/native_lib/Cargo.toml");
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("native_lib");
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| String::from("cargo"));
+
+    let status = Command::new(cargo)
+        .arg("build")
+        .arg("--manifest-path").arg(&manifest)
+        .arg("--target-dir").arg(&target_dir)
+        .status()
+        .expect("Couldn't run cargo to build the fixture library");
+    assert!(status.success(), "Building the fixture library failed");
+
+    target_dir.join("debug")
+}
+
+/// Name of the class natives are registered on from this test, rather than the fixture library
+const UNLOAD_CLASS: &str = "rustjni/fixture/Unload";
+
+/// Name of the class natives are registered on from this test, defined in a fresh class loader so
+/// it can be unloaded
+const COLLECTED_CLASS: &str = "rustjni/fixture/Collected";
+
+/// Native registered on the unload and collected classes by this test
+extern "system" fn seven(_env: *mut ffi::JNIEnv, _cls: *mut ffi::JClass) -> ffi::JInt {
+    7
+}
+
+/// Native registered on the fixture class by this test, loading the library at the passed path
+/// with `System.load`. That loads for its caller's class loader, and over JNI with no Java frame
+/// the caller would be the boot loader. Called from this native, the caller is the fixture class,
+/// so the library goes to the loader its exports are looked up in
+extern "system" fn load_library(env: *mut ffi::JNIEnv, _cls: *mut ffi::JClass, path: *mut ffi::JString) {
+    let env = match JNIEnv::new(env) {
+        Ok(env) => env,
+        Err(_) => return
+    };
+
+    catch_panic(&env, (), || {
+        let path = JString::new(path).expect("Null path passed to loadLibrary");
+        let system_cls = env.find_class("java.lang.System").expect("Couldn't find System");
+        let load = env.get_static_method_id(&system_cls, "load", "(java.lang.String) -> void")
+            .expect("Couldn't find System.load");
+        if let Err(err) = env.call_static_method(&system_cls, &load, &vec![path.as_object().into()]) {
+            env.throw_error(&err).expect("Couldn't throw error");
+        }
+    })
+}
+
+/// Define the class declaring the fixture's natives with the system class loader
+fn define_fixture_class<'a>(env: &JNIEnv<'a>) -> JClass<'a> {
+    let bytes = ClassFileBuilder::new(CLASS)
+        .native_method("echo", "(Ljava/lang/String;)Ljava/lang/String;", false)
+        .native_method("add_one", "(I)I", false)
+        .native_method("fail", "(Ljava/lang/String;)V", false)
+        .native_method("callClosure", "(I)I", false)
+        .native_method("loadLibrary", "(Ljava/lang/String;)V", true)
+        .build();
+    env.define_class_in_system_loader(CLASS, &bytes).expect("Couldn't define fixture class")
+}
+
+#[test]
+fn test_native_library() {
+    // Building the fixture runs the build script again, which needs JAVA_HOME to link against the
+    // same JDK this test runs on
+    if std::env::var_os("JAVA_HOME").is_none() {
+        eprintln!("JAVA_HOME isn't set, skipping the native library integration test");
+        return
+    }
+
+    let lib_dir = build_fixture();
+    let lib_path = lib_dir.join(format!("{}{}{}", DLL_PREFIX, LIBRARY, DLL_SUFFIX));
+    let (_vm, env) = JavaVM::create(JNIVersion::Ver18).expect("Couldn't create JVM");
+
+    let cls = define_fixture_class(&env);
+
+    // Loading runs JNI_OnLoad, which registers the natives the library doesn't export
+    let method = JNINativeMethod::new::<ffi::JInt>("loadLibrary", "(Ljava/lang/String;)V", load_library as *mut _);
+    env.register_natives(&cls, &[method]).unwrap();
+    let load = env.get_static_method_id(&cls, "loadLibrary", "(java.lang.String) -> void").unwrap();
+    let path = env.new_string_utf(&lib_path.display().to_string()).unwrap();
+    if let Err(err) = env.call_static_method(&cls, &load, &vec![path.as_object().into()]) {
+        panic!("Couldn't load the fixture library from {}: {}", lib_path.display(), err);
+    }
+
+    let obj = env.alloc_object(&cls).unwrap();
+
+    // String round trip through the #[java] export
+    let echo = env.get_method_id(&cls, "echo", "(java.lang.String) -> java.lang.String").unwrap();
+    let chars: Vec<char> = "h\u{e9}llo \u{1F600}".chars().collect();
+    let msg = env.new_string(&chars).unwrap();
+    let result = env.call_value_method(&obj, &echo, &vec![msg.as_object().into()])
+        .unwrap()
+        .into_obj()
+        .unwrap()
+        .expect("echo returned null");
+    // SAFETY: The fixture class declares echo as returning a String
+    let result = env.get_string_strict(&unsafe { result.upcast_raw() }).unwrap();
+    assert_eq!(result, "h\u{e9}llo \u{1F600} (via rust)");
+
+    // The hand-mangled export is found under the name the mangling module gives
+    assert_eq!(jni_export_name("rustjni.fixture.Natives", "add_one", None).unwrap(), "Java_rustjni_fixture_Natives_add_1one");
+    let add_one = env.get_method_id(&cls, "add_one", "(int) -> int").unwrap();
+    let result = env.call_value_method(&obj, &add_one, &vec![JValue::Int(41)]).unwrap();
+    assert_eq!(result.into_int().unwrap(), 42);
+
+    // Errors thrown by the registered native arrive as the Java exception they map to
+    let fail = env.get_method_id(&cls, "fail", "(java.lang.String) -> void").unwrap();
+    let msg = env.new_string_utf("bad argument").unwrap();
+    match env.call_method(&obj, &fail, &vec![msg.as_object().into()]) {
+        Err(Error::Exception(name, msg)) => {
+            assert_eq!(name, "java.lang.IllegalArgumentException");
+            assert_eq!(msg.as_deref(), Some("bad argument"));
+        }
+        other => panic!("Expected the thrown exception, got {:?}", other.map(|_| ()))
+    }
+
+    // A closure run on a Java thread calls back into the library
+    let call_closure = env.get_method_id(&cls, "callClosure", "(int) -> int").unwrap();
+    let result = env.call_value_method(&obj, &call_closure, &vec![JValue::Int(21)]).unwrap();
+    assert_eq!(result.into_int().unwrap(), 42);
+    assert!(!env.exception_check());
+
+    // Last, as releasing unregisters the natives registered above
+    check_release_library_refs(&env);
+}
+
+/// Check registering natives doesn't hold a class alive, and that releasing the library's
+/// references unregisters natives and empties the caches, which are then looked up again
+fn check_release_library_refs(env: &JNIEnv) {
+    let system_cls = env.find_class("java.lang.System").unwrap();
+    let gc = env.get_static_method_id(&system_cls, "gc", "() -> void").unwrap();
+
+    // A class only a fresh class loader holds can be unloaded with natives still registered
+    let loader_cls = env.find_class("java.net.URLClassLoader").unwrap();
+    let url_cls = env.find_class("java.net.URL").unwrap();
+    let loader_ctor = env.get_method_id(&loader_cls, "<init>", "(java.net.URL[]) -> void").unwrap();
+    let urls = env.new_object_array(0, &url_cls, None).unwrap();
+    let loader = env.new_object(&loader_cls, &loader_ctor, &vec![urls.as_object().into()]).unwrap();
+
+    let bytes = ClassFileBuilder::new(COLLECTED_CLASS)
+        .native_method("seven", "()I", true)
+        .build();
+    let cls = env.define_class(COLLECTED_CLASS, &loader, &bytes).unwrap();
+    let method = JNINativeMethod::new::<ffi::JInt>("seven", "()I", seven as *mut _);
+    env.register_natives(&cls, &[method]).unwrap();
+    let weak = env.new_weak_global_ref(cls.as_object()).unwrap();
+    env.delete_local_ref(cls.downcast());
+    env.delete_local_ref(loader);
+    env.delete_local_ref(urls.downcast());
+
+    let mut collected = false;
+    for _ in 0..10 {
+        env.call_static_method(&system_cls, &gc, &vec![]).unwrap();
+        if env.upgrade_weak(&weak).unwrap().is_none() {
+            collected = true;
+            break
+        }
+    }
+    assert!(collected, "The class natives were registered on is still held");
+    env.delete_weak_global_ref(weak);
+
+    // A class that stays loaded has its natives unregistered
+    let bytes = ClassFileBuilder::new(UNLOAD_CLASS)
+        .native_method("seven", "()I", true)
+        .build();
+    let cls = env.define_class_in_system_loader(UNLOAD_CLASS, &bytes).unwrap();
+    let method = JNINativeMethod::new::<ffi::JInt>("seven", "()I", seven as *mut _);
+    env.register_natives(&cls, &[method]).unwrap();
+    let id = env.get_static_method_id(&cls, "seven", "() -> int").unwrap();
+    let result = env.call_static_method(&cls, &id, &vec![]).unwrap().unwrap();
+    assert_eq!(result.into_int().unwrap(), 7);
+
+    // SAFETY: No other thread uses the library, and no class cached before is kept past this
+    unsafe { env.release_library_refs().unwrap() };
+
+    // Turning the exception into an error goes through the emptied caches, looking them up again
+    match env.call_static_method(&cls, &id, &vec![]) {
+        Err(Error::Exception(name, _)) => assert_eq!(name, "java.lang.UnsatisfiedLinkError"),
+        other => panic!("Expected the native to be unregistered, got {:?}", other.map(|_| ()))
+    }
+}